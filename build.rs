@@ -0,0 +1,28 @@
+/*
+ Generates the server and the client of the gRPC service, see src/grpc.rs. The messages are
+ written there by hand, so protoc isn't needed.
+ */
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let scan = Method::builder()
+        .name("scan")
+        .route_name("Scan")
+        .input_type("crate::grpc::ScanRequest")
+        .output_type("crate::grpc::ScanReport")
+        .codec_path("tonic_prost::ProstCodec")
+        .build();
+    let service = Service::builder()
+        .name("Strings")
+        .package("strings")
+        .method(scan)
+        .build();
+    Builder::new().compile(&[service]);
+}
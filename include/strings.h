@@ -0,0 +1,56 @@
+/*
+ The C interface of the strings library, libstrings.so (strings.dll, libstrings.dylib), built by
+ cargo along with the command line tool. See src/ffi.rs.
+ */
+
+#ifndef STRINGS_H
+#define STRINGS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define STRINGS_API_VERSION 1
+
+/* The scan options, NULL for the defaults of the command line tool. */
+typedef struct StringsOptions {
+    /* The minimum length of the strings in characters, -n. */
+    uint16_t min_length;
+    /* The encoding as given to -e: "s", "S", "b", "l", "B", "L", "cp037" or "cp500". NULL for
+       "s". */
+    const char *encoding;
+    /* Treat all the whitespace as a part of the strings, -w. */
+    int include_all_whitespace;
+} StringsOptions;
+
+/* A found string, valid only during the call of the callback. */
+typedef struct StringsResult {
+    /* Offset of the string in the data. */
+    uint64_t offset;
+    /* Number of the bytes the string occupies in the data. */
+    uint64_t length;
+    /* The string, UTF-8 unless a legacy code page is scanned, not terminated with a NUL. */
+    const uint8_t *text;
+    size_t text_length;
+    /* Name of the encoding, e.g. "7bit" or "16le". */
+    const char *encoding;
+} StringsResult;
+
+typedef void (*StringsCallback)(const StringsResult *result, void *user_data);
+
+/* Returns the version of the interface the library implements, STRINGS_API_VERSION. */
+int strings_api_version(void);
+
+/* Scans the data and calls the callback with every string found, in the order of their
+   offsets. Returns the number of the strings, or -1 if the arguments are invalid. */
+int64_t strings_scan_buffer(const uint8_t *data, size_t length, const StringsOptions *options,
+                            StringsCallback callback, void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,116 @@
+/*
+ Custom sets of the characters which make up the strings, see `--chars`. They replace the
+ built-in printable check, for the targets with their own idea of text: DOS text with the box
+ drawing characters, the menus of a firmware in a few letters, the protocol dumps. The set is of
+ the characters up to U+00FF: the bytes of the 8-bit scan, the code units of the wide encodings
+ and the decoded EBCDIC characters.
+ */
+
+/**
+A set of the characters up to U+00FF.
+ */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Alphabet {
+    bits: [u64; 4],
+}
+
+impl Alphabet {
+    /**
+    Parses a set like `a-zA-Z0-9_./:- `: characters and ranges of them. A range is a `-`
+    between two letters, digits or escapes, any other `-` is the character itself, so that the
+    punctuation needs no escaping. The escapes `\t`, `\n`, `\r`, `\\`, `\-` and `\xNN` name
+    the other characters. Returns an error message for an empty set, a character above U+00FF,
+    a reversed range and an unknown escape.
+     */
+    pub fn parse(spec: &str) -> Result<Alphabet, String> {
+        let mut chars = Vec::new();
+        let mut spec_chars = spec.chars();
+        // the characters with whether they can start or end a range, `None` for a `-`
+        while let Some(c) = spec_chars.next() {
+            let escaped = c == '\\';
+            let c = match c {
+                '\\' => match spec_chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some(c @ ('\\' | '-')) => c,
+                    Some('x') => {
+                        let hex: String = spec_chars.by_ref().take(2).collect();
+                        match u8::from_str_radix(&hex, 16) {
+                            Ok(byte) if hex.len() == 2 => byte as char,
+                            _ => return Err(format!("invalid escape \\x{}", hex)),
+                        }
+                    }
+                    Some(other) => return Err(format!("invalid escape \\{}", other)),
+                    None => return Err("ends with a backslash".to_string()),
+                },
+                '-' => {
+                    chars.push(None);
+                    continue;
+                }
+                c => c,
+            };
+            chars.push(Some((c, escaped || c.is_alphanumeric())));
+        }
+
+        let mut alphabet = Alphabet { bits: [0; 4] };
+        let mut index = 0;
+        while index < chars.len() {
+            let (first, last) = match (chars[index], chars.get(index + 1), chars.get(index + 2)) {
+                (Some((first, true)), Some(None), Some(Some((last, true)))) => {
+                    index += 3;
+                    (first, *last)
+                }
+                (first, _, _) => {
+                    index += 1;
+                    let c = first.map_or('-', |(c, _)| c);
+                    (c, c)
+                }
+            };
+            if first > last {
+                return Err(format!("invalid range {}-{}", first, last));
+            }
+            if last > '\u{ff}' {
+                return Err(format!("character above \\xff: {}", last));
+            }
+            for c in first..=last {
+                alphabet.bits[c as usize / 64] |= 1 << (c as usize % 64);
+            }
+        }
+
+        if alphabet.bits == [0; 4] {
+            return Err("empty set".to_string());
+        }
+        return Ok(alphabet);
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        return c <= '\u{ff}' && self.bits[c as usize / 64] & (1 << (c as usize % 64)) != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let alphabet = Alphabet::parse("a-cX0-9_./:- ").unwrap();
+        for c in "abcX059_./:- ".chars() {
+            assert!(alphabet.contains(c), "{}", c);
+        }
+        for c in "dAx\t\u{ff}\u{100}".chars() {
+            assert!(!alphabet.contains(c), "{}", c);
+        }
+
+        let alphabet = Alphabet::parse(r"-\t\x80-\xff\-\\").unwrap();
+        for c in "-\t\u{80}\u{e9}\u{ff}\\".chars() {
+            assert!(alphabet.contains(c), "{}", c);
+        }
+        assert!(!alphabet.contains('\u{7f}'));
+
+        for wrong in ["", "z-a", "é-ā", r"\q", r"\xz1", "\\"] {
+            assert!(Alphabet::parse(wrong).is_err(), "{}", wrong);
+        }
+    }
+}
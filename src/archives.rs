@@ -0,0 +1,352 @@
+/*
+ Scanning of the files inside archives (`--archives`): tar, also compressed with gzip, xz, zstd
+ or bzip2, and zip with the formats built on it, .jar, .apk, .docx and so on. The files are
+ scanned as `archive.zip!path/in/archive`. Only the zip members stored as is or deflated are
+ read, the other compression methods and the encrypted members are skipped with a warning.
+ */
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::Path;
+use flate2::read::DeflateDecoder;
+use super::decompress::{decompress, Compression};
+use super::diagnostics;
+use super::limits::{self, ContainerLimits, ExpandError};
+use super::packages::{normalize_path, read_tar, PackageFile};
+use super::strings::{self, Options, StringSink};
+use super::utils::{read_u16_le, read_u32_le};
+
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+/// Size of the end of the central directory record without its comment.
+const ZIP_END_SIZE: usize = 22;
+const ZIP_CENTRAL_HEADER_SIZE: usize = 46;
+const ZIP_LOCAL_HEADER_SIZE: usize = 30;
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+const ZIP_ENCRYPTED: u16 = 0x1;
+
+/// Offset of the `ustar` magic in the header of a tar entry.
+const TAR_MAGIC_OFFSET: usize = 257;
+
+struct Archive {
+    files: Vec<PackageFile>,
+    /// Why the files which are not in `files` were skipped.
+    skipped: Vec<String>,
+}
+
+/**
+Prints the strings of all the files in the archive. Returns `None` if the file is not an archive
+(or a broken one), so that it can be scanned as is.
+ */
+pub fn print_strings_for_archive(
+    file_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let filename = file_path.as_os_str();
+
+    let mut magic = [0u8; TAR_MAGIC_OFFSET + 5];
+    let num_read = std::fs::File::open(file_path)
+        .and_then(|mut file| file.read(&mut magic))
+        .ok()?;
+    if !might_be_archive(&magic[..num_read]) {
+        return None;
+    }
+
+    let data = std::fs::read(file_path).ok()?;
+    let archive = match read_archive(&data, &options.container_limits) {
+        Ok(Some(archive)) => archive,
+        Ok(None) => return None,
+        Err(ExpandError::Limit(limit)) => {
+            limits::warn(&filename.to_string_lossy(), &limit);
+            return None;
+        }
+        Err(ExpandError::Invalid(err)) => {
+            diagnostics::warning(format_args!(
+                "'{}' is a broken archive ({}), scanned as is", file_path.display(), err
+            ));
+            return None;
+        }
+    };
+    for reason in &archive.skipped {
+        diagnostics::warning(format_args!("'{}': {}", file_path.display(), reason));
+    }
+
+    let mut num_found = 0u64;
+    for file in &archive.files {
+        let mut name = filename.to_os_string();
+        name.push(format!("!{}", file.path));
+        num_found += strings::print_strings_for_bytes(&name, &file.data, options, sink);
+    }
+    return Some(num_found);
+}
+
+/**
+Prints the strings of all the files in the zip archive in memory, as `name!path`. Returns `None`
+if it couldn't be read, so that it can be scanned as is.
+ */
+pub(crate) fn print_strings_for_zip_data(
+    name: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let archive = match read_zip(data, &options.container_limits) {
+        Ok(archive) => archive,
+        Err(ExpandError::Limit(limit)) => {
+            limits::warn(&name.to_string_lossy(), &limit);
+            return None;
+        }
+        Err(ExpandError::Invalid(_)) => return None,
+    };
+    for reason in &archive.skipped {
+        diagnostics::warning(format_args!("'{}': {}", name.to_string_lossy(), reason));
+    }
+
+    let mut num_found = 0u64;
+    for file in &archive.files {
+        let mut file_name = name.to_os_string();
+        file_name.push(format!("!{}", file.path));
+        num_found += strings::print_strings_for_bytes(&file_name, &file.data, options, sink);
+    }
+    return Some(num_found);
+}
+
+fn might_be_archive(magic: &[u8]) -> bool {
+    return read_u32_le(magic, 0) == Some(ZIP_LOCAL_HEADER)
+        || read_u32_le(magic, 0) == Some(ZIP_END_OF_CENTRAL_DIRECTORY)
+        || is_tar(magic)
+        || Compression::detect(magic).is_some();
+}
+
+fn is_tar(data: &[u8]) -> bool {
+    return data.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5) == Some(b"ustar");
+}
+
+/**
+Returns `None` if the data is not an archive.
+ */
+fn read_archive(data: &[u8], limits: &ContainerLimits) -> Result<Option<Archive>, ExpandError> {
+    limits.check_depth(1)?;
+
+    if read_u32_le(data, 0) == Some(ZIP_LOCAL_HEADER)
+        || read_u32_le(data, 0) == Some(ZIP_END_OF_CENTRAL_DIRECTORY) {
+        return read_zip(data, limits).map(Some);
+    }
+    let data = match Compression::detect(data) {
+        Some(compression) => Cow::Owned(decompress(compression, data, limits)?),
+        None => Cow::Borrowed(data),
+    };
+    if is_tar(&data) {
+        return Ok(Some(Archive { files: read_tar(&data)?, skipped: Vec::new() }));
+    }
+    return Ok(None);
+}
+
+// region zip
+
+/**
+Returns the regular files of a zip archive and the reasons the unsupported ones were skipped.
+The files are listed by the central directory at the end of the archive, the local headers
+only tell where their data starts.
+ */
+fn read_zip(data: &[u8], limits: &ContainerLimits) -> Result<Archive, ExpandError> {
+    let end = find_end_of_central_directory(data)
+        .ok_or("no end of the central directory")?;
+    let num_entries = read_u16_le(data, end + 10).ok_or("truncated")?;
+    let directory_offset = read_u32_le(data, end + 16).ok_or("truncated")?;
+    if num_entries == u16::MAX || directory_offset == u32::MAX {
+        return Err(ExpandError::Invalid("ZIP64 is not supported".to_string()));
+    }
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut position = directory_offset as usize;
+    for _ in 0..num_entries {
+        if read_u32_le(data, position) != Some(ZIP_CENTRAL_HEADER) {
+            return Err(ExpandError::Invalid("broken central directory".to_string()));
+        }
+        let header = data.get(position..position + ZIP_CENTRAL_HEADER_SIZE)
+            .ok_or("truncated central directory")?;
+        let flags = read_u16_le(header, 8).unwrap();
+        let method = read_u16_le(header, 10).unwrap();
+        let compressed_size = read_u32_le(header, 20).unwrap() as usize;
+        let name_length = read_u16_le(header, 28).unwrap() as usize;
+        let extra_length = read_u16_le(header, 30).unwrap() as usize;
+        let comment_length = read_u16_le(header, 32).unwrap() as usize;
+        let local_offset = read_u32_le(header, 42).unwrap() as usize;
+
+        let name_start = position + ZIP_CENTRAL_HEADER_SIZE;
+        let name_bytes = data.get(name_start..name_start + name_length)
+            .ok_or("truncated central directory")?;
+        let path = normalize_path(&String::from_utf8_lossy(name_bytes));
+        position = name_start + name_length + extra_length + comment_length;
+
+        if path.is_empty() || path.ends_with('/') {
+            continue;
+        }
+        if flags & ZIP_ENCRYPTED != 0 {
+            skipped.push(format!("{} is encrypted, skipped", path));
+            continue;
+        }
+        let compressed = local_data(data, local_offset, compressed_size)
+            .ok_or_else(|| format!("broken local header of {}", path))?;
+        let contents = match method {
+            ZIP_STORED => compressed.to_vec(),
+            ZIP_DEFLATED => inflate(compressed, limits)
+                .map_err(|err| match err {
+                    ExpandError::Invalid(err) => ExpandError::Invalid(format!("{}: {}", path, err)),
+                    limit => limit,
+                })?,
+            _ => {
+                skipped.push(format!("{} is compressed with method {}, skipped", path, method));
+                continue;
+            }
+        };
+        files.push(PackageFile { path, data: contents });
+    }
+    return Ok(Archive { files, skipped });
+}
+
+/**
+Finds the end of the central directory record, which is followed only by the comment of the
+archive, at most 64 KiB long.
+ */
+fn find_end_of_central_directory(data: &[u8]) -> Option<usize> {
+    let last = data.len().checked_sub(ZIP_END_SIZE)?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    return (first..=last).rev()
+        .find(|position| read_u32_le(data, *position) == Some(ZIP_END_OF_CENTRAL_DIRECTORY));
+}
+
+fn local_data(data: &[u8], offset: usize, size: usize) -> Option<&[u8]> {
+    if read_u32_le(data, offset)? != ZIP_LOCAL_HEADER {
+        return None;
+    }
+    let name_length = read_u16_le(data, offset + 26)? as usize;
+    let extra_length = read_u16_le(data, offset + 28)? as usize;
+    let start = offset + ZIP_LOCAL_HEADER_SIZE + name_length + extra_length;
+    return data.get(start..start.checked_add(size)?);
+}
+
+fn inflate(compressed: &[u8], limits: &ContainerLimits) -> Result<Vec<u8>, ExpandError> {
+    let mut decoder = limits.limit_expansion(
+        DeflateDecoder::new(compressed), compressed.len() as u64
+    );
+    let mut contents = Vec::new();
+    let result = decoder.read_to_end(&mut contents);
+    if let Some(limit) = decoder.exceeded() {
+        return Err(ExpandError::Limit(limit));
+    }
+    if let Err(err) = result {
+        return Err(ExpandError::Invalid(format!("deflate failed: {}", err)));
+    }
+    return Ok(contents);
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::DeflateEncoder;
+
+    /**
+    Builds a zip archive of the files, the ones with the method 8 deflated.
+     */
+    fn zip(files: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+        for (path, method, contents) in files {
+            let data = if *method == ZIP_DEFLATED {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                contents.to_vec()
+            };
+            let offset = archive.len() as u32;
+            archive.extend(ZIP_LOCAL_HEADER.to_le_bytes());
+            archive.extend([20, 0, 0, 0]);
+            archive.extend(method.to_le_bytes());
+            archive.extend([0u8; 8]);
+            archive.extend((data.len() as u32).to_le_bytes());
+            archive.extend((contents.len() as u32).to_le_bytes());
+            archive.extend((path.len() as u16).to_le_bytes());
+            archive.extend([0, 0]);
+            archive.extend(path.as_bytes());
+            archive.extend(&data);
+
+            directory.extend(ZIP_CENTRAL_HEADER.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0u8; 8]);
+            directory.extend((data.len() as u32).to_le_bytes());
+            directory.extend((contents.len() as u32).to_le_bytes());
+            directory.extend((path.len() as u16).to_le_bytes());
+            directory.extend([0u8; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(path.as_bytes());
+        }
+        let directory_offset = archive.len() as u32;
+        archive.extend(&directory);
+        archive.extend(ZIP_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend([0u8; 4]);
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend(3u16.to_le_bytes());
+        archive.extend(b"end");
+        return archive;
+    }
+
+    #[test]
+    fn test_read_zip() {
+        let archive = zip(&[
+            ("META-INF/", ZIP_STORED, b""),
+            ("META-INF/MANIFEST.MF", ZIP_STORED, b"Manifest-Version: 1.0\n"),
+            ("res/strings.xml", ZIP_DEFLATED, b"<string>hello hello hello</string>"),
+            ("classes.lzma", 14, b"\0"),
+        ]);
+        let limits = ContainerLimits::default();
+        let Archive { files, skipped } = read_archive(&archive, &limits).unwrap().unwrap();
+        assert_eq!(
+            vec![
+                ("META-INF/MANIFEST.MF", &b"Manifest-Version: 1.0\n"[..]),
+                ("res/strings.xml", &b"<string>hello hello hello</string>"[..]),
+            ],
+            files.iter().map(|file| (file.path.as_str(), &file.data[..])).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["classes.lzma is compressed with method 14, skipped"], skipped);
+
+        let limits = ContainerLimits { max_depth: 0, ..Default::default() };
+        assert!(matches!(read_archive(&archive, &limits), Err(ExpandError::Limit(_))));
+        assert!(matches!(
+            read_archive(&archive[..archive.len() - 30], &ContainerLimits::default()),
+            Err(ExpandError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_compressed_tar() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "./etc/motd", &b"hello"[..]).unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&builder.into_inner().unwrap()).unwrap();
+
+        let limits = ContainerLimits::default();
+        let files = read_archive(&gzip.finish().unwrap(), &limits).unwrap().unwrap().files;
+        assert_eq!(vec![("etc/motd".to_string(), b"hello".to_vec())],
+                   files.into_iter().map(|file| (file.path, file.data)).collect::<Vec<_>>());
+        assert!(read_archive(b"plain text", &limits).unwrap().is_none());
+    }
+}
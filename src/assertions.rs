@@ -0,0 +1,162 @@
+use regex::bytes::Regex;
+use super::strings::{FoundString, StringSink};
+
+enum AssertionKind {
+    Contains(String),
+    Regex(String),
+    Absent(String),
+}
+
+struct Assertion {
+    kind: AssertionKind,
+    pattern: Regex,
+    // filename and address of the first matching string
+    first_match: Option<(String, u64)>,
+}
+
+/**
+Checks the found strings against the `--assert-*` options. `Contains` and `Regex` assertions
+pass if at least one found string matches, `Absent` assertions pass if none does.
+ */
+pub struct Assertions {
+    assertions: Vec<Assertion>,
+}
+
+impl Assertions {
+    pub fn new(
+        contains: &[String],
+        regexes: &[String],
+        absent: &[String],
+    ) -> Result<Assertions, regex::Error> {
+        let mut assertions = Vec::new();
+
+        for needle in contains {
+            assertions.push(Assertion {
+                kind: AssertionKind::Contains(needle.clone()),
+                pattern: Regex::new(&regex::escape(needle))?,
+                first_match: None,
+            });
+        }
+
+        for pattern in regexes {
+            assertions.push(Assertion {
+                kind: AssertionKind::Regex(pattern.clone()),
+                pattern: Regex::new(pattern)?,
+                first_match: None,
+            });
+        }
+
+        for needle in absent {
+            assertions.push(Assertion {
+                kind: AssertionKind::Absent(needle.clone()),
+                pattern: Regex::new(&regex::escape(needle))?,
+                first_match: None,
+            });
+        }
+
+        return Ok(Assertions { assertions });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.assertions.is_empty();
+    }
+
+    /**
+    Returns a description of every assertion which didn't hold.
+     */
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for assertion in &self.assertions {
+            match (&assertion.kind, &assertion.first_match) {
+                (AssertionKind::Contains(needle), None) => {
+                    failures.push(format!("no string contains {:?}", needle));
+                }
+                (AssertionKind::Regex(pattern), None) => {
+                    failures.push(format!("no string matches /{}/", pattern));
+                }
+                (AssertionKind::Absent(needle), Some((filename, address))) => {
+                    failures.push(format!(
+                        "found {:?} in {} at offset {:#x}", needle, filename, address
+                    ));
+                }
+                _ => {
+                    // assertion holds
+                }
+            }
+        }
+
+        return failures;
+    }
+}
+
+impl StringSink for Assertions {
+    fn on_string(&mut self, found: &FoundString) {
+        for assertion in self.assertions.iter_mut() {
+            if assertion.first_match.is_none() && assertion.pattern.is_match(found.bytes) {
+                let filename = found.filename.to_string_lossy().into_owned();
+                assertion.first_match = Some((filename, found.address));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use crate::strings::{EncodingKind, Termination};
+
+    fn found_string(bytes: &[u8]) -> FoundString<'_> {
+        return FoundString {
+            filename: OsStr::new("test"),
+            address: 0x10,
+            end_address: 0x10 + bytes.len() as u64,
+            bytes,
+            text: bytes,
+            encoding: EncodingKind::Bit7,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        };
+    }
+
+    #[test]
+    fn test_assertions_hold() {
+        let mut assertions = Assertions::new(
+            &["GPL".to_string()],
+            &["v[0-9]+\\.[0-9]+".to_string()],
+            &["/home/".to_string()],
+        ).unwrap();
+
+        assertions.on_string(&found_string(b"License: GPL-3.0"));
+        assertions.on_string(&found_string(b"version v1.2"));
+
+        assert!(assertions.failures().is_empty());
+    }
+
+    #[test]
+    fn test_assertions_fail() {
+        let mut assertions = Assertions::new(
+            &["GPL".to_string()],
+            &["v[0-9]+\\.[0-9]+".to_string()],
+            &["/home/".to_string()],
+        ).unwrap();
+
+        assertions.on_string(&found_string(b"/home/user/src/main.c"));
+
+        assert_eq!(
+            vec![
+                "no string contains \"GPL\"".to_string(),
+                "no string matches /v[0-9]+\\.[0-9]+/".to_string(),
+                "found \"/home/\" in test at offset 0x10".to_string(),
+            ],
+            assertions.failures()
+        );
+    }
+}
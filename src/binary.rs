@@ -0,0 +1,255 @@
+/*
+ The binary output formats, MessagePack and CBOR, see `--format msgpack` and `--format cbor`:
+ the records of the JSON output, encoded without the text overhead. The output is a stream of
+ maps, one after another with nothing in between, which the decoders of both formats read one by
+ one.
+ */
+
+use std::ffi::OsStr;
+use std::io::Write;
+use serde::Serialize;
+use serde_json::Value;
+use super::diagnostics::check_write;
+use super::hash::HashKind;
+use super::json::{self, PackageRecord, StringRecord};
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BinaryFormat {
+    MessagePack,
+    Cbor,
+}
+
+/**
+Prints the records of the found strings and of the packages in a binary format, with the fields
+of the JSON output.
+ */
+pub struct BinaryPrinter<'a> {
+    writer: &'a mut dyn Write,
+    format: BinaryFormat,
+    highlight: bool,
+    hash: Option<HashKind>,
+}
+
+impl<'a> BinaryPrinter<'a> {
+    pub fn new(
+        writer: &'a mut dyn Write,
+        format: BinaryFormat,
+        options: &Options,
+    ) -> BinaryPrinter<'a> {
+        return BinaryPrinter {
+            writer,
+            format,
+            highlight: json::highlights(options),
+            hash: options.string_hash,
+        };
+    }
+
+    fn write<T: Serialize>(&mut self, record: &T) {
+        let value = serde_json::to_value(record).expect("Couldn't encode the record");
+        let mut output = Vec::new();
+        encode(self.format, &value, &mut output);
+        check_write(self.writer.write_all(&output));
+    }
+}
+
+impl StringSink for BinaryPrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut record = StringRecord::new(found).with_hash(self.hash);
+        if self.highlight {
+            record = record.with_highlight(found);
+        }
+        self.write(&record);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.write(&PackageRecord { file: filename.to_string_lossy(), package });
+    }
+}
+
+/**
+Appends the encoded value.
+ */
+fn encode(format: BinaryFormat, value: &Value, output: &mut Vec<u8>) {
+    match format {
+        BinaryFormat::MessagePack => encode_msgpack(value, output),
+        BinaryFormat::Cbor => encode_cbor(value, output),
+    }
+}
+
+fn encode_msgpack(value: &Value, output: &mut Vec<u8>) {
+    match value {
+        Value::Null => output.push(0xc0),
+        Value::Bool(value) => output.push(if *value { 0xc3 } else { 0xc2 }),
+        Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                match value {
+                    0..=0x7f => output.push(value as u8),
+                    0x80..=0xff => output.extend([0xcc, value as u8]),
+                    0x100..=0xffff => msgpack_header(output, 0xcd, &(value as u16).to_be_bytes()),
+                    0x1_0000..=0xffff_ffff => {
+                        msgpack_header(output, 0xce, &(value as u32).to_be_bytes());
+                    }
+                    _ => msgpack_header(output, 0xcf, &value.to_be_bytes()),
+                }
+            } else if let Some(value) = number.as_i64() {
+                match value {
+                    -0x20..=-1 => output.push(value as u8),
+                    -0x80..=-0x21 => output.extend([0xd0, value as u8]),
+                    -0x8000..=-0x81 => msgpack_header(output, 0xd1, &(value as i16).to_be_bytes()),
+                    -0x8000_0000..=-0x8001 => {
+                        msgpack_header(output, 0xd2, &(value as i32).to_be_bytes());
+                    }
+                    _ => msgpack_header(output, 0xd3, &value.to_be_bytes()),
+                }
+            } else {
+                msgpack_header(output, 0xcb, &number.as_f64().unwrap_or(f64::NAN).to_be_bytes());
+            }
+        }
+        Value::String(text) => msgpack_text(output, text),
+        Value::Array(values) => {
+            msgpack_collection(output, values.len(), 0x90, 0xdc);
+            for value in values {
+                encode_msgpack(value, output);
+            }
+        }
+        Value::Object(fields) => {
+            msgpack_collection(output, fields.len(), 0x80, 0xde);
+            for (key, value) in fields {
+                msgpack_text(output, key);
+                encode_msgpack(value, output);
+            }
+        }
+    }
+}
+
+fn msgpack_text(output: &mut Vec<u8>, text: &str) {
+    let length = text.len();
+    match length {
+        0..=0x1f => output.push(0xa0 | length as u8),
+        0x20..=0xff => output.extend([0xd9, length as u8]),
+        0x100..=0xffff => msgpack_header(output, 0xda, &(length as u16).to_be_bytes()),
+        _ => msgpack_header(output, 0xdb, &(length as u32).to_be_bytes()),
+    }
+    output.extend(text.as_bytes());
+}
+
+fn msgpack_header(output: &mut Vec<u8>, marker: u8, bytes: &[u8]) {
+    output.push(marker);
+    output.extend(bytes);
+}
+
+/**
+Appends the header of an array or a map: the short form holds up to 15 items, then the 16-bit
+and the 32-bit forms follow the marker of the 16-bit one.
+ */
+fn msgpack_collection(output: &mut Vec<u8>, length: usize, short_marker: u8, marker: u8) {
+    match length {
+        0..=0xf => output.push(short_marker | length as u8),
+        0x10..=0xffff => msgpack_header(output, marker, &(length as u16).to_be_bytes()),
+        _ => msgpack_header(output, marker + 1, &(length as u32).to_be_bytes()),
+    }
+}
+
+fn encode_cbor(value: &Value, output: &mut Vec<u8>) {
+    const UNSIGNED: u8 = 0;
+    const NEGATIVE: u8 = 1;
+    const TEXT: u8 = 3;
+    const ARRAY: u8 = 4;
+    const MAP: u8 = 5;
+
+    match value {
+        Value::Null => output.push(0xf6),
+        Value::Bool(value) => output.push(if *value { 0xf5 } else { 0xf4 }),
+        Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                cbor_header(output, UNSIGNED, value);
+            } else if let Some(value) = number.as_i64() {
+                // -1 - n
+                cbor_header(output, NEGATIVE, !value as u64);
+            } else {
+                output.push(0xfb);
+                output.extend(number.as_f64().unwrap_or(f64::NAN).to_be_bytes());
+            }
+        }
+        Value::String(text) => {
+            cbor_header(output, TEXT, text.len() as u64);
+            output.extend(text.as_bytes());
+        }
+        Value::Array(values) => {
+            cbor_header(output, ARRAY, values.len() as u64);
+            for value in values {
+                encode_cbor(value, output);
+            }
+        }
+        Value::Object(fields) => {
+            cbor_header(output, MAP, fields.len() as u64);
+            for (key, value) in fields {
+                cbor_header(output, TEXT, key.len() as u64);
+                output.extend(key.as_bytes());
+                encode_cbor(value, output);
+            }
+        }
+    }
+}
+
+/**
+Appends the initial byte of a CBOR item, its major type and its argument: the argument itself
+up to 23, otherwise the number of the bytes following it.
+ */
+fn cbor_header(output: &mut Vec<u8>, major_type: u8, argument: u64) {
+    let major_type = major_type << 5;
+    match argument {
+        0..=23 => output.push(major_type | argument as u8),
+        24..=0xff => output.extend([major_type | 24, argument as u8]),
+        0x100..=0xffff => {
+            output.push(major_type | 25);
+            output.extend((argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            output.push(major_type | 26);
+            output.extend((argument as u32).to_be_bytes());
+        }
+        _ => {
+            output.push(major_type | 27);
+            output.extend(argument.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encoded(format: BinaryFormat, value: Value) -> Vec<u8> {
+        let mut output = Vec::new();
+        encode(format, &value, &mut output);
+        return output;
+    }
+
+    #[test]
+    fn test_encode_msgpack() {
+        let msgpack = |value: Value| encoded(BinaryFormat::MessagePack, value);
+        assert_eq!(vec![0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0x92, 0xc0, 0xc3],
+                   msgpack(json!({"a": 1, "b": [null, true]})));
+        assert_eq!(vec![0xcd, 0x01, 0x00], msgpack(json!(256)));
+        assert_eq!(vec![0xff], msgpack(json!(-1)));
+        assert_eq!(vec![0xd0, 0x80], msgpack(json!(-128)));
+        assert_eq!(vec![0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0], msgpack(json!(0.5)));
+        let text = "x".repeat(40);
+        assert_eq!(vec![0xd9, 40], msgpack(json!(text))[..2].to_vec());
+    }
+
+    #[test]
+    fn test_encode_cbor() {
+        let cbor = |value: Value| encoded(BinaryFormat::Cbor, value);
+        assert_eq!(vec![0xa2, 0x61, b'a', 0x01, 0x61, b'b', 0x82, 0xf6, 0xf5],
+                   cbor(json!({"a": 1, "b": [null, true]})));
+        assert_eq!(vec![0x18, 0x18], cbor(json!(24)));
+        assert_eq!(vec![0x19, 0x01, 0x00], cbor(json!(256)));
+        assert_eq!(vec![0x38, 0x63], cbor(json!(-100)));
+        assert_eq!(vec![0xfb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0], cbor(json!(0.5)));
+    }
+}
@@ -0,0 +1,317 @@
+/*
+ Carving of the files embedded in a blob, see `--carve`: ELF and PE images, zip archives, gzip
+ streams and PNG images found past the start of the data are scanned on their own, named after
+ their kind and offset, `outer.bin→embedded.elf@0x1234`, and addressed from their start. The
+ gzip streams are scanned inflated and the zip archives file by file. The embedded files are
+ carved in turn, as deep as the containers may nest; the rest of the blob is scanned as usual,
+ at its own offsets.
+ */
+
+use std::ffi::{OsStr, OsString};
+use std::io::Read;
+use flate2::bufread::GzDecoder;
+use super::archives;
+use super::limits::{self, ContainerLimits};
+use super::overlay;
+use super::strings::{self, Options, StringSink};
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const PE_MAGIC: &[u8] = b"MZ";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZIP_END_MAGIC: &[u8] = b"PK\x05\x06";
+/// Size of the end of central directory record, without the comment.
+const ZIP_END_SIZE: usize = 22;
+/// The magic number and the deflate method, the only one gzip has.
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b\x08";
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Elf,
+    Pe,
+    Zip,
+    /// A gzip stream, with the data inflated.
+    Gzip(Vec<u8>),
+    Png,
+}
+
+impl Kind {
+    fn extension(&self) -> &'static str {
+        return match self {
+            Kind::Elf => "elf",
+            Kind::Pe => "exe",
+            Kind::Zip => "zip",
+            Kind::Gzip(_) => "gz",
+            Kind::Png => "png",
+        };
+    }
+}
+
+/// A file embedded in the blob, at `start..end`.
+#[derive(Debug, PartialEq)]
+struct Embedded {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+/**
+Prints the strings of the blob, carving the files embedded in it. The input is already reported
+to the sink.
+ */
+pub(crate) fn print_strings_for_blob(
+    name: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    return print_strings_carved(name, data, 1, options, sink);
+}
+
+fn print_strings_carved(
+    name: &OsStr,
+    data: &[u8],
+    depth: usize,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let limits = &options.container_limits;
+    let embedded = match limits.check_depth(depth) {
+        Ok(()) => find_embedded(data, limits),
+        Err(limit) => {
+            limits::warn(&name.to_string_lossy(), &limit);
+            Vec::new()
+        }
+    };
+
+    let mut num_found = 0u64;
+    let mut position = 0;
+    for file in embedded {
+        num_found += strings::scan_strings_in_memory(
+            name, position as u64, &data[position..file.start], options, sink,
+        );
+        position = file.end;
+
+        let mut embedded_name = name.to_os_string();
+        embedded_name.push(format!("→embedded.{}@{:#x}", file.kind.extension(), file.start));
+        let embedded_data = &data[file.start..file.end];
+        num_found += match &file.kind {
+            Kind::Zip => archives::print_strings_for_zip_data(
+                &embedded_name, embedded_data, options, sink,
+            ).unwrap_or_else(|| {
+                return print_strings_for_embedded(
+                    &embedded_name, embedded_data, depth, options, sink,
+                );
+            }),
+            Kind::Gzip(inflated) => {
+                print_strings_for_embedded(&embedded_name, inflated, depth, options, sink)
+            }
+            _ => print_strings_for_embedded(&embedded_name, embedded_data, depth, options, sink),
+        };
+    }
+    num_found += strings::scan_strings_in_memory(
+        name, position as u64, &data[position..], options, sink,
+    );
+    return num_found;
+}
+
+fn print_strings_for_embedded(
+    name: &OsString,
+    data: &[u8],
+    depth: usize,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    sink.on_input(name, Some(data.len() as u64));
+    return print_strings_carved(name, data, depth + 1, options, sink);
+}
+
+/**
+Returns the files embedded in the data, in order and not overlapping. A file at the start is the
+data itself, and so it is not carved.
+ */
+fn find_embedded(data: &[u8], limits: &ContainerLimits) -> Vec<Embedded> {
+    let mut embedded = Vec::new();
+    let mut position = 1;
+    while position < data.len() {
+        match embedded_at(data, position, limits) {
+            Some(file) => {
+                position = file.end;
+                embedded.push(file);
+            }
+            None => position += 1,
+        }
+    }
+    return embedded;
+}
+
+fn embedded_at(data: &[u8], start: usize, limits: &ContainerLimits) -> Option<Embedded> {
+    let rest = &data[start..];
+    let (kind, size) = if rest.starts_with(ELF_MAGIC) {
+        (Kind::Elf, overlay::overlay_start(rest)? as usize)
+    } else if rest.starts_with(PE_MAGIC) {
+        (Kind::Pe, overlay::overlay_start(rest)? as usize)
+    } else if rest.starts_with(ZIP_MAGIC) {
+        (Kind::Zip, zip_size(rest)?)
+    } else if rest.starts_with(GZIP_MAGIC) {
+        let (inflated, size) = inflate_gzip(rest, limits)?;
+        (Kind::Gzip(inflated), size)
+    } else if rest.starts_with(PNG_MAGIC) {
+        (Kind::Png, png_size(rest)?)
+    } else {
+        return None;
+    };
+    if size == 0 {
+        return None;
+    }
+    return Some(Embedded { kind, start, end: start + size });
+}
+
+/**
+Returns the size of the zip archive: it ends with the first end of central directory record and
+its comment.
+ */
+fn zip_size(data: &[u8]) -> Option<usize> {
+    let end = data.windows(ZIP_END_MAGIC.len()).position(|window| window == ZIP_END_MAGIC)?;
+    let comment_size = data.get(end + 20..end + 22)?;
+    let size = end + ZIP_END_SIZE + u16::from_le_bytes([comment_size[0], comment_size[1]]) as usize;
+    return if size <= data.len() { Some(size) } else { None };
+}
+
+/**
+Inflates the gzip stream, returning the data and the size of the stream. A broken stream is not
+carved, nor one which exceeds the expansion limit.
+ */
+fn inflate_gzip(data: &[u8], limits: &ContainerLimits) -> Option<(Vec<u8>, usize)> {
+    let mut decoder = limits.limit_expansion(GzDecoder::new(data), data.len() as u64);
+    let mut inflated = Vec::new();
+    let result = decoder.read_to_end(&mut inflated);
+    if let Some(limit) = decoder.exceeded() {
+        limits::warn("embedded gzip stream", &limit);
+        return None;
+    }
+    result.ok()?;
+    let remaining = decoder.into_inner().into_inner();
+    return Some((inflated, data.len() - remaining.len()));
+}
+
+/**
+Returns the size of the PNG image: its chunks, each a length, a type, the data and a CRC, run to
+the IEND chunk.
+ */
+fn png_size(data: &[u8]) -> Option<usize> {
+    let mut position = PNG_MAGIC.len();
+    loop {
+        let header = data.get(position..position + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        position = position.checked_add(length)?.checked_add(12)?;
+        if position > data.len() {
+            return None;
+        }
+        if &header[4..] == b"IEND" {
+            return Some(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::gzip;
+    use crate::strings::FoundString;
+
+    fn png(text: &[u8]) -> Vec<u8> {
+        let mut image = PNG_MAGIC.to_vec();
+        for (kind, data) in [(&b"tEXt"[..], text), (b"IEND", b"")] {
+            image.extend((data.len() as u32).to_be_bytes());
+            image.extend(kind);
+            image.extend(data);
+            image.extend([0; 4]);
+        }
+        return image;
+    }
+
+    struct Collector {
+        found: Vec<(String, u64, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((
+                found.filename.to_string_lossy().into_owned(),
+                found.address,
+                String::from_utf8_lossy(found.text).into_owned(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_find_embedded() {
+        let elf = std::fs::read("test-resources/a.out").unwrap();
+        let zip = std::fs::read("test-resources/archive.zip").unwrap();
+        let compressed = gzip(b"inflated words");
+        let image = png(b"Comment\0in the image");
+
+        let mut blob = b"header\0".to_vec();
+        let elf_start = blob.len();
+        blob.extend(&elf);
+        blob.extend(b"\0between\0");
+        let gzip_start = blob.len();
+        blob.extend(&compressed);
+        let png_start = blob.len();
+        blob.extend(&image);
+        let zip_start = blob.len();
+        blob.extend(&zip);
+        // a truncated image is not carved
+        blob.extend(&image[..20]);
+
+        let limits = ContainerLimits::default();
+        assert_eq!(
+            vec![
+                Embedded { kind: Kind::Elf, start: elf_start, end: elf_start + elf.len() },
+                Embedded {
+                    kind: Kind::Gzip(b"inflated words".to_vec()),
+                    start: gzip_start,
+                    end: gzip_start + compressed.len(),
+                },
+                Embedded { kind: Kind::Png, start: png_start, end: png_start + image.len() },
+                Embedded { kind: Kind::Zip, start: zip_start, end: zip_start + zip.len() },
+            ],
+            find_embedded(&blob, &limits)
+        );
+        // the data itself is not carved
+        assert_eq!(Vec::<Embedded>::new(), find_embedded(&elf, &limits));
+    }
+
+    #[test]
+    fn test_nested_names() {
+        let inner = [&b"\0inner text\0"[..], &png(b"Title\0deepest text")].concat();
+        let blob = [&b"outer text\0"[..], &gzip(&inner), b"\0trailing text"].concat();
+        let mut collector = Collector { found: Vec::new() };
+        let options = Options::default();
+        print_strings_for_blob(OsStr::new("blob.bin"), &blob, &options, &mut collector);
+
+        let found = |name: &str, address: u64, text: &str| {
+            return (name.to_string(), address, text.to_string());
+        };
+        let trailing_start = blob.len() as u64 - 13;
+        assert_eq!(
+            vec![
+                found("blob.bin", 0, "outer text"),
+                found("blob.bin→embedded.gz@0xb", 1, "inner text"),
+                found("blob.bin→embedded.gz@0xb→embedded.png@0xc", 12, "tEXtTitle"),
+                found("blob.bin→embedded.gz@0xb→embedded.png@0xc", 22, "deepest text"),
+                found("blob.bin→embedded.gz@0xb→embedded.png@0xc", 42, "IEND"),
+                found("blob.bin", trailing_start, "trailing text"),
+            ],
+            collector.found
+        );
+
+        // the nesting is limited like the one of the archives
+        let limits = ContainerLimits { max_depth: 1, ..ContainerLimits::default() };
+        let options = Options { container_limits: limits, ..Options::default() };
+        let mut collector = Collector { found: Vec::new() };
+        print_strings_for_blob(OsStr::new("blob.bin"), &blob, &options, &mut collector);
+        assert!(collector.found.iter().all(|(name, _, _)| !name.contains("png")));
+    }
+}
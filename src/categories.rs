@@ -0,0 +1,174 @@
+/*
+ Built-in classification of the strings (`--classify`, `--only`): every string is tagged with
+ the well-known kinds of data it contains, e.g. `url` or `ipv4`.
+ */
+
+use std::ffi::OsStr;
+use regex::bytes::Regex;
+use super::patterns;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/// Tags of the categories and the patterns of their contents.
+const CATEGORIES: &[(&str, &str)] = &[
+    ("url", patterns::URL),
+    ("email", patterns::EMAIL),
+    ("ipv4", patterns::IPV4),
+    ("ipv6", patterns::IPV6),
+    ("path", patterns::PATH),
+    ("registry-key", patterns::REGISTRY_KEY),
+    ("guid", patterns::GUID),
+    ("format-string", patterns::FORMAT_STRING),
+];
+
+/**
+Tags the strings with the categories of their contents and, with `--only`, drops the strings
+which are in none of the requested categories.
+ */
+pub struct Categorizer {
+    patterns: Vec<(&'static str, Regex)>,
+    /// Tags of the requested categories, empty to keep all the strings
+    only: Vec<&'static str>,
+}
+
+impl Categorizer {
+    /**
+    Accepts the `--only` values: comma-separated category tags, or their plurals, `ips` stands
+    for both `ipv4` and `ipv6`. Categorization is enabled if `classify` is set or any category
+    is requested.
+     */
+    pub fn new(classify: bool, only: &[String]) -> Result<Categorizer, String> {
+        let mut requested = Vec::new();
+        for kind in only.iter().flat_map(|value| value.split(',')) {
+            let tags: Vec<&'static str> = match kind {
+                "ip" | "ips" => vec!["ipv4", "ipv6"],
+                _ => CATEGORIES.iter()
+                    .map(|(tag, _)| *tag)
+                    .filter(|tag| *tag == kind || format!("{}s", tag) == kind)
+                    .collect()
+            };
+            if tags.is_empty() {
+                return Err(format!("invalid argument to --only: {}", kind));
+            }
+            for tag in tags {
+                if !requested.contains(&tag) {
+                    requested.push(tag);
+                }
+            }
+        }
+
+        let mut patterns = Vec::new();
+        if classify || !requested.is_empty() {
+            for (tag, source) in CATEGORIES {
+                patterns.push((*tag, Regex::new(source).unwrap()));
+            }
+        }
+
+        return Ok(Categorizer { patterns, only: requested });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.patterns.is_empty();
+    }
+
+    /**
+    Returns the patterns of the requested categories, to highlight their matches.
+     */
+    pub fn only_patterns(&self) -> Vec<String> {
+        return CATEGORIES.iter()
+            .filter(|(tag, _)| self.only.contains(tag))
+            .map(|(_, source)| source.to_string())
+            .collect();
+    }
+
+    /**
+    Returns the tags of the categories the string is in.
+     */
+    pub fn categorize(&self, string: &[u8]) -> Vec<&'static str> {
+        return self.patterns.iter()
+            .filter(|(_, pattern)| pattern.is_match(string))
+            .map(|(tag, _)| *tag)
+            .collect();
+    }
+}
+
+/**
+Categorizes the strings before passing them to the inner sink.
+ */
+pub struct CategorizingSink<'a> {
+    categorizer: &'a Categorizer,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> CategorizingSink<'a> {
+    pub fn new(categorizer: &'a Categorizer, inner: &'a mut dyn StringSink) -> CategorizingSink<'a> {
+        return CategorizingSink { categorizer, inner };
+    }
+}
+
+impl StringSink for CategorizingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.categorizer.is_empty() {
+            self.inner.on_string(found);
+            return;
+        }
+
+        let categories = self.categorizer.categorize(found.bytes);
+        let only = &self.categorizer.only;
+        if !only.is_empty() && !categories.iter().any(|tag| only.contains(tag)) {
+            return;
+        }
+
+        let mut tags: Vec<String> = found.tags.to_vec();
+        for tag in categories {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        let categorizer = Categorizer::new(true, &[]).unwrap();
+        let categorize = |string: &str| categorizer.categorize(string.as_bytes());
+
+        assert_eq!(vec!["url", "path"], categorize("see https://example.org/docs or /usr/share/doc"));
+        assert_eq!(vec!["email"], categorize("mailto john@mail.org"));
+        assert_eq!(vec!["ipv4"], categorize("connecting to 10.0.0.1"));
+        assert_eq!(vec!["ipv6"], categorize("fe80::1ff:fe23:4567:890a"));
+        assert_eq!(vec!["path"], categorize(r"C:\Windows\System32\kernel32.dll"));
+        assert_eq!(vec!["registry-key"], categorize(r"HKLM\Software\Microsoft"));
+        assert_eq!(vec!["guid"], categorize("{6B29FC40-CA47-1067-B31D-00DD010662DA}"));
+        assert_eq!(vec!["format-string"], categorize("read %zu bytes from %s"));
+        assert_eq!(vec!["format-string"], categorize("read {} bytes from {path}"));
+        assert_eq!(vec!["format-string"], categorize("{0:x8} of {1,-10}"));
+        assert!(categorize("GLIBC_2.2.5").is_empty());
+        assert!(categorize("50% off").is_empty());
+        assert!(categorize("{not a placeholder}").is_empty());
+    }
+
+    #[test]
+    fn test_only() {
+        let categorizer = Categorizer::new(false, &["urls,ips".to_string()]).unwrap();
+        assert_eq!(vec!["url", "ipv4", "ipv6"], categorizer.only);
+        assert!(!categorizer.is_empty());
+        assert_eq!(3, categorizer.only_patterns().len());
+
+        assert!(Categorizer::new(false, &[]).unwrap().is_empty());
+        assert!(Categorizer::new(false, &["passwords".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::ffi::OsStr;
+use encoding_rs::{DecoderResult, Encoding};
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink, Termination};
+use super::utils::wide_char_is_printable;
+
+/**
+Transcodes the strings found by the 8-bit scan from a legacy code page to UTF-8 (`--charset`).
+The parts of a string which are not valid in the code page, or decode to control characters,
+split it; the pieces shorter than the minimum length are dropped.
+ */
+pub struct CharsetSink<'a> {
+    charset: &'static Encoding,
+    options: &'a Options,
+    inner: &'a mut dyn StringSink,
+}
+
+/**
+Returns the code page with the given label, e.g. `shift_jis` or `cp1252`, if the 8-bit scan can
+decode it. The labels are the ones of the web, where `latin1` and `ascii` are windows-1252.
+ */
+pub fn for_label(label: &str) -> Option<&'static Encoding> {
+    return Encoding::for_label(label.as_bytes()).filter(|found| {
+        return *found != encoding_rs::UTF_16LE && *found != encoding_rs::UTF_16BE
+            && *found != encoding_rs::REPLACEMENT;
+    });
+}
+
+/**
+Returns the code page of the character locale of the environment (see `--locale`): the one of
+`LC_ALL`, `LC_CTYPE` or `LANG`, the first of them which is set, as the C library picks it.
+`None` for the C and POSIX locales, whose only printable characters are the ASCII ones.
+ */
+pub fn locale_charset() -> Result<Option<&'static Encoding>, String> {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    return charset_of_locale(&locale);
+}
+
+fn charset_of_locale(locale: &str) -> Result<Option<&'static Encoding>, String> {
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return Ok(None);
+    }
+    // language[_territory][.codeset][@modifier]
+    let name = locale.split('@').next().unwrap_or(locale);
+    let Some((_, codeset)) = name.split_once('.') else {
+        return Err(format!("the locale {} doesn't name its character set", locale));
+    };
+    // the C library names some of them differently from the web, eucJP for euc-jp
+    let found = for_label(codeset).or_else(|| {
+        let rest = codeset.strip_prefix("euc")?;
+        return for_label(&format!("euc-{}", rest));
+    });
+    return match found {
+        Some(found) => Ok(Some(found)),
+        None => Err(format!("unknown character set of the locale {}", locale)),
+    };
+}
+
+impl<'a> CharsetSink<'a> {
+    pub fn new(
+        charset: &'static Encoding,
+        options: &'a Options,
+        inner: &'a mut dyn StringSink,
+    ) -> CharsetSink<'a> {
+        return CharsetSink { charset, options, inner };
+    }
+
+    fn emit(
+        &mut self,
+        found: &FoundString,
+        start: usize,
+        end: usize,
+        text: &str,
+        termination: Termination,
+    ) {
+        // -n counts the characters, not the bytes they are encoded in
+        if text.chars().count() < self.options.min_length as usize {
+            return;
+        }
+
+        let mut piece = found.with_contents(text.as_bytes(), text.as_bytes());
+        piece.address = found.address + start as u64;
+        piece.end_address = found.address + end as u64;
+        piece.termination = termination;
+        self.inner.on_string(&piece);
+    }
+}
+
+impl StringSink for CharsetSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let bytes = found.bytes;
+        let mut decoder = self.charset.new_decoder_without_bom_handling();
+
+        // the bytes are fed one by one to know where every character starts
+        let mut output = [0u8; 16];
+        let mut text = String::new();
+        let mut piece_start = 0usize;
+        let mut char_start = 0usize;
+        let mut position = 0usize;
+
+        while position < bytes.len() {
+            let (result, read, written) = decoder.decode_to_utf8_without_replacement(
+                &bytes[position..position + 1], &mut output, false,
+            );
+
+            match result {
+                DecoderResult::InputEmpty => {
+                    position += read;
+                    if written == 0 {
+                        // in the middle of a multi-byte character
+                        continue;
+                    }
+
+                    let decoded = std::str::from_utf8(&output[..written]).unwrap_or("\u{0}");
+                    let include_all_whitespace = self.options.include_all_whitespace;
+                    if decoded.chars().all(|c| wide_char_is_printable(c, include_all_whitespace)) {
+                        text.push_str(decoded);
+                    } else {
+                        self.emit(found, piece_start, char_start, &text, Termination::NonPrintable);
+                        text.clear();
+                        piece_start = position;
+                    }
+                    char_start = position;
+                }
+                DecoderResult::Malformed(_, consumed_after) => {
+                    // a byte which is not part of the malformed sequence may be left unread
+                    position += read;
+                    self.emit(found, piece_start, char_start, &text, Termination::NonPrintable);
+                    text.clear();
+                    piece_start = position - consumed_after as usize;
+                    char_start = piece_start;
+                }
+                DecoderResult::OutputFull => {
+                    // a single byte can't produce that much output
+                    unreachable!();
+                }
+            }
+        }
+
+        // an incomplete character at the end is not a part of the string
+        self.emit(found, piece_start, char_start, &text, found.termination);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    struct Collector {
+        found: Vec<(u64, u64, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((
+                found.address,
+                found.end_address,
+                String::from_utf8(found.bytes.to_vec()).unwrap(),
+            ));
+        }
+    }
+
+    fn transcode(charset: &'static Encoding, bytes: &[u8]) -> Vec<(u64, u64, String)> {
+        let options = Options::default();
+        let mut collector = Collector { found: Vec::new() };
+        let mut sink = CharsetSink::new(charset, &options, &mut collector);
+
+        sink.on_string(&FoundString {
+            filename: OsStr::new("test"),
+            address: 0x100,
+            end_address: 0x100 + bytes.len() as u64,
+            bytes,
+            text: bytes,
+            encoding: EncodingKind::Bit8,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+
+        return collector.found;
+    }
+
+    #[test]
+    fn test_charset_of_locale() {
+        assert_eq!(Ok(None), charset_of_locale("C"));
+        assert_eq!(Ok(None), charset_of_locale(""));
+        assert_eq!(Ok(Some(encoding_rs::UTF_8)), charset_of_locale("C.UTF-8"));
+        assert_eq!(
+            Ok(Some(encoding_rs::ISO_8859_15)),
+            charset_of_locale("de_DE.ISO-8859-15@euro")
+        );
+        assert_eq!(Ok(Some(encoding_rs::EUC_JP)), charset_of_locale("ja_JP.eucJP"));
+        assert!(charset_of_locale("en_US").is_err());
+        assert!(charset_of_locale("en_US.UTF-16").is_err());
+    }
+
+    #[test]
+    fn test_transcode_shift_jis() {
+        // "ファイル名" (file name) followed by an incomplete character
+        let bytes = b"\x83\x74\x83\x40\x83\x43\x83\x8b\x96\xbc\x83";
+
+        assert_eq!(
+            vec![(0x100, 0x10a, "ファイル名".to_string())],
+            transcode(encoding_rs::SHIFT_JIS, bytes)
+        );
+    }
+
+    #[test]
+    fn test_transcode_min_length_in_characters() {
+        // "名前" (name) takes 4 bytes, but it's 2 characters
+        assert_eq!(
+            Vec::<(u64, u64, String)>::new(),
+            transcode(encoding_rs::SHIFT_JIS, b"\x96\xbc\x91\x4f")
+        );
+        assert_eq!(
+            vec![(0x100, 0x108, "名前です".to_string())],
+            transcode(encoding_rs::SHIFT_JIS, b"\x96\xbc\x91\x4f\x82\xc5\x82\xb7")
+        );
+    }
+
+    #[test]
+    fn test_transcode_splits_at_invalid_bytes() {
+        // "Привет" in KOI8-R, then a byte which is never valid in EUC-JP
+        assert_eq!(
+            vec![(0x100, 0x106, "Привет".to_string())],
+            transcode(encoding_rs::KOI8_R, b"\xf0\xd2\xc9\xd7\xc5\xd4")
+        );
+        assert_eq!(
+            vec![(0x100, 0x104, "test".to_string()), (0x105, 0x10a, "valid".to_string())],
+            transcode(encoding_rs::EUC_JP, b"test\xffvalid")
+        );
+    }
+}
@@ -0,0 +1,360 @@
+/*
+ Checkpoints of the long scans, see `--checkpoint FILE`: while a large file or a disk image is
+ scanned, the offset to resume the scan at is saved to FILE every few seconds, so that a scan
+ which is interrupted continues from there when it is run again instead of from the start. A
+ scan which ends removes its checkpoint.
+
+ Nothing is pending in the scan at the end of the string last reported, so the scan resumes
+ there, unless no string is reported for a long stretch: then it resumes a little before the
+ position of the reading, which only a string longer than that would run across. The strings
+ reported between the last checkpoint and the interruption are reported again.
+ */
+
+use std::cell::Cell;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use super::diagnostics;
+use super::packages::PackageInfo;
+use super::sparse::{self, LARGE_BUFFER_SIZE};
+use super::strings::{self, FoundString, Options, StringSink};
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of bytes read between the checks whether to save the checkpoint.
+const CHECK_INTERVAL: u64 = 1 << 20;
+/// How far before the reading a string in progress may start, see `resume_offset`.
+const MAX_PENDING: u64 = 64 * 1024;
+
+/**
+The saved state of a scan.
+ */
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct State {
+    /// The input and its size, to tell that the checkpoint is of it.
+    file: String,
+    size: u64,
+    /// The offset the scan resumes at.
+    offset: u64,
+    /// The number of the strings found before the offset.
+    strings: u64,
+}
+
+/**
+The checkpoint of a scan, saved to a file.
+ */
+pub struct Checkpoint {
+    path: PathBuf,
+    /// The state of the interrupted scan, `None` if the scan starts from the beginning.
+    state: Option<State>,
+    save_interval: Duration,
+}
+
+impl Checkpoint {
+    /**
+    Reads the checkpoint of the interrupted scan, or starts a new one if there is no file.
+     */
+    pub fn load(path: &Path) -> Result<Checkpoint, String> {
+        let state = match fs::read(path) {
+            Ok(data) => Some(serde_json::from_slice(&data)
+                .map_err(|err| format!("{}: not a checkpoint: {}", path.display(), err))?),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        };
+        return Ok(Checkpoint { path: path.to_path_buf(), state, save_interval: SAVE_INTERVAL });
+    }
+
+    /**
+    Returns true if an interrupted scan is resumed.
+     */
+    pub fn is_resumed(&self) -> bool {
+        return self.state.is_some();
+    }
+
+    /**
+    Writes the checkpoint next to the file first and renames it over it, so that there is always
+    a whole one.
+     */
+    fn save(&self) -> Result<(), String> {
+        let mut temporary = self.path.as_os_str().to_os_string();
+        temporary.push(".tmp");
+        let data = serde_json::to_vec(&self.state).expect("Couldn't encode the checkpoint");
+        return fs::write(&temporary, data)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|err| format!("{}: {}", self.path.display(), err));
+    }
+}
+
+/**
+Scans the file from the offset of the checkpoint, saving the checkpoint as the scan goes on.
+Returns the number of strings found, the ones before the checkpoint included, or `None` if the
+file couldn't be read; the checkpoint is kept then, to resume the scan once more.
+ */
+pub fn print_strings_with_checkpoint(
+    file_path: &OsStr,
+    checkpoint: &mut Checkpoint,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let path = Path::new(file_path);
+    let report = |err: &io::Error| {
+        diagnostics::error(format_args!("{}: {}", path.display(), err));
+    };
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            report(&err);
+            return None;
+        }
+    };
+    // devices have no size in the metadata
+    let size = match file.metadata() {
+        Ok(metadata) if sparse::is_block_device(&metadata) => sparse::device_size(&file),
+        Ok(metadata) => Ok(metadata.len()),
+        Err(err) => Err(err),
+    };
+    let size = match size {
+        Ok(size) => size,
+        Err(err) => {
+            report(&err);
+            return None;
+        }
+    };
+
+    let name = path.display().to_string();
+    match &checkpoint.state {
+        Some(state) if state.file != name || state.size != size => {
+            diagnostics::error(format_args!(
+                "{}: the checkpoint is of {} ({} bytes)",
+                checkpoint.path.display(), state.file, state.size
+            ));
+            return None;
+        }
+        Some(state) => {
+            if let Err(err) = file.seek(SeekFrom::Start(state.offset)) {
+                report(&err);
+                return None;
+            }
+        }
+        None => checkpoint.state = Some(State { file: name, size, offset: 0, strings: 0 }),
+    }
+
+    let mut reader = BufReader::with_capacity(LARGE_BUFFER_SIZE, file);
+    let (num_found, error) = scan_resumable(
+        file_path, size, &mut reader, checkpoint, options, sink,
+    );
+    if let Some(err) = error {
+        report(&err);
+        return None;
+    }
+    if let Err(err) = fs::remove_file(&checkpoint.path) {
+        if err.kind() != ErrorKind::NotFound {
+            diagnostics::warning(format_args!("{}: {}", checkpoint.path.display(), err));
+        }
+    }
+    return Some(num_found);
+}
+
+/**
+Scans the data of the reader, which is at the offset of the checkpoint. Returns the number of
+strings found with the ones before the checkpoint, and the error which ended the reading early.
+ */
+fn scan_resumable(
+    name: &OsStr,
+    size: u64,
+    reader: &mut dyn Read,
+    checkpoint: &mut Checkpoint,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> (u64, Option<io::Error>) {
+    let state = checkpoint.state.as_ref().expect("The checkpoint is of the input");
+    let (offset, found_before) = (state.offset, state.strings);
+
+    let last_end = Cell::new(offset);
+    let num_reported = Cell::new(found_before);
+    let mut tracked = TrackingReader {
+        inner: reader,
+        position: offset,
+        next_check: offset + CHECK_INTERVAL,
+        last_save: Instant::now(),
+        last_end: &last_end,
+        num_reported: &num_reported,
+        checkpoint,
+    };
+    let mut reported = ReportedStrings {
+        last_end: &last_end,
+        num_reported: &num_reported,
+        inner: sink,
+    };
+    let (num_found, error) = strings::print_strings_for_reader(
+        name, offset, Some(size), &mut tracked, options, &mut reported,
+    );
+    return (found_before + num_found, error);
+}
+
+/**
+Returns the offset to resume the scan at, given the position of the reading and the end of the
+last string reported. The offset is a multiple of 4 unless it is the end of a string, so that
+the scans of the wide encodings stay aligned.
+ */
+fn resume_offset(position: u64, last_end: u64) -> u64 {
+    let pending_start = position.saturating_sub(MAX_PENDING).next_multiple_of(4);
+    return last_end.max(pending_start);
+}
+
+/**
+Counts the bytes read and saves the checkpoint as often as the interval of the checkpoint.
+ */
+struct TrackingReader<'a> {
+    inner: &'a mut dyn Read,
+    position: u64,
+    next_check: u64,
+    last_save: Instant,
+    last_end: &'a Cell<u64>,
+    num_reported: &'a Cell<u64>,
+    checkpoint: &'a mut Checkpoint,
+}
+
+impl TrackingReader<'_> {
+    fn save(&mut self) {
+        let state = self.checkpoint.state.as_mut().expect("The checkpoint is of the input");
+        state.offset = resume_offset(self.position, self.last_end.get());
+        state.strings = self.num_reported.get();
+        if let Err(err) = self.checkpoint.save() {
+            diagnostics::warning(format_args!("{}", err));
+        }
+        self.last_save = Instant::now();
+    }
+}
+
+impl Read for TrackingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.inner.read(buf)?;
+        self.position += num_read as u64;
+        if self.position >= self.next_check {
+            self.next_check = self.position + CHECK_INTERVAL;
+            if self.last_save.elapsed() >= self.checkpoint.save_interval {
+                self.save();
+            }
+        }
+        return Ok(num_read);
+    }
+}
+
+/**
+Keeps the end of the last string reported and the number of the strings.
+ */
+struct ReportedStrings<'a> {
+    last_end: &'a Cell<u64>,
+    num_reported: &'a Cell<u64>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for ReportedStrings<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        self.last_end.set(self.last_end.get().max(found.end_address));
+        self.num_reported.set(self.num_reported.get() + 1);
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collector {
+        found: Vec<(u64, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((found.address, String::from_utf8_lossy(found.text).into_owned()));
+        }
+    }
+
+    /// Fails the reading once the data is read, as an interrupted scan.
+    struct Interrupted<'a> {
+        data: &'a [u8],
+    }
+
+    impl Read for Interrupted<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.data.is_empty() {
+                return Err(io::Error::other("interrupted"));
+            }
+            return self.data.read(buf);
+        }
+    }
+
+    #[test]
+    fn test_resume_offset() {
+        assert_eq!(100, resume_offset(1000, 100));
+        assert_eq!(200_004, resume_offset(200_000 + MAX_PENDING + 1, 100));
+        assert_eq!(200_003, resume_offset(200_000 + MAX_PENDING, 200_003));
+    }
+
+    #[test]
+    fn test_resumed_scan() {
+        let mut data = vec![0u8; 3 << 20];
+        for index in 0..30 {
+            let text = format!("string number {}", index);
+            let position = index * 100_000 + 7;
+            data[position..position + text.len()].copy_from_slice(text.as_bytes());
+        }
+        let name = OsStr::new("image");
+        let options = Options::default();
+        let path = std::env::temp_dir().join(format!("strings-checkpoint-{}", std::process::id()));
+        let size = data.len() as u64;
+        let new_checkpoint = || {
+            let state = State { file: "image".to_string(), size, offset: 0, strings: 0 };
+            let path = path.clone();
+            return Checkpoint { path, state: Some(state), save_interval: Duration::ZERO };
+        };
+
+        let mut whole = Collector { found: Vec::new() };
+        let mut checkpoint = new_checkpoint();
+        let (num_found, error) = scan_resumable(
+            name, size, &mut &data[..], &mut checkpoint, &options, &mut whole,
+        );
+        assert!(error.is_none());
+        assert_eq!(30, num_found);
+
+        // interrupted after 2.5 MiB, past the checkpoints at 1 and 2 MiB
+        let mut interrupted = Collector { found: Vec::new() };
+        let mut checkpoint = new_checkpoint();
+        let mut reader = Interrupted { data: &data[..5 << 19] };
+        let (_, error) = scan_resumable(
+            name, size, &mut reader, &mut checkpoint, &options, &mut interrupted,
+        );
+        assert!(error.is_some());
+
+        let mut checkpoint = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        checkpoint.save_interval = Duration::ZERO;
+        let state = checkpoint.state.as_ref().unwrap();
+        // the last string before 2 MiB ends at 2_000_023, more than MAX_PENDING before it
+        assert_eq!((2 * 1024 * 1024 - MAX_PENDING, 21), (state.offset, state.strings));
+        let offset = state.offset as usize;
+
+        let mut resumed = Collector { found: Vec::new() };
+        let (num_found, error) = scan_resumable(
+            name, size, &mut &data[offset..], &mut checkpoint, &options, &mut resumed,
+        );
+        assert!(error.is_none());
+        assert_eq!(30, num_found);
+        let mut found = interrupted.found;
+        found.truncate(21);
+        found.extend(resumed.found);
+        assert_eq!(whole.found, found);
+    }
+}
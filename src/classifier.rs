@@ -0,0 +1,255 @@
+/*
+ External string classifiers loaded from WASM modules (`--classifier plugin.wasm`).
+
+ A classifier module must export:
+  - `memory`: its linear memory;
+  - `alloc(len: i32) -> i32`: returns the address of a buffer of `len` bytes in `memory`, the
+    string to classify is copied there;
+  - `classify(ptr: i32, len: i32) -> i64`: classifies the string at `ptr`, returns the address of
+    the result in the high 32 bits and its length in the low 32 bits. The result is a list of
+    tags separated by commas or newlines, an empty result means no tags.
+
+ Each call may reuse the memory of the previous one. Modules run sandboxed: no host functions
+ are provided, so a module importing anything fails to load, and every call is limited both in
+ memory and in the number of executed instructions.
+ */
+
+use std::ffi::OsStr;
+use std::path::Path;
+use super::diagnostics;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+#[cfg(feature = "wasm-classifier")]
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+/// Maximum size of the linear memory of a classifier.
+#[cfg(feature = "wasm-classifier")]
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+/// Fuel (roughly, the number of instructions) available to a single `classify` call.
+#[cfg(feature = "wasm-classifier")]
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+#[cfg(feature = "wasm-classifier")]
+pub struct WasmClassifier {
+    name: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    classify: TypedFunc<(i32, i32), i64>,
+}
+
+#[cfg(feature = "wasm-classifier")]
+impl WasmClassifier {
+    pub fn load(path: &Path) -> Result<WasmClassifier, String> {
+        let wasm = std::fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        return WasmClassifier::from_bytes(&path.display().to_string(), &wasm);
+    }
+
+    fn from_bytes(name: &str, wasm: &[u8]) -> Result<WasmClassifier, String> {
+        let fail = |err: wasmi::Error| format!("{}: {}", name, err);
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(fail)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(|err| format!("{}: {}", name, err))?;
+
+        // nothing is defined in the linker, so the module can't reach the outside world
+        let linker = Linker::<StoreLimits>::new(&engine);
+        let instance = linker.instantiate(&mut store, &module).map_err(fail)?
+            .start(&mut store).map_err(fail)?;
+
+        let memory = instance.get_memory(&store, "memory").ok_or_else(
+            || format!("{}: module doesn't export \"memory\"", name)
+        )?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").map_err(fail)?;
+        let classify = instance.get_typed_func::<(i32, i32), i64>(&store, "classify").map_err(fail)?;
+
+        return Ok(WasmClassifier {
+            name: name.to_string(),
+            store,
+            memory,
+            alloc,
+            classify,
+        });
+    }
+
+    /**
+    Returns the tags the module assigns to the given string.
+     */
+    pub fn classify(&mut self, bytes: &[u8]) -> Result<Vec<String>, String> {
+        let name = &self.name;
+        let fail = |err: wasmi::Error| format!("{}: {}", name, err);
+
+        self.store.set_fuel(FUEL_PER_CALL).map_err(|err| format!("{}: {}", name, err))?;
+
+        let len = i32::try_from(bytes.len()).map_err(
+            |_| format!("{}: string is too long", name)
+        )?;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(fail)?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, bytes).map_err(
+            |err| format!("{}: alloc returned a bad buffer: {}", name, err)
+        )?;
+
+        let result = self.classify.call(&mut self.store, (ptr, len)).map_err(fail)? as u64;
+        let result_ptr = (result >> 32) as usize;
+        let result_len = (result & 0xffff_ffff) as usize;
+
+        let data = self.memory.data(&self.store);
+        let result = data.get(result_ptr..result_ptr + result_len).ok_or_else(
+            || format!("{}: classify returned a bad buffer", name)
+        )?;
+
+        return Ok(String::from_utf8_lossy(result)
+            .split([',', '\n'])
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect());
+    }
+}
+
+/**
+Stands in for the classifier when the crate is built without the `wasm-classifier` feature.
+It can't be created, so `--classifier` fails right away.
+ */
+#[cfg(not(feature = "wasm-classifier"))]
+pub enum WasmClassifier {}
+
+#[cfg(not(feature = "wasm-classifier"))]
+impl WasmClassifier {
+    pub fn load(path: &Path) -> Result<WasmClassifier, String> {
+        return Err(format!(
+            "{}: strings was built without WASM classifier support (the wasm-classifier feature)",
+            path.display()
+        ));
+    }
+
+    pub fn classify(&mut self, _bytes: &[u8]) -> Result<Vec<String>, String> {
+        match *self {}
+    }
+}
+
+/**
+Tags the strings with every classifier before passing them to the inner sink.
+ */
+pub struct ClassifyingSink<'a> {
+    classifiers: &'a mut [WasmClassifier],
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> ClassifyingSink<'a> {
+    pub fn new(
+        classifiers: &'a mut [WasmClassifier],
+        inner: &'a mut dyn StringSink,
+    ) -> ClassifyingSink<'a> {
+        return ClassifyingSink { classifiers, inner };
+    }
+}
+
+impl StringSink for ClassifyingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.classifiers.is_empty() {
+            self.inner.on_string(found);
+            return;
+        }
+
+        let mut tags: Vec<String> = found.tags.to_vec();
+        for classifier in self.classifiers.iter_mut() {
+            match classifier.classify(found.bytes) {
+                Ok(new_tags) => {
+                    for tag in new_tags {
+                        if !tags.contains(&tag) {
+                            tags.push(tag);
+                        }
+                    }
+                }
+                Err(err) => {
+                    diagnostics::warning(format_args!(
+                        "classifier failed at offset {:#x}: {}", found.address, err
+                    ));
+                }
+            }
+        }
+
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(all(test, feature = "wasm-classifier"))]
+mod tests {
+    use super::*;
+
+    const PATH_CLASSIFIER: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "path,absolute")
+          (func (export "alloc") (param i32) (result i32)
+            (i32.const 1024))
+          (func (export "classify") (param $ptr i32) (param $len i32) (result i64)
+            (if (result i64) (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 47))
+              (then (i64.const 13))
+              (else (i64.const 0)))))
+    "#;
+
+    fn load(source: &str) -> Result<WasmClassifier, String> {
+        return WasmClassifier::from_bytes("test.wasm", &wat::parse_str(source).unwrap());
+    }
+
+    #[test]
+    fn test_classify() {
+        let mut classifier = load(PATH_CLASSIFIER).unwrap();
+
+        assert_eq!(
+            vec!["path".to_string(), "absolute".to_string()],
+            classifier.classify(b"/usr/lib").unwrap()
+        );
+        assert!(classifier.classify(b"GLIBC_2.2.5").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_classify_runs_out_of_fuel() {
+        let mut classifier = load(r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32)
+                (i32.const 0))
+              (func (export "classify") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                (i64.const 0)))
+        "#).unwrap();
+
+        assert!(classifier.classify(b"string").is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_imports() {
+        let loaded = load(r#"
+            (module
+              (import "env" "open" (func (param i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32)
+                (i32.const 0))
+              (func (export "classify") (param i32 i32) (result i64)
+                (i64.const 0)))
+        "#);
+
+        assert!(loaded.is_err());
+    }
+}
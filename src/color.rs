@@ -0,0 +1,44 @@
+/*
+ Colouring of the text output, see `--color`. The colours are the ones of grep: magenta file
+ names, green offsets, bold red matches of `--match`, and red on white for the characters
+ highlighted with `-u highlight`.
+ */
+
+use atty::Stream;
+
+pub const FILENAME: &str = "\x1B[35m";
+pub const OFFSET: &str = "\x1B[32m";
+pub const MATCH: &str = "\x1B[01;31m";
+pub const HIGHLIGHT: &str = "\x1B[31;47m";
+/// Back to the default colour.
+pub const RESET: &str = "\x1B[0m";
+
+/**
+Decides if the output is coloured for `--color=WHEN`: `always`, `never`, or `auto` to colour
+it if stdout is a terminal and the `NO_COLOR` environment variable is not set (or empty).
+ */
+pub fn use_color(when: &str) -> Result<bool, String> {
+    return match when {
+        "always" => Ok(true),
+        "never" => Ok(false),
+        "auto" => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            Ok(!no_color && atty::is(Stream::Stdout))
+        }
+        wrong => Err(format!(
+            "invalid argument to --color: {}, expected one of auto, always, never", wrong
+        ))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_color() {
+        assert_eq!(Ok(true), use_color("always"));
+        assert_eq!(Ok(false), use_color("never"));
+        assert!(use_color("yes").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ The strings shared by the inputs, see `--common-across N`: the strings are collected from all
+ the inputs and, once they are scanned, the ones found in at least N of them are printed with
+ the inputs they are found in, e.g. the fingerprints of a library linked into the samples of a
+ malware corpus. The strings found in the most inputs come first.
+ */
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::Write;
+use serde::Serialize;
+use super::diagnostics::check_write;
+use super::strings::{FoundString, StringSink};
+
+/**
+Collects the inputs every string is found in. The input being scanned is told by
+`current_input`, an index into the names of the inputs, so that the files inside an archive or
+an image count as their input.
+ */
+pub struct CommonStrings<'a> {
+    current_input: &'a Cell<usize>,
+    /// The strings in the order they are first found, with the inputs they are found in.
+    strings: Vec<(String, Vec<usize>)>,
+    positions: HashMap<String, usize>,
+}
+
+impl<'a> CommonStrings<'a> {
+    pub fn new(current_input: &'a Cell<usize>) -> CommonStrings<'a> {
+        return CommonStrings { current_input, strings: Vec::new(), positions: HashMap::new() };
+    }
+
+    /**
+    Returns the strings found in at least `min_inputs` inputs with the indexes of the inputs,
+    the ones found in the most inputs first, otherwise in the order they are found.
+     */
+    pub fn common(&self, min_inputs: usize) -> Vec<(&str, &[usize])> {
+        let mut common: Vec<(&str, &[usize])> = self.strings.iter()
+            .filter(|(_, inputs)| inputs.len() >= min_inputs)
+            .map(|(text, inputs)| (text.as_str(), inputs.as_slice()))
+            .collect();
+        common.sort_by_key(|(_, inputs)| std::cmp::Reverse(inputs.len()));
+        return common;
+    }
+}
+
+impl StringSink for CommonStrings<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let input = self.current_input.get();
+        let text = String::from_utf8_lossy(found.text);
+        match self.positions.get(text.as_ref()) {
+            Some(position) => {
+                let inputs = &mut self.strings[*position].1;
+                // the inputs are scanned one after another
+                if inputs.last() != Some(&input) {
+                    inputs.push(input);
+                }
+            }
+            None => {
+                self.positions.insert(text.to_string(), self.strings.len());
+                self.strings.push((text.into_owned(), vec![input]));
+            }
+        }
+    }
+}
+
+/**
+Prints a line per common string, the names of the inputs it is found in before it, as `-f`
+prints the name of the file.
+ */
+pub fn print_common(common: &[(&str, &[usize])], names: &[String], writer: &mut dyn Write) {
+    for (text, inputs) in common {
+        let found_in: Vec<&str> = inputs.iter().map(|input| names[*input].as_str()).collect();
+        check_write(writeln!(writer, "{}: {}", found_in.join(", "), text));
+    }
+}
+
+/**
+A common string as a JSON line.
+ */
+#[derive(Serialize)]
+struct CommonRecord<'a> {
+    string: &'a str,
+    files: Vec<&'a str>,
+}
+
+/**
+Prints the common strings as JSON lines, each with the list of the inputs it is found in.
+ */
+pub fn print_common_json(common: &[(&str, &[usize])], names: &[String], writer: &mut dyn Write) {
+    for (string, inputs) in common {
+        let files = inputs.iter().map(|input| names[*input].as_str()).collect();
+        check_write(serde_json::to_writer(&mut *writer, &CommonRecord { string, files }));
+        check_write(writer.write_all(b"\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use crate::strings::{EncodingKind, Termination};
+
+    fn found(text: &str) -> FoundString<'_> {
+        return FoundString {
+            filename: OsStr::new("input"),
+            address: 0,
+            end_address: text.len() as u64,
+            bytes: text.as_bytes(),
+            text: text.as_bytes(),
+            encoding: EncodingKind::Bit7,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        };
+    }
+
+    #[test]
+    fn test_common_strings() {
+        let current_input = Cell::new(0);
+        let mut collector = CommonStrings::new(&current_input);
+        for (input, texts) in [
+            (0, &["libfoo 1.0", "only here", "libfoo 1.0"][..]),
+            (1, &["libbar", "libfoo 1.0"]),
+            (2, &["libfoo 1.0", "libbar"]),
+        ] {
+            current_input.set(input);
+            for text in texts {
+                collector.on_string(&found(text));
+            }
+        }
+        assert_eq!(
+            vec![("libfoo 1.0", &[0, 1, 2][..]), ("libbar", &[1, 2][..])],
+            collector.common(2)
+        );
+        assert_eq!(3, collector.common(1).len());
+
+        let names = vec!["a.exe".to_string(), "b.exe".to_string(), "c.exe".to_string()];
+        let mut output = Vec::new();
+        print_common(&collector.common(2), &names, &mut output);
+        assert_eq!(
+            "a.exe, b.exe, c.exe: libfoo 1.0\nb.exe, c.exe: libbar\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ Default options: the config file (`~/.config/strings-rust/config.toml`) and the `STRINGS_OPTS`
+ environment variable. The config file maps the long option names to their values:
+
+     bytes = 8
+     radix = "x"
+     encoding = ["s", "l"]
+     print-file-name = true
+
+ `STRINGS_OPTS` holds the options as they are written on the command line, e.g. `-n 8 -t x`.
+ The command line overrides `STRINGS_OPTS`, which overrides the config file: an option set in
+ a later source replaces the same option of the earlier ones, all of its values if it can be
+ repeated, and drops their options which conflict with it, e.g. `--output-format` drops
+ `format = "json"`. So do the options of a group of exclusive ones, which clap accepts together
+ with the last one winning or with a fixed precedence, e.g. `-a` drops `data = true`.
+ */
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use clap::{Arg, ArgMatches, Command, ValueSource};
+use super::response_files::split_args;
+use super::utils::{os_str_bytes, os_string_from_bytes};
+
+/// The environment variable with the default options.
+pub const ENV_VAR: &str = "STRINGS_OPTS";
+
+/**
+Returns the path of the config file: `strings-rust/config.toml` in `$XDG_CONFIG_HOME`, in
+`~/.config` if it is not set, or in `%APPDATA%` on Windows.
+ */
+pub fn config_path() -> Option<PathBuf> {
+    let directory = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(directory) => PathBuf::from(directory),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    return Some(directory.join("strings-rust").join("config.toml"));
+}
+
+/**
+Converts the config file to command line arguments: `--key=value` for every value, `--key` for
+`true`. The keys set to `false` are left out. Returns an error message if the file is not valid
+TOML or has a value which is not a string, a number, a boolean or an array of them.
+ */
+pub fn config_args(text: &str) -> Result<Vec<OsString>, String> {
+    let table = text.parse::<toml::Table>().map_err(|err| err.to_string())?;
+
+    let mut args = Vec::new();
+    for (key, value) in &table {
+        let values = match value {
+            toml::Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(true) => {
+                    args.push(OsString::from(format!("--{}", key)));
+                    continue;
+                }
+                toml::Value::Boolean(false) => continue,
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                _ => return Err(format!("unsupported value of '{}'", key)),
+            };
+            args.push(OsString::from(format!("--{}={}", key, value)));
+        }
+    }
+    return Ok(args);
+}
+
+/**
+Splits the value of `STRINGS_OPTS` into arguments, with the quoting of the response files.
+ */
+pub fn env_args(value: &OsStr) -> Vec<OsString> {
+    let bytes = os_str_bytes(value);
+    return split_args(&bytes).into_iter().map(os_string_from_bytes).collect();
+}
+
+/**
+Returns the command line with the default options of `sources` inserted after the program
+name. The sources are named by their first item for the error messages and are ordered from
+the weakest to the strongest, the command line itself being the strongest one. `exclusive`
+lists the ids of the groups of options which override each other. Returns an error message if a
+source doesn't parse or sets anything but options.
+ */
+pub fn merge_args(
+    command: &Command,
+    exclusive: &[&[&str]],
+    sources: &[(String, Vec<OsString>)],
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, String> {
+    let mut args = args.into_iter();
+    let program = args.next().unwrap_or_default();
+    let args: Vec<OsString> = args.collect();
+
+    // the options of every source, the command line is only checked for what it sets
+    let mut parsed = Vec::new();
+    for (name, source_args) in sources {
+        let matches = parse(command, &program, source_args)
+            .map_err(|err| format!("{}: {}", name, err))?;
+        if command.get_arguments().any(|arg| arg.is_positional() && is_set(&matches, arg)) {
+            return Err(format!("{}: only options can be set", name));
+        }
+        parsed.push(matches);
+    }
+    // the command line errors are reported by the final parse
+    let overriding = parse(command, &program, &args).ok();
+
+    let mut merged = vec![program];
+    for (i, matches) in parsed.iter().enumerate() {
+        let mut overridden = HashSet::new();
+        for stronger in parsed[i + 1..].iter().chain(overriding.iter()) {
+            for arg in command.get_arguments() {
+                if is_set(stronger, arg) {
+                    overridden.extend(overridden_by(command, exclusive, arg));
+                }
+            }
+        }
+
+        for arg in command.get_arguments() {
+            if is_set(matches, arg) && !overridden.contains(arg.get_id()) {
+                merged.extend(to_args(matches, arg));
+            }
+        }
+    }
+    merged.extend(args);
+    return Ok(merged);
+}
+
+/**
+Returns the ids of the options which the option replaces when it is set in a stronger source:
+the option itself, the ones it conflicts with either way and the other ones of its exclusive
+groups.
+ */
+fn overridden_by<'a>(command: &'a Command, exclusive: &[&[&'a str]], arg: &'a Arg) -> Vec<&'a str> {
+    let mut ids = vec![arg.get_id()];
+    ids.extend(command.get_arg_conflicts_with(arg).iter().map(|other| other.get_id()));
+    for other in command.get_arguments() {
+        if command.get_arg_conflicts_with(other).iter().any(|c| c.get_id() == arg.get_id()) {
+            ids.push(other.get_id());
+        }
+    }
+    for group in exclusive.iter().filter(|group| group.contains(&arg.get_id())) {
+        ids.extend(group.iter());
+    }
+    return ids;
+}
+
+fn parse(command: &Command, program: &OsStr, args: &[OsString]) -> Result<ArgMatches, String> {
+    let all_args = std::iter::once(program).chain(args.iter().map(OsString::as_os_str));
+    return command.clone().try_get_matches_from(all_args).map_err(|err| {
+        // the first line, without the usage which is about the command line
+        let message = err.to_string();
+        return message.lines().next().unwrap_or("").trim_start_matches("error: ").to_string();
+    });
+}
+
+fn is_set(matches: &ArgMatches, arg: &clap::Arg) -> bool {
+    let builtin = matches!(arg.get_id(), "help" | "version");
+    return !builtin && matches.value_source(arg.get_id()) == Some(ValueSource::CommandLine);
+}
+
+/**
+Writes the values of the option back as arguments.
+ */
+fn to_args(matches: &ArgMatches, arg: &clap::Arg) -> Vec<OsString> {
+    let name = match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) => format!("--{}", long),
+        (None, Some(short)) => format!("-{}", short),
+        (None, None) => return Vec::new(),
+    };
+    if !arg.is_takes_value_set() {
+        return vec![OsString::from(name)];
+    }
+
+    let mut args = Vec::new();
+    for value in matches.get_raw(arg.get_id()).into_iter().flatten() {
+        let mut with_value = OsString::from(format!("{}=", name));
+        with_value.push(value);
+        args.push(with_value);
+    }
+    return args;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command<'static> {
+        return Command::new("strings")
+            .arg(Arg::new("bytes").short('n').long("bytes").takes_value(true))
+            .arg(Arg::new("radix").short('t').long("radix").takes_value(true))
+            .arg(Arg::new("encoding").short('e').long("encoding").takes_value(true)
+                .multiple_occurrences(true))
+            .arg(Arg::new("print_file_name").short('f').long("print-file-name"))
+            .arg(Arg::new("octal_radix").short('o'))
+            .arg(Arg::new("all").short('a').long("all"))
+            .arg(Arg::new("data").short('d').long("data"))
+            .arg(Arg::new("segments").long("segments").conflicts_with("data"))
+            .arg(Arg::new("format").long("format").takes_value(true))
+            .arg(Arg::new("output_format").long("output-format").takes_value(true)
+                .conflicts_with("format"))
+            .arg(Arg::new("files").multiple_values(true));
+    }
+
+    const EXCLUSIVE: &[&[&str]] = &[&["all", "data", "segments"], &["octal_radix", "radix"]];
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        return args.iter().map(OsString::from).collect();
+    }
+
+    #[test]
+    fn test_config_args() {
+        let args = config_args(
+            "bytes = 8\nradix = \"x\"\nencoding = [\"s\", \"l\"]\nprint-file-name = true\n\
+             include-all-whitespace = false\n"
+        ).unwrap();
+        // the keys come sorted
+        assert_eq!(
+            os_args(&[
+                "--bytes=8", "--encoding=s", "--encoding=l", "--print-file-name", "--radix=x",
+            ]),
+            args
+        );
+
+        assert!(config_args("bytes = ").is_err());
+        assert!(config_args("[table]\nkey = 1").is_err());
+    }
+
+    #[test]
+    fn test_merge_args() {
+        let config_args = os_args(&["--bytes=8", "--radix=x", "--encoding=s", "-f"]);
+        let config = ("config".to_string(), config_args);
+        let env = (ENV_VAR.to_string(), env_args(OsStr::new("-e l -e 'b' -n 6")));
+
+        assert_eq!(
+            os_args(&["strings", "--radix=x", "--print-file-name", "--bytes=6", "--encoding=l",
+                      "--encoding=b", "a.out"]),
+            merge_args(&command(), EXCLUSIVE, &[config.clone(), env.clone()], os_args(&["strings", "a.out"]))
+                .unwrap()
+        );
+        assert_eq!(
+            os_args(&["strings", "--radix=x", "--print-file-name", "-e", "L", "-n", "3"]),
+            merge_args(&command(), EXCLUSIVE, &[config, env], os_args(&["strings", "-e", "L", "-n", "3"]))
+                .unwrap()
+        );
+
+        let with_file = (ENV_VAR.to_string(), os_args(&["a.out"]));
+        assert!(merge_args(&command(), EXCLUSIVE, &[with_file], os_args(&["strings"])).is_err());
+        let unknown = ("config".to_string(), os_args(&["--unknown=1"]));
+        assert!(merge_args(&command(), EXCLUSIVE, &[unknown], os_args(&["strings"])).is_err());
+    }
+
+    #[test]
+    fn test_merge_args_conflicts_and_exclusive() {
+        let merge = |config: &[&str], args: &[&str]| {
+            let config = ("config".to_string(), os_args(config));
+            return merge_args(&command(), EXCLUSIVE, &[config], os_args(args)).unwrap();
+        };
+
+        // exclusive, the weaker one would win if both were kept
+        assert_eq!(os_args(&["strings", "-o"]), merge(&["--radix=x"], &["strings", "-o"]));
+        assert_eq!(os_args(&["strings", "-t", "d"]), merge(&["-o"], &["strings", "-t", "d"]));
+        assert_eq!(os_args(&["strings", "-a"]), merge(&["--data"], &["strings", "-a"]));
+        // conflicting, clap would fail if both were kept
+        assert_eq!(
+            os_args(&["strings", "--output-format", "{string}"]),
+            merge(&["--format=json"], &["strings", "--output-format", "{string}"])
+        );
+        assert_eq!(os_args(&["strings", "-d"]), merge(&["--segments"], &["strings", "-d"]));
+        assert_eq!(
+            os_args(&["strings", "--format=json"]),
+            merge(&["--output-format={string}"], &["strings", "--format=json"])
+        );
+        // the others are kept
+        assert_eq!(
+            os_args(&["strings", "--bytes=8", "-d"]),
+            merge(&["--bytes=8", "--segments"], &["strings", "-d"])
+        );
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ ELF core dumps, scanned with -d and --segments: they have no sections, and their PT_LOAD
+ segments are the memory of the process, so the strings are addressed with the virtual addresses
+ of the segments. The NT_FILE note of the dump tells which files were mapped where, and the
+ strings found in the memory of a mapped file are tagged with `file:PATH`.
+ */
+
+use std::ffi::OsStr;
+use object::elf::{FileHeader32, FileHeader64, ET_CORE, NT_FILE};
+use object::read::elf::{FileHeader, ProgramHeader};
+use object::{Endianness, FileKind};
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/// A file mapped into the memory of the process, from the NT_FILE note.
+#[derive(Debug, PartialEq)]
+pub(crate) struct FileMapping {
+    pub start: u64,
+    pub end: u64,
+    pub path: String,
+}
+
+/**
+Returns the files mapped into the memory of the process if the data is an ELF core dump, `None`
+if it is not one.
+ */
+pub(crate) fn file_mappings(data: &[u8]) -> Option<Vec<FileMapping>> {
+    return match FileKind::parse(data).ok()? {
+        FileKind::Elf32 => elf_file_mappings::<FileHeader32<Endianness>>(data),
+        FileKind::Elf64 => elf_file_mappings::<FileHeader64<Endianness>>(data),
+        _ => None,
+    };
+}
+
+fn elf_file_mappings<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+) -> Option<Vec<FileMapping>> {
+    let header = Elf::parse(data).ok()?;
+    let endian = header.endian().ok()?;
+    if header.e_type(endian) != ET_CORE {
+        return None;
+    }
+
+    let word_size = if header.is_type_64() { 8 } else { 4 };
+    let mut mappings = Vec::new();
+    for segment in header.program_headers(endian, data).ok()? {
+        let Ok(Some(mut notes)) = segment.notes(endian, data) else {
+            continue;
+        };
+        while let Ok(Some(note)) = notes.next() {
+            if note.name() == b"CORE" && note.n_type(endian) == NT_FILE {
+                mappings.extend(parse_file_note(note.desc(), word_size, endian));
+            }
+        }
+    }
+    return Some(mappings);
+}
+
+/**
+Parses the description of an NT_FILE note: the number of the mappings and the page size, the
+start, the end and the file offset of every mapping, then the NUL-terminated paths of them.
+ */
+fn parse_file_note(desc: &[u8], word_size: usize, endian: Endianness) -> Vec<FileMapping> {
+    let word = |index: usize| -> Option<u64> {
+        let bytes = desc.get(index * word_size..(index + 1) * word_size)?;
+        let mut value = 0u64;
+        for i in 0..word_size {
+            let byte = match endian {
+                Endianness::Little => bytes[word_size - 1 - i],
+                Endianness::Big => bytes[i],
+            };
+            value = (value << 8) | byte as u64;
+        }
+        return Some(value);
+    };
+
+    let Some(count) = word(0) else {
+        return Vec::new();
+    };
+    // the paths follow the table, which must fit in the note
+    let table_size = (count as usize).saturating_mul(3).saturating_add(2);
+    let paths_start = table_size.saturating_mul(word_size);
+    let Some(paths) = desc.get(paths_start..) else {
+        return Vec::new();
+    };
+
+    let mut mappings = Vec::new();
+    for (i, path) in paths.split(|byte| *byte == 0).take(count as usize).enumerate() {
+        let (Some(start), Some(end)) = (word(2 + 3 * i), word(3 + 3 * i)) else {
+            break;
+        };
+        mappings.push(FileMapping { start, end, path: String::from_utf8_lossy(path).into_owned() });
+    }
+    return mappings;
+}
+
+/**
+Tags the strings found in the memory of a mapped file with its path, `file:PATH`.
+ */
+pub(crate) struct MappedFileSink<'a> {
+    pub path: &'a str,
+    pub inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for MappedFileSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut tags = found.tags.to_vec();
+        tags.push(format!("file:{}", self.path));
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_mappings() {
+        let data = std::fs::read("test-resources/core.elf").unwrap();
+        assert_eq!(
+            Some(vec![
+                FileMapping { start: 0x400000, end: 0x401000, path: "/usr/bin/demo".into() },
+            ]),
+            file_mappings(&data)
+        );
+        assert_eq!(None, file_mappings(&std::fs::read("test-resources/a.out").unwrap()));
+    }
+
+    #[test]
+    fn test_parse_file_note() {
+        let mut desc = Vec::new();
+        for word in [2u32, 4096, 0x1000, 0x2000, 0, 0x8000, 0x9000, 1] {
+            desc.extend(word.to_be_bytes());
+        }
+        desc.extend(b"/lib/a.so\0/lib/b.so\0");
+        let mapping = |start: u64, end: u64, path: &str| {
+            return FileMapping { start, end, path: path.to_string() };
+        };
+        assert_eq!(
+            vec![mapping(0x1000, 0x2000, "/lib/a.so"), mapping(0x8000, 0x9000, "/lib/b.so")],
+            parse_file_note(&desc, 4, Endianness::Big)
+        );
+        // the table runs past the note
+        assert_eq!(Vec::<FileMapping>::new(), parse_file_note(&desc[..24], 4, Endianness::Big));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::Write;
+use super::diagnostics::check_write;
+use super::hash::HashKind;
+use super::json::{self, StringRecord};
+use super::strings::{FoundString, Options, StringSink};
+
+const HEADER: &str = "file,offset,length,encoding,termination,string,tags,xor_key,decoded_from,score";
+
+/**
+Prints found strings as CSV: a header row, then a row per string. Tags are joined with `;`.
+Package metadata is not printed, it doesn't fit in the rows. With `-u highlight` there is one
+more column with the ranges of the non-ASCII bytes, as `start-end` joined with `;`, after the
+column of the hashes with `--hash`.
+ */
+pub struct CsvPrinter<'a> {
+    writer: &'a mut dyn Write,
+    highlight: bool,
+    hash: Option<HashKind>,
+}
+
+impl<'a> CsvPrinter<'a> {
+    /**
+    Creates the printer and writes the header row.
+     */
+    pub fn new(writer: &'a mut dyn Write, options: &Options) -> CsvPrinter<'a> {
+        let highlight = json::highlights(options);
+        let hash = options.string_hash;
+        let mut header = HEADER.to_string();
+        if hash.is_some() {
+            header.push_str(",hash");
+        }
+        if highlight {
+            header.push_str(",highlight");
+        }
+        check_write(writeln!(writer, "{}", header));
+        return CsvPrinter { writer, highlight, hash };
+    }
+}
+
+impl StringSink for CsvPrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let record = StringRecord::new(found).with_hash(self.hash);
+        let mut fields = vec![
+            escape(&record.file),
+            record.offset.to_string(),
+            record.length.to_string(),
+            record.encoding.to_string(),
+            record.termination.to_string(),
+            escape(&record.string),
+            escape(&record.tags.join(";")),
+            record.xor_key.map(|key| key.to_string()).unwrap_or_default(),
+            record.decoded_from.unwrap_or_default().to_string(),
+            record.score.map(|score| score.to_string()).unwrap_or_default(),
+        ];
+        if let Some(hash) = record.hash {
+            fields.push(hash);
+        }
+        if self.highlight {
+            let ranges = json::non_ascii_ranges(found.bytes).iter()
+                .map(|[start, end]| format!("{}-{}", start, end))
+                .collect::<Vec<String>>();
+            fields.push(ranges.join(";"));
+        }
+        check_write(writeln!(self.writer, "{}", fields.join(",")));
+    }
+}
+
+/**
+Quotes the field if it contains a separator, a quote or a line break (RFC 4180).
+ */
+fn escape(field: &str) -> String {
+    if !field.contains([',', '"', '\n', '\r']) {
+        return field.to_string();
+    }
+    return format!("\"{}\"", field.replace('"', "\"\""));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use crate::strings::{EncodingKind, Termination};
+
+    #[test]
+    fn test_csv_printer() {
+        let tags = vec!["path".to_string(), "url".to_string()];
+        let mut output = Vec::new();
+        let mut printer = CsvPrinter::new(&mut output, &Options::default());
+
+        printer.on_string(&FoundString {
+            filename: OsStr::new("a.out"),
+            address: 0x10,
+            end_address: 0x1c,
+            bytes: b"say \"hi\", ok",
+            text: b"say \"hi\", ok",
+            encoding: EncodingKind::Bit7,
+            tags: &tags,
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Newline,
+            score: Some(0.25),
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+        printer.on_string(&FoundString {
+            filename: OsStr::new("a.out"),
+            address: 0x20,
+            end_address: 0x24,
+            bytes: b"text",
+            text: b"text",
+            encoding: EncodingKind::Bit8,
+            tags: &[],
+            xor_key: Some(7),
+            decoded_from: None,
+            termination: Termination::SectionEnd,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+
+        assert_eq!(
+            concat!(
+                "file,offset,length,encoding,termination,string,tags,xor_key,decoded_from,score\n",
+                "a.out,16,12,7bit,newline,\"say \"\"hi\"\", ok\",path;url,,,0.25\n",
+                "a.out,32,4,8bit,section-end,text,,7,,\n",
+            ),
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ Decoding of the base64 and hex runs inside the found strings (`--decode`). The decoded payload
+ is reported right after the original string if it is printable text, so that random words which
+ happen to be valid base64 don't add noise.
+ */
+
+use std::ffi::OsStr;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink};
+use super::utils::wide_char_is_printable;
+
+/// Shortest run of base64 characters, without the padding, which is decoded.
+const MIN_BASE64_LENGTH: usize = 16;
+/// Shortest run of hex digits which is decoded.
+const MIN_HEX_LENGTH: usize = 16;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DecodeKind {
+    Base64,
+    Hex,
+}
+
+impl DecodeKind {
+    /**
+    Parses the value of `--decode`: one of {base64,hex}.
+     */
+    pub fn parse(value: &str) -> Option<DecodeKind> {
+        return match value {
+            "base64" => Some(DecodeKind::Base64),
+            "hex" => Some(DecodeKind::Hex),
+            _ => None
+        };
+    }
+
+    pub const fn name(&self) -> &'static str {
+        return match self {
+            DecodeKind::Base64 => "base64",
+            DecodeKind::Hex => "hex",
+        };
+    }
+
+    fn is_run_char(&self, byte: u8) -> bool {
+        return match self {
+            DecodeKind::Base64 => base64_value(byte).is_some(),
+            DecodeKind::Hex => byte.is_ascii_hexdigit(),
+        };
+    }
+
+    /**
+    Decodes a run, returns `None` if it isn't long enough or can't be decoded.
+     */
+    fn decode(&self, run: &[u8]) -> Option<Vec<u8>> {
+        return match self {
+            DecodeKind::Base64 => decode_base64(run),
+            DecodeKind::Hex => decode_hex(run),
+        };
+    }
+}
+
+/**
+Passes the strings on, each one followed by the printable payloads of the encoded runs it
+contains, marked with the encoding they were decoded from.
+ */
+pub struct DecodingSink<'a> {
+    options: &'a Options,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> DecodingSink<'a> {
+    pub fn new(options: &'a Options, inner: &'a mut dyn StringSink) -> DecodingSink<'a> {
+        return DecodingSink { options, inner };
+    }
+
+    fn is_printable(&self, decoded: &[u8]) -> bool {
+        return match std::str::from_utf8(decoded) {
+            Ok(text) => text.chars().count() >= self.options.min_length as usize
+                && text.chars().all(|c| wide_char_is_printable(c, self.options.include_all_whitespace)),
+            Err(_) => false
+        };
+    }
+}
+
+impl StringSink for DecodingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        self.inner.on_string(found);
+
+        // the offsets of the runs are known only if every byte of the string is one symbol
+        let num_symbols = found.bytes.len() as u64;
+        let symbol_size = match found.end_address - found.address {
+            size if num_symbols > 0 && size.is_multiple_of(num_symbols) => Some(size / num_symbols),
+            _ => None
+        };
+
+        for kind in &self.options.decode {
+            for (start, end) in find_runs(found.bytes, *kind) {
+                let decoded = match kind.decode(&found.bytes[start..end]) {
+                    Some(decoded) if self.is_printable(&decoded) => decoded,
+                    _ => continue
+                };
+
+                let mut payload = found.with_contents(&decoded, &decoded);
+                if let Some(symbol_size) = symbol_size {
+                    payload.address = found.address + start as u64 * symbol_size;
+                    payload.end_address = found.address + end as u64 * symbol_size;
+                }
+                payload.decoded_from = Some(*kind);
+                self.inner.on_string(&payload);
+            }
+        }
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Returns the ranges of the maximal runs of the encoding's characters, with the base64 padding.
+ */
+fn find_runs(bytes: &[u8], kind: DecodeKind) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut position = 0usize;
+
+    while position < bytes.len() {
+        if !kind.is_run_char(bytes[position]) {
+            position += 1;
+            continue;
+        }
+
+        let start = position;
+        while position < bytes.len() && kind.is_run_char(bytes[position]) {
+            position += 1;
+        }
+        if kind == DecodeKind::Base64 {
+            let mut num_padding = 0;
+            while num_padding < 2 && position < bytes.len() && bytes[position] == b'=' {
+                position += 1;
+                num_padding += 1;
+            }
+        }
+        runs.push((start, position));
+    }
+
+    return runs;
+}
+
+fn base64_value(byte: u8) -> Option<u8> {
+    return match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        // the URL-safe alphabet is accepted as well
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None
+    };
+}
+
+fn decode_base64(run: &[u8]) -> Option<Vec<u8>> {
+    let data = run.strip_suffix(b"==").or_else(|| run.strip_suffix(b"=")).unwrap_or(run);
+    // a single character left over doesn't make a byte
+    if data.len() < MIN_BASE64_LENGTH || data.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut num_bits = 0u32;
+    for byte in data {
+        accumulator = (accumulator << 6) | base64_value(*byte)? as u32;
+        num_bits += 6;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            decoded.push((accumulator >> num_bits) as u8);
+        }
+    }
+
+    return Some(decoded);
+}
+
+fn decode_hex(run: &[u8]) -> Option<Vec<u8>> {
+    if run.len() < MIN_HEX_LENGTH || !run.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let digit = |byte: u8| (byte as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(run.len() / 2);
+    for pair in run.chunks(2) {
+        decoded.push((digit(pair[0])? * 16 + digit(pair[1])?) as u8);
+    }
+
+    return Some(decoded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    struct Collector {
+        found: Vec<(u64, u64, Option<DecodeKind>, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((
+                found.address,
+                found.end_address,
+                found.decoded_from,
+                String::from_utf8(found.bytes.to_vec()).unwrap(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Some(b"hello, world!".to_vec()), decode_base64(b"aGVsbG8sIHdvcmxkIQ=="));
+        assert_eq!(Some(b"hello, world".to_vec()), decode_base64(b"aGVsbG8sIHdvcmxk"));
+        assert_eq!(None, decode_base64(b"aGVsbG8"));
+        assert_eq!(Some(b"password".to_vec()), decode_hex(b"70617373776F7264"));
+        assert_eq!(None, decode_hex(b"70617373776F726"));
+    }
+
+    #[test]
+    fn test_decoding_sink() {
+        let mut options = Options::default();
+        options.decode = vec![DecodeKind::Base64, DecodeKind::Hex];
+        let mut collector = Collector { found: Vec::new() };
+        let mut sink = DecodingSink::new(&options, &mut collector);
+
+        // an identifier is valid base64 too, but it doesn't decode to text
+        let text = b"key=aGVsbG8sIHdvcmxkIQ== id=ConfigurationManager 70617373776f7264";
+        sink.on_string(&FoundString {
+            filename: OsStr::new("test"),
+            address: 0x100,
+            end_address: 0x100 + text.len() as u64,
+            bytes: text,
+            text,
+            encoding: EncodingKind::Bit7,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+
+        assert_eq!(
+            vec![
+                (0x100, 0x141, None, String::from_utf8(text.to_vec()).unwrap()),
+                (0x104, 0x118, Some(DecodeKind::Base64), "hello, world!".to_string()),
+                (0x131, 0x141, Some(DecodeKind::Hex), "password".to_string()),
+            ],
+            collector.found
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::io::{self, BufRead, Cursor, Read, Write};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use ruzstd::decoding::{FrameDecoder, StreamingDecoder};
+use super::limits::{ContainerLimits, ExpandError, InputCounter};
+
+/**
+Compression formats recognized by their magic numbers.
+ */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    pub fn detect(data: &[u8]) -> Option<Compression> {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            return Some(Compression::Gzip);
+        }
+        if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Some(Compression::Xz);
+        }
+        if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(Compression::Zstd);
+        }
+        if data.starts_with(b"BZh") {
+            return Some(Compression::Bzip2);
+        }
+        return None;
+    }
+
+    pub const fn name(&self) -> &'static str {
+        return match self {
+            Compression::Gzip => "gzip",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        };
+    }
+}
+
+/**
+Decompresses the whole data. Concatenated streams (members, frames) are decompressed one after
+another. Stops with an error once the output exceeds the expansion ratio limit.
+ */
+pub fn decompress(
+    compression: Compression,
+    data: &[u8],
+    limits: &ContainerLimits,
+) -> Result<Vec<u8>, ExpandError> {
+    let mut output = limits.limit_expansion(Vec::new(), data.len() as u64);
+
+    let result = decompress_into(compression, data, &mut output);
+
+    if let Some(limit) = output.exceeded() {
+        return Err(ExpandError::Limit(limit));
+    }
+    if let Err(err) = result {
+        return Err(ExpandError::Invalid(
+            format!("{} decompression failed: {}", compression.name(), err)
+        ));
+    }
+    return Ok(output.into_inner());
+}
+
+fn decompress_into<W: Write>(
+    compression: Compression,
+    data: &[u8],
+    output: &mut W,
+) -> Result<(), String> {
+    let mut input = data;
+    match compression {
+        Compression::Gzip => {
+            io::copy(&mut MultiGzDecoder::new(input), output).map_err(|err| err.to_string())?;
+        }
+        Compression::Bzip2 => {
+            io::copy(&mut MultiBzDecoder::new(input), output).map_err(|err| err.to_string())?;
+        }
+        Compression::Xz => {
+            lzma_rs::xz_decompress(&mut input, output).map_err(|err| err.to_string())?;
+        }
+        Compression::Zstd => {
+            while !input.is_empty() {
+                let mut decoder = StreamingDecoder::new(&mut input)
+                    .map_err(|err| err.to_string())?;
+                io::copy(&mut decoder, output).map_err(|err| err.to_string())?;
+            }
+        }
+    }
+    return Ok(());
+}
+
+/**
+Decompresses the data if it starts with a known magic number, otherwise returns it as is.
+ */
+pub fn decompress_if_needed(
+    data: Vec<u8>,
+    limits: &ContainerLimits,
+) -> Result<Vec<u8>, ExpandError> {
+    return match Compression::detect(&data) {
+        Some(compression) => decompress(compression, &data, limits),
+        None => Ok(data)
+    };
+}
+
+/**
+Returns a reader of the decompressed data, decompressing it while it is read, see `--decompress`.
+The xz data is decompressed as a whole first, since lzma-rs has no reader for it. The size of
+the input is not known, so the output is limited by its ratio to the input read so far: the
+reading fails with the `LimitExceeded` error once it grows too much, see `limits::limit_of`.
+ */
+pub fn decompressing_reader<'a>(
+    compression: Compression,
+    input: Box<dyn BufRead + 'a>,
+    limits: &ContainerLimits,
+) -> io::Result<Box<dyn Read + 'a>> {
+    let counter = InputCounter::default();
+    let mut input = counter.count(input);
+    let decompressed: Box<dyn Read + 'a> = match compression {
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(input)),
+        Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(input)),
+        Compression::Zstd => Box::new(ZstdFrames { decoder: None, input: Some(Box::new(input)) }),
+        Compression::Xz => {
+            let mut output = limits.limit_stream_expansion(Vec::new(), &counter);
+            let result = lzma_rs::xz_decompress(&mut input, &mut output);
+            if let Some(limit) = output.exceeded() {
+                return Err(io::Error::other(limit));
+            }
+            result.map_err(io::Error::other)?;
+            Box::new(Cursor::new(output.into_inner()))
+        }
+    };
+    let limited = limits.limit_stream_expansion(decompressed, &counter);
+    return Ok(Box::new(TruncationError { inner: limited }));
+}
+
+/**
+Turns the end of the input in the middle of the compressed data into an error, which the
+readers of the decompressed data would take for its end.
+ */
+struct TruncationError<R> {
+    inner: R,
+}
+
+impl<R: Read> Read for TruncationError<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        return self.inner.read(buf).map_err(|err| {
+            if err.kind() != io::ErrorKind::UnexpectedEof {
+                return err;
+            }
+            return io::Error::new(io::ErrorKind::InvalidData, "truncated compressed data");
+        });
+    }
+}
+
+/**
+Reads the zstd frames one after another, the streaming decoder stopping at the end of a frame.
+ */
+struct ZstdFrames<'a> {
+    decoder: Option<StreamingDecoder<Box<dyn BufRead + 'a>, FrameDecoder>>,
+    /// The input between the frames.
+    input: Option<Box<dyn BufRead + 'a>>,
+}
+
+impl Read for ZstdFrames<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(decoder) = &mut self.decoder {
+                let num_read = decoder.read(buf)?;
+                if num_read > 0 || buf.is_empty() {
+                    return Ok(num_read);
+                }
+                self.input = self.decoder.take().map(StreamingDecoder::into_inner);
+            }
+            let Some(input) = &mut self.input else {
+                return Ok(0);
+            };
+            if input.fill_buf()?.is_empty() {
+                return Ok(0);
+            }
+            let input = self.input.take().unwrap();
+            self.decoder = Some(StreamingDecoder::new(input).map_err(io::Error::other)?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{self, LimitExceeded};
+
+    #[test]
+    fn test_decompress() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"first ").unwrap();
+        let mut data = gzip.finish().unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"second").unwrap();
+        data.extend(gzip.finish().unwrap());
+
+        let limits = ContainerLimits::default();
+        assert_eq!(Some(Compression::Gzip), Compression::detect(&data));
+        assert_eq!(b"first second".to_vec(), decompress_if_needed(data, &limits).unwrap());
+
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(b"bzip2 data").unwrap();
+        assert_eq!(b"bzip2 data".to_vec(), decompress_if_needed(bzip2.finish().unwrap(), &limits).unwrap());
+
+        assert_eq!(b"plain".to_vec(), decompress_if_needed(b"plain".to_vec(), &limits).unwrap());
+    }
+
+    #[test]
+    fn test_decompress_expansion_limit() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gzip.write_all(&[0u8; 1 << 20]).unwrap();
+        let data = gzip.finish().unwrap();
+
+        let limits = ContainerLimits { max_expansion_ratio: 100, ..Default::default() };
+        assert!(matches!(
+            decompress(Compression::Gzip, &data, &limits),
+            Err(ExpandError::Limit(LimitExceeded::ExpansionRatio { max_ratio: 100, .. }))
+        ));
+
+        let limits = ContainerLimits { max_expansion_ratio: 0, ..Default::default() };
+        assert_eq!(1 << 20, decompress(Compression::Gzip, &data, &limits).unwrap().len());
+    }
+
+    #[test]
+    fn test_decompressing_reader() {
+        let read = |compression: Compression, data: &[u8]| -> Vec<u8> {
+            let mut output = Vec::new();
+            decompressing_reader(compression, Box::new(data), &ContainerLimits::default()).unwrap()
+                .read_to_end(&mut output).unwrap();
+            return output;
+        };
+
+        let mut data = Vec::new();
+        for part in [&b"first "[..], b"second"] {
+            let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            gzip.write_all(part).unwrap();
+            data.extend(gzip.finish().unwrap());
+        }
+        assert_eq!(b"first second".to_vec(), read(Compression::Gzip, &data));
+        let limits = ContainerLimits::default();
+        let mut truncated =
+            decompressing_reader(Compression::Gzip, Box::new(&data[..20]), &limits).unwrap();
+        let err = truncated.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+        // two frames of raw blocks: magic, descriptor with the single segment flag, size, block
+        let mut data = Vec::new();
+        for part in [&b"zstd "[..], b"frames"] {
+            data.extend([0x28, 0xb5, 0x2f, 0xfd, 0x20, part.len() as u8]);
+            data.extend(((part.len() as u32) << 3 | 1).to_le_bytes()[..3].iter());
+            data.extend(part);
+        }
+        assert_eq!(b"zstd frames".to_vec(), read(Compression::Zstd, &data));
+
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut &b"xz data"[..], &mut xz).unwrap();
+        assert_eq!(b"xz data".to_vec(), read(Compression::Xz, &xz));
+        let broken = &b"\xfd7zXZ\0broken"[..];
+        assert!(decompressing_reader(Compression::Xz, Box::new(broken), &limits).is_err());
+    }
+
+    #[test]
+    fn test_decompressing_reader_expansion_limit() {
+        let zeros = vec![0u8; 1 << 20];
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gzip.write_all(&zeros).unwrap();
+        let gzip = gzip.finish().unwrap();
+        // 64 KiB of zeros in 132 bytes, lzma-rs doesn't compress that well
+        let xz = std::fs::read("test-resources/zeros.xz").unwrap();
+
+        let limits = ContainerLimits { max_expansion_ratio: 100, ..Default::default() };
+        let mut reader = decompressing_reader(Compression::Gzip, Box::new(&gzip[..]), &limits)
+            .unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert!(matches!(
+            limits::limit_of(&err),
+            Some(LimitExceeded::ExpansionRatio { max_ratio: 100, .. })
+        ));
+        let err = decompressing_reader(Compression::Xz, Box::new(&xz[..]), &limits).err().unwrap();
+        assert!(limits::limit_of(&err).is_some());
+
+        let limits = ContainerLimits { max_expansion_ratio: 0, ..Default::default() };
+        let mut output = Vec::new();
+        decompressing_reader(Compression::Gzip, Box::new(&gzip[..]), &limits).unwrap()
+            .read_to_end(&mut output).unwrap();
+        assert_eq!(zeros, output);
+    }
+}
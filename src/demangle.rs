@@ -0,0 +1,134 @@
+/*
+ Demangling of the symbol names among the strings, see `--demangle`: the string tables of the
+ binaries which are not fully stripped hold the mangled names of the functions, which read much
+ better demangled, as `nm -C` prints them. The Itanium C++ ABI names (`_Z...`, used by GCC and
+ Clang) and the Rust ones, both the legacy (`_ZN...E`) and the v0 (`_R...`) schemes, are
+ recognized, with the extra leading `_` of Mach-O and the `@VERSION` suffix of the versioned
+ ELF symbols. The other strings are left as they are.
+ */
+
+use std::ffi::OsStr;
+use cpp_demangle::{DemangleOptions, Symbol};
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DemangleStyle {
+    /// The demangled name instead of the symbol
+    Replace,
+    /// The demangled name followed by the symbol in brackets
+    Both,
+}
+
+impl DemangleStyle {
+    pub fn parse(name: &str) -> Option<DemangleStyle> {
+        return match name {
+            "replace" => Some(DemangleStyle::Replace),
+            "both" => Some(DemangleStyle::Both),
+            _ => None,
+        };
+    }
+}
+
+/**
+Returns the demangled name if the string is a whole mangled symbol.
+ */
+pub fn demangle(text: &str) -> Option<String> {
+    // the version of an ELF symbol, e.g. `@GLIBCXX_3.4` or `@@CXXABI_1.3`, is kept as it is
+    let (symbol, version) = match text.find('@') {
+        Some(position) => text.split_at(position),
+        None => (text, ""),
+    };
+    let unprefixed = symbol.strip_prefix("__").or_else(|| symbol.strip_prefix('_'));
+    match unprefixed {
+        Some(rest) if rest.starts_with('Z') || rest.starts_with('R') => {}
+        _ => return None,
+    }
+    if symbol.bytes().any(|byte| !byte.is_ascii_graphic()) {
+        return None;
+    }
+
+    // Rust first, as its legacy symbols are valid C++ ones, demangled with the hash then
+    if let Ok(demangled) = rustc_demangle::try_demangle(symbol) {
+        return Some(format!("{:#}{}", demangled, version));
+    }
+    // on Mach-O, the C++ symbols have one more leading `_`
+    let symbol = match symbol.strip_prefix("__Z") {
+        Some(_) => &symbol[1..],
+        None => symbol,
+    };
+    if !symbol.starts_with("_Z") {
+        return None;
+    }
+    let demangled = Symbol::new(symbol).ok()?.demangle(&DemangleOptions::default()).ok()?;
+    return Some(format!("{}{}", demangled, version));
+}
+
+/**
+Passes the strings to the inner sink with the mangled symbols demangled.
+ */
+pub struct DemanglingSink<'a> {
+    style: DemangleStyle,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> DemanglingSink<'a> {
+    pub fn new(style: DemangleStyle, inner: &'a mut dyn StringSink) -> DemanglingSink<'a> {
+        return DemanglingSink { style, inner };
+    }
+}
+
+impl StringSink for DemanglingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let demangled = match std::str::from_utf8(found.text).ok().and_then(demangle) {
+            Some(demangled) => demangled,
+            None => {
+                self.inner.on_string(found);
+                return;
+            }
+        };
+        let text = match self.style {
+            DemangleStyle::Replace => demangled,
+            DemangleStyle::Both => {
+                format!("{} [{}]", demangled, String::from_utf8_lossy(found.text))
+            }
+        };
+        // the bytes stay the ones of the input, as the offsets
+        self.inner.on_string(&found.with_contents(found.bytes, text.as_bytes()));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(Some("foo::bar(int)".to_string()), demangle("_ZN3foo3barEi"));
+        assert_eq!(Some("foo::bar(int)".to_string()), demangle("__ZN3foo3barEi"));
+        assert_eq!(
+            Some("std::terminate()@GLIBCXX_3.4".to_string()),
+            demangle("_ZSt9terminatev@GLIBCXX_3.4")
+        );
+        assert_eq!(
+            Some("core::panicking::panic".to_string()),
+            demangle("_ZN4core9panicking5panic17h1234567890abcdefE")
+        );
+        assert_eq!(
+            Some("std::rt::lang_start".to_string()),
+            demangle("_RNvNtCs1234_3std2rt10lang_start")
+        );
+        assert_eq!(None, demangle("_ZN3foo"));
+        assert_eq!(None, demangle("Zebra"));
+        assert_eq!(None, demangle("_Z is not a symbol"));
+        assert_eq!(None, demangle("printf"));
+    }
+}
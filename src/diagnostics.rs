@@ -0,0 +1,68 @@
+/*
+ Reporting of the inputs which can't be scanned (the errors) and of the ones which are scanned
+ differently than asked (the warnings), e.g. a container left unexpanded. Everything goes to
+ stderr, so that the output stays clean when it is piped. `--quiet` silences the reports and
+ `--strict` fails the run if there was any warning, so the state is process-wide.
+
+ The output can't be written only partly, so an error writing it ends the run, quietly if the
+ reader went away, e.g. with `strings a.out | head`.
+ */
+
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Exit code used when the output couldn't be written, as when an input couldn't be read.
+const EXIT_WRITE_FAILED: i32 = 1;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static NUM_WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/**
+Stops printing the errors and the warnings, they are still counted.
+ */
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/**
+Returns the number of warnings reported so far.
+ */
+pub fn num_warnings() -> usize {
+    return NUM_WARNINGS.load(Ordering::Relaxed);
+}
+
+/**
+Reports an input which couldn't be scanned. The caller makes the run fail.
+ */
+pub fn error(message: impl Display) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
+}
+
+/**
+Reports a problem which didn't stop the scan.
+ */
+pub fn warning(message: impl Display) {
+    NUM_WARNINGS.fetch_add(1, Ordering::Relaxed);
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+/**
+Returns the result of a write to the output, or exits: with code 0 and no message if the
+output is a closed pipe, and with the error and code 1 otherwise. The errors of serde are the
+ones of the writer as well.
+ */
+pub fn check_write<T, E: Into<std::io::Error>>(result: Result<T, E>) -> T {
+    return match result.map_err(Into::into) {
+        Ok(value) => value,
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(err) => {
+            error(format!("Couldn't write the output: {}", err));
+            std::process::exit(EXIT_WRITE_FAILED)
+        }
+    };
+}
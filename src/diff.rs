@@ -0,0 +1,205 @@
+/*
+ Comparison of the strings of two inputs, see `strings diff A B`: the strings found only in A,
+ only in B and in both, each with the offset of its first occurrence and the number of its
+ occurrences. Every list is in the order of the offsets, unlike a diff of the two outputs, which
+ is thrown off by the strings that moved and loses their offsets.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+use serde::Serialize;
+use super::diagnostics::check_write;
+use super::strings::{FoundString, StringSink};
+
+/// Where a string occurs in an input.
+#[derive(Serialize, Copy, Clone, PartialEq, Debug)]
+pub struct Occurrences {
+    /// offset of the first occurrence
+    pub offset: u64,
+    pub count: u64,
+}
+
+#[derive(Default, PartialEq, Debug)]
+pub struct StringDiff {
+    pub only_a: Vec<(String, Occurrences)>,
+    pub only_b: Vec<(String, Occurrences)>,
+    /// the strings in both inputs, with their occurrences in A and in B
+    pub common: Vec<(String, Occurrences, Occurrences)>,
+}
+
+/**
+Collects the distinct strings of an input with their occurrences.
+ */
+#[derive(Default)]
+pub struct OccurrenceCollector {
+    strings: HashMap<String, Occurrences>,
+}
+
+impl OccurrenceCollector {
+    pub fn new() -> OccurrenceCollector {
+        return OccurrenceCollector::default();
+    }
+}
+
+impl StringSink for OccurrenceCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        let text = String::from_utf8_lossy(found.text).into_owned();
+        self.strings.entry(text)
+            .and_modify(|occurrences| {
+                occurrences.offset = occurrences.offset.min(found.address);
+                occurrences.count += 1;
+            })
+            .or_insert(Occurrences { offset: found.address, count: 1 });
+    }
+}
+
+/**
+Compares the strings of the two inputs. The lists are sorted by the offsets, in A for the
+common strings.
+ */
+pub fn diff_strings(a: OccurrenceCollector, b: OccurrenceCollector) -> StringDiff {
+    let mut diff = StringDiff::default();
+    for (text, in_b) in &b.strings {
+        if !a.strings.contains_key(text) {
+            diff.only_b.push((text.clone(), *in_b));
+        }
+    }
+    for (text, in_a) in a.strings {
+        match b.strings.get(&text) {
+            Some(in_b) => diff.common.push((text, in_a, *in_b)),
+            None => diff.only_a.push((text, in_a)),
+        }
+    }
+    diff.only_a.sort_by(|x, y| (x.1.offset, &x.0).cmp(&(y.1.offset, &y.0)));
+    diff.only_b.sort_by(|x, y| (x.1.offset, &x.0).cmp(&(y.1.offset, &y.0)));
+    diff.common.sort_by(|x, y| (x.1.offset, &x.0).cmp(&(y.1.offset, &y.0)));
+    return diff;
+}
+
+/**
+Prints the diff as text: a section per list, headed by the number of its strings, with a line
+per string, its offsets in hex and the numbers of its occurrences if there are more than one.
+ */
+pub fn print_diff(diff: &StringDiff, name_a: &str, name_b: &str, writer: &mut dyn Write) {
+    let plural = |count: usize| -> &'static str {
+        return if count == 1 { "" } else { "s" };
+    };
+    let times = |occurrences: &Occurrences| -> String {
+        return match occurrences.count {
+            1 => String::new(),
+            count => format!(" (x{})", count),
+        };
+    };
+
+    for (name, strings) in [(name_a, &diff.only_a), (name_b, &diff.only_b)] {
+        check_write(writeln!(
+            writer, "only in {}: {} string{}", name, strings.len(), plural(strings.len())
+        ));
+        for (text, occurrences) in strings {
+            check_write(writeln!(
+                writer, "{:>8x} {}{}", occurrences.offset, text, times(occurrences)
+            ));
+        }
+    }
+
+    let num_common = diff.common.len();
+    check_write(writeln!(writer, "in both: {} string{}", num_common, plural(num_common)));
+    for (text, in_a, in_b) in &diff.common {
+        let counts = if in_a.count > 1 || in_b.count > 1 {
+            format!(" (x{}/x{})", in_a.count, in_b.count)
+        } else {
+            String::new()
+        };
+        check_write(writeln!(
+            writer, "{:>8x} {:>8x} {}{}", in_a.offset, in_b.offset, text, counts
+        ));
+    }
+}
+
+/**
+A string of the diff as a JSON line, with its occurrences in the inputs it is found in.
+ */
+#[derive(Serialize)]
+struct DiffRecord<'a> {
+    string: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a: Option<Occurrences>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b: Option<Occurrences>,
+}
+
+/**
+Prints the diff as JSON lines, the strings only in A first, then the ones only in B and the
+common ones.
+ */
+pub fn print_diff_json(diff: &StringDiff, writer: &mut dyn Write) {
+    let records = diff.only_a.iter()
+        .map(|(string, in_a)| DiffRecord { string, a: Some(*in_a), b: None })
+        .chain(diff.only_b.iter().map(|(string, in_b)| {
+            return DiffRecord { string, a: None, b: Some(*in_b) };
+        }))
+        .chain(diff.common.iter().map(|(string, in_a, in_b)| {
+            return DiffRecord { string, a: Some(*in_a), b: Some(*in_b) };
+        }));
+    for record in records {
+        check_write(serde_json::to_writer(&mut *writer, &record));
+        check_write(writer.write_all(b"\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use crate::strings::{EncodingKind, Termination};
+
+    fn collect(strings: &[(u64, &str)]) -> OccurrenceCollector {
+        let mut collector = OccurrenceCollector::new();
+        for (address, text) in strings {
+            collector.on_string(&FoundString {
+                filename: OsStr::new("input"),
+                address: *address,
+                end_address: *address + text.len() as u64,
+                bytes: text.as_bytes(),
+                text: text.as_bytes(),
+                encoding: EncodingKind::Bit7,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Nul,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+        }
+        return collector;
+    }
+
+    #[test]
+    fn test_diff_strings() {
+        let a = collect(&[
+            (0x10, "version 1.2"), (0x40, "shared"), (0x60, "removed"), (0x80, "shared"),
+        ]);
+        let b = collect(&[(0x8, "shared"), (0x20, "version 1.3"), (0x30, "added")]);
+        let diff = diff_strings(a, b);
+        let once = |offset: u64| Occurrences { offset, count: 1 };
+        assert_eq!(
+            StringDiff {
+                only_a: vec![("version 1.2".into(), once(0x10)), ("removed".into(), once(0x60))],
+                only_b: vec![("version 1.3".into(), once(0x20)), ("added".into(), once(0x30))],
+                common: vec![("shared".into(), Occurrences { offset: 0x40, count: 2 }, once(0x8))],
+            },
+            diff
+        );
+
+        let mut output = Vec::new();
+        print_diff(&diff, "a.bin", "b.bin", &mut output);
+        assert_eq!(
+            "only in a.bin: 2 strings\n      10 version 1.2\n      60 removed\n\
+             only in b.bin: 2 strings\n      20 version 1.3\n      30 added\n\
+             in both: 1 string\n      40        8 shared (x2/x1)\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
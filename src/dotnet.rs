@@ -0,0 +1,270 @@
+/*
+ Strings of the metadata of .NET assemblies, see `--dotnet`. The names of the types, methods and
+ fields are kept in the `#Strings` heap as NUL-terminated UTF-8, the string literals of the code
+ in the `#US` heap as UTF-16 prefixed with their length, which the scan of the bytes cuts at the
+ prefixes and mixes with the native code around. Both heaps are read from the CLI metadata and
+ the strings are reported with their offsets in the heap, which is what the metadata tokens
+ refer to.
+ */
+
+use std::ffi::OsStr;
+use std::ops::Range;
+use object::pe;
+use object::read::pe::{PeFile32, PeFile64};
+use object::FileKind;
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::{read_u16_le, read_u32_le};
+
+/// "BSJB", the start of the metadata.
+const METADATA_SIGNATURE: u32 = 0x424a_5342;
+/// Offset of the metadata directory in the CLI header.
+const CLI_HEADER_METADATA: usize = 8;
+
+/**
+A string of a heap, at its offset in the file.
+ */
+#[derive(PartialEq, Debug)]
+struct HeapString {
+    offset: usize,
+    /// Offset right after the last character.
+    end: usize,
+    text: String,
+    /// Offset in the heap.
+    index: usize,
+}
+
+/**
+Scans the `#Strings` and `#US` heaps of a .NET assembly. Returns `None` if the data is not a PE
+file with the CLI metadata.
+ */
+pub(crate) fn print_strings_for_dotnet_metadata(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let (metadata, metadata_offset) = cli_metadata(data)?;
+    let mut num_found = 0u64;
+
+    for (name, range) in streams(metadata)? {
+        let heap = &metadata[range.clone()];
+        let offset = metadata_offset + range.start;
+        let (strings, encoding, termination) = match name.as_str() {
+            "#Strings" => (parse_strings_heap(heap, offset), EncodingKind::Bit8, Termination::Nul),
+            "#US" => (
+                parse_user_strings_heap(heap, offset), EncodingKind::LittleEndian16,
+                Termination::Length,
+            ),
+            _ => continue,
+        };
+
+        for string in strings {
+            if string.text.chars().count() < options.min_length as usize {
+                continue;
+            }
+            let tags = [format!("{}:{:#x}", name, string.index)];
+            sink.on_string(&FoundString {
+                filename,
+                address: string.offset as u64,
+                end_address: string.end as u64,
+                bytes: string.text.as_bytes(),
+                text: string.text.as_bytes(),
+                encoding,
+                tags: &tags,
+                xor_key: None,
+                decoded_from: None,
+                termination,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found += 1;
+        }
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the CLI metadata of a PE file, with its offset in the file.
+ */
+fn cli_metadata(data: &[u8]) -> Option<(&[u8], usize)> {
+    let (sections, cli_directory) = match FileKind::parse(data).ok()? {
+        FileKind::Pe32 => {
+            let file = PeFile32::parse(data).ok()?;
+            (file.section_table(), file.data_directory(pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)?)
+        }
+        FileKind::Pe64 => {
+            let file = PeFile64::parse(data).ok()?;
+            (file.section_table(), file.data_directory(pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)?)
+        }
+        _ => return None,
+    };
+    let cli_header = cli_directory.data(data, &sections).ok()?;
+
+    let address = read_u32_le(cli_header, CLI_HEADER_METADATA)?;
+    let size = read_u32_le(cli_header, CLI_HEADER_METADATA + 4)? as usize;
+    let (offset, _) = sections.pe_file_range_at(address)?;
+    let metadata = sections.pe_data_at(data, address)?;
+    return Some((&metadata[..size.min(metadata.len())], offset as usize));
+}
+
+/**
+Returns the names of the streams of the metadata and their ranges in it. The stream headers
+follow the version string, each one the offset and the size of the stream and its
+NUL-terminated name, padded to 4 bytes.
+ */
+fn streams(metadata: &[u8]) -> Option<Vec<(String, Range<usize>)>> {
+    if read_u32_le(metadata, 0)? != METADATA_SIGNATURE {
+        return None;
+    }
+    let version_length = read_u32_le(metadata, 12)? as usize;
+    let mut position = 16 + version_length;
+    let num_streams = read_u16_le(metadata, position + 2)?;
+    position += 4;
+
+    let mut streams = Vec::new();
+    for _ in 0..num_streams {
+        let start = read_u32_le(metadata, position)? as usize;
+        let size = read_u32_le(metadata, position + 4)? as usize;
+        let name_bytes = metadata.get(position + 8..)?;
+        let name_length = name_bytes.iter().position(|byte| *byte == 0)?;
+        position += 8 + ((name_length + 4) & !3);
+
+        let end = start.checked_add(size)?;
+        if end <= metadata.len() {
+            let name = String::from_utf8_lossy(&name_bytes[..name_length]).into_owned();
+            streams.push((name, start..end));
+        }
+    }
+    return Some(streams);
+}
+
+/**
+Reads the NUL-terminated UTF-8 strings of the `#Strings` heap. The empty string at its start
+is left out.
+ */
+fn parse_strings_heap(heap: &[u8], offset: usize) -> Vec<HeapString> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    for part in heap.split(|byte| *byte == 0) {
+        if !part.is_empty() {
+            strings.push(HeapString {
+                offset: offset + start,
+                end: offset + start + part.len(),
+                text: String::from_utf8_lossy(part).into_owned(),
+                index: start,
+            });
+        }
+        start += part.len() + 1;
+    }
+    return strings;
+}
+
+/**
+Reads the length of a blob, 1, 2 or 4 bytes long depending on the high bits of the first one.
+Returns the length and the number of bytes it takes.
+ */
+fn read_blob_length(heap: &[u8], position: usize) -> Option<(usize, usize)> {
+    let first = *heap.get(position)? as usize;
+    if first & 0x80 == 0 {
+        return Some((first, 1));
+    }
+    if first & 0xc0 == 0x80 {
+        return Some((((first & 0x3f) << 8) | *heap.get(position + 1)? as usize, 2));
+    }
+    let bytes = heap.get(position + 1..position + 4)?;
+    let length = ((first & 0x1f) << 24) | (bytes[0] as usize) << 16 | (bytes[1] as usize) << 8
+        | bytes[2] as usize;
+    return Some((length, 4));
+}
+
+/**
+Reads the strings of the `#US` heap: blobs of UTF-16LE code units followed by a byte telling if
+any of them is special, which is not a part of the string.
+ */
+fn parse_user_strings_heap(heap: &[u8], offset: usize) -> Vec<HeapString> {
+    let mut strings = Vec::new();
+    let mut position = 0;
+    while let Some((length, length_size)) = read_blob_length(heap, position) {
+        let start = position + length_size;
+        let end = start + length;
+        if end > heap.len() {
+            break;
+        }
+
+        let units: Vec<u16> = heap[start..end - length % 2].chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        if !units.is_empty() {
+            strings.push(HeapString {
+                offset: offset + start,
+                end: offset + start + units.len() * 2,
+                text: String::from_utf16_lossy(&units),
+                index: position,
+            });
+        }
+        position = end;
+    }
+    return strings;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streams() {
+        let mut metadata = Vec::new();
+        metadata.extend(METADATA_SIGNATURE.to_le_bytes());
+        metadata.extend([1, 0, 1, 0, 0, 0, 0, 0]);
+        metadata.extend(4u32.to_le_bytes());
+        metadata.extend(b"v4\0\0");
+        metadata.extend([0, 0, 2, 0]);
+        metadata.extend(64u32.to_le_bytes());
+        metadata.extend(8u32.to_le_bytes());
+        metadata.extend(b"#Strings\0\0\0\0");
+        metadata.extend(72u32.to_le_bytes());
+        metadata.extend(100u32.to_le_bytes());
+        metadata.extend(b"#US\0");
+        metadata.resize(80, 0);
+
+        // the second stream doesn't fit the metadata
+        assert_eq!(Some(vec![("#Strings".to_string(), 64..72)]), streams(&metadata));
+        assert_eq!(None, streams(b"MZ\0\0"));
+    }
+
+    #[test]
+    fn test_parse_strings_heap() {
+        assert_eq!(
+            vec![
+                HeapString { offset: 0x201, end: 0x209, text: "<Module>".to_string(), index: 1 },
+                HeapString { offset: 0x20a, end: 0x20e, text: "Main".to_string(), index: 10 },
+            ],
+            parse_strings_heap(b"\0<Module>\0Main\0\0", 0x200)
+        );
+    }
+
+    #[test]
+    fn test_parse_user_strings_heap() {
+        let mut heap = vec![0u8];
+        heap.push(11);
+        heap.extend("Hello".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        heap.push(0);
+        // a two-byte length
+        heap.extend([0x80, 201]);
+        heap.extend("x".repeat(100).encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        heap.push(0);
+
+        let strings = parse_user_strings_heap(&heap, 0x100);
+        assert_eq!(2, strings.len());
+        assert_eq!(
+            HeapString { offset: 0x102, end: 0x10c, text: "Hello".to_string(), index: 1 },
+            strings[0]
+        );
+        assert_eq!((0x10f, "x".repeat(100), 13), (strings[1].offset, strings[1].text.clone(),
+                                                     strings[1].index));
+        assert_eq!(Some((0x1234, 2)), read_blob_length(&[0x92, 0x34], 0));
+        assert_eq!(Some((0x12345, 4)), read_blob_length(&[0xc0, 0x01, 0x23, 0x45], 0));
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ Strings of the DWARF string sections, see `--debug-strings`: `.debug_str` with the names of
+ the compilation units, the functions and the types, and `.debug_line_str` with the directories
+ and the files of the line tables. These are where the source paths of the build end up, so the
+ strings which look like them are marked, along with the compiler identification. The marks come
+ from the strings themselves, not from the debugging information referring to them.
+ */
+
+use std::ffi::OsStr;
+use object::{Object, ObjectSection};
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+
+/// Names of the sections without the `.` of ELF or the `__` of Mach-O.
+const STRING_SECTIONS: [&str; 2] = ["debug_str", "debug_line_str"];
+
+/// Extensions of the source files the compilers take.
+const SOURCE_EXTENSIONS: [&str; 20] = [
+    "c", "cc", "cpp", "cxx", "c++", "h", "hh", "hpp", "hxx", "inc", "m", "mm", "s", "asm", "rs",
+    "go", "swift", "zig", "f90", "d",
+];
+
+/**
+Scans the DWARF string sections of an object file. The addresses are the offsets in the
+section, which the debugging information refers to the strings by. The stripped objects have
+no strings, rather than having the rest of their data scanned.
+ */
+pub(crate) fn print_strings_for_debug_sections(
+    filename: &OsStr,
+    object: &object::File,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let mut num_found = 0u64;
+    for section in object.sections() {
+        let Ok(name) = section.name() else {
+            continue;
+        };
+        let short_name = name.trim_start_matches('.').trim_start_matches("__");
+        if !STRING_SECTIONS.contains(&short_name) {
+            continue;
+        }
+        // the compressed sections are skipped if the decompression is not available
+        let Ok(data) = section.uncompressed_data() else {
+            continue;
+        };
+
+        let mut start = 0usize;
+        for part in data.split(|byte| *byte == 0) {
+            let text = String::from_utf8_lossy(part);
+            if !part.is_empty() && text.chars().count() >= options.min_length as usize {
+                let tags: Vec<String> = attribute(&text).map(str::to_string).into_iter().collect();
+                sink.on_string(&FoundString {
+                    filename,
+                    address: start as u64,
+                    end_address: (start + part.len()) as u64,
+                    bytes: text.as_bytes(),
+                    text: text.as_bytes(),
+                    encoding: EncodingKind::Bit8,
+                    tags: &tags,
+                    xor_key: None,
+                    decoded_from: None,
+                    termination: Termination::Nul,
+                    score: None,
+                    context_before: &[],
+                    context_after: &[],
+                    section: Some(name),
+                });
+                num_found += 1;
+            }
+            start += part.len() + 1;
+        }
+    }
+    return num_found;
+}
+
+/**
+Tells what a debug string is likely to be: `source` for the name of a source file, `directory`
+for an absolute path, such as the build directory of a compilation unit, and `producer` for the
+identification of the compiler.
+ */
+fn attribute(text: &str) -> Option<&'static str> {
+    if text.starts_with("GNU ") || text.contains("clang version") || text.starts_with("rustc ") {
+        return Some("producer");
+    }
+    if text.contains(char::is_control) {
+        return None;
+    }
+
+    let file_name = text.rsplit(['/', '\\']).next().unwrap_or(text);
+    if let Some((stem, extension)) = file_name.rsplit_once('.') {
+        let extension = extension.to_ascii_lowercase();
+        if !stem.is_empty() && SOURCE_EXTENSIONS.contains(&extension.as_str()) {
+            return Some("source");
+        }
+    }
+
+    let windows_drive = text.len() > 2 && text.as_bytes()[0].is_ascii_alphabetic()
+        && text[1..].starts_with(":\\");
+    if text.starts_with('/') || text.starts_with("\\\\") || windows_drive {
+        return Some("directory");
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(Some("producer"), attribute("GNU C17 13.2.0 -mtune=generic -g"));
+        assert_eq!(Some("producer"), attribute("Ubuntu clang version 18.1.3"));
+        assert_eq!(Some("source"), attribute("src/main.rs"));
+        assert_eq!(Some("source"), attribute("/usr/include/stdio.h"));
+        assert_eq!(Some("source"), attribute("test.C"));
+        assert_eq!(Some("directory"), attribute("/home/builder/project"));
+        assert_eq!(Some("directory"), attribute("C:\\build\\project"));
+        assert_eq!(None, attribute("main"));
+        assert_eq!(None, attribute("size_t"));
+        assert_eq!(None, attribute(".h"));
+    }
+}
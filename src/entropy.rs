@@ -0,0 +1,185 @@
+/*
+ Filtering of the strings by their character distribution: by the Shannon entropy
+ (`--min-entropy`, `--max-entropy`) and by the number of distinct characters
+ (`--min-distinct-chars`). Text and identifiers are usually below 4.5 bits per character,
+ while keys, hashes and printable runs of compressed data are above it. Padding like `AAAA`
+ or `////` is made of one or two distinct characters.
+ */
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink};
+
+/**
+Returns the Shannon entropy of the string in bits per character. Invalid UTF-8 sequences
+count as U+FFFD.
+ */
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    let text = String::from_utf8_lossy(bytes);
+    let mut counts = HashMap::<char, usize>::new();
+    let mut num_chars = 0usize;
+    for c in text.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+        num_chars += 1;
+    }
+
+    let mut entropy = 0f64;
+    for count in counts.values() {
+        let probability = *count as f64 / num_chars as f64;
+        entropy -= probability * probability.log2();
+    }
+    return entropy;
+}
+
+/**
+Returns the number of distinct characters in the string. Invalid UTF-8 sequences count as
+U+FFFD.
+ */
+pub fn num_distinct_chars(bytes: &[u8]) -> usize {
+    let mut chars: Vec<char> = String::from_utf8_lossy(bytes).chars().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    return chars.len();
+}
+
+/**
+Passes on only the strings with the entropy within the bounds.
+ */
+pub struct EntropyFilter<'a> {
+    min_entropy: Option<f64>,
+    max_entropy: Option<f64>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> EntropyFilter<'a> {
+    pub fn new(options: &Options, inner: &'a mut dyn StringSink) -> EntropyFilter<'a> {
+        return EntropyFilter {
+            min_entropy: options.min_entropy,
+            max_entropy: options.max_entropy,
+            inner,
+        };
+    }
+}
+
+impl StringSink for EntropyFilter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let entropy = shannon_entropy(found.bytes);
+        if self.min_entropy.is_some_and(|min| entropy < min)
+            || self.max_entropy.is_some_and(|max| entropy > max) {
+            return;
+        }
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Passes on only the strings made of at least the given number of distinct characters.
+ */
+pub struct DistinctCharsFilter<'a> {
+    min_distinct_chars: usize,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> DistinctCharsFilter<'a> {
+    pub fn new(options: &Options, inner: &'a mut dyn StringSink) -> DistinctCharsFilter<'a> {
+        return DistinctCharsFilter {
+            min_distinct_chars: options.min_distinct_chars,
+            inner,
+        };
+    }
+}
+
+impl StringSink for DistinctCharsFilter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if num_distinct_chars(found.bytes) < self.min_distinct_chars {
+            return;
+        }
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    struct Collector {
+        found: Vec<String>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push(String::from_utf8(found.bytes.to_vec()).unwrap());
+        }
+    }
+
+    fn push(sink: &mut dyn StringSink, strings: &[&[u8]]) {
+        for string in strings {
+            sink.on_string(&FoundString {
+                filename: OsStr::new("test"),
+                address: 0,
+                end_address: string.len() as u64,
+                bytes: string,
+                text: string,
+                encoding: EncodingKind::Bit7,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Nul,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+        }
+    }
+
+    #[test]
+    fn test_entropy_filter() {
+        assert_eq!(0.0, shannon_entropy(b"aaaa"));
+        assert_eq!(2.0, shannon_entropy(b"abcdabcd"));
+        assert_eq!(0.0, shannon_entropy(b""));
+
+        let mut options = Options::default();
+        options.min_entropy = Some(1.0);
+        options.max_entropy = Some(4.0);
+        let mut collector = Collector { found: Vec::new() };
+        let mut filter = EntropyFilter::new(&options, &mut collector);
+
+        push(&mut filter, &[b"aaaaaaaa", b"hello, world", b"Zm9vYmFyYmF6cXV4MTIzNDU2Nzg5"]);
+
+        assert_eq!(vec!["hello, world".to_string()], collector.found);
+    }
+
+    #[test]
+    fn test_distinct_chars_filter() {
+        assert_eq!(1, num_distinct_chars(b"////////"));
+        assert_eq!(3, num_distinct_chars(b"ABABABC"));
+
+        let mut options = Options::default();
+        options.min_distinct_chars = 3;
+        let mut collector = Collector { found: Vec::new() };
+        let mut filter = DistinctCharsFilter::new(&options, &mut collector);
+
+        push(&mut filter, &[b"AAAAAAAA", b"=-=-=-=-", b"GLIBC_2.2.5", b"abc"]);
+
+        assert_eq!(vec!["GLIBC_2.2.5".to_string(), "abc".to_string()], collector.found);
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ Backslash escape sequences: in the arguments, e.g. `-s '\t'`, as the shells make it awkward to
+ pass the control characters, and in the output, so that the control characters of the found
+ strings (e.g. ANSI escape sequences) don't reach the terminal, see `--escape-output`.
+ */
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use super::color;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/**
+Replaces the escape sequences `\n`, `\r`, `\t`, `\0`, `\\` and `\xNN` with the bytes they
+stand for. Returns an error message for an unknown or incomplete sequence.
+ */
+pub fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0u8; 4];
+            result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push(b'\n'),
+            Some('r') => result.push(b'\r'),
+            Some('t') => result.push(b'\t'),
+            Some('0') => result.push(0),
+            Some('\\') => result.push(b'\\'),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 => result.push(byte),
+                    _ => {
+                        return Err(
+                            format!("'\\x{}' is not a byte, expected two hex digits", digits)
+                        );
+                    }
+                }
+            }
+            Some(other) => return Err(format!("unknown escape sequence '\\{}'", other)),
+            None => return Err("the trailing '\\' is not escaped, use '\\\\'".to_string())
+        }
+    }
+    return Ok(result);
+}
+
+/**
+Replaces the control characters with `\xNN` escapes: C0 except the tab and the line feed, DEL,
+and C1 either as a raw byte or encoded in UTF-8. The colours of `-u highlight` and `--match`
+are kept.
+Backslashes are not escaped, so the result is safe to display but not reversible.
+ */
+pub fn escape_control(text: &[u8]) -> Cow<'_, [u8]> {
+    let is_control = |byte: u8| {
+        return (byte < 0x20 && byte != b'\t' && byte != b'\n') || byte == 0x7f;
+    };
+    let is_c1 = |byte: u8| (0x80..=0x9f).contains(&byte);
+    if !text.iter().any(|byte| is_control(*byte) || is_c1(*byte)) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = Vec::with_capacity(text.len() + 16);
+    let escape = |bytes: &[u8], result: &mut Vec<u8>| {
+        for byte in bytes {
+            result.extend_from_slice(format!("\\x{:02x}", byte).as_bytes());
+        }
+    };
+
+    for chunk in text.utf8_chunks() {
+        let mut valid = chunk.valid();
+        while !valid.is_empty() {
+            if let Some(highlight) = [color::HIGHLIGHT, color::MATCH, color::RESET].iter()
+                .find(|highlight| valid.starts_with(**highlight)) {
+                result.extend_from_slice(highlight.as_bytes());
+                valid = &valid[highlight.len()..];
+                continue;
+            }
+
+            let c = valid.chars().next().unwrap();
+            let encoded = &valid.as_bytes()[..c.len_utf8()];
+            if (c.is_ascii() && is_control(c as u8)) || ('\u{80}'..='\u{9f}').contains(&c) {
+                escape(encoded, &mut result);
+            } else {
+                result.extend_from_slice(encoded);
+            }
+            valid = &valid[c.len_utf8()..];
+        }
+
+        // 8-bit text, e.g. with `-e S`, is kept unless it is a C1 control character
+        for byte in chunk.invalid() {
+            if is_c1(*byte) {
+                escape(&[*byte], &mut result);
+            } else {
+                result.push(*byte);
+            }
+        }
+    }
+    return Cow::Owned(result);
+}
+
+/**
+Escapes the control characters of the displayed text of the strings. The strings themselves
+are not changed, the structured output formats escape them on their own.
+ */
+pub struct EscapingSink<'a> {
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> EscapingSink<'a> {
+    pub fn new(inner: &'a mut dyn StringSink) -> EscapingSink<'a> {
+        return EscapingSink { inner };
+    }
+}
+
+impl StringSink for EscapingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        match escape_control(found.text) {
+            Cow::Borrowed(_) => self.inner.on_string(found),
+            Cow::Owned(text) => self.inner.on_string(&found.with_contents(found.bytes, &text)),
+        }
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(b"a, b".to_vec(), unescape("a, b").unwrap());
+        assert_eq!(b"\n\r\t\0\\".to_vec(), unescape(r"\n\r\t\0\\").unwrap());
+        assert_eq!(b"\x1e\xff|".to_vec(), unescape(r"\x1e\xFF|").unwrap());
+        assert_eq!("é\n".as_bytes().to_vec(), unescape(r"é\n").unwrap());
+
+        assert!(unescape(r"\q").is_err());
+        assert!(unescape(r"\x4").is_err());
+        assert!(unescape(r"\xzz").is_err());
+        assert!(unescape("\\").is_err());
+    }
+
+    #[test]
+    fn test_escape_control() {
+        assert!(matches!(escape_control(b"plain\ttext\n"), Cow::Borrowed(_)));
+        assert_eq!(
+            br"\x1b[2Jcleared\x0d\x7f".to_vec(),
+            escape_control(b"\x1b[2Jcleared\r\x7f").into_owned()
+        );
+        // C1 in UTF-8 and as a raw byte, the other 8-bit bytes are kept
+        assert_eq!(
+            b"\\xc2\\x9b\xc3\xa9\\x9b\xe9".to_vec(),
+            escape_control(b"\xc2\x9b\xc3\xa9\x9b\xe9").into_owned()
+        );
+        let highlighted = format!("a{}\\u00e9{}\x07", color::HIGHLIGHT, color::RESET);
+        assert_eq!(
+            format!("a{}\\u00e9{}\\x07", color::HIGHLIGHT, color::RESET).into_bytes(),
+            escape_control(highlighted.as_bytes()).into_owned()
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ The C interface, for the programs embedding the scanner instead of running the tool: the
+ library is also built as a cdylib, libstrings.so (strings.dll, libstrings.dylib), and
+ `include/strings.h` declares what is here. The data is scanned in memory, with the options the
+ command line gives with -n, -e and -w, and the strings are passed to a callback one by one.
+ The structures are part of the interface: fields are only ever added at their ends.
+ The Python module of the `pyo3` feature, see `python.rs`, takes the same options.
+ */
+
+use std::ffi::{c_char, c_int, c_void, CStr, OsStr};
+use std::panic::{self, AssertUnwindSafe};
+use super::strings::{self, EncodingKind, FoundString, Options, StringSink};
+
+/// The version of the interface, `STRINGS_API_VERSION` in the header.
+const API_VERSION: c_int = 1;
+
+/**
+The scan options, `NULL` for the defaults of the command line tool.
+ */
+#[repr(C)]
+pub struct StringsOptions {
+    /// The minimum length of the strings in characters, -n.
+    pub min_length: u16,
+    /// The encoding as given to -e: "s", "S", "b", "l", "B", "L", "cp037" or "cp500". `NULL`
+    /// for "s".
+    pub encoding: *const c_char,
+    /// Treat all the whitespace as a part of the strings, -w.
+    pub include_all_whitespace: c_int,
+}
+
+/**
+A found string, valid only during the call of the callback.
+ */
+#[repr(C)]
+pub struct StringsResult {
+    /// Offset of the string in the data.
+    pub offset: u64,
+    /// Number of the bytes the string occupies in the data.
+    pub length: u64,
+    /// The string, UTF-8 unless a legacy code page is scanned, not terminated with a NUL.
+    pub text: *const u8,
+    pub text_length: usize,
+    /// Name of the encoding, e.g. "7bit" or "16le", a static NUL-terminated string.
+    pub encoding: *const c_char,
+}
+
+pub type StringsCallback = extern "C" fn(result: *const StringsResult, user_data: *mut c_void);
+
+/**
+Returns the version of the interface the library implements.
+ */
+#[no_mangle]
+pub extern "C" fn strings_api_version() -> c_int {
+    return API_VERSION;
+}
+
+/**
+Scans the data and calls the callback with every string found, in the order of their offsets.
+Returns the number of the strings, or -1 if the arguments are invalid.
+
+# Safety
+
+`data` must point to `length` readable bytes, or be `NULL` if `length` is 0. `options` must be
+`NULL` or point to a valid `StringsOptions`, the encoding of which is `NULL` or a NUL-terminated
+string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn strings_scan_buffer(
+    data: *const u8,
+    length: usize,
+    options: *const StringsOptions,
+    callback: Option<StringsCallback>,
+    user_data: *mut c_void,
+) -> i64 {
+    let Some(callback) = callback else {
+        return -1;
+    };
+    if data.is_null() && length > 0 {
+        return -1;
+    }
+    let data = if length == 0 { &[][..] } else { std::slice::from_raw_parts(data, length) };
+    let Some(options) = to_options(options.as_ref()) else {
+        return -1;
+    };
+
+    // a panic must not unwind into the caller
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut sink = CallbackSink { callback, user_data };
+        return strings::print_strings_for_bytes(OsStr::new(""), data, &options, &mut sink);
+    }));
+    return match result {
+        Ok(num_found) => num_found as i64,
+        Err(_) => -1,
+    };
+}
+
+unsafe fn to_options(options: Option<&StringsOptions>) -> Option<Options> {
+    let Some(options) = options else {
+        return Some(Options::default());
+    };
+    let encoding = if options.encoding.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(options.encoding).to_str().ok()?)
+    };
+    return scan_options(options.min_length, encoding, options.include_all_whitespace != 0);
+}
+
+/**
+Returns the options of -n, -e and -w, or `None` if they are invalid. The encoding defaults to
+"s".
+ */
+pub(crate) fn scan_options(
+    min_length: u16,
+    encoding: Option<&str>,
+    include_all_whitespace: bool,
+) -> Option<Options> {
+    if min_length == 0 {
+        return None;
+    }
+    let encoding = match encoding {
+        None => Options::default().encoding,
+        Some(encoding) => match EncodingKind::parse(encoding)? {
+            // these are resolved by the command line tool
+            EncodingKind::Auto16 | EncodingKind::Auto32 => return None,
+            encoding => encoding,
+        },
+    };
+    return Some(Options { min_length, encoding, include_all_whitespace, ..Options::default() });
+}
+
+/**
+Returns the name of the encoding as a static C string.
+ */
+pub(crate) fn encoding_name(encoding: EncodingKind) -> &'static CStr {
+    return match encoding {
+        EncodingKind::Bit7 => c"7bit",
+        EncodingKind::Bit8 => c"8bit",
+        EncodingKind::BigEndian16 => c"16be",
+        EncodingKind::LittleEndian16 => c"16le",
+        EncodingKind::BigEndian32 => c"32be",
+        EncodingKind::LittleEndian32 => c"32le",
+        EncodingKind::Auto16 => c"auto16",
+        EncodingKind::Auto32 => c"auto32",
+        EncodingKind::Ebcdic037 => c"cp037",
+        EncodingKind::Ebcdic500 => c"cp500",
+    };
+}
+
+struct CallbackSink {
+    callback: StringsCallback,
+    user_data: *mut c_void,
+}
+
+impl StringSink for CallbackSink {
+    fn on_string(&mut self, found: &FoundString) {
+        let result = StringsResult {
+            offset: found.address,
+            length: found.end_address - found.address,
+            text: found.text.as_ptr(),
+            text_length: found.text.len(),
+            encoding: encoding_name(found.encoding).as_ptr(),
+        };
+        (self.callback)(&result, self.user_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn collect(result: *const StringsResult, user_data: *mut c_void) {
+        let (result, found) = unsafe {
+            (&*result, &mut *(user_data as *mut Vec<(u64, String, String)>))
+        };
+        let text = unsafe { std::slice::from_raw_parts(result.text, result.text_length) };
+        let encoding = unsafe { CStr::from_ptr(result.encoding) };
+        found.push((
+            result.offset,
+            String::from_utf8(text.to_vec()).unwrap(),
+            encoding.to_str().unwrap().to_string(),
+        ));
+    }
+
+    fn scan(data: &[u8], options: Option<&StringsOptions>) -> (i64, Vec<(u64, String, String)>) {
+        let mut found = Vec::new();
+        let num_found = unsafe {
+            strings_scan_buffer(
+                data.as_ptr(), data.len(),
+                options.map_or(std::ptr::null(), |options| options as *const StringsOptions),
+                Some(collect), &mut found as *mut _ as *mut c_void,
+            )
+        };
+        return (num_found, found);
+    }
+
+    #[test]
+    fn test_strings_scan_buffer() {
+        let data = b"\x01abc\0hello\0w\0o\0r\0l\0d\0\0\x02";
+        assert_eq!(
+            (1, vec![(5, "hello".to_string(), "7bit".to_string())]),
+            scan(data, None)
+        );
+
+        let options = StringsOptions {
+            min_length: 3,
+            encoding: c"l".as_ptr(),
+            include_all_whitespace: 0,
+        };
+        assert_eq!(
+            // the last letter of hello and its NUL make a UTF-16LE character too
+            (1, vec![(9, "oworld".to_string(), "16le".to_string())]),
+            scan(data, Some(&options))
+        );
+
+        let wrong = StringsOptions { encoding: c"x".as_ptr(), ..options };
+        assert_eq!(-1, scan(data, Some(&wrong)).0);
+        let empty = unsafe {
+            strings_scan_buffer(std::ptr::null(), 0, std::ptr::null(), Some(collect),
+                                &mut Vec::<(u64, String, String)>::new() as *mut _ as *mut c_void)
+        };
+        assert_eq!(0, empty);
+        assert_eq!(1, strings_api_version());
+    }
+}
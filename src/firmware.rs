@@ -0,0 +1,421 @@
+/*
+ Firmware images, see `--firmware`: the UEFI firmware volumes and the SquashFS file systems of
+ the embedded Linux systems. Every module of a firmware volume, an FFS file, is scanned on its
+ own as `IMAGE!GUID`, tagged with its name, `name:NAME`, if it has a user interface section. A
+ module is scanned as its sections are laid out, the ones compressed with LZMA decompressed; the
+ volumes nested in a module are scanned as `IMAGE!GUID/GUID`. The rest of the image, e.g. the
+ flash descriptor, is scanned as usual. The files of a SquashFS image are scanned as
+ `IMAGE!path/to/file`.
+ */
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use super::diagnostics;
+use super::limits::{self, ContainerLimits, ExpandError};
+use super::packages::PackageInfo;
+use super::partitions::format_guid;
+use super::squashfs::{self, SQUASHFS_MAGIC};
+use super::strings::{self, FoundString, Options, StringSink};
+use super::utils::{read_u16_le, read_u32_le, read_u64_le};
+
+const VOLUME_SIGNATURE: &[u8] = b"_FVH";
+const VOLUME_SIGNATURE_OFFSET: usize = 40;
+/// Size of the volume header up to its block map.
+const VOLUME_HEADER_SIZE: usize = 56;
+/// The file systems of the volumes with FFS files, FFS2 and FFS3.
+const FFS_GUIDS: [&str; 2] =
+    ["8C8CE578-8A3D-4F1C-9935-896185C32DD3", "5473C07A-3DCB-4DCA-BD6F-1E9689E7349A"];
+const LZMA_GUID: &str = "EE4E5898-3914-4259-9D6E-DC7BD79403CF";
+const FILE_HEADER_SIZE: usize = 24;
+const LARGE_FILE_HEADER_SIZE: usize = 32;
+const FILE_ATTRIBUTE_LARGE: u8 = 0x01;
+const FILE_TYPE_RAW: u8 = 0x01;
+const FILE_TYPE_PAD: u8 = 0xf0;
+const SECTION_HEADER_SIZE: usize = 4;
+const LARGE_SECTION_HEADER_SIZE: usize = 8;
+const SECTION_COMPRESSION: u8 = 0x01;
+const SECTION_GUID_DEFINED: u8 = 0x02;
+const SECTION_USER_INTERFACE: u8 = 0x15;
+const SECTION_FIRMWARE_VOLUME_IMAGE: u8 = 0x17;
+/// A GUID-defined section with this attribute must be decoded to get its sections.
+const GUID_PROCESSING_REQUIRED: u16 = 0x01;
+
+/// A module of a firmware volume or a file of a SquashFS image.
+struct FirmwareFile {
+    path: String,
+    /// The name of the module, from its user interface section.
+    name: Option<String>,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Firmware {
+    files: Vec<FirmwareFile>,
+    /// Where the volumes are in the image, the rest of it is scanned as usual.
+    volumes: Vec<(usize, usize)>,
+    /// Why the parts which are scanned as they are couldn't be read.
+    skipped: Vec<String>,
+}
+
+/// The data and the name of a module, with the volumes nested in it.
+#[derive(Default)]
+struct Module {
+    data: Vec<u8>,
+    name: Option<String>,
+    volumes: Vec<Vec<u8>>,
+    skipped: Vec<String>,
+}
+
+/**
+Prints the strings of every module or file of the firmware image. Returns `None` if it is
+neither a firmware volume nor a SquashFS image (or a broken one), so that it is scanned as is.
+ */
+pub(crate) fn print_strings_for_firmware(
+    image_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let filename = image_path.as_os_str();
+    let data = fs::read(image_path).ok()?;
+    let limits = &options.container_limits;
+    let result = if data.starts_with(SQUASHFS_MAGIC) {
+        squashfs::read_squashfs(&data, limits).map(|files| {
+            return Some(Firmware {
+                files: files.into_iter()
+                    .map(|file| FirmwareFile { path: file.path, name: None, data: file.data })
+                    .collect(),
+                volumes: vec![(0, data.len())],
+                skipped: Vec::new(),
+            });
+        })
+    } else {
+        read_firmware_volumes(&data, limits)
+    };
+    let firmware = match result {
+        Ok(Some(firmware)) => firmware,
+        Ok(None) => return None,
+        Err(ExpandError::Limit(limit)) => {
+            limits::warn(&filename.to_string_lossy(), &limit);
+            return None;
+        }
+        Err(ExpandError::Invalid(err)) => {
+            diagnostics::warning(format_args!(
+                "'{}' is a broken firmware image ({}), scanned as is", image_path.display(), err
+            ));
+            return None;
+        }
+    };
+    for reason in &firmware.skipped {
+        diagnostics::warning(format_args!("'{}': {}", image_path.display(), reason));
+    }
+
+    let mut num_found = 0u64;
+    let mut position = 0;
+    for (start, end) in &firmware.volumes {
+        num_found += strings::scan_strings_in_memory(
+            filename, position as u64, &data[position..*start], options, sink,
+        );
+        position = *end;
+    }
+    num_found += strings::scan_strings_in_memory(
+        filename, position as u64, &data[position..], options, sink,
+    );
+
+    for file in &firmware.files {
+        let mut name = filename.to_os_string();
+        name.push(format!("!{}", file.path));
+        let mut name_sink;
+        let sink: &mut dyn StringSink = match &file.name {
+            Some(module_name) => {
+                name_sink = ModuleNameSink { name: module_name, inner: sink };
+                &mut name_sink
+            }
+            None => sink,
+        };
+        num_found += strings::print_strings_for_bytes(&name, &file.data, options, sink);
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the modules of all the firmware volumes in the image, or `None` if there are none.
+ */
+fn read_firmware_volumes(
+    data: &[u8],
+    limits: &ContainerLimits,
+) -> Result<Option<Firmware>, ExpandError> {
+    let mut firmware = Firmware::default();
+    let mut position = 0;
+    while let Some(signature) = find(data, VOLUME_SIGNATURE, position + VOLUME_SIGNATURE_OFFSET) {
+        let start = signature - VOLUME_SIGNATURE_OFFSET;
+        match volume_length(&data[start..]) {
+            Some(length) => {
+                read_volume(&data[start..start + length], "", 1, limits, &mut firmware)?;
+                firmware.volumes.push((start, start + length));
+                position = start + length;
+            }
+            None => position = start + 1,
+        }
+    }
+    return Ok(if firmware.volumes.is_empty() { None } else { Some(firmware) });
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    let position = data.get(from..)?.windows(needle.len()).position(|window| window == needle)?;
+    return Some(from + position);
+}
+
+/**
+Returns the length of the firmware volume at the start of the data if it holds FFS files and
+its header checksum is right.
+ */
+fn volume_length(data: &[u8]) -> Option<usize> {
+    if data.get(VOLUME_SIGNATURE_OFFSET..VOLUME_SIGNATURE_OFFSET + 4)? != VOLUME_SIGNATURE {
+        return None;
+    }
+    let header_size = read_u16_le(data, 48)? as usize;
+    let header = data.get(..header_size).filter(|_| header_size >= VOLUME_HEADER_SIZE)?;
+    let checksum = header.chunks_exact(2)
+        .fold(0u16, |sum, word| sum.wrapping_add(u16::from_le_bytes([word[0], word[1]])));
+    if checksum != 0 || !FFS_GUIDS.contains(&format_guid(&data[16..32]).as_str()) {
+        return None;
+    }
+    let length = usize::try_from(read_u64_le(data, 32)?).ok()?;
+    return if (header_size..=data.len()).contains(&length) { Some(length) } else { None };
+}
+
+/**
+Adds the modules of the volume, their paths prefixed with `prefix`. The files of a volume are
+aligned to 8 bytes and followed by the free space, all 0xff.
+ */
+fn read_volume(
+    volume: &[u8],
+    prefix: &str,
+    depth: usize,
+    limits: &ContainerLimits,
+    firmware: &mut Firmware,
+) -> Result<(), ExpandError> {
+    limits.check_depth(depth)?;
+    let header_size = read_u16_le(volume, 48).ok_or("truncated volume header")? as usize;
+    let extended_header = read_u16_le(volume, 52).ok_or("truncated volume header")? as usize;
+    let mut position = match extended_header {
+        0 => header_size,
+        offset => offset + read_u32_le(volume, offset + 16).ok_or("truncated volume header")?
+            as usize,
+    };
+
+    loop {
+        position = position.next_multiple_of(8);
+        let Some(header) = volume.get(position..position + FILE_HEADER_SIZE) else {
+            break;
+        };
+        if header.iter().all(|byte| *byte == 0xff) {
+            break;
+        }
+        let (file_header_size, size) = if header[19] & FILE_ATTRIBUTE_LARGE != 0 {
+            let size = read_u64_le(volume, position + FILE_HEADER_SIZE).unwrap_or(0);
+            (LARGE_FILE_HEADER_SIZE, usize::try_from(size).unwrap_or(usize::MAX))
+        } else {
+            (FILE_HEADER_SIZE, read_u24_le(header, 20))
+        };
+        let Some(file) = position.checked_add(size)
+            .and_then(|end| volume.get(position..end))
+            .filter(|_| size >= file_header_size)
+        else {
+            firmware.skipped.push(format!(
+                "{}broken FFS file at {:#x}, the rest of the volume is skipped", prefix, position
+            ));
+            break;
+        };
+        position += size;
+        let file_type = header[18];
+        if file_type == FILE_TYPE_PAD {
+            continue;
+        }
+
+        let path = format!("{}{}", prefix, format_guid(&header[..16]));
+        let body = &file[file_header_size..];
+        let mut module = Module::default();
+        if file_type == FILE_TYPE_RAW {
+            module.data = body.to_vec();
+        } else if let Err(err) = read_sections(body, depth, limits, &mut module) {
+            let ExpandError::Invalid(reason) = err else {
+                return Err(err);
+            };
+            module = Module { data: body.to_vec(), ..Module::default() };
+            module.skipped.push(format!("{}, scanned as is", reason));
+        }
+
+        for reason in &module.skipped {
+            firmware.skipped.push(format!("{}: {}", path, reason));
+        }
+        let nested_prefix = format!("{}/", path);
+        let name = module.name;
+        firmware.files.push(FirmwareFile { path: path.clone(), name, data: module.data });
+        for nested in &module.volumes {
+            match volume_length(nested) {
+                Some(length) => {
+                    read_volume(&nested[..length], &nested_prefix, depth + 1, limits, firmware)?;
+                }
+                None => {
+                    firmware.skipped.push(format!("{}: unknown nested volume skipped", path));
+                }
+            }
+        }
+    }
+    return Ok(());
+}
+
+/**
+Adds the sections to the module. The encapsulation sections are replaced with the sections in
+them, decompressed if they are compressed with LZMA; the ones compressed otherwise are kept as
+they are. Each section is aligned to 4 bytes.
+ */
+fn read_sections(
+    sections: &[u8],
+    depth: usize,
+    limits: &ContainerLimits,
+    module: &mut Module,
+) -> Result<(), ExpandError> {
+    limits.check_depth(depth)?;
+    let mut position = 0;
+    while position + SECTION_HEADER_SIZE <= sections.len() {
+        let header = &sections[position..];
+        let (header_size, size) = match read_u24_le(header, 0) {
+            0xff_ffff => (
+                LARGE_SECTION_HEADER_SIZE,
+                read_u32_le(header, 4).ok_or("truncated section")? as usize,
+            ),
+            size => (SECTION_HEADER_SIZE, size),
+        };
+        let section = header.get(..size).filter(|_| size >= header_size)
+            .ok_or("broken section")?;
+        let body = &section[header_size..];
+        position = (position + size).next_multiple_of(4);
+
+        match header[3] {
+            // the uncompressed size, then the compression, 0 for none
+            SECTION_COMPRESSION if body.get(4) == Some(&0) => {
+                read_sections(&body[5..], depth + 1, limits, module)?;
+            }
+            SECTION_COMPRESSION => {
+                module.skipped.push("EFI compressed section scanned as is".to_string());
+                module.data.extend(section);
+            }
+            SECTION_GUID_DEFINED => {
+                let guid = format_guid(body.get(..16).ok_or("truncated section")?);
+                let data_offset = read_u16_le(body, 16).ok_or("truncated section")? as usize;
+                let attributes = read_u16_le(body, 18).ok_or("truncated section")?;
+                let contents = section.get(data_offset..).ok_or("broken section")?;
+                if guid == LZMA_GUID {
+                    let decompressed = decompress_lzma(contents, limits)?;
+                    read_sections(&decompressed, depth + 1, limits, module)?;
+                } else if attributes & GUID_PROCESSING_REQUIRED == 0 {
+                    read_sections(contents, depth + 1, limits, module)?;
+                } else {
+                    module.skipped.push(format!("section encoded with {} scanned as is", guid));
+                    module.data.extend(section);
+                }
+            }
+            SECTION_USER_INTERFACE => {
+                let name: Vec<u16> = body.chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .take_while(|unit| *unit != 0)
+                    .collect();
+                module.name = Some(String::from_utf16_lossy(&name));
+                module.data.extend(section);
+            }
+            SECTION_FIRMWARE_VOLUME_IMAGE => module.volumes.push(body.to_vec()),
+            _ => module.data.extend(section),
+        }
+    }
+    return Ok(());
+}
+
+fn decompress_lzma(data: &[u8], limits: &ContainerLimits) -> Result<Vec<u8>, ExpandError> {
+    let mut output = limits.limit_expansion(Vec::new(), data.len() as u64);
+    let result = lzma_rs::lzma_decompress(&mut &data[..], &mut output);
+    if let Some(limit) = output.exceeded() {
+        return Err(ExpandError::Limit(limit));
+    }
+    if let Err(err) = result {
+        return Err(ExpandError::Invalid(format!("LZMA decompression failed: {}", err)));
+    }
+    return Ok(output.into_inner());
+}
+
+fn read_u24_le(data: &[u8], position: usize) -> usize {
+    return data[position] as usize
+        | (data[position + 1] as usize) << 8
+        | (data[position + 2] as usize) << 16;
+}
+
+/**
+Tags the strings of a module with its name, `name:NAME`.
+ */
+struct ModuleNameSink<'a> {
+    name: &'a str,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for ModuleNameSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut tags = found.tags.to_vec();
+        tags.push(format!("name:{}", self.name));
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_firmware_volumes() {
+        let data = std::fs::read("test-resources/firmware.fd").unwrap();
+        let limits = ContainerLimits::default();
+        let firmware = read_firmware_volumes(&data, &limits).unwrap().unwrap();
+        assert_eq!(vec![(0x40, 0x440)], firmware.volumes);
+        assert_eq!(Vec::<String>::new(), firmware.skipped);
+        let modules: Vec<(&str, Option<&str>)> = firmware.files.iter()
+            .map(|file| (file.path.as_str(), file.name.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("A2F436EA-A127-4EF8-957C-8048606FF670", Some("NetworkStack")),
+                ("9E21FD93-9C72-4C15-8C4B-E77F1DB2D792", None),
+                (
+                    "9E21FD93-9C72-4C15-8C4B-E77F1DB2D792/F1B5FE3A-0C5F-4B5A-8A6F-3B1C2D4E5F60",
+                    Some("Nested"),
+                ),
+                ("1BA0062E-C779-4582-8566-336AE8F78F09", None),
+            ],
+            modules
+        );
+        assert_eq!(b"\0raw file text\0".to_vec(), firmware.files[3].data);
+
+        // the sections in the compressed section are one level deeper
+        let limits = ContainerLimits { max_depth: 1, ..limits };
+        assert!(matches!(read_firmware_volumes(&data, &limits), Err(ExpandError::Limit(_))));
+        assert!(read_firmware_volumes(b"no volume here", &limits).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_volume_length() {
+        let data = std::fs::read("test-resources/firmware.fd").unwrap();
+        assert_eq!(Some(0x400), volume_length(&data[0x40..]));
+        // a broken header checksum
+        let mut broken = data[0x40..].to_vec();
+        broken[32] ^= 1;
+        assert_eq!(None, volume_length(&broken));
+    }
+}
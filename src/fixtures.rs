@@ -0,0 +1,49 @@
+/*
+ Builders of the archives and the compressed streams the unit tests scan, so that the tests of
+ the containers make their inputs the same way.
+ */
+
+use std::io::Write;
+use flate2::write::GzEncoder;
+
+/**
+Returns a tar archive of the files, with their paths as given.
+ */
+pub(crate) fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    return builder.into_inner().unwrap();
+}
+
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    return encoder.finish().unwrap();
+}
+
+/**
+Returns an xz stream of the data. The encoder of lzma-rs doesn't compress, it only wraps the
+data.
+ */
+pub(crate) fn xz(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    lzma_rs::xz_compress(&mut &data[..], &mut output).unwrap();
+    return output;
+}
+
+/**
+Returns a zstd frame of a single raw block.
+ */
+pub(crate) fn zstd(data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0xa0];
+    frame.extend((data.len() as u32).to_le_bytes());
+    frame.extend(((data.len() as u32) << 3 | 1).to_le_bytes()[..3].iter());
+    frame.extend(data);
+    return frame;
+}
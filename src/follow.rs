@@ -0,0 +1,99 @@
+/*
+ Following of a growing file, see `--follow`: like `tail -f`, the end of the file is not the end
+ of the data, the reader waits for more to be appended. The scan blocks in the read and keeps
+ its state, so a string split between two writes is found whole once its end arrives. The file
+ getting shorter (truncated or replaced) ends the scan, as the offsets wouldn't be valid
+ anymore.
+ */
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+use super::diagnostics;
+
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub(crate) struct FollowReader {
+    file: File,
+    name: String,
+    /// offset of the next byte to be read
+    position: u64,
+    poll_interval: Duration,
+    /// the scan reads a few more times after the end of the data
+    truncated: bool,
+}
+
+impl FollowReader {
+    pub(crate) fn new(file: File, name: String) -> FollowReader {
+        return FollowReader {
+            file,
+            name,
+            position: 0,
+            poll_interval: POLL_INTERVAL,
+            truncated: false,
+        };
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.truncated {
+            return Ok(0);
+        }
+        loop {
+            let num_read = self.file.read(buf)?;
+            if num_read > 0 {
+                self.position += num_read as u64;
+                return Ok(num_read);
+            }
+            if self.file.metadata()?.len() < self.position {
+                diagnostics::warning(format_args!("{}: file truncated", self.name));
+                self.truncated = true;
+                return Ok(0);
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    #[test]
+    fn test_follow_reader() {
+        let path = std::env::temp_dir().join(format!("strings-follow-{}", std::process::id()));
+        fs::write(&path, b"first").unwrap();
+
+        let mut reader = FollowReader {
+            file: File::open(&path).unwrap(),
+            name: "log".to_string(),
+            position: 0,
+            poll_interval: Duration::from_millis(10),
+            truncated: false,
+        };
+        let mut buffer = [0u8; 16];
+        let num_read = reader.read(&mut buffer).unwrap();
+        assert_eq!(b"first", &buffer[..num_read]);
+
+        let appended = path.clone();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let mut file = OpenOptions::new().append(true).open(appended).unwrap();
+            file.write_all(b"second").unwrap();
+        });
+        let num_read = reader.read(&mut buffer).unwrap();
+        assert_eq!(b"second", &buffer[..num_read]);
+        writer.join().unwrap();
+
+        fs::write(&path, b"new").unwrap();
+        assert_eq!(0, reader.read(&mut buffer).unwrap());
+        fs::write(&path, b"longer than before").unwrap();
+        assert_eq!(0, reader.read(&mut buffer).unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+}
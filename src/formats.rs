@@ -0,0 +1,86 @@
+/*
+ The format of the inputs told by their first bytes, for the headers of `--group-by-file`.
+ */
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use object::FileKind;
+use super::decompress::Compression;
+
+/// Enough for the headers of the object files, up to the PE signature after the DOS stub.
+const HEAD_SIZE: u64 = 4096;
+/// The minor and major versions of the oldest class files, as a big-endian integer: 0.45.
+const JAVA_MIN_VERSION: u32 = 45;
+
+/**
+Returns the name of the format of the file, or `None` if the name is not a file, e.g. a member
+of a container.
+ */
+pub fn describe_file(path: &Path) -> Option<&'static str> {
+    if !path.is_file() {
+        return None;
+    }
+    let mut head = Vec::new();
+    File::open(path).and_then(|file| file.take(HEAD_SIZE).read_to_end(&mut head)).ok()?;
+    return Some(describe(&head));
+}
+
+/**
+Returns the name of the format of the data starting with `head`.
+ */
+pub fn describe(head: &[u8]) -> &'static str {
+    // the class files share the magic of the universal Mach-O, where the next field is the
+    // number of the architectures, never as large as the class file versions
+    if head.len() >= 8 && head.starts_with(b"\xca\xfe\xba\xbe")
+        && u32::from_be_bytes(head[4..8].try_into().unwrap()) >= JAVA_MIN_VERSION {
+        return "Java class";
+    }
+    if let Ok(kind) = FileKind::parse(head) {
+        let name = match kind {
+            FileKind::Archive => Some("ar archive"),
+            FileKind::Coff => Some("COFF"),
+            FileKind::DyldCache => Some("dyld cache"),
+            FileKind::Elf32 => Some("ELF 32-bit"),
+            FileKind::Elf64 => Some("ELF 64-bit"),
+            FileKind::MachO32 => Some("Mach-O 32-bit"),
+            FileKind::MachO64 => Some("Mach-O 64-bit"),
+            FileKind::MachOFat32 | FileKind::MachOFat64 => Some("Mach-O universal"),
+            FileKind::Pe32 => Some("PE32"),
+            FileKind::Pe64 => Some("PE32+"),
+            _ => None,
+        };
+        if let Some(name) = name {
+            return name;
+        }
+    }
+    if let Some(compression) = Compression::detect(head) {
+        return compression.name();
+    }
+
+    const MAGICS: &[(&[u8], &str)] = &[
+        (b"PK\x03\x04", "zip"),
+        (b"%PDF-", "PDF"),
+        (b"dex\n", "Android DEX"),
+        (b"hsqs", "SquashFS"),
+    ];
+    return MAGICS.iter()
+        .find(|(magic, _)| head.starts_with(magic))
+        .map_or("data", |(_, name)| name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(Some("ELF 64-bit"), describe_file(Path::new("test-resources/a.out")));
+        assert_eq!(Some("PE32"), describe_file(Path::new("test-resources/pe.exe")));
+        assert_eq!(None, describe_file(Path::new("test-resources/archive.zip!stored.txt")));
+        assert_eq!("zip", describe(b"PK\x03\x04\x14\x00"));
+        assert_eq!("gzip", describe(b"\x1f\x8b\x08\x00"));
+        assert_eq!("Java class", describe(b"\xca\xfe\xba\xbe\x00\x00\x00\x34"));
+        assert_eq!("data", describe(b"hello"));
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ Glob expansion of the input arguments, see `--glob`. The shells of Windows leave `*.dll` to
+ the program, so the patterns are expanded there by default, elsewhere on request.
+ */
+
+use std::ffi::OsString;
+use std::path::Path;
+use glob::MatchOptions;
+
+/**
+Replaces the arguments which are glob patterns (with `*`, `?` or `[`) with the files they
+match, sorted by name. The directories are left out. A pattern which matches no files, or which
+is the name of an existing file, is kept as is, so that a missing file is reported by its
+pattern. Returns an error message for an invalid pattern.
+ */
+pub fn expand(args: &[OsString]) -> Result<Vec<OsString>, String> {
+    let options = MatchOptions {
+        // the file systems of Windows are case-insensitive
+        case_sensitive: !cfg!(windows),
+        ..MatchOptions::new()
+    };
+
+    let mut expanded = Vec::with_capacity(args.len());
+    for arg in args {
+        let pattern = match arg.to_str() {
+            Some(pattern) if pattern.contains(['*', '?', '[']) && !Path::new(arg).exists() => {
+                pattern
+            }
+            _ => {
+                expanded.push(arg.clone());
+                continue;
+            }
+        };
+
+        let paths = glob::glob_with(pattern, options)
+            .map_err(|err| format!("invalid glob pattern '{}': {}", pattern, err))?;
+        let num_expanded = expanded.len();
+        // the paths which can't be read are left out, as the shells do
+        for path in paths.flatten() {
+            if !path.is_dir() {
+                expanded.push(path.into_os_string());
+            }
+        }
+        if expanded.len() == num_expanded {
+            expanded.push(arg.clone());
+        }
+    }
+    return Ok(expanded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<OsString>>();
+
+        assert_eq!(
+            Ok(args(&["-", "test-resources/a.out", "test-resources/pe.exe", "missing/*.bin"])),
+            expand(&args(&["-", "test-resources/[ap]*.*[te]", "missing/*.bin"]))
+        );
+        // the directory is left out
+        assert_eq!(
+            Ok(args(&["test-resources/test.c"])),
+            expand(&args(&["test-res*/test.[c]", "test-r*[s]"]))
+                .map(|expanded| expanded[..1].to_vec())
+        );
+        assert!(expand(&args(&["test-resources/[a"])).is_err());
+    }
+}
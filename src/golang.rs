@@ -0,0 +1,237 @@
+/*
+ Recovery of the strings of Go binaries, see `--go`. The Go linker puts the string literals one
+ after another without NULs, since a Go string is a pointer and a length, so the scan of the
+ bytes finds one long run of them. The strings are cut out of the read-only data at the places
+ the binary refers to: the string headers of the static data (a pointer into the read-only data
+ followed by a length) and, on x86-64, the code loading a pointer with `lea` and the length with
+ `mov` right after. What nothing refers to this way is not reported.
+ */
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use object::{Architecture, Object, ObjectSection, SectionKind};
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+
+/// Sections which only Go binaries have, without the `.` of ELF or the `__` of Mach-O.
+const GO_SECTIONS: [&str; 3] = ["gopclntab", "go.buildinfo", "gosymtab"];
+/// Longest string believed to be referred to, the longer lengths are likely something else.
+const MAX_STRING_LEN: u64 = 4096;
+
+/**
+A section of the read-only data the strings are cut from.
+ */
+struct StringData<'data> {
+    name: String,
+    address: u64,
+    file_offset: u64,
+    data: &'data [u8],
+}
+
+/**
+Reports the strings referred to in a Go binary. Returns `None` if the data is not an object
+file built by Go.
+ */
+pub(crate) fn print_strings_for_go_binary(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let object = object::File::parse(data).ok()?;
+    let is_go = object.sections().any(|section| {
+        let name = section.name().unwrap_or("");
+        return GO_SECTIONS.contains(&name.trim_start_matches('.').trim_start_matches("__"));
+    });
+    if !is_go {
+        return None;
+    }
+
+    let mut string_data = Vec::new();
+    for section in object.sections() {
+        if !matches!(section.kind(), SectionKind::ReadOnlyData | SectionKind::ReadOnlyString) {
+            continue;
+        }
+        if let (Ok(name), Ok(data), Some((file_offset, _))) =
+            (section.name(), section.data(), section.file_range()) {
+            string_data.push(StringData {
+                name: name.to_string(),
+                address: section.address(),
+                file_offset,
+                data,
+            });
+        }
+    }
+
+    let pointer_size = if object.is_64() { 8 } else { 4 };
+    let mut references = BTreeSet::new();
+    for section in object.sections() {
+        let Ok(data) = section.data() else {
+            continue;
+        };
+        match section.kind() {
+            SectionKind::Data | SectionKind::ReadOnlyData | SectionKind::ReadOnlyDataWithRel => {
+                references.extend(
+                    header_references(data, pointer_size, object.is_little_endian())
+                );
+            }
+            SectionKind::Text if object.architecture() == Architecture::X86_64 => {
+                references.extend(lea_mov_references(data, section.address()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut num_found = 0u64;
+    for (address, len) in references {
+        let Some(section) = string_data.iter().find(|section| {
+            return address >= section.address
+                && address - section.address + len <= section.data.len() as u64;
+        }) else {
+            continue;
+        };
+        let start = (address - section.address) as usize;
+        let Some(text) = go_string(&section.data[start..start + len as usize]) else {
+            continue;
+        };
+        if text.chars().count() < options.min_length as usize {
+            continue;
+        }
+
+        sink.on_string(&FoundString {
+            filename,
+            address: section.file_offset + start as u64,
+            end_address: section.file_offset + start as u64 + len,
+            bytes: text.as_bytes(),
+            text: text.as_bytes(),
+            encoding: EncodingKind::Bit8,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Length,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: Some(&section.name),
+        });
+        num_found += 1;
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the pointers and the lengths of the pairs of words which may be string headers.
+Whether the pointers point to the strings is checked later.
+ */
+fn header_references(data: &[u8], pointer_size: usize, little_endian: bool) -> Vec<(u64, u64)> {
+    let read_word = |bytes: &[u8]| -> u64 {
+        let mut word = [0u8; 8];
+        if little_endian {
+            word[..pointer_size].copy_from_slice(bytes);
+            return u64::from_le_bytes(word);
+        }
+        word[8 - pointer_size..].copy_from_slice(bytes);
+        return u64::from_be_bytes(word);
+    };
+
+    let mut references = Vec::new();
+    // the headers are aligned to the word, not to the pair
+    let mut position = 0;
+    while position + pointer_size * 2 <= data.len() {
+        let pointer = read_word(&data[position..position + pointer_size]);
+        let len = read_word(&data[position + pointer_size..position + pointer_size * 2]);
+        if pointer != 0 && (1..=MAX_STRING_LEN).contains(&len) {
+            references.push((pointer, len));
+        }
+        position += pointer_size;
+    }
+    return references;
+}
+
+/**
+Returns the pointers and the lengths loaded by x86-64 code like `lea rax, [rip + X]` followed by
+`mov ebx, N`, which is how Go passes a constant string in the registers.
+ */
+fn lea_mov_references(code: &[u8], address: u64) -> Vec<(u64, u64)> {
+    const LEA_LEN: usize = 7;
+
+    let mut references = Vec::new();
+    for position in 0..code.len().saturating_sub(LEA_LEN) {
+        let lea = &code[position..position + LEA_LEN];
+        // REX.W (R), LEA, ModRM of a RIP-relative operand
+        let is_lea = (lea[0] == 0x48 || lea[0] == 0x4c) && lea[1] == 0x8d && lea[2] & 0xc7 == 0x05;
+        if !is_lea {
+            continue;
+        }
+        let next = position + LEA_LEN;
+        let displacement = i32::from_le_bytes([lea[3], lea[4], lea[5], lea[6]]) as i64;
+        let target = (address + next as u64).wrapping_add_signed(displacement);
+
+        // MOV r32, imm32, with the REX.B prefix for r8d-r15d
+        if let Some([0x41, 0xb8..=0xbf, a, b, c, d, ..] | [0xb8..=0xbf, a, b, c, d, ..]) =
+            code.get(next..) {
+            let len = u32::from_le_bytes([*a, *b, *c, *d]) as u64;
+            if (1..=MAX_STRING_LEN).contains(&len) {
+                references.push((target, len));
+            }
+        }
+    }
+    return references;
+}
+
+/**
+Returns the text of the bytes if they look like a string literal: valid UTF-8 without control
+characters other than the whitespace.
+ */
+fn go_string(bytes: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    if text.chars().any(|c| c.is_control() && !c.is_ascii_whitespace()) {
+        return None;
+    }
+    return Some(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_references() {
+        let mut data = Vec::new();
+        data.extend(0x4a_1000u64.to_le_bytes());
+        data.extend(5u64.to_le_bytes());
+        // a header with no length, then one at an odd word
+        data.extend(0x4a_2000u64.to_le_bytes());
+        data.extend(0u64.to_le_bytes());
+        data.extend(0u64.to_le_bytes());
+        data.extend(0x4a_3000u64.to_le_bytes());
+        data.extend(12u64.to_le_bytes());
+
+        let references = header_references(&data, 8, true);
+        assert!(references.contains(&(0x4a_1000, 5)));
+        assert!(references.contains(&(0x4a_3000, 12)));
+        assert!(!references.iter().any(|(_, len)| *len == 0));
+
+        let big_endian = [0x00, 0x4a, 0x10, 0x00, 0x00, 0x00, 0x00, 0x07];
+        assert_eq!(vec![(0x4a_1000, 7)], header_references(&big_endian, 4, false));
+    }
+
+    #[test]
+    fn test_lea_mov_references() {
+        // lea rax, [rip + 0x100]; mov ebx, 11; lea rcx, [rip - 0x10]; mov r8d, 3
+        let code = [
+            0x48, 0x8d, 0x05, 0x00, 0x01, 0x00, 0x00, 0xbb, 0x0b, 0x00, 0x00, 0x00,
+            0x48, 0x8d, 0x0d, 0xf0, 0xff, 0xff, 0xff, 0x41, 0xb8, 0x03, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            vec![(0x1000 + 7 + 0x100, 11), (0x1000 + 19 - 0x10, 3)],
+            lea_mov_references(&code, 0x1000)
+        );
+    }
+
+    #[test]
+    fn test_go_string() {
+        assert_eq!(Some("hello, %s\n"), go_string(b"hello, %s\n"));
+        assert_eq!(None, go_string(b"\x01\x02"));
+        assert_eq!(None, go_string(b"\xff"));
+    }
+}
@@ -0,0 +1,224 @@
+/*
+ gRPC service mode: `strings serve --grpc ADDR`, next to the HTTP one.
+
+ `strings.Strings/Scan` of `proto/strings.proto` scans the data of the request and returns the
+ report of `POST /strings` as messages. The limits of the HTTP service apply: a larger request
+ is rejected with OUT_OF_RANGE, and no more than `max_concurrent` requests are scanned at
+ the same time. The service is generated by `build.rs`, the messages are here.
+ */
+
+use std::ffi::OsStr;
+use std::net::TcpListener;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use super::json;
+use super::strings::{self, EncodingKind, FoundString, Options, StringSink};
+
+include!(concat!(env!("OUT_DIR"), "/strings.Strings.rs"));
+
+pub use strings_client::StringsClient;
+pub use strings_server::StringsServer;
+
+/// Name of the scanned data in the report, if the request doesn't give one.
+const DEFAULT_NAME: &str = "<upload>";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScanRequest {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+    /// -n, the default if 0.
+    #[prost(uint32, tag = "3")]
+    pub min_length: u32,
+    /// -e, the default if empty.
+    #[prost(string, tag = "4")]
+    pub encoding: String,
+    #[prost(bool, tag = "5")]
+    pub data_only: bool,
+    #[prost(bool, tag = "6")]
+    pub segments_only: bool,
+    #[prost(bool, tag = "7")]
+    pub include_all_whitespace: bool,
+    #[prost(bool, tag = "8")]
+    pub decode_wide: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ScanReport {
+    #[prost(string, tag = "1")]
+    pub file: String,
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub strings: Vec<StringRecord>,
+}
+
+/**
+A found string, the fields of `json::StringRecord` the HTTP service reports.
+ */
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StringRecord {
+    #[prost(uint64, tag = "1")]
+    pub offset: u64,
+    #[prost(uint64, tag = "2")]
+    pub length: u64,
+    #[prost(string, tag = "3")]
+    pub encoding: String,
+    #[prost(string, tag = "4")]
+    pub termination: String,
+    #[prost(string, tag = "5")]
+    pub string: String,
+}
+
+pub struct StringsService {
+    /// Permits to scan, `max_concurrent` of them.
+    permits: Arc<Semaphore>,
+}
+
+#[tonic::async_trait]
+impl strings_server::Strings for StringsService {
+    async fn scan(&self, request: Request<ScanRequest>) -> Result<Response<ScanReport>, Status> {
+        let request = request.into_inner();
+        let options = to_options(&request).map_err(Status::invalid_argument)?;
+        let _permit = self.permits.acquire().await
+            .map_err(|_| Status::unavailable("the server is stopping"))?;
+        // the scan doesn't yield, so it is kept off the threads of the runtime
+        let report = tokio::task::spawn_blocking(move || report(&request, &options)).await
+            .map_err(|err| Status::internal(format!("the scan failed: {}", err)))?;
+        return Ok(Response::new(report));
+    }
+}
+
+/**
+Binds the address, so that the errors are reported before the server is run with `run`.
+ */
+pub fn bind(address: &str) -> Result<TcpListener, String> {
+    let listener = TcpListener::bind(address).map_err(
+        |err| format!("couldn't listen on {}: {}", address, err)
+    )?;
+    listener.set_nonblocking(true).map_err(|err| err.to_string())?;
+    return Ok(listener);
+}
+
+/**
+Runs the gRPC server on the bound listener until the process is stopped.
+ */
+pub fn run(
+    listener: TcpListener,
+    max_body_bytes: u64,
+    max_concurrent: usize,
+) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("couldn't start the gRPC server: {}", err))?;
+    return runtime.block_on(async {
+        let listener = tokio::net::TcpListener::from_std(listener).map_err(|err| err.to_string())?;
+        let service = StringsService { permits: Arc::new(Semaphore::new(max_concurrent.max(1))) };
+        return Server::builder()
+            .add_service(
+                StringsServer::new(service)
+                    .max_decoding_message_size(max_body_bytes.try_into().unwrap_or(usize::MAX)),
+            )
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .map_err(|err| format!("the gRPC server failed: {}", err));
+    });
+}
+
+fn to_options(request: &ScanRequest) -> Result<Options, String> {
+    let mut options = Options::default();
+    if request.min_length > 0 {
+        options.min_length = request.min_length.try_into().map_err(
+            |_| format!("invalid minimum string length: {}", request.min_length)
+        )?;
+    }
+    if !request.encoding.is_empty() {
+        options.encoding = EncodingKind::parse(&request.encoding).ok_or_else(
+            || format!("invalid encoding: {}", request.encoding)
+        )?;
+    }
+    options.datasection_only = request.data_only;
+    options.segments_only = request.segments_only;
+    options.include_all_whitespace = request.include_all_whitespace;
+    options.decode_wide = request.decode_wide;
+    return Ok(options);
+}
+
+struct RecordCollector {
+    records: Vec<StringRecord>,
+}
+
+impl StringSink for RecordCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        let record = json::StringRecord::new(found);
+        self.records.push(StringRecord {
+            offset: record.offset,
+            length: record.length,
+            encoding: record.encoding.to_string(),
+            termination: record.termination.to_string(),
+            string: record.string.into_owned(),
+        });
+    }
+}
+
+fn report(request: &ScanRequest, options: &Options) -> ScanReport {
+    let name = if request.name.is_empty() { DEFAULT_NAME } else { &request.name };
+    let mut collector = RecordCollector { records: Vec::new() };
+    let count = strings::print_strings_for_bytes(
+        OsStr::new(name), &request.data, options, &mut collector
+    );
+    return ScanReport { file: name.to_string(), count, strings: collector.records };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let listener = bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || run(listener, 64, 1));
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let mut client = StringsClient::connect(format!("http://{}", address)).await.unwrap();
+
+            let request = ScanRequest {
+                data: b"\x01hello\x00ab\x00world!\x02".to_vec(),
+                ..ScanRequest::default()
+            };
+            let report = client.scan(request.clone()).await.unwrap().into_inner();
+            assert_eq!("<upload>", report.file);
+            assert_eq!(2, report.count);
+            assert_eq!(
+                StringRecord {
+                    offset: 10,
+                    length: 6,
+                    encoding: "7bit".to_string(),
+                    termination: "non-printable".to_string(),
+                    string: "world!".to_string(),
+                },
+                report.strings[1]
+            );
+
+            let short = ScanRequest { name: "short".to_string(), min_length: 2, ..request.clone() };
+            let report = client.scan(short).await.unwrap().into_inner();
+            assert_eq!(("short", 3), (report.file.as_str(), report.count));
+
+            let wrong = ScanRequest { encoding: "x".to_string(), ..request.clone() };
+            let status = client.scan(wrong).await.unwrap_err();
+            assert_eq!((tonic::Code::InvalidArgument, "invalid encoding: x"),
+                       (status.code(), status.message()));
+
+            let large = ScanRequest { data: vec![b'a'; 100], ..request };
+            let status = client.scan(large).await.unwrap_err();
+            assert_eq!(tonic::Code::OutOfRange, status.code());
+        });
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ Hashes of the found strings in the structured output, see `--hash`: the consumers deduplicating
+ the strings or looking them up in threat intelligence feeds read the hash instead of hashing
+ the strings again. The hash is of the UTF-8 of the string as it is output, so it can be checked
+ against the `string` field.
+ */
+
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HashKind {
+    /// XXH3, 64 bits: fast, for deduplication
+    Xxh3,
+    /// SHA-256, for the lookups in the feeds which index it
+    Sha256,
+}
+
+impl HashKind {
+    pub fn parse(name: &str) -> Option<HashKind> {
+        return match name {
+            "xxh3" => Some(HashKind::Xxh3),
+            "sha256" => Some(HashKind::Sha256),
+            _ => None,
+        };
+    }
+
+    /**
+    Returns the hash of the string in lowercase hex.
+     */
+    pub fn hash(&self, string: &str) -> String {
+        return match self {
+            HashKind::Xxh3 => format!("{:016x}", xxh3::xxh3_64(string.as_bytes())),
+            HashKind::Sha256 => Sha256::digest(string.as_bytes()).iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(Some(HashKind::Xxh3), HashKind::parse("xxh3"));
+        assert_eq!(None, HashKind::parse("md5"));
+        assert_eq!("2d06800538d394c2", HashKind::Xxh3.hash(""));
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            HashKind::Sha256.hash("abc")
+        );
+    }
+}
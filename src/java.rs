@@ -0,0 +1,330 @@
+/*
+ Strings of the Java class files and the Android DEX files, see `--java`. Both keep their
+ strings in a pool: the `CONSTANT_Utf8` entries of the constant pool of a class, prefixed with
+ their length, and the string data of a DEX file which the `string_ids` point to. The strings
+ are in modified UTF-8, where a NUL takes two bytes and the characters above U+FFFF are encoded
+ as two surrogates, so they are decoded as such and reported with their index in the pool. The
+ control characters, such as the NUL of two bytes, end the strings as in the byte scan.
+ */
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::{read_u32_le, wide_char_is_printable};
+
+const CLASS_MAGIC: &[u8] = b"\xca\xfe\xba\xbe";
+/// The first version of the class files. The fat Mach-O files start with the same magic, but
+/// with the number of the architectures where the version is.
+const CLASS_MIN_MAJOR_VERSION: u16 = 45;
+const DEX_MAGIC: &[u8] = b"dex\n";
+/// Offset of `string_ids_size` and `string_ids_off` in the DEX header.
+const DEX_STRING_IDS: usize = 0x38;
+
+/**
+A string of a pool, at its offset in the file.
+ */
+#[derive(PartialEq, Debug)]
+struct PoolString {
+    offset: usize,
+    /// Offset right after the last byte.
+    end: usize,
+    /// `const:N` for a class, `string:N` for a DEX file.
+    label: String,
+    /// A string literal of the code rather than a name, as far as the format tells.
+    literal: bool,
+}
+
+/**
+Reports the strings of the pool of a class or DEX file. Returns `None` if the data is neither.
+ */
+pub(crate) fn print_strings_for_java(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let (strings, termination) = if data.starts_with(CLASS_MAGIC) {
+        (parse_class_constants(data)?, Termination::Length)
+    } else if data.starts_with(DEX_MAGIC) {
+        (parse_dex_strings(data)?, Termination::Nul)
+    } else {
+        return None;
+    };
+
+    let mut num_found = 0u64;
+    for string in strings {
+        let mut tags = vec![string.label];
+        if string.literal {
+            tags.push("literal".to_string());
+        }
+        let bytes = &data[string.offset..string.end];
+        for (start, end, stop) in printable_runs(bytes, options.include_all_whitespace) {
+            let text = decode_modified_utf8(&bytes[start..end]);
+            if text.chars().count() < options.min_length as usize {
+                continue;
+            }
+            sink.on_string(&FoundString {
+                filename,
+                address: (string.offset + start) as u64,
+                end_address: (string.offset + end) as u64,
+                bytes: text.as_bytes(),
+                text: text.as_bytes(),
+                encoding: EncodingKind::Bit8,
+                tags: &tags,
+                xor_key: None,
+                decoded_from: None,
+                termination: stop
+                    .map_or(termination, |unit| Termination::after(unit, EncodingKind::Bit8)),
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found += 1;
+        }
+    }
+    return Some(num_found);
+}
+
+fn read_u16_be(data: &[u8], position: usize) -> Option<u16> {
+    let bytes = data.get(position..position.checked_add(2)?)?;
+    return Some(u16::from_be_bytes([bytes[0], bytes[1]]));
+}
+
+/**
+Reads the `CONSTANT_Utf8` entries of the constant pool of a class file. The ones a
+`CONSTANT_String` refers to are the string literals.
+ */
+fn parse_class_constants(data: &[u8]) -> Option<Vec<PoolString>> {
+    if read_u16_be(data, 6)? < CLASS_MIN_MAJOR_VERSION {
+        return None;
+    }
+    let count = read_u16_be(data, 8)?;
+
+    let mut strings = Vec::new();
+    let mut literals = HashSet::new();
+    let mut position = 10;
+    let mut index = 1;
+    while index < count {
+        let tag = *data.get(position)?;
+        position += 1;
+        let size = match tag {
+            // Utf8
+            1 => {
+                let length = read_u16_be(data, position)? as usize;
+                let start = position + 2;
+                // the entry must be whole
+                data.get(start..start + length)?;
+                strings.push((index, PoolString {
+                    offset: start,
+                    end: start + length,
+                    label: format!("const:{}", index),
+                    literal: false,
+                }));
+                2 + length
+            }
+            // String
+            8 => {
+                literals.insert(read_u16_be(data, position)?);
+                2
+            }
+            // Class, MethodType, Module, Package
+            7 | 16 | 19 | 20 => 2,
+            // MethodHandle
+            15 => 3,
+            // Integer, Float, the references, NameAndType, Dynamic, InvokeDynamic
+            3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+            // Long and Double take two entries
+            5 | 6 => {
+                index += 1;
+                8
+            }
+            _ => return None,
+        };
+        position += size;
+        index += 1;
+    }
+
+    return Some(strings.into_iter()
+        .map(|(index, mut string)| {
+            string.literal = literals.contains(&index);
+            return string;
+        })
+        .collect());
+}
+
+/**
+Reads the strings of a DEX file: `string_ids` is a table of the offsets of the string data,
+each one the number of UTF-16 code units as ULEB128 followed by the NUL-terminated text.
+ */
+fn parse_dex_strings(data: &[u8]) -> Option<Vec<PoolString>> {
+    let count = read_u32_le(data, DEX_STRING_IDS)? as usize;
+    let ids_offset = read_u32_le(data, DEX_STRING_IDS + 4)? as usize;
+
+    let mut strings = Vec::new();
+    for index in 0..count {
+        let Some(mut position) = read_u32_le(data, ids_offset + index * 4) else {
+            break;
+        };
+        // the length in code units is not needed with the NUL at the end
+        while data.get(position as usize).is_some_and(|byte| byte & 0x80 != 0) {
+            position += 1;
+        }
+        let start = position as usize + 1;
+        let Some(length) = data.get(start..).and_then(|rest| rest.iter().position(|b| *b == 0))
+        else {
+            continue;
+        };
+        strings.push(PoolString {
+            offset: start,
+            end: start + length,
+            label: format!("string:{}", index),
+            literal: false,
+        });
+    }
+    return Some(strings);
+}
+
+/**
+Decodes the UTF-16 code unit of modified UTF-8 at the position: the 1 to 3 bytes long sequences
+of UTF-8 make the code units of the text. Returns the unit and the number of bytes it takes, a
+malformed sequence is a U+FFFD of one byte.
+ */
+fn decode_unit(bytes: &[u8], position: usize) -> (u16, usize) {
+    let lead = bytes[position] as u16;
+    let continuation = |offset: usize| -> Option<u16> {
+        let byte = *bytes.get(position + offset)?;
+        return (byte & 0xc0 == 0x80).then_some((byte & 0x3f) as u16);
+    };
+    return match lead {
+        0x00..=0x7f => (lead, 1),
+        0xc0..=0xdf => match continuation(1) {
+            Some(low) => (((lead & 0x1f) << 6) | low, 2),
+            None => (0xfffd, 1),
+        },
+        0xe0..=0xef => match (continuation(1), continuation(2)) {
+            (Some(middle), Some(low)) => (((lead & 0x0f) << 12) | (middle << 6) | low, 3),
+            _ => (0xfffd, 1),
+        },
+        _ => (0xfffd, 1),
+    };
+}
+
+/**
+Decodes modified UTF-8. The malformed sequences are replaced with U+FFFD.
+ */
+fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+    while position < bytes.len() {
+        let (unit, len) = decode_unit(bytes, position);
+        units.push(unit);
+        position += len;
+    }
+    return String::from_utf16_lossy(&units);
+}
+
+/**
+Splits modified UTF-8 at the characters which aren't printable. Returns the byte ranges of the
+printable runs, each one with the character which ends it, if any.
+ */
+fn printable_runs(bytes: &[u8], include_all_whitespace: bool) -> Vec<(usize, usize, Option<u32>)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut position = 0;
+    while position < bytes.len() {
+        let (unit, len) = decode_unit(bytes, position);
+        // the surrogates aren't characters on their own, but they make printable ones
+        let printable = char::from_u32(unit as u32)
+            .is_none_or(|c| wide_char_is_printable(c, include_all_whitespace));
+        if !printable {
+            if position > start {
+                runs.push((start, position, Some(unit as u32)));
+            }
+            start = position + len;
+        }
+        position += len;
+    }
+    if position > start {
+        runs.push((start, position, None));
+    }
+    return runs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_modified_utf8() {
+        assert_eq!("a\0é€", decode_modified_utf8(b"a\xc0\x80\xc3\xa9\xe2\x82\xac"));
+        // U+10348 as two surrogates
+        assert_eq!("\u{10348}", decode_modified_utf8(b"\xed\xa0\x80\xed\xbd\x88"));
+        assert_eq!("\u{fffd}x", decode_modified_utf8(b"\xffx"));
+    }
+
+    #[test]
+    fn test_printable_runs() {
+        // the NUL of two bytes in the middle
+        let bytes = b"K\xc3\xb6ln \xc0\x80 nul inside";
+        assert_eq!(vec![(0, 6, Some(0)), (8, 19, None)], printable_runs(bytes, false));
+        assert_eq!("Köln ", decode_modified_utf8(&bytes[0..6]));
+        assert_eq!(" nul inside", decode_modified_utf8(&bytes[8..19]));
+        // the surrogates of U+10348 and a tab
+        assert_eq!(vec![(0, 7, None)], printable_runs(b"\xed\xa0\x80\xed\xbd\x88\t", false));
+        assert_eq!(vec![(1, 2, Some(10))], printable_runs(b"\na\n", false));
+        assert_eq!(vec![(0, 3, None)], printable_runs(b"\na\n", true));
+    }
+
+    #[test]
+    fn test_parse_class_constants() {
+        let mut class = CLASS_MAGIC.to_vec();
+        class.extend([0, 0, 0, 52]);
+        class.extend(7u16.to_be_bytes());
+        // #1 Utf8 "Main", #2 Class #1, #3 Long (two entries), #5 String #6, #6 Utf8 "hello"
+        class.extend([1, 0, 4]);
+        class.extend(b"Main");
+        class.extend([7, 0, 1]);
+        class.extend([5, 0, 0, 0, 0, 0, 0, 0, 1]);
+        class.extend([8, 0, 6]);
+        class.extend([1, 0, 5]);
+        class.extend(b"hello");
+
+        let strings = parse_class_constants(&class).unwrap();
+        assert_eq!(
+            vec![("Main", "const:1", false, 13), ("hello", "const:6", true, 35)],
+            strings.iter()
+                .map(|s| {
+                    let text = std::str::from_utf8(&class[s.offset..s.end]).unwrap();
+                    return (text, s.label.as_str(), s.literal, s.offset);
+                })
+                .collect::<Vec<_>>()
+        );
+
+        // a fat Mach-O file with two architectures
+        assert!(parse_class_constants(b"\xca\xfe\xba\xbe\0\0\0\x02").is_none());
+    }
+
+    #[test]
+    fn test_parse_dex_strings() {
+        let mut dex = b"dex\n035\0".to_vec();
+        dex.resize(0x70, 0);
+        dex[0x38..0x3c].copy_from_slice(&2u32.to_le_bytes());
+        dex[0x3c..0x40].copy_from_slice(&0x70u32.to_le_bytes());
+        dex.extend(0x78u32.to_le_bytes());
+        dex.extend(0x7eu32.to_le_bytes());
+        dex.extend(b"\x04Main\0");
+        dex.extend(b"\x05hello\0");
+
+        let strings = parse_dex_strings(&dex).unwrap();
+        assert_eq!(
+            vec![("Main", "string:0", 0x79, 0x7d), ("hello", "string:1", 0x7f, 0x84)],
+            strings.iter()
+                .map(|s| {
+                    let text = std::str::from_utf8(&dex[s.offset..s.end]).unwrap();
+                    return (text, s.label.as_str(), s.offset, s.end);
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Write;
+use serde::Serialize;
+use super::diagnostics::check_write;
+use super::hash::HashKind;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink, UnicodeDisplayKind};
+
+/**
+A found string in the structured output formats.
+ */
+#[derive(Serialize)]
+pub struct StringRecord<'a> {
+    pub file: Cow<'a, str>,
+    pub offset: u64,
+    /// Number of bytes the string occupies in the input.
+    pub length: u64,
+    pub encoding: &'static str,
+    /// How the string ends, e.g. `nul` or `eof`.
+    pub termination: &'static str,
+    /// The string, with the bytes which are not valid UTF-8 replaced by U+FFFD.
+    pub string: Cow<'a, str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xor_key: Option<u8>,
+    /// `base64` or `hex` if the string is decoded from a run inside the previous one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_from: Option<&'static str>,
+    /// English-likeness from 0 to 1, if the strings are scored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Hash of `string` in hex, with `--hash`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Hex dump of the bytes of the input before and after the string, if the context is
+    /// requested with `-B` and `-A`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_after: Option<String>,
+    /// Ranges of the bytes of the string which are not ASCII, relative to its offset and
+    /// without the end, with `-u highlight`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<Vec<[u64; 2]>>,
+}
+
+impl<'a> StringRecord<'a> {
+    pub fn new(found: &'a FoundString) -> StringRecord<'a> {
+        return StringRecord {
+            file: found.filename.to_string_lossy(),
+            offset: found.address,
+            length: found.end_address - found.address,
+            encoding: found.encoding.name(),
+            termination: found.termination.name(),
+            string: String::from_utf8_lossy(found.bytes),
+            tags: found.tags,
+            xor_key: found.xor_key,
+            decoded_from: found.decoded_from.map(|kind| kind.name()),
+            // two decimal places are enough to compare with the threshold
+            score: found.score.map(|score| (score * 100.0).round() / 100.0),
+            hash: None,
+            context_before: to_hex(found.context_before),
+            context_after: to_hex(found.context_after),
+            highlight: None,
+        };
+    }
+
+    /**
+    Adds the ranges of the non-ASCII bytes, for the programs reading the output to highlight
+    them as `-u highlight` does on the terminal.
+     */
+    pub fn with_highlight(mut self, found: &FoundString) -> StringRecord<'a> {
+        self.highlight = Some(non_ascii_ranges(found.bytes));
+        return self;
+    }
+
+    /**
+    Adds the hash of the string, if one is requested.
+     */
+    pub fn with_hash(mut self, hash: Option<HashKind>) -> StringRecord<'a> {
+        self.hash = hash.map(|kind| kind.hash(&self.string));
+        return self;
+    }
+}
+
+/**
+Returns the ranges of the bytes from 0x80, `[start, end)`.
+ */
+pub fn non_ascii_ranges(bytes: &[u8]) -> Vec<[u64; 2]> {
+    let mut ranges: Vec<[u64; 2]> = Vec::new();
+    for (position, _) in bytes.iter().enumerate().filter(|(_, byte)| !byte.is_ascii()) {
+        let position = position as u64;
+        match ranges.last_mut() {
+            Some(last) if last[1] == position => last[1] += 1,
+            _ => ranges.push([position, position + 1]),
+        }
+    }
+    return ranges;
+}
+
+/**
+Returns true if the structured output marks the non-ASCII bytes, see `StringRecord::highlight`.
+ */
+pub fn highlights(options: &Options) -> bool {
+    return matches!(options.unicode_display, UnicodeDisplayKind::Highlight);
+}
+
+fn to_hex(bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() {
+        return None;
+    }
+    return Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect());
+}
+
+/**
+Metadata of a package, printed before the strings of its files.
+ */
+#[derive(Serialize)]
+pub struct PackageRecord<'a> {
+    pub file: Cow<'a, str>,
+    pub package: &'a PackageInfo,
+}
+
+/**
+Prints found strings as JSON lines: one object per string.
+ */
+pub struct JsonPrinter<'a> {
+    writer: &'a mut dyn Write,
+    highlight: bool,
+    hash: Option<HashKind>,
+}
+
+impl<'a> JsonPrinter<'a> {
+    pub fn new(writer: &'a mut dyn Write, options: &Options) -> JsonPrinter<'a> {
+        return JsonPrinter { writer, highlight: highlights(options), hash: options.string_hash };
+    }
+}
+
+impl StringSink for JsonPrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut record = StringRecord::new(found).with_hash(self.hash);
+        if self.highlight {
+            record = record.with_highlight(found);
+        }
+        check_write(serde_json::to_writer(&mut *self.writer, &record));
+        check_write(self.writer.write_all(b"\n"));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        let record = PackageRecord { file: filename.to_string_lossy(), package };
+        check_write(serde_json::to_writer(&mut *self.writer, &record));
+        check_write(self.writer.write_all(b"\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    #[test]
+    fn test_json_printer() {
+        let tags = vec!["path".to_string()];
+        let mut output = Vec::new();
+        let mut printer = JsonPrinter::new(&mut output, &Options::default());
+
+        printer.on_string(&FoundString {
+            filename: OsStr::new("a.out"),
+            address: 0x10,
+            end_address: 0x1a,
+            bytes: b"/usr/\"lib\"",
+            text: b"/usr/\"lib\"",
+            encoding: EncodingKind::Bit7,
+            tags: &tags,
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+        printer.on_string(&FoundString {
+            filename: OsStr::new("a.out"),
+            address: 0x20,
+            end_address: 0x28,
+            bytes: b"\xffabc",
+            text: b"\xffabc",
+            encoding: EncodingKind::LittleEndian16,
+            tags: &[],
+            xor_key: Some(0x5a),
+            decoded_from: None,
+            termination: Termination::Eof,
+            score: Some(0.456),
+            context_before: b"\x00\x01",
+            context_after: &[],
+            section: None,
+        });
+
+        assert_eq!(
+            concat!(
+                r#"{"file":"a.out","offset":16,"length":10,"encoding":"7bit","termination":"nul","string":"/usr/\"lib\"","tags":["path"]}"#, "\n",
+                r#"{"file":"a.out","offset":32,"length":8,"encoding":"16le","termination":"eof","string":"�abc","xor_key":90,"score":0.46,"context_before":"0001"}"#, "\n",
+            ),
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_ranges() {
+        assert_eq!(vec![[3, 5], [6, 10]], non_ascii_ranges("café 𐍈 x".as_bytes()));
+        assert!(non_ascii_ranges(b"plain").is_empty());
+    }
+}
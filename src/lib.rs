@@ -0,0 +1,106 @@
+/*!
+ Finds the strings of printable characters in binary data, as GNU `strings` does.
+
+ [`scan_bytes`] and [`scan_path`] are the stable entry points: they scan with the defaults of
+ the command line tool (strings of at least 4 7-bit characters, the whole input) and return
+ the found strings. [`scan_slice`] returns the strings as slices of the scanned data instead
+ of copies, for the consumers of large buffers or memory-mapped files. [`StreamScanner`]
+ scans a stream fed in chunks, e.g. from an async reader, and with the `tokio` feature
+ [`extract_strings_async`] scans a tokio reader with it. The [`strings::Options`] and
+ [`strings::StringSink`] machinery behind the command line tool gives control over everything
+ else.
+
+ ```
+ let found = strings::scan_bytes(b"\x7fELF\x02\x01hello, world\0\x03");
+ assert_eq!(1, found.len());
+ assert_eq!("hello, world", found[0].text());
+ assert_eq!(6, found[0].offset);
+ ```
+ */
+
+pub mod alphabet;
+mod archives;
+pub mod assertions;
+pub mod binary;
+mod carve;
+pub mod categories;
+pub mod charset;
+pub mod checkpoint;
+pub mod classifier;
+pub mod color;
+pub mod common;
+pub mod config;
+mod coredump;
+pub mod csv;
+pub mod decode;
+pub mod demangle;
+mod decompress;
+pub mod diagnostics;
+pub mod diff;
+mod dotnet;
+mod dwarf;
+mod ebcdic;
+pub mod entropy;
+pub mod escapes;
+pub mod ffi;
+mod firmware;
+#[cfg(test)]
+mod fixtures;
+mod follow;
+mod formats;
+pub mod globs;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod golang;
+pub mod hash;
+mod java;
+pub mod json;
+pub mod limits;
+mod macho;
+pub mod matching;
+pub mod oci;
+mod overlay;
+pub mod packages;
+pub mod pager;
+mod partitions;
+pub mod pascal;
+mod pdf;
+mod patterns;
+pub mod process;
+mod progress;
+#[cfg(feature = "pyo3")]
+mod python;
+mod records;
+pub mod redact;
+mod resources;
+pub mod response_files;
+mod scanner;
+pub mod score;
+pub mod secrets;
+pub mod seen;
+#[cfg(feature = "serve")]
+pub mod serve;
+mod smart;
+pub mod sort;
+mod sparse;
+pub mod sqlite;
+mod squashfs;
+mod stack;
+pub mod stats;
+pub mod streams;
+pub mod strings;
+pub mod template;
+pub mod timing;
+pub mod tui;
+mod utf8;
+mod utils;
+#[cfg(feature = "wasm-bindgen")]
+pub mod web;
+pub mod xor;
+pub mod yara;
+
+pub use strings::{
+    scan_bytes, scan_path, scan_slice, ScanObserver, ScannedString, StreamScanner, StringMatch,
+};
+#[cfg(feature = "tokio")]
+pub use strings::extract_strings_async;
@@ -0,0 +1,324 @@
+/*
+ Resource limits of the container expansion (`--max-depth-containers`, `--max-expansion-ratio`),
+ protecting from decompression bombs. A container which hits a limit is not expanded, with a
+ warning on stderr, and the scan goes on.
+ */
+
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+use super::diagnostics;
+
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+pub const DEFAULT_MAX_EXPANSION_RATIO: u64 = 1000;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ContainerLimits {
+    /// Maximum nesting of the archives to expand. Compression doesn't count as a level: the
+    /// files of a deb package are 2 levels deep, inside the ar archive and the data tarball.
+    pub max_depth: usize,
+    /// Maximum ratio of the decompressed size to the compressed one, 0 disables the limit.
+    pub max_expansion_ratio: u64,
+}
+
+impl Default for ContainerLimits {
+    fn default() -> Self {
+        ContainerLimits {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_expansion_ratio: DEFAULT_MAX_EXPANSION_RATIO,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LimitExceeded {
+    Depth { max_depth: usize },
+    ExpansionRatio { max_ratio: u64, input_size: u64 },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            LimitExceeded::Depth { max_depth } => write!(
+                f, "archives nested deeper than {} levels (--max-depth-containers)", max_depth
+            ),
+            LimitExceeded::ExpansionRatio { max_ratio, input_size } => write!(
+                f, "{} bytes expand more than {} times (--max-expansion-ratio)",
+                input_size, max_ratio
+            ),
+        };
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/**
+Returns the limit which stopped the reading, if it is what the error is about.
+ */
+pub fn limit_of(err: &io::Error) -> Option<LimitExceeded> {
+    return err.get_ref()?.downcast_ref::<LimitExceeded>().copied();
+}
+
+/**
+Reason why a container couldn't be expanded.
+ */
+#[derive(Debug)]
+pub enum ExpandError {
+    Limit(LimitExceeded),
+    /// The container is broken or not supported
+    Invalid(String),
+}
+
+impl From<LimitExceeded> for ExpandError {
+    fn from(limit: LimitExceeded) -> Self {
+        return ExpandError::Limit(limit);
+    }
+}
+
+impl From<String> for ExpandError {
+    fn from(message: String) -> Self {
+        return ExpandError::Invalid(message);
+    }
+}
+
+impl From<&str> for ExpandError {
+    fn from(message: &str) -> Self {
+        return ExpandError::Invalid(message.to_string());
+    }
+}
+
+impl ContainerLimits {
+    /**
+    Checks if an archive at the given nesting level (1 for the input itself) can be expanded.
+     */
+    pub fn check_depth(&self, depth: usize) -> Result<(), LimitExceeded> {
+        if depth > self.max_depth {
+            return Err(LimitExceeded::Depth { max_depth: self.max_depth });
+        }
+        return Ok(());
+    }
+
+    /**
+    Wraps the reader of the decompressed data, or the writer it is decompressed to, so that it
+    fails once the output exceeds the allowed ratio to `input_size`.
+     */
+    pub fn limit_expansion<T>(&self, inner: T, input_size: u64) -> ExpansionLimiter<T> {
+        return ExpansionLimiter {
+            inner,
+            budget: self.expansion_budget(input_size),
+            exceeded: false,
+            counted_input: None,
+        };
+    }
+
+    /**
+    Returns how many bytes may be expanded from `input_size` bytes, for the containers which
+    count their output themselves.
+     */
+    pub fn expansion_budget(&self, input_size: u64) -> ExpansionBudget {
+        let mut budget =
+            ExpansionBudget { remaining: 0, max_ratio: self.max_expansion_ratio, input_size: 0 };
+        budget.grow(input_size);
+        return budget;
+    }
+
+    /**
+    Like `limit_expansion`, for a compressed stream of an unknown size, e.g. stdin: the output
+    is limited by its ratio to the input read so far, which `input` counts.
+     */
+    pub fn limit_stream_expansion<T>(&self, inner: T, input: &InputCounter) -> ExpansionLimiter<T> {
+        let mut limiter = self.limit_expansion(inner, 0);
+        limiter.counted_input = Some(input.clone());
+        return limiter;
+    }
+}
+
+/**
+The output left to an input under `--max-expansion-ratio`.
+ */
+pub struct ExpansionBudget {
+    remaining: u64,
+    max_ratio: u64,
+    input_size: u64,
+}
+
+impl ExpansionBudget {
+    /**
+    Takes `num_bytes` of the output, or fails if there are not that many left.
+     */
+    pub fn take(&mut self, num_bytes: u64) -> Result<(), LimitExceeded> {
+        if num_bytes > self.remaining {
+            return Err(self.limit());
+        }
+        self.remaining -= num_bytes;
+        return Ok(());
+    }
+
+    /**
+    Adds the output allowed by the input grown to `input_size` bytes.
+     */
+    fn grow(&mut self, input_size: u64) {
+        self.remaining = match self.max_ratio {
+            0 => u64::MAX,
+            ratio => self
+                .remaining
+                .saturating_add((input_size - self.input_size).saturating_mul(ratio)),
+        };
+        self.input_size = input_size;
+    }
+
+    fn limit(&self) -> LimitExceeded {
+        return LimitExceeded::ExpansionRatio {
+            max_ratio: self.max_ratio,
+            input_size: self.input_size,
+        };
+    }
+}
+
+/**
+The number of bytes read by a `CountingReader`, shared with the limiter of the data
+decompressed from them.
+ */
+#[derive(Clone, Default)]
+pub struct InputCounter {
+    num_bytes: Rc<Cell<u64>>,
+}
+
+impl InputCounter {
+    /**
+    Wraps the compressed input, so that the bytes the decoder takes from it are counted.
+     */
+    pub fn count<R: BufRead>(&self, inner: R) -> CountingReader<R> {
+        return CountingReader { inner, counter: self.clone() };
+    }
+
+    fn add(&self, num_bytes: usize) {
+        self.num_bytes.set(self.num_bytes.get() + num_bytes as u64);
+    }
+}
+
+pub struct CountingReader<R> {
+    inner: R,
+    counter: InputCounter,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.inner.read(buf)?;
+        self.counter.add(num_read);
+        return Ok(num_read);
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        return self.inner.fill_buf();
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.counter.add(amount);
+        self.inner.consume(amount);
+    }
+}
+
+/**
+Counts the bytes passing through and fails once there are too many of them. The error may
+reach the caller wrapped by a decoder, so it is remembered to be checked with `exceeded`.
+ */
+pub struct ExpansionLimiter<T> {
+    inner: T,
+    budget: ExpansionBudget,
+    exceeded: bool,
+    /// The input read so far, for the limit of a stream
+    counted_input: Option<InputCounter>,
+}
+
+impl<T> ExpansionLimiter<T> {
+    pub fn exceeded(&self) -> Option<LimitExceeded> {
+        return if self.exceeded { Some(self.budget.limit()) } else { None };
+    }
+
+    pub fn into_inner(self) -> T {
+        return self.inner;
+    }
+
+    fn consume(&mut self, num_bytes: usize) -> io::Result<()> {
+        if let Some(input) = &self.counted_input {
+            self.budget.grow(input.num_bytes.get());
+        }
+        return self.budget.take(num_bytes as u64).map_err(|limit| {
+            self.exceeded = true;
+            return io::Error::other(limit);
+        });
+    }
+}
+
+impl<R: Read> Read for ExpansionLimiter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.inner.read(buf)?;
+        self.consume(num_read)?;
+        return Ok(num_read);
+    }
+}
+
+impl<W: Write> Write for ExpansionLimiter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.consume(buf.len())?;
+        return self.inner.write(buf);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+/**
+Reports the container which was not expanded.
+ */
+pub fn warn(container: &str, limit: &LimitExceeded) {
+    diagnostics::warning(format_args!("'{}' not expanded: {}", container, limit));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expansion_limiter() {
+        let limits = ContainerLimits { max_depth: 2, max_expansion_ratio: 4 };
+        assert_eq!(Ok(()), limits.check_depth(2));
+        assert_eq!(Err(LimitExceeded::Depth { max_depth: 2 }), limits.check_depth(3));
+
+        let mut output = limits.limit_expansion(Vec::new(), 2);
+        output.write_all(b"12345678").unwrap();
+        assert_eq!(None, output.exceeded());
+        assert!(output.write_all(b"9").is_err());
+        assert_eq!(
+            Some(LimitExceeded::ExpansionRatio { max_ratio: 4, input_size: 2 }),
+            output.exceeded()
+        );
+
+        let input = InputCounter::default();
+        let mut compressed = input.count(&b"1234"[..]);
+        let mut output = limits.limit_stream_expansion(Vec::new(), &input);
+        // nothing read yet
+        assert!(output.write_all(b"1").is_err());
+        let mut output = limits.limit_stream_expansion(Vec::new(), &input);
+        compressed.read_exact(&mut [0u8; 2]).unwrap();
+        output.write_all(b"12345678").unwrap();
+        assert!(output.write_all(b"9").is_err());
+        compressed.consume(2);
+        output.write_all(b"12345678").unwrap();
+        let err = output.write_all(b"9").unwrap_err();
+        assert_eq!(
+            Some(LimitExceeded::ExpansionRatio { max_ratio: 4, input_size: 4 }),
+            limit_of(&err)
+        );
+
+        let unlimited = ContainerLimits { max_depth: 2, max_expansion_ratio: 0 };
+        let mut data = Vec::new();
+        unlimited.limit_expansion(&[0u8; 100][..], 1).read_to_end(&mut data).unwrap();
+        assert_eq!(100, data.len());
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ Strings of the literal sections of Mach-O files, see `--macho-literals`. The linker gathers the
+ C string literals in the sections of the type S_CSTRING_LITERALS, `__TEXT,__cstring`, and the
+ literals of the wide CFStrings in `__TEXT,__ustring`, as UTF-16 in the byte order of the file.
+ Every literal there ends with a NUL, so the sections are split at them rather than scanned,
+ which keeps the short literals and the ones with characters the scan stops at.
+ */
+
+use std::ffi::OsStr;
+use object::macho;
+use object::{BinaryFormat, Object, ObjectSection, SectionFlags};
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+
+/// Name of the section of the UTF-16 literals, which has no type of its own.
+const USTRING_SECTION: &str = "__ustring";
+
+/**
+A literal of a section, at its offset in the section.
+ */
+#[derive(PartialEq, Debug)]
+struct Literal {
+    offset: usize,
+    /// Offset right after the last character.
+    end: usize,
+    text: String,
+}
+
+/**
+Reports the literals of the `__cstring` and `__ustring` sections of a Mach-O file, at their
+addresses. Returns `None` if the data is not a Mach-O file or has neither section.
+ */
+pub(crate) fn print_strings_for_macho_literals(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let object = object::File::parse(data).ok()?;
+    if object.format() != BinaryFormat::MachO {
+        return None;
+    }
+
+    let mut num_found: Option<u64> = None;
+    for section in object.sections() {
+        let (Ok(name), Ok(section_data)) = (section.name(), section.data()) else {
+            continue;
+        };
+        let section_type = match section.flags() {
+            SectionFlags::MachO { flags } => flags & macho::SECTION_TYPE,
+            _ => continue,
+        };
+        let (literals, encoding) = if section_type == macho::S_CSTRING_LITERALS {
+            (parse_cstrings(section_data), EncodingKind::Bit8)
+        } else if name == USTRING_SECTION {
+            let little_endian = object.is_little_endian();
+            let encoding = if little_endian {
+                EncodingKind::LittleEndian16
+            } else {
+                EncodingKind::BigEndian16
+            };
+            (parse_ustrings(section_data, little_endian), encoding)
+        } else {
+            continue;
+        };
+
+        let mut found_in_section = 0u64;
+        for literal in literals {
+            if literal.text.chars().count() < options.min_length as usize {
+                continue;
+            }
+            sink.on_string(&FoundString {
+                filename,
+                address: section.address() + literal.offset as u64,
+                end_address: section.address() + literal.end as u64,
+                bytes: literal.text.as_bytes(),
+                text: literal.text.as_bytes(),
+                encoding,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Nul,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: Some(name),
+            });
+            found_in_section += 1;
+        }
+        num_found = Some(num_found.unwrap_or(0) + found_in_section);
+    }
+    return num_found;
+}
+
+/**
+Splits a section of C string literals at the NULs. The literals are UTF-8, as the compilers put
+them there.
+ */
+fn parse_cstrings(data: &[u8]) -> Vec<Literal> {
+    let mut literals = Vec::new();
+    let mut start = 0;
+    for part in data.split(|byte| *byte == 0) {
+        if !part.is_empty() {
+            literals.push(Literal {
+                offset: start,
+                end: start + part.len(),
+                text: String::from_utf8_lossy(part).into_owned(),
+            });
+        }
+        start += part.len() + 1;
+    }
+    return literals;
+}
+
+/**
+Splits a section of UTF-16 literals at the NUL code units.
+ */
+fn parse_ustrings(data: &[u8], little_endian: bool) -> Vec<Literal> {
+    let units: Vec<u16> = data.chunks_exact(2)
+        .map(|unit| if little_endian {
+            u16::from_le_bytes([unit[0], unit[1]])
+        } else {
+            u16::from_be_bytes([unit[0], unit[1]])
+        })
+        .collect();
+
+    let mut literals = Vec::new();
+    let mut start = 0;
+    for part in units.split(|unit| *unit == 0) {
+        if !part.is_empty() {
+            literals.push(Literal {
+                offset: start * 2,
+                end: (start + part.len()) * 2,
+                text: String::from_utf16_lossy(part),
+            });
+        }
+        start += part.len() + 1;
+    }
+    return literals;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cstrings() {
+        assert_eq!(
+            vec![
+                Literal { offset: 0, end: 2, text: "%s".to_string() },
+                Literal { offset: 4, end: 11, text: "héllo\n".to_string() },
+            ],
+            parse_cstrings("%s\0\0héllo\n\0".as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_parse_ustrings() {
+        let mut data = Vec::new();
+        data.extend("Ünï".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        data.extend([0, 0]);
+        data.extend("ok".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        data.extend([0, 0]);
+        assert_eq!(
+            vec![
+                Literal { offset: 0, end: 6, text: "Ünï".to_string() },
+                Literal { offset: 8, end: 12, text: "ok".to_string() },
+            ],
+            parse_ustrings(&data, true)
+        );
+        assert_eq!("ab", parse_ustrings(b"\0a\0b\0\0", false)[0].text);
+    }
+}
@@ -1,148 +1,600 @@
-mod strings;
-mod utils;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::io::{stdout, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use atty::Stream;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use strings::{charset, checkpoint, color, common, config, diagnostics, diff, escapes, globs,
+              limits, oci, pascal, process, response_files, stats, streams, timing, xor, yara};
+use strings::alphabet::Alphabet;
+use strings::assertions::Assertions;
+use strings::charset::CharsetSink;
+use strings::decode::{DecodeKind, DecodingSink};
+use strings::demangle::{DemangleStyle, DemanglingSink};
+use strings::entropy::{DistinctCharsFilter, EntropyFilter};
+use strings::escapes::EscapingSink;
+use strings::binary::{BinaryFormat, BinaryPrinter};
+use strings::categories::{Categorizer, CategorizingSink};
+use strings::classifier::{ClassifyingSink, WasmClassifier};
+use strings::checkpoint::Checkpoint;
+use strings::common::CommonStrings;
+use strings::csv::CsvPrinter;
+use strings::hash::HashKind;
+use strings::json::JsonPrinter;
+use strings::limits::ContainerLimits;
+use strings::matching::{MatchFilter, MatchHighlighter, Matcher};
+use strings::pager::Pager;
+use strings::packages::PackageInfo;
+use strings::pascal::LengthPrefix;
+use strings::redact::{Redactor, RedactingSink};
+use strings::score::ScoringSink;
+use strings::secrets::{SecretDetector, SecretsFilter};
+use strings::sort::{SortKey, SortingSink};
+use strings::tui::{self, StringCollector};
+use strings::seen::{SeenFilter, SeenSet};
+use strings::sqlite::SqlitePrinter;
+use strings::stats::StatsSink;
+use strings::template::{Template, TemplatePrinter};
+use strings::timing::{ByteCounter, InputTiming};
+use strings::strings::{
+    FoundString, Options, UnicodeDisplayKind, EncodingKind, RadixKind, OutputFormat, MultiSink,
+    StringPrinter, StringSink, TerminatorFilter, print_strings_for_file, print_strings_for_stdin,
+};
 
-use std::ffi::{OsString};
-use clap::{Parser};
-use strings::{Options, UnicodeDisplayKind, EncodingKind, RadixKind};
+/// Exit code used when any of the inputs couldn't be read or the output couldn't be written.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code used for the invalid command lines, as clap does.
+const EXIT_USAGE: i32 = 2;
+/// Exit code used when fewer strings than expected were found (see `--expect-min`).
+const EXIT_NOT_ENOUGH_STRINGS: i32 = 3;
+/// Exit code used when any of `--assert-*` checks failed.
+const EXIT_ASSERTION_FAILED: i32 = 4;
+/// Exit code used when `--secrets` found any credentials.
+const EXIT_SECRETS_FOUND: i32 = 5;
 
-impl Options {
-    fn new(args: &CliArgs) -> Options {
-        // defaults
-        let mut datasection_only = false;
-        let mut print_filenames = false;
-        let min_length = args.min_bytes;
-        let mut include_all_whitespace = false;
-        let mut print_addresses = false;
-        let mut address_radix: RadixKind = RadixKind::Hex;
-        let mut output_separator: Option<String> = None;
-        let mut encoding: EncodingKind = EncodingKind::Bit7;
-        let mut unicode_display = UnicodeDisplayKind::Default;
+/// The options which override each other when a config source and the command line both set
+/// one of them, see `config::merge_args`: -a, -d and --segments, -o and -t.
+const EXCLUSIVE_OPTIONS: &[&[&str]] = &[&["all", "data", "segments"], &["octal-radix", "radix"]];
 
-        if args.all {
-            datasection_only = false;
-        }
+/// Number of bytes of the input before and after the strings dumped by `--tui`.
+const TUI_CONTEXT_SIZE: usize = 32;
 
-        if args.data {
-            datasection_only = true;
-        }
+/**
+Builds the scan options from the command line. Returns an error message for the invalid values
+and the options which can't be combined.
+ */
+fn new_options(args: &CliArgs) -> Result<Options, String> {
+    // defaults
+    let mut datasection_only = false;
+    let mut print_filenames = false;
+    let min_length = args.min_bytes;
+    let mut include_all_whitespace = false;
+    let mut print_addresses = false;
+    let mut address_radix: RadixKind = RadixKind::Hex;
+    let mut output_separator: Option<Vec<u8>> = None;
+    let mut encoding: EncodingKind = EncodingKind::Bit7;
+    let mut unicode_display = UnicodeDisplayKind::Default;
 
-        if args.print_file_name {
-            print_filenames = true;
-        }
+    if args.all {
+        datasection_only = false;
+    }
+
+    if args.data {
+        datasection_only = true;
+    }
+
+    if args.print_file_name {
+        print_filenames = true;
+    }
+
+    if args.include_all_whitespace {
+        include_all_whitespace = true;
+    }
+
+    if args.octal_radix {
+        print_addresses = true;
+        address_radix = RadixKind::Oct;
+    }
 
-        if args.include_all_whitespace {
-            include_all_whitespace = true;
+    if let Some(radix) = args.radix.as_deref() {
+        print_addresses = true;
+        match radix {
+            "o" => { address_radix = RadixKind::Oct; }
+            "d" => { address_radix = RadixKind::Dec; }
+            "x" => { address_radix = RadixKind::Hex; }
+            wrong => {
+                return Err(format!("invalid argument to -t/--radix: {}", wrong));
+            }
         }
+    }
 
-        if args.octal_radix {
-            print_addresses = true;
-            address_radix = RadixKind::Oct;
+    let address_zero_pad = match args.address_pad.as_str() {
+        "space" => false,
+        "zero" => true,
+        wrong => {
+            return Err(format!("invalid argument to --address-pad: {}", wrong));
         }
+    };
 
-        if let Some(radix) = args.radix.as_deref() {
-            print_addresses = true;
-            match radix {
-                "o" => { address_radix = RadixKind::Oct; }
-                "d" => { address_radix = RadixKind::Dec; }
-                "x" => { address_radix = RadixKind::Hex; }
-                wrong => {
-                    panic!("Wrong value of radix argument: {}", wrong)
-                }
+    // the end offset is printed next to the start one
+    if args.print_end_offset {
+        print_addresses = true;
+    }
+
+    let mut extra_encodings: Vec<EncodingKind> = Vec::new();
+    for (i, enc) in args.encoding.iter().enumerate() {
+        if enc == "all" {
+            encoding = EncodingKind::Bit7;
+            extra_encodings = vec![
+                EncodingKind::BigEndian16,
+                EncodingKind::LittleEndian16,
+                EncodingKind::BigEndian32,
+                EncodingKind::LittleEndian32,
+            ];
+            break;
+        }
+        let kind = match EncodingKind::parse(enc) {
+            Some(kind) => kind,
+            None => {
+                return Err(format!("invalid argument to -e/--encoding: {}", enc));
             }
+        };
+        if i == 0 {
+            encoding = kind;
+        } else {
+            extra_encodings.push(kind);
         }
+    }
 
-        if let Some(enc) = args.encoding.as_deref() {
-            encoding = EncodingKind::from(enc.parse().expect(
-                &format!("invalid char argument {}", enc)
-            ))
-        }
+    // Windows binaries keep most of the strings in UTF-16LE, which the byte scan can't see; the
+    // rules of yara-gen choose between the ascii and wide modifiers by the encodings found
+    let scans_bytes = matches!(encoding, EncodingKind::Bit7 | EncodingKind::Bit8);
+    let mut detect_utf16 = scans_bytes
+        && (args.detect_utf16 || matches!(args.command, Some(Command::YaraGen(_))));
 
-        if let Some(separator) = args.output_separator.as_deref() {
-            output_separator = Some(separator.to_string())
+    if let Some(separator) = args.output_separator.as_deref() {
+        match escapes::unescape(separator) {
+            Ok(separator) => output_separator = Some(separator),
+            Err(err) => return Err(format!("invalid argument to --output-separator: {}", err))
         }
+    }
+
+    if args.print0 {
+        output_separator = Some(b"\0".to_vec());
+    }
 
-        if let Some(unicode) = args.unicode.as_deref() {
-            unicode_display = UnicodeDisplayKind::from(unicode);
+    // the terminal checks are about stdout, not the files of -O
+    let color = color::use_color(&args.color)?
+        && (args.output.is_none() || args.color != "auto");
+
+    let escape_output = match args.escape_output.as_str() {
+        _ if args.raw => false,
+        "auto" => args.output.is_none() && atty::is(Stream::Stdout),
+        "always" => true,
+        "never" => false,
+        wrong => {
+            return Err(format!("invalid argument to --escape-output: {}", wrong));
         }
+    };
+
+    // the bar would mix with the strings printed to the same terminal
+    let progress = match args.progress.as_str() {
+        "auto" => atty::is(Stream::Stderr) && (args.output.is_some() || atty::isnt(Stream::Stdout)),
+        "always" => true,
+        _ => false,
+    };
 
+    if let Some(unicode) = args.unicode.as_deref() {
+        unicode_display = parse_unicode_display(unicode)?;
+    }
+
+    if !matches!(unicode_display, UnicodeDisplayKind::Default) {
+        encoding = EncodingKind::Bit8;
+        extra_encodings.clear();
+        detect_utf16 = false;
+    }
+
+    let mut charset = None;
+    if let Some(label) = args.charset.as_deref() {
+        charset = match charset::for_label(label) {
+            Some(found) => Some(found),
+            None => return Err(format!("invalid argument to --charset: {}", label)),
+        };
+    }
+    if args.locale {
+        charset = charset::locale_charset()?;
+        if charset.is_none() {
+            // the C library takes only ASCII for printable in the C locale
+            encoding = EncodingKind::Bit7;
+            extra_encodings.clear();
+            detect_utf16 = false;
+        }
+    }
+    if charset.is_some() {
         if !matches!(unicode_display, UnicodeDisplayKind::Default) {
-            encoding = EncodingKind::Bit8;
+            return Err("--charset can't be combined with -u/--unicode".to_string());
         }
+        // multi-byte characters are collected by the 8-bit scan and decoded afterwards
+        encoding = EncodingKind::Bit8;
+        extra_encodings.clear();
+        detect_utf16 = false;
+    }
+
+    if args.smart_encoding && !matches!(unicode_display, UnicodeDisplayKind::Default) {
+        return Err("--smart-encoding can't be combined with -u/--unicode".to_string());
+    }
+    if args.smart_encoding && charset.is_some() {
+        return Err("--smart-encoding can't be combined with --charset".to_string());
+    }
 
-        Options {
-            datasection_only,
-            print_filenames,
-            min_length,
-            include_all_whitespace,
-            print_addresses,
-            address_radix,
-            output_separator,
-            encoding,
-            unicode_display,
+    let mut xor_keys = Vec::new();
+    if let Some(keys) = args.xor.as_deref() {
+        xor_keys = match xor::parse_keys(keys) {
+            Some(parsed) => parsed,
+            None => return Err(format!("invalid argument to --xor: {}", keys))
+        };
+        if !matches!(unicode_display, UnicodeDisplayKind::Default) {
+            return Err("--xor can't be combined with -u/--unicode".to_string());
+        }
+        if args.smart_encoding {
+            return Err("--xor can't be combined with --smart-encoding".to_string());
         }
     }
-}
 
-impl UnicodeDisplayKind {
-    fn from(kind: &str) -> UnicodeDisplayKind {
-        return match kind {
-            "default" | "d" => UnicodeDisplayKind::Default,
-            "locale" | "l" => UnicodeDisplayKind::Show,
-            "escape" | "e" => UnicodeDisplayKind::Escape,
-            "invalid" | "i" => UnicodeDisplayKind::Invalid,
-            "hex" | "x" => UnicodeDisplayKind::Hex,
-            "highlight" | "h" => UnicodeDisplayKind::Highlight,
-            wrong => {
-                panic!("invalid argument to -u/--unicode: {}", wrong);
+    let mut decode = Vec::new();
+    for kind in args.decode.iter().flat_map(|value| value.split(',')) {
+        match DecodeKind::parse(kind) {
+            Some(parsed) => {
+                if !decode.contains(&parsed) {
+                    decode.push(parsed);
+                }
             }
+            None => return Err(format!("invalid argument to --decode: {}", kind))
+        }
+    }
+
+    let mut pascal = Vec::new();
+    if let Some(sizes) = args.pascal.as_deref() {
+        pascal = match LengthPrefix::parse_list(sizes) {
+            Some(parsed) => parsed,
+            None => return Err(format!("invalid argument to --pascal: {}", sizes)),
         };
+        if !scans_bytes || !extra_encodings.is_empty() || detect_utf16 {
+            return Err("--pascal needs the 7-bit or 8-bit encoding".to_string());
+        }
+    }
+
+    let alphabet = match args.chars.as_deref() {
+        Some(spec) => match Alphabet::parse(spec) {
+            Ok(alphabet) => Some(alphabet),
+            Err(err) => return Err(format!("invalid argument to --chars: {}", err)),
+        },
+        None => None,
+    };
+
+    let decode_wide = args.decode_wide;
+    let expand_packages = args.packages;
+    let container_limits = ContainerLimits {
+        max_depth: args.max_depth_containers,
+        max_expansion_ratio: args.max_expansion_ratio,
+    };
+
+    let output_format = match args.format.as_str() {
+        "text" => OutputFormat::Text,
+        "json" => OutputFormat::Json,
+        "csv" => OutputFormat::Csv,
+        "msgpack" => OutputFormat::Binary(BinaryFormat::MessagePack),
+        "cbor" => OutputFormat::Binary(BinaryFormat::Cbor),
+        wrong => {
+            return Err(format!("invalid argument to --format: {}", wrong));
+        }
+    };
+    if matches!(output_format, OutputFormat::Binary(_)) && (args.stats || args.stats_only) {
+        return Err(format!("--stats can't be printed with --format {}", args.format));
+    }
+    let string_hash = match args.hash.as_deref() {
+        Some(name) => Some(HashKind::parse(name).ok_or_else(|| {
+            return format!("invalid argument to --hash: {}", name);
+        })?),
+        None => None,
+    };
+    if args.group_by_file && (!matches!(output_format, OutputFormat::Text)
+        || args.sqlite_output().is_some()) {
+        return Err("--group-by-file needs the text output".to_string());
     }
+    if args.raw && (!matches!(output_format, OutputFormat::Text)
+        || args.sqlite_output().is_some()) {
+        return Err("--raw needs the text output".to_string());
+    }
+    if string_hash.is_some() && matches!(output_format, OutputFormat::Text)
+        && args.sqlite_output().is_none() {
+        return Err("--hash needs a structured output: --format json, csv, msgpack or cbor, or \
+            -O sqlite:PATH".to_string());
+    }
+
+    // the browser dumps the bytes around the strings
+    let default_context = if args.tui { TUI_CONTEXT_SIZE } else { 0 };
+
+    return Ok(Options {
+        datasection_only,
+        segments_only: args.segments,
+        pe_resources: args.pe_resources,
+        dotnet_metadata: args.dotnet,
+        debug_strings: args.debug_strings,
+        go_strings: args.go,
+        java_strings: args.java,
+        macho_literals: args.macho_literals,
+        overlay_only: args.overlay,
+        hex_records: args.hex_records,
+        pdf_objects: args.pdf,
+        stack_strings: args.stack_strings,
+        partitions: args.partitions,
+        carve: args.carve,
+        firmware: args.firmware,
+        print_filenames,
+        min_length,
+        include_all_whitespace,
+        print_addresses,
+        address_radix,
+        output_separator,
+        escape_output,
+        raw_output: args.raw,
+        color,
+        encoding,
+        extra_encodings,
+        detect_utf16,
+        unicode_display,
+        decode_wide,
+        output_format,
+        string_hash,
+        charset,
+        expand_packages,
+        decompress: args.decompress,
+        expand_archives: args.archives,
+        container_limits,
+        xor_keys,
+        compat_address_format: args.compat_address_format,
+        address_width: args.address_width,
+        address_zero_pad,
+        decode,
+        strict_units: args.strict_units,
+        print_end_offset: args.print_end_offset,
+        print_length: args.print_length,
+        group_by_file: args.group_by_file,
+        context_before: args.before_context.or(args.context).unwrap_or(default_context),
+        context_after: args.after_context.or(args.context).unwrap_or(default_context),
+        pascal,
+        require_terminator: args.require_terminator,
+        smart_encoding: args.smart_encoding,
+        min_entropy: args.min_entropy,
+        max_entropy: args.max_entropy,
+        min_score: args.min_score,
+        min_distinct_chars: args.min_distinct_chars,
+        progress,
+        follow: args.follow,
+        alphabet,
+        observer: None,
+    });
 }
 
-impl EncodingKind {
-    fn from(kind: char) -> EncodingKind {
-        return match kind {
-            's' => EncodingKind::Bit7,
-            'S' => EncodingKind::Bit8,
-            'b' => EncodingKind::BigEndian16,
-            'l' => EncodingKind::LittleEndian16,
-            'B' => EncodingKind::BigEndian32,
-            'L' => EncodingKind::LittleEndian32,
-            wrong => {
-                panic!("invalid argument to -e/--encoding: {}", wrong);
-            }
-        };
+/// Values of -u/--unicode, with the single-letter forms and 'locale' for 'show' as GNU accepts.
+const UNICODE_VALUES: [&str; 16] = [
+    "default", "d", "show", "s", "locale", "l", "replace", "escape", "e", "escape-gnu",
+    "invalid", "i", "hex", "x", "highlight", "h",
+];
+
+fn parse_unicode_display(kind: &str) -> Result<UnicodeDisplayKind, String> {
+    return match kind {
+        "default" | "d" => Ok(UnicodeDisplayKind::Default),
+        "show" | "s" | "locale" | "l" => Ok(UnicodeDisplayKind::Show),
+        "replace" => Ok(UnicodeDisplayKind::Replace),
+        "escape" | "e" => Ok(UnicodeDisplayKind::Escape),
+        "escape-gnu" => Ok(UnicodeDisplayKind::EscapeGnu),
+        "invalid" | "i" => Ok(UnicodeDisplayKind::Invalid),
+        "hex" | "x" => Ok(UnicodeDisplayKind::Hex),
+        "highlight" | "h" => Ok(UnicodeDisplayKind::Highlight),
+        wrong => Err(format!("invalid argument to -u/--unicode: {}", wrong))
+    };
+}
+
+/**
+Checks a value of -e/--encoding while the command line is parsed, so that clap reports it
+along with the usage.
+ */
+fn parse_encoding_arg(value: &str) -> Result<String, String> {
+    if value == "all" || EncodingKind::parse(value).is_some() {
+        return Ok(value.to_string());
     }
+    return Err("expected one of s, S, b, l, B, L, auto16, auto32, cp037, cp500, all".to_string());
 }
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct CliArgs {
 
-    /// Sets the input file(s) to scan (stdin by default)
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Sets the input file(s) to scan (stdin by default).  '-' stands for stdin, so that it
+    /// can be scanned along with the files; use ./- for a file named '-'.  The first file is
+    /// taken for a subcommand if it is named like one, e.g. 'diff', so such a file is given as
+    /// ./diff or after --.  An argument @FILE is replaced with the options and files listed in
+    /// FILE, separated by whitespace, as GNU strings does.
     #[clap()]
     files: Vec<OsString>,
 
+    /// Expand the glob patterns of the input files, e.g. 'logs/*.bin', for the shells which
+    /// don't do it.  Always on on Windows.  A pattern matching no files is kept as a file name.
+    #[clap(long)]
+    glob: bool,
+
+    /// Don't print the errors and the warnings about the inputs, e.g. the missing files.  The
+    /// exit code still reports them.
+    #[clap(short = 'q', long)]
+    quiet: bool,
+
+    /// Exit with code 1 if there was any warning, e.g. a file which is not an object with -d,
+    /// a broken package or a container left unexpanded by the limits.
+    #[clap(long)]
+    strict: bool,
+
+    /// Ignore the config file (~/.config/strings-rust/config.toml) and the STRINGS_OPTS
+    /// environment variable, which set the default options.
+    #[clap(long = "no-config")]
+    no_config: bool,
+
+    /// Print NAME as the file name of stdin with -f and in the other output formats, instead
+    /// of <stdin>.
+    #[clap(long, value_name = "NAME", default_value = "<stdin>")]
+    label: OsString,
+
     /// Scan the entire file, not just the data section [default].
     #[clap(short, long)]
     all: bool,
 
-    /// Scan only the initialized data section(s) of object files.
+    /// Scan only the initialized data section(s) of object files.  Core dumps have none, their
+    /// segments are scanned as with --segments.
     #[clap(short, long)]
     data: bool,
 
+    /// Scan only the loadable segments of object files, the data which ends up in memory: the
+    /// PT_LOAD segments of ELF files.  Unlike the sections of -d, the segments are there in the
+    /// stripped binaries without the section headers.  The offsets are the virtual addresses,
+    /// and in core dumps the strings are tagged with the files mapped there as {file:PATH}.
+    #[clap(long, conflicts_with = "data")]
+    segments: bool,
+
+    /// Read the strings of the string tables and the version information of PE files from
+    /// their resource directory, instead of scanning the files.  The strings are tagged with
+    /// their ID (or the name of the version field) and the language of the resource.  Other
+    /// files are scanned as usual.
+    #[clap(long = "pe-resources", conflicts_with_all = &["data", "segments"])]
+    pe_resources: bool,
+
+    /// Read the strings of the metadata of .NET assemblies: the names of the #Strings heap and
+    /// the string literals of the #US heap, tagged with their offset in the heap.  Other files
+    /// are scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources"])]
+    dotnet: bool,
+
+    /// Scan only the DWARF string sections of object files, .debug_str and .debug_line_str, to
+    /// see the source paths and the names left in the debugging information.  The offsets are
+    /// the ones in the section.  The strings which look like source files, absolute
+    /// directories and compiler versions are tagged 'source', 'directory' and 'producer'.
+    #[clap(long = "debug-strings", conflicts_with_all = &["data", "segments", "pe-resources",
+                                                          "dotnet"])]
+    debug_strings: bool,
+
+    /// Cut the strings of Go binaries, which are stored without NULs between them, at the
+    /// places the binary refers to them: the string headers of the data and, on x86-64, the
+    /// code loading a string.  The strings nothing refers to are not reported.  Other files
+    /// are scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings"])]
+    go: bool,
+
+    /// Read the strings of the constant pool of Java class files and of the string pool of
+    /// Android DEX files, decoded from their modified UTF-8.  The strings are tagged with their
+    /// index in the pool, and the string literals of a class with 'literal'.  Other files are
+    /// scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go"])]
+    java: bool,
+
+    /// Split the C string literal sections of Mach-O files, __TEXT,__cstring, and their
+    /// UTF-16 literals, __TEXT,__ustring, at the literals instead of scanning them, so that
+    /// every literal is reported whole, at its address.  Other files are scanned as usual.
+    #[clap(long = "macho-literals", conflicts_with_all = &["data", "segments", "pe-resources",
+                                                           "dotnet", "debug-strings", "go",
+                                                           "java"])]
+    macho_literals: bool,
+
+    /// Scan only the overlay of PE and ELF files, the data appended past the end of the image
+    /// such as the archive of an installer.  The offsets stay the ones in the file and the
+    /// strings are tagged with their offset in the overlay, 'overlay+0xN'.  Other files are
+    /// scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals"])]
+    overlay: bool,
+
+    /// Decode the firmware images of Intel HEX and Motorola S-record files and scan the bytes
+    /// instead of the text, at their addresses in the target memory.  The runs of consecutive
+    /// addresses are scanned one by one, so no string spans a gap.  Other files are scanned as
+    /// usual.
+    #[clap(long = "hex-records", conflicts_with_all = &["data", "segments", "pe-resources",
+                                                        "dotnet", "debug-strings", "go",
+                                                        "java", "macho-literals", "overlay"])]
+    hex_records: bool,
+
+    /// Scan the objects of PDF files with their streams inflated: the FlateDecode streams,
+    /// where the text of the pages is, are scanned decompressed, at their offsets in the
+    /// inflated data, and the dictionaries as they are.  The streams with other filters, such
+    /// as the images, are skipped.  The strings are tagged with their object, 'obj:N'.  Other
+    /// files are scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals",
+                                        "overlay", "hex-records"])]
+    pdf: bool,
+
+    /// Put together the strings which x86 and x86-64 code builds on the stack a few characters
+    /// at a time, e.g. with 'mov byte ptr [rbp-0x10], 0x68', as obfuscated code and malware do,
+    /// like FLOSS.  Only the runs of consecutive stores are seen.  The strings are printed at
+    /// the offset of the first store, tagged with 'stack', the UTF-16LE ones as well.  Other
+    /// files are scanned as usual.
+    #[clap(long = "stack-strings", conflicts_with_all = &["data", "segments", "pe-resources",
+                                                          "dotnet", "debug-strings", "go",
+                                                          "java", "macho-literals", "overlay",
+                                                          "hex-records", "pdf"])]
+    stack_strings: bool,
+
+    /// Scan the partitions of disk images one by one, as FILE!pN, the offsets relative to the
+    /// start of the partition: the primary partitions of an MBR are 1 to 4 and the logical ones
+    /// 5 and up, the partitions of a GPT are numbered as its entries and tagged with their
+    /// unique GUID, 'guid:GUID'.  The images without a partition table are scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals",
+                                        "overlay", "hex-records", "pdf", "stack-strings"])]
+    partitions: bool,
+
+    /// Carve the files embedded in the input, like binwalk: the ELF and PE images, zip
+    /// archives, gzip streams and PNG images past its start are scanned on their own, as
+    /// FILE→embedded.elf@0x1234, the offsets relative to their start.  The gzip streams are
+    /// scanned inflated and the zip archives file by file, and the embedded files are carved in
+    /// turn, as deep as --max-depth-containers allows.  The rest of the input is scanned as
+    /// usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals",
+                                        "overlay", "hex-records", "pdf", "stack-strings",
+                                        "partitions"])]
+    carve: bool,
+
+    /// Scan the modules of UEFI firmware volumes and the files of SquashFS images one by one.
+    /// A module is scanned as IMAGE!GUID, with its LZMA-compressed sections decompressed, and
+    /// tagged with its name, 'name:NAME', if it has one; the volumes nested in it are scanned
+    /// as IMAGE!GUID/GUID.  The rest of the image is scanned as usual.  The files of a SquashFS
+    /// image are scanned as IMAGE!path.  Other files are scanned as usual.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals",
+                                        "overlay", "hex-records", "pdf", "stack-strings",
+                                        "partitions", "carve"])]
+    firmware: bool,
+
     /// Print the name of the file before each string.
     #[clap(short = 'f', long = "print-file-name")]
     print_file_name: bool,
 
     /// Print graphic char sequences, MIN-LEN or more bytes long, that are followed by a NUL or
     /// a newline.  Default is 4.
-    #[clap(short = 'n', long="bytes", default_value = "4")]
+    #[clap(short = 'n', long="bytes", default_value = "4",
+           value_parser = clap::value_parser!(u16).range(1..))]
     min_bytes: u16,
 
     /// Print the offset within the file before each string, in octal/hex/decimal.
     /// Values are {o,x,d}.
-    #[clap(short = 't', long)]
+    #[clap(short = 't', long, value_parser = ["o", "d", "x"])]
     radix: Option<String>,
 
     /// Like -to. (Some other implementations have -o like -to, others like -td.
@@ -150,45 +602,1305 @@ struct CliArgs {
     #[clap(short = 'o')]
     octal_radix: bool,
 
+    /// Pad the offsets to 7 characters like GNU strings does.  By default they are padded to
+    /// the width of the largest offset of the file, so that the strings stay aligned.
+    #[clap(long = "compat-address-format")]
+    compat_address_format: bool,
+
+    /// Pad the offsets to N characters, whatever the size of the input, e.g. 16 for the 64-bit
+    /// addresses of -d.  The longer offsets are not truncated.  By default the width is chosen
+    /// per input as described for --compat-address-format, which matches GNU strings for the
+    /// inputs below 256 MiB.
+    #[clap(long = "address-width", value_name = "N", conflicts_with = "compat-address-format")]
+    address_width: Option<usize>,
+
+    /// Pad the offsets with 'space' (the default, as GNU strings does) or 'zero'.
+    #[clap(long = "address-pad", value_name = "PAD", default_value = "space",
+           value_parser = ["space", "zero"])]
+    address_pad: String,
+
+    /// Print the offset just past the end of each string after its start offset, in the radix
+    /// of -t (hex by default, implies -t x).  The json and csv output always carry the length.
+    #[clap(long = "print-end-offset")]
+    print_end_offset: bool,
+
+    /// Print the number of bytes each string occupies in the input, in decimal.  With the
+    /// 16-bit and 32-bit encodings it is not the number of characters.
+    #[clap(long = "print-length")]
+    print_length: bool,
+
+    /// Print the strings of every input after a header with its name, size and format, e.g.
+    /// '==> a.out (16696 bytes, ELF 64-bit) <==', and the number of its strings after them,
+    /// instead of the name before every string as -f does.  With -d, the strings of every
+    /// section follow a line with its name.
+    #[clap(long = "group-by-file",
+           conflicts_with_all = &["output-format", "quiet-match", "stats-only", "common-across"])]
+    group_by_file: bool,
+
+    /// Print the strings of every input sorted by KEY: 'string', 'length' (the shortest first),
+    /// 'count' (every distinct string once, where it is first found, tagged with the number of
+    /// times it is found, the most frequent first) or 'offset'.  The strings are held until the
+    /// input ends.
+    #[clap(long, value_name = "KEY", value_parser = ["string", "length", "count", "offset"],
+           conflicts_with_all = &["follow", "checkpoint"])]
+    sort: Option<String>,
+
+    /// Reverse the order of --sort.
+    #[clap(long, requires = "sort")]
+    reverse: bool,
+
+    /// Sort the strings of every input on their own ('file', the default) or of all the inputs
+    /// together ('global').
+    #[clap(long = "sort-scope", value_name = "SCOPE", value_parser = ["file", "global"],
+           requires = "sort")]
+    sort_scope: Option<String>,
+
+    /// Browse the strings in the terminal instead of printing them: a scrollable list, filtered
+    /// as the filter is typed after /, with the bytes around the selected string dumped below.
+    /// The terms of the filter must all match: 'enc:NAME' the encoding, 'sec:NAME' the section,
+    /// 're:PATTERN' a regular expression and any other the text.  32 bytes before and after the
+    /// strings are dumped, unless -A, -B or -C set it differently.
+    #[clap(long, conflicts_with_all = &["output", "format", "output-format", "group-by-file",
+                                        "stats", "stats-only", "quiet-match", "common-across",
+                                        "follow", "checkpoint", "raw", "print0",
+                                        "output-separator"])]
+    tui: bool,
+
+    /// Print a hex dump of N bytes of the input after each string, like the context lines of
+    /// grep.  The whole input is read to memory.
+    #[clap(short = 'A', long = "after-context", value_name = "N")]
+    after_context: Option<usize>,
+
+    /// Print a hex dump of N bytes of the input before each string.
+    #[clap(short = 'B', long = "before-context", value_name = "N")]
+    before_context: Option<usize>,
+
+    /// Print a hex dump of N bytes of the input before and after each string, unless -A or -B
+    /// set it differently.
+    #[clap(short = 'C', long = "context", value_name = "N")]
+    context: Option<usize>,
+
     /// By default tab and space are the only whitespace included in graphic char sequences.
     /// This option considers all of isspace() valid.
     #[clap(short = 'w', long="include-all-whitespace")]
     include_all_whitespace: bool,
 
+    /// Make the strings of the characters in SET only, instead of the printable ones: e.g.
+    /// 'a-zA-Z0-9_./:- ' for the identifiers and paths.  A '-' between two letters, digits or
+    /// escapes makes a range, any other '-' is the character itself.  \t, \n, \r, \\, \- and
+    /// \xNN are the escapes.  The characters up to \xff are accepted, the wide encodings and
+    /// EBCDIC are matched by the decoded character.
+    #[clap(long = "chars", value_name = "SET", conflicts_with = "include-all-whitespace")]
+    chars: Option<String>,
+
     /// Select character encoding: 7-bit-character, 8-bit-character, bigendian 16-bit,
     /// littleendian 16-bit, bigendian 32-bit,  littleendian 32-bit. Values are {s,S,b,l,B,L}.
-    #[clap(short, long)]
-    encoding: Option<String>,
+    /// Can be repeated to scan for several encodings in one pass, 'all' selects {s,b,l,B,L}.
+    /// 'auto16' and 'auto32' detect the endianness of every string by BOM or NUL positions.
+    /// 'cp037' and 'cp500' find EBCDIC strings and print them as UTF-8.
+    /// Strings are then tagged with the encoding they were found in.
+    #[clap(short, long, multiple_occurrences = true, value_parser = parse_encoding_arg)]
+    encoding: Vec<String>,
+
+    /// Also look for UTF-16 strings during a 7-bit or 8-bit scan.  The endianness is picked by
+    /// the NUL bytes around every string: mostly at the odd addresses for UTF-16LE, at the even
+    /// ones for UTF-16BE, so a string is found once, in the endianness of its 2-byte aligned code
+    /// units.  The strings are tagged with the encoding they were found in.
+    #[clap(long = "detect-utf16")]
+    detect_utf16: bool,
+
+    /// Decode the strings from a legacy code page, e.g. shift_jis, euc-jp, gbk, koi8-r or
+    /// latin1, and print them as UTF-8.  The invalid byte sequences and the characters which
+    /// are not printable in the code page split the strings.  The names are the ones of the
+    /// web, so latin1 is windows-1252.  Implies --encoding=S.
+    #[clap(long, value_name = "NAME", visible_alias = "8bit-charset")]
+    charset: Option<String>,
+
+    /// Decode the strings from the code page of the locale, from LC_ALL, LC_CTYPE or LANG, like
+    /// --charset does.  The C and POSIX locales have ASCII only, like --encoding=s.
+    #[clap(long, conflicts_with_all = &["charset", "unicode"])]
+    locale: bool,
+
+    /// Also scan the data XOR-ed with every single-byte key, 1-255 by default, and tag the
+    /// strings with the key.  KEYS are comma-separated keys or ranges, e.g. 0x20-0x7f,0xff.
+    /// NUL bytes are not XOR-ed and end the strings.
+    #[clap(long, value_name = "KEYS", min_values = 0, require_equals = true,
+           default_missing_value = xor::DEFAULT_KEYS)]
+    xor: Option<String>,
+
+    /// Find base64 or hex encoded runs in the strings and print what they decode to after the
+    /// string, if it is printable text.  Values are {base64,hex}.  Can be repeated or
+    /// comma-separated.
+    #[clap(long, value_name = "KIND", multiple_occurrences = true)]
+    decode: Vec<String>,
+
+    /// Print the C++ (Itanium ABI) and Rust mangled symbols among the strings demangled, as
+    /// nm -C does.  STYLE is 'replace' (the default) or 'both', printing the symbol in brackets
+    /// after the demangled name.  The filters and --match see the symbols as found.
+    #[clap(long, value_name = "STYLE", min_values = 0, require_equals = true,
+           default_missing_value = "replace", value_parser = ["replace", "both"])]
+    demangle: Option<String>,
+
+    /// Print only the strings with the Shannon entropy of at least BITS per character, e.g. 4.5
+    /// to hunt for keys and tokens.
+    #[clap(long = "min-entropy", value_name = "BITS")]
+    min_entropy: Option<f64>,
+
+    /// Print only the strings with the Shannon entropy of at most BITS per character, e.g. 4.5
+    /// to drop keys and printable runs of compressed data.
+    #[clap(long = "max-entropy", value_name = "BITS")]
+    max_entropy: Option<f64>,
+
+    /// Print only the strings which look like English text, scoring at least SCORE from 0 to 1.
+    /// Prose scores above 0.8, most of the printable garbage below 0.5.  The score is included
+    /// in the json and csv output.
+    #[clap(long = "min-score", value_name = "SCORE")]
+    min_score: Option<f64>,
+
+    /// Print only the strings made of at least N distinct characters, to skip the padding like
+    /// AAAAAAAA or ////////.  2 drops the runs of a single character.
+    #[clap(long = "min-distinct-chars", value_name = "N", default_value_t = 0)]
+    min_distinct_chars: usize,
+
+    /// Decode 16-bit and 32-bit encodings as UTF-16 and UTF-32 and print the strings as UTF-8.
+    /// By default only the characters which fit a single byte are accepted, as GNU strings does.
+    /// The scan stays aligned to the code unit size in this mode.
+    #[clap(long = "decode-wide")]
+    decode_wide: bool,
+
+    /// With 16-bit and 32-bit encodings, don't print the strings followed by an incomplete code
+    /// unit at the end of the input.  The incomplete unit itself is never printed.
+    #[clap(long = "strict-units")]
+    strict_units: bool,
+
+    /// Print only the strings immediately followed by a NUL or a newline, as the strings in the
+    /// data and the string tables usually are.  Drops most of the false positives in code.
+    #[clap(long = "require-terminator")]
+    require_terminator: bool,
+
+    /// Choose the encoding for every 64 KiB region of the input (or of a section with -d):
+    /// ASCII, UTF-16LE or UTF-16BE, whichever most of its text is in.  The choices are printed
+    /// to stderr and the strings are tagged with the encoding.  -e is ignored.
+    #[clap(long = "smart-encoding")]
+    smart_encoding: bool,
+
+    /// Find the strings stored after their length instead of before a NUL, as the serialized
+    /// formats and the older binaries do, and print them from the offset of the length, tagged
+    /// with its size, e.g. {pascal:u16}.  SIZES are comma-separated 'u8', 'u16', 'u32'
+    /// (little-endian), 'u16be' and 'u32be', u8,u16,u32 by default.  The strings must fit in a
+    /// printable run, and must end it if the length is itself printable.  Takes -e s or -e S.
+    #[clap(long, value_name = "SIZES", min_values = 0, require_equals = true,
+           default_missing_value = pascal::DEFAULT_PREFIXES,
+           conflicts_with_all = &["unicode", "smart-encoding", "xor", "charset", "locale",
+                                  "detect-utf16", "raw"])]
+    pascal: Option<String>,
 
     /// Determine how to handle UTF-8 unicode characters.  The default  is no special treatment.
     /// All other versions of this option  only apply if the encoding is valid and enabling the
     /// option implies --encoding=S.  The 'show' option displays the characters according to
-    /// the current locale.  The 'invalid' option treats them as non-string characters.
+    /// the current locale, and 'replace' does the same but keeps the invalid bytes inside the
+    /// strings as U+FFFD.  The 'invalid' option treats them as non-string characters.
     /// The 'hex' option displays them as hex byte sequences.  The 'escape' option displays
-    /// them as escape sequences and the 'highlight' option displays them as coloured escape
-    /// sequences. Values are {default|show|invalid|hex|escape|highlight}.
-    #[clap(short, long)]
+    /// them as \uXXXX escape sequences, with surrogate pairs for the characters above U+FFFF,
+    /// and the 'highlight' option displays them as coloured escape sequences (plain ones
+    /// without --color, the json and csv output list the ranges of the non-ASCII bytes
+    /// instead).  'escape-gnu'
+    /// escapes the characters above U+FFFF as GNU strings does, e.g. U+10348 as \u040348.
+    /// Values are {default|show|replace|invalid|hex|escape|escape-gnu|highlight}.
+    #[clap(short, long, value_parser = UNICODE_VALUES, hide_possible_values = true)]
     unicode: Option<String>,
 
-    /// String used to separate parsed strings in output.  Default is newline.
+    /// String used to separate parsed strings in output.  Default is newline.  The escape
+    /// sequences \n, \r, \t, \0, \\ and \xNN are replaced with the bytes they stand for.
     #[clap(short='s', long="output-separator")]
-    output_separator: Option<String>
+    output_separator: Option<String>,
+
+    /// End each string with a NUL byte instead of a newline, for xargs -0 and the other tools
+    /// which read NUL-separated input.  Safe even if the strings contain newlines, which they
+    /// can with -w.
+    #[clap(short = '0', long = "print0", conflicts_with = "output-separator")]
+    print0: bool,
+
+    /// Write the output to the file at PATH instead of stdout.  With sqlite:PATH, write an
+    /// SQLite database instead, with a row per string in the table 'strings': the file, offset,
+    /// length, encoding, termination, section, string, tags (joined with ';'), xor_key,
+    /// decoded_from and score.
+    #[clap(short = 'O', long = "output", value_name = "PATH")]
+    output: Option<OsString>,
+
+    /// With -O, treat PATH as a directory and write the output of every input to a separate
+    /// file in it, named after the input: a.out.strings, stdin.strings, and so on.  A number
+    /// is added to the names which repeat.
+    #[clap(long = "split-per-input", requires = "output")]
+    split_per_input: bool,
+
+    /// Print the control characters of the strings as \xNN escapes, so that they can't mess up
+    /// the terminal: the carriage returns kept by -w, or the 8-bit controls of -e S which the
+    /// terminals take for escape sequences.  WHEN is 'auto' (when the output is a terminal, the
+    /// default), 'always' or 'never'.  The json and csv output are escaped anyway.
+    #[clap(long = "escape-output", value_name = "WHEN", default_value = "auto",
+           min_values = 0, require_equals = true, default_missing_value = "always",
+           value_parser = ["auto", "always", "never"])]
+    escape_output: String,
+
+    /// Write the bytes every string occupies in the input, unchanged, instead of its text: the
+    /// 8-bit strings are not made valid UTF-8, and the 16-bit and 32-bit ones keep their code
+    /// units, also with --decode-wide.  -u chooses the strings found, not the bytes written.
+    /// Implies --escape-output=never.
+    #[clap(long, conflicts_with_all = &["charset", "locale", "xor", "decode", "demangle",
+                                        "redact", "output-format"])]
+    raw: bool,
+
+    /// Draw a progress bar on stderr while scanning the files which take longer than half a
+    /// second.  WHEN is 'auto' (when stderr is a terminal and the output is not, the default),
+    /// 'always' or 'never'.  There is no bar for stdin, pipes and the scans needing the whole
+    /// file in memory.
+    #[clap(long = "progress", value_name = "WHEN", default_value = "auto",
+           min_values = 0, require_equals = true, default_missing_value = "always",
+           value_parser = ["auto", "always", "never"])]
+    progress: String,
+
+    /// Page the output, as git does: through STRINGS_PAGER, PAGER or less, run with LESS=FRX
+    /// unless LESS is set, so that it quits if the strings fit on one screen and keeps the
+    /// colours.  WHEN is 'auto' (when the output is a terminal, the default of --pager alone),
+    /// 'always' or 'never' (the default).  A pager named cat, or empty, turns it off.
+    #[clap(long = "pager", value_name = "WHEN", default_value = "never",
+           min_values = 0, require_equals = true, default_missing_value = "auto",
+           value_parser = ["auto", "always", "never"], conflicts_with_all = &["output", "tui"])]
+    pager: String,
+
+    /// Keep scanning the file as it grows, like tail -f, and print the new strings as they are
+    /// appended, until interrupted.  A string is printed once its end is written.  Takes a
+    /// single file, not stdin.
+    #[clap(long, conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                        "debug-strings", "go", "java", "macho-literals",
+                                        "overlay", "hex-records", "pdf", "stack-strings",
+                                        "packages", "context", "before-context",
+                                        "after-context", "oci", "pid", "partitions",
+                                        "decompress", "archives", "carve", "firmware"])]
+    follow: bool,
+
+    /// Save the offset the scan is at to FILE every 10 seconds, so that a scan of a large image
+    /// which is interrupted resumes from there when it is run again with the same FILE.  The
+    /// strings found after the last save are printed again, and -O appends to the output of
+    /// the interrupted scan.  FILE is removed once the scan ends.  Takes a single file.
+    #[clap(long, value_name = "FILE",
+           conflicts_with_all = &["data", "segments", "pe-resources", "dotnet",
+                                  "debug-strings", "go", "java", "macho-literals", "overlay",
+                                  "hex-records", "pdf", "stack-strings", "packages", "context",
+                                  "before-context", "after-context", "oci", "pid", "partitions",
+                                  "decompress", "archives", "carve", "firmware", "follow",
+                                  "streams", "stats", "stats-only", "common-across",
+                                  "split-per-input"])]
+    checkpoint: Option<OsString>,
+
+    /// Colour the file names, the offsets and the characters highlighted with -u highlight.
+    /// WHEN is 'auto' (when the output is a terminal and NO_COLOR is not set, the default),
+    /// 'always' or 'never'.
+    #[clap(long = "color", value_name = "WHEN", default_value = "auto",
+           min_values = 0, require_equals = true, default_missing_value = "always",
+           value_parser = ["auto", "always", "never"])]
+    color: String,
+
+    /// Scan the files inside deb, rpm and apk packages as PACKAGE!path, after a line with the
+    /// package name, version and architecture.  Other files are scanned as usual.
+    #[clap(long)]
+    packages: bool,
+
+    /// Scan the files inside tar archives, also compressed, and zip archives such as .jar and
+    /// .apk, as ARCHIVE!path.  Other files are scanned as usual.
+    #[clap(long)]
+    archives: bool,
+
+    /// Scan the decompressed data of the gzip, xz, zstd and bzip2 files and stdin, recognized by
+    /// their magic numbers, as FILE[gzip] and so on.  The data is decompressed while it is
+    /// scanned, unless the whole of it is needed, e.g. for -d or --context.  Other inputs are
+    /// scanned as usual.
+    #[clap(short = 'z', long)]
+    decompress: bool,
+
+    /// Scan the files of a container image: a `docker save` tarball or an OCI image layout
+    /// packed with tar.  The layers, plain or compressed with gzip, zstd or bzip2, are applied
+    /// with their whiteouts and the strings are attributed to IMAGE!LAYER:path.  Can be repeated.
+    #[clap(long, value_name = "IMAGE", multiple_occurrences = true)]
+    oci: Vec<OsString>,
+
+    /// Scan the memory of a running process on Linux: every readable mapping of it, as
+    /// PID!MAPPING, where MAPPING is the mapped file, [heap], [stack] or [anon].  The offsets
+    /// are the virtual addresses.  Needs the permission to trace the process.  Can be repeated.
+    #[clap(long, value_name = "PID", multiple_occurrences = true)]
+    pid: Vec<u32>,
+
+    /// Scan the other data streams of the files too, as FILE:STREAM: the alternate data streams
+    /// of NTFS on Windows and the resource forks on macOS.  Other systems have none.
+    #[clap(long)]
+    streams: bool,
+
+    /// Don't expand archives nested deeper than N levels: the files of a deb package or of a
+    /// container image are 2 levels deep.  Compressed data doesn't add a level.
+    #[clap(long = "max-depth-containers", value_name = "N", default_value_t = limits::DEFAULT_MAX_DEPTH)]
+    max_depth_containers: usize,
+
+    /// Don't expand compressed data which grows more than N times, 0 means no limit.  The
+    /// containers hitting a limit are skipped with a warning.  With -z the size of the input
+    /// may not be known, so the data is scanned until it grows more than N times the
+    /// compressed data read so far.
+    #[clap(long = "max-expansion-ratio", value_name = "N", default_value_t = limits::DEFAULT_MAX_EXPANSION_RATIO)]
+    max_expansion_ratio: u64,
+
+    /// Output format. 'text' is the GNU strings format, 'json' prints an object per line with
+    /// the file, offset, length, encoding, termination (how the string ends: nul, newline,
+    /// non-printable, eof or section-end) and the string, 'csv' prints the same fields with a
+    /// header row. 'msgpack' and 'cbor' write the records of 'json' as a stream of MessagePack
+    /// or CBOR maps, which are smaller and faster to read. Values are
+    /// {text,json,csv,msgpack,cbor}.
+    #[clap(long, default_value = "text", value_name = "FORMAT",
+           value_parser = ["text", "json", "csv", "msgpack", "cbor"])]
+    format: String,
+
+    /// Add the hash of each string to the structured output: 'xxh3' is the 64-bit XXH3, fast to
+    /// deduplicate the strings, 'sha256' the SHA-256 the threat intelligence feeds are looked up
+    /// by.  The hash is of the string as output, in UTF-8.  Values are {xxh3,sha256}.
+    #[clap(long, value_name = "ALGORITHM", value_parser = ["xxh3", "sha256"],
+           conflicts_with_all = &["output-format", "common-across"])]
+    hash: Option<String>,
+
+    /// Lay out each string with the TEMPLATE instead of --format, e.g.
+    /// "{file}:{offset:#x} {string}".  The placeholders are file, offset, end, length,
+    /// section (with -d and --debug-strings), encoding, termination, string and tags.  The
+    /// numbers take a spec like {offset:#010x}: '#' for the 0x prefix, '0' and the width to
+    /// pad, and the radix x, X, o or d (the radix of -t by default).  '<' and '>' align, {{
+    /// and }} are braces.
+    #[clap(long = "output-format", value_name = "TEMPLATE", conflicts_with = "format")]
+    output_format: Option<String>,
+
+    /// Exit with a non-zero code if no strings are found.  Same as --expect-min=1.
+    #[clap(long = "fail-if-empty")]
+    fail_if_empty: bool,
+
+    /// Exit with a non-zero code if fewer than N strings are found in all inputs together.  The
+    /// strings dropped by the filters, e.g. --match or --min-entropy, don't count.
+    #[clap(long = "expect-min", value_name = "N")]
+    expect_min: Option<u64>,
+
+    /// Exit with a non-zero code unless some string contains STRING.  Can be repeated.
+    #[clap(long = "assert-contains", value_name = "STRING", multiple_occurrences = true)]
+    assert_contains: Vec<String>,
+
+    /// Exit with a non-zero code unless some string matches REGEX.  Can be repeated.
+    #[clap(long = "assert-regex", value_name = "REGEX", multiple_occurrences = true)]
+    assert_regex: Vec<String>,
+
+    /// Exit with a non-zero code if any string contains STRING.  Can be repeated.
+    #[clap(long = "assert-absent", value_name = "STRING", multiple_occurrences = true)]
+    assert_absent: Vec<String>,
+
+    /// Print only the strings which match REGEX, coloured with --color.  Can be repeated to
+    /// print the strings matching any of them.  The decoded text of the strings is matched.
+    #[clap(long = "match", value_name = "REGEX", multiple_occurrences = true)]
+    match_patterns: Vec<String>,
+
+    /// Print only the strings with likely credentials, tagged with their kind: AWS access keys,
+    /// private key headers, JSON web tokens, bearer tokens, and the passwords of connection
+    /// strings and URLs.  The values which don't look random, e.g. placeholders, are ignored.
+    /// Exits with 5 if any is found, to gate the release artifacts in CI.
+    #[clap(long)]
+    secrets: bool,
+
+    /// Print nothing and exit with 0 at the first string matching --match, 1 if there is none,
+    /// like grep -q.  Useful to check that a binary doesn't contain debug paths or secrets.
+    #[clap(long = "quiet-match", requires = "match-patterns",
+           conflicts_with_all = &["expect-min", "fail-if-empty", "assert-contains",
+                                  "assert-regex", "assert-absent"])]
+    quiet_match: bool,
+
+    /// After the strings, print per input the number of strings, the size, a histogram of the
+    /// string lengths and the counts per encoding and per section.  JSON lines with
+    /// --format json.
+    #[clap(long)]
+    stats: bool,
+
+    /// Like --stats, but print only the statistics, not the strings.
+    #[clap(long = "stats-only", conflicts_with = "quiet-match")]
+    stats_only: bool,
+
+    /// Print only the strings found in at least N of the inputs, once all of them are scanned,
+    /// each after the names of the inputs it is found in, e.g. 'a.exe, b.exe: string'.  The
+    /// files inside an archive or an image count as their input.  The strings found in the
+    /// most inputs come first.  JSON lines with --format json.
+    #[clap(long = "common-across", value_name = "N",
+           value_parser = clap::value_parser!(u64).range(1..),
+           conflicts_with_all = &["stats-only", "quiet-match", "output-format", "follow",
+                                  "split-per-input"])]
+    common_across: Option<u64>,
+
+    /// Print only the strings not printed before, by this run or by the previous runs with the
+    /// same PATH, and remember them in PATH for the next runs, e.g. to see only the new strings
+    /// in the nightly scans of a corpus.  PATH is created by the first run.  It is a Bloom
+    /// filter of 16 MiB, so a few new strings are taken for seen ones once it holds millions of
+    /// them.
+    #[clap(long = "seen-db", value_name = "PATH", conflicts_with_all = &["quiet-match", "follow"])]
+    seen_db: Option<OsString>,
+
+    /// When done, print to stderr the bytes scanned, the time taken and the speed for every
+    /// input and in total.
+    #[clap(long)]
+    time: bool,
+
+    /// Mask the matching parts of the printed strings with '*', keeping their lengths.
+    /// Values are {secrets,emails,ips,custom:REGEX}.  Can be repeated or comma-separated.
+    #[clap(long, value_name = "KIND", multiple_occurrences = true)]
+    redact: Vec<String>,
+
+    /// Tag the strings using the classifier from the given WASM module, see `classifier.rs` for
+    /// the interface it implements.  Can be repeated.  Requires the wasm-classifier feature.
+    #[clap(long, value_name = "PLUGIN", multiple_occurrences = true)]
+    classifier: Vec<OsString>,
+
+    /// Tag the strings with the kinds of data they contain: url, email, ipv4, ipv6, path,
+    /// registry-key, guid and format-string, the printf conversions like %s and the {}
+    /// placeholders of the Rust, Python and .NET format strings.
+    #[clap(long)]
+    classify: bool,
+
+    /// Print only the strings of the given kinds, implies --classify.  Values are the tags of
+    /// --classify or their plurals, 'ips' is both ipv4 and ipv6.  Can be repeated or
+    /// comma-separated, e.g. --only urls,ips.  The data of the kinds is coloured with --color,
+    /// e.g. the conversions of --only format-strings.
+    #[clap(long, value_name = "KINDS", multiple_occurrences = true)]
+    only: Vec<String>,
+}
+
+/**
+An input of a run: a file, a container image, the memory of a process or stdin.
+ */
+#[derive(Copy, Clone)]
+enum Input<'a> {
+    File(&'a OsStr),
+    Image(&'a OsStr),
+    Process(u32),
+    Stdin,
+}
+
+/**
+Returns the name of the input as the timings and --common-across report it.
+ */
+fn input_name(input: &Input, label: &OsStr) -> String {
+    return match *input {
+        Input::File(name) | Input::Image(name) => name.to_string_lossy().into_owned(),
+        Input::Process(pid) => format!("pid {}", pid),
+        Input::Stdin => label.to_string_lossy().into_owned(),
+    };
+}
+
+/**
+Returns the path of the output of the input with `--split-per-input`: its file name with the
+`.strings` extension in the output directory, with a number added if the name is taken.
+ */
+fn split_output_path(directory: &OsStr, input: Input, taken: &mut HashSet<OsString>) -> PathBuf {
+    let name = match input {
+        Input::File(path) | Input::Image(path) => {
+            Path::new(path).file_name().unwrap_or(path).to_os_string()
+        }
+        Input::Process(pid) => OsString::from(format!("pid-{}", pid)),
+        Input::Stdin => OsString::from("stdin"),
+    };
+
+    let mut candidate = name.clone();
+    let mut number = 1;
+    while !taken.insert(candidate.clone()) {
+        number += 1;
+        candidate = name.clone();
+        candidate.push(format!("-{}", number));
+    }
+    candidate.push(".strings");
+    return Path::new(directory).join(candidate);
+}
+
+fn create_output(path: &Path) -> BufWriter<File> {
+    return match File::create(path) {
+        Ok(file) => BufWriter::new(file),
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(EXIT_FAILURE)
+        }
+    };
+}
+
+/**
+Opens the output file, see `--checkpoint`, appending to it if `append`.
+ */
+fn open_unbuffered_output(path: &Path, append: bool) -> File {
+    let file = OpenOptions::new().write(true).create(true).append(append).truncate(!append)
+        .open(path);
+    return match file {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(EXIT_FAILURE)
+        }
+    };
+}
+
+/**
+Returns the command line with the default options of the config file and of `STRINGS_OPTS`
+added, see `config.rs`.
+ */
+fn with_defaults(args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let mut sources = Vec::new();
+    if let Some(path) = config::config_path() {
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let name = path.display().to_string();
+                let config_args = config::config_args(&text)
+                    .map_err(|err| format!("{}: {}", name, err))?;
+                sources.push((name, config_args));
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+    if let Some(value) = std::env::var_os(config::ENV_VAR) {
+        sources.push((config::ENV_VAR.to_string(), config::env_args(&value)));
+    }
+    if sources.is_empty() {
+        return Ok(args);
+    }
+    return config::merge_args(&CliArgs::command(), EXCLUSIVE_OPTIONS, &sources, args);
+}
+
+/**
+The printer of `--quiet-match`: the first string which gets through all the filters is a match,
+so the run ends there.
+ */
+struct ExitOnString;
+
+impl StringSink for ExitOnString {
+    fn on_string(&mut self, _found: &FoundString) {
+        std::process::exit(0)
+    }
+}
+
+/**
+The printer of `--stats-only`: the strings are only counted.
+ */
+struct DiscardStrings;
+
+impl StringSink for DiscardStrings {
+    fn on_string(&mut self, _found: &FoundString) {}
+}
+
+/**
+Counts the strings which reach the printer, that is the ones left by the filters, for
+`--expect-min`.
+ */
+struct CountingSink<'a> {
+    num_found: &'a Cell<u64>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for CountingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        self.num_found.set(self.num_found.get() + 1);
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run an HTTP server which returns the strings of the posted data as JSON.
+    Serve(ServeArgs),
+    /// Compare the strings of two inputs: list the strings found only in A, only in B and in
+    /// both, in the order of their offsets, with the offset in hex of the first occurrence and
+    /// the number of the occurrences.  The options of the scan, e.g. -n or -e, go before
+    /// 'diff'; with --format json the strings are printed as JSON lines.
+    Diff(DiffArgs),
+    /// Make the skeleton of a YARA rule matching the samples, of the strings found in the most
+    /// of them which score best by their length, entropy and rarity.  The strings get the
+    /// 'ascii' or 'wide' modifier as they are found by the 8-bit or the UTF-16LE scan.  The
+    /// options of the scan, e.g. -n, go before 'yara-gen'.
+    YaraGen(YaraGenArgs),
+}
+
+#[derive(Args, Debug)]
+struct YaraGenArgs {
+    /// Number of strings in the rule.
+    #[clap(long, value_name = "N", default_value_t = 20,
+           value_parser = clap::value_parser!(u64).range(1..))]
+    top: u64,
+
+    /// Name of the rule, by default the name of the first sample.
+    #[clap(long = "rule-name", value_name = "NAME")]
+    rule_name: Option<String>,
+
+    /// The samples the rule is for.
+    #[clap(value_name = "FILE", required = true)]
+    files: Vec<OsString>,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// The first input, A.
+    #[clap(value_name = "A")]
+    a: OsString,
+
+    /// The second input, B.
+    #[clap(value_name = "B")]
+    b: OsString,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on.
+    #[clap(long = "http", value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    address: String,
+
+    /// Reject requests with a body larger than this many bytes.
+    #[clap(long = "max-size", value_name = "BYTES", default_value = "104857600")]
+    max_size: u64,
+
+    /// Number of requests handled at the same time, the others wait.
+    #[clap(long = "max-concurrent", value_name = "N", default_value = "4")]
+    max_concurrent: usize,
+
+    /// Allow scanning the files under DIR by their path, GET /strings?path=PATH.  Without it
+    /// only the uploaded data is scanned.
+    #[clap(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    /// Serve the scan over gRPC too, on this address: strings.Strings/Scan of
+    /// proto/strings.proto.  Needs the grpc feature.
+    #[clap(long = "grpc", value_name = "ADDR")]
+    grpc_address: Option<String>,
+}
+
+impl CliArgs {
+    fn expected_min_strings(&self) -> u64 {
+        let mut expected_min = self.expect_min.unwrap_or(0);
+        if self.fail_if_empty && expected_min == 0 {
+            expected_min = 1;
+        }
+        return expected_min;
+    }
+
+    /**
+    Returns the path of the database if the output is `sqlite:PATH`.
+     */
+    fn sqlite_output(&self) -> Option<&OsStr> {
+        let path = self.output.as_deref()?.to_str()?.strip_prefix("sqlite:")?;
+        return Some(OsStr::new(path));
+    }
+}
+
+#[cfg(feature = "serve")]
+fn run_server(args: &ServeArgs) -> Result<(), String> {
+    use strings::serve;
+
+    return serve::serve(&serve::ServeOptions {
+        address: args.address.clone(),
+        max_body_bytes: args.max_size,
+        max_concurrent: args.max_concurrent,
+        root: args.root.clone(),
+        grpc_address: args.grpc_address.clone(),
+    });
+}
+
+#[cfg(not(feature = "serve"))]
+fn run_server(_args: &ServeArgs) -> Result<(), String> {
+    return Err("strings was built without the serve feature".to_string());
+}
+
+/**
+Prints the strings found only in one of the two inputs and the ones in both, see `diff`.
+Returns the exit code.
+ */
+fn run_diff(args: &DiffArgs, options: &Options) -> i32 {
+    let mut a = diff::OccurrenceCollector::new();
+    let mut b = diff::OccurrenceCollector::new();
+    if print_strings_for_file(&args.a, options, &mut a).is_none()
+        || print_strings_for_file(&args.b, options, &mut b).is_none() {
+        return EXIT_FAILURE;
+    }
+    let diff = diff::diff_strings(a, b);
+
+    let stdout = stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    match options.output_format {
+        OutputFormat::Json => diff::print_diff_json(&diff, &mut writer),
+        _ => diff::print_diff(
+            &diff, &args.a.to_string_lossy(), &args.b.to_string_lossy(), &mut writer,
+        ),
+    }
+    diagnostics::check_write(writer.flush());
+    return 0;
+}
+
+fn run_yara_gen(args: &YaraGenArgs, options: &Options) -> i32 {
+    let mut collector = yara::CandidateCollector::new();
+    for (index, file) in args.files.iter().enumerate() {
+        collector.input = index;
+        if print_strings_for_file(file, options, &mut collector).is_none() {
+            return EXIT_FAILURE;
+        }
+    }
+    let strings = collector.rank(args.top as usize);
+    if strings.is_empty() {
+        eprintln!("No strings to make a rule of");
+        return EXIT_FAILURE;
+    }
+
+    let samples: Vec<String> = args.files.iter()
+        .map(|file| Path::new(file).display().to_string())
+        .collect();
+    let name = match &args.rule_name {
+        Some(name) => yara::rule_name(name),
+        None => {
+            let file_name = Path::new(&args.files[0]).file_name().unwrap_or_default();
+            yara::rule_name(&file_name.to_string_lossy())
+        }
+    };
+    let stdout = stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    yara::write_rule(&name, &samples, &strings, &mut writer);
+    diagnostics::check_write(writer.flush());
+    return 0;
 }
 
 fn main() {
-    let cli_args = CliArgs::parse();
+    let args = match response_files::expand_args(std::env::args_os()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+    let mut cli_args = CliArgs::parse_from(&args);
+    if !cli_args.no_config {
+        match with_defaults(args) {
+            Ok(args) => cli_args = CliArgs::parse_from(args),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(EXIT_USAGE)
+            }
+        }
+    }
+
+    if let Some(Command::Serve(serve_args)) = &cli_args.command {
+        if let Err(err) = run_server(serve_args) {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_FAILURE)
+        }
+        std::process::exit(0)
+    }
+
+    let run_options = match new_options(&cli_args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+
+    if let Some(Command::Diff(diff_args)) = &cli_args.command {
+        std::process::exit(run_diff(diff_args, &run_options))
+    }
+    if let Some(Command::YaraGen(yara_args)) = &cli_args.command {
+        std::process::exit(run_yara_gen(yara_args, &run_options))
+    }
+
+    let expected_min_strings = cli_args.expected_min_strings();
+
+    let mut assertions = match Assertions::new(
+        &cli_args.assert_contains, &cli_args.assert_regex, &cli_args.assert_absent,
+    ) {
+        Ok(assertions) => assertions,
+        Err(err) => {
+            eprintln!("invalid argument to --assert-regex: {}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+
+    let demangle_style = cli_args.demangle.as_deref().and_then(DemangleStyle::parse);
+    let sort_key = cli_args.sort.as_deref().and_then(SortKey::parse);
+    let sorts_globally = cli_args.sort_scope.as_deref() == Some("global");
+    if sorts_globally && cli_args.group_by_file {
+        eprintln!("--sort-scope=global can't be combined with --group-by-file");
+        std::process::exit(EXIT_USAGE)
+    }
+    if cli_args.tui && !cfg!(feature = "tui") {
+        eprintln!("--tui: strings was built without the tui feature");
+        std::process::exit(EXIT_USAGE)
+    }
+    if cli_args.tui && atty::isnt(Stream::Stdout) {
+        eprintln!("--tui needs a terminal");
+        std::process::exit(EXIT_USAGE)
+    }
+
+    let redactor = match Redactor::new(&cli_args.redact) {
+        Ok(redactor) => redactor,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+
+    let matcher = match Matcher::new(&cli_args.match_patterns) {
+        Ok(matcher) => matcher,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+
+    let template = match cli_args.output_format.as_deref().map(Template::parse) {
+        Some(Ok(template)) => Some(template),
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+        None => None
+    };
+
+    let categorizer = match Categorizer::new(cli_args.classify, &cli_args.only) {
+        Ok(categorizer) => categorizer,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+    // the matches of --match and the data of the kinds of --only are coloured alike
+    let highlighter = Matcher::new(&[
+        cli_args.match_patterns.clone(), categorizer.only_patterns(),
+    ].concat()).expect("The patterns are valid");
+
+    let mut classifiers = Vec::new();
+    for path in &cli_args.classifier {
+        match WasmClassifier::load(Path::new(path)) {
+            Ok(classifier) => classifiers.push(classifier),
+            Err(err) => {
+                eprintln!("invalid argument to --classifier: {}", err);
+                std::process::exit(EXIT_USAGE)
+            }
+        }
+    }
 
-    let run_options = Options::new(&cli_args);
+    let mut seen = match &cli_args.seen_db {
+        Some(path) => match SeenSet::load(Path::new(path)) {
+            Ok(seen) => Some(seen),
+            Err(err) => {
+                eprintln!("invalid argument to --seen-db: {}", err);
+                std::process::exit(EXIT_USAGE)
+            }
+        },
+        None => None,
+    };
 
-    let mut success = true;
+    diagnostics::set_quiet(cli_args.quiet);
 
-    if !cli_args.files.is_empty() {
-        for file in cli_args.files {
-            success &= strings::print_strings_for_file(file.as_os_str(), &run_options);
+    let files = if cli_args.glob || cfg!(windows) {
+        match globs::expand(&cli_args.files) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(EXIT_USAGE)
+            }
         }
     } else {
-        strings::print_strings_for_stdin(&run_options);
+        cli_args.files.clone()
+    };
+    if cli_args.follow && (files.len() != 1 || files[0] == "-") {
+        eprintln!("--follow takes a single file");
+        std::process::exit(EXIT_USAGE)
+    }
+    if cli_args.checkpoint.is_some() && (files.len() != 1 || files[0] == "-") {
+        eprintln!("--checkpoint takes a single file");
+        std::process::exit(EXIT_USAGE)
+    }
+
+    let mut inputs = Vec::new();
+    inputs.extend(files.iter().map(|file| {
+        return if file == "-" { Input::Stdin } else { Input::File(file) };
+    }));
+    inputs.extend(cli_args.oci.iter().map(|image| Input::Image(image)));
+    inputs.extend(cli_args.pid.iter().map(|pid| Input::Process(*pid)));
+    if inputs.is_empty() {
+        inputs.push(Input::Stdin);
+    }
+
+    let print_stats = cli_args.stats || cli_args.stats_only;
+    let sqlite_output = cli_args.sqlite_output();
+    if sqlite_output.is_some()
+        && (print_stats || template.is_some() || cli_args.common_across.is_some()) {
+        eprintln!("-O sqlite: can't be combined with --stats, --output-format or --common-across");
+        std::process::exit(EXIT_USAGE)
+    }
+    let binary_output = matches!(run_options.output_format, OutputFormat::Binary(_));
+    if cli_args.checkpoint.is_some() && (sqlite_output.is_some() || binary_output) {
+        eprintln!("--checkpoint can't be combined with -O sqlite:, --format msgpack or cbor");
+        std::process::exit(EXIT_USAGE)
+    }
+    let mut checkpoint = match &cli_args.checkpoint {
+        Some(path) => match Checkpoint::load(Path::new(path)) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(err) => {
+                eprintln!("invalid argument to --checkpoint: {}", err);
+                std::process::exit(EXIT_USAGE)
+            }
+        },
+        None => None,
+    };
+    // a resumed scan adds to the output of the interrupted one
+    let append_output = checkpoint.as_ref().is_some_and(Checkpoint::is_resumed);
+    let output = sqlite_output.or(cli_args.output.as_deref());
+    // started before the scan, so that the first strings show while the rest are searched for
+    let pager = match cli_args.pager.as_str() {
+        "always" => Pager::start(),
+        "auto" if atty::is(Stream::Stdout) => Pager::start(),
+        _ => None,
+    };
+    let mut timings = Vec::new();
+    let run_started = Instant::now();
+    // the input being scanned, for --common-across
+    let current_input = Cell::new(0usize);
+    let secret_detector = cli_args.secrets.then(SecretDetector::new);
+    let num_secrets = Cell::new(0u64);
+    // returns the number of strings printed and false if some input couldn't be read
+    let mut scan_inputs = |writer: &mut dyn Write, inputs: &[Input]| -> (u64, bool) {
+        let mut success = true;
+        let num_found = Cell::new(0u64);
+
+        let mut text_printer = None;
+        let mut json_printer;
+        let mut csv_printer;
+        let mut binary_printer;
+        let mut template_printer;
+        let mut sqlite_printer = None;
+        let mut collector = None;
+        let mut common_strings = None;
+        let mut exit_on_string;
+        let mut discarded;
+        let printer: &mut dyn StringSink = match (&template, run_options.output_format) {
+            _ if cli_args.quiet_match => {
+                exit_on_string = ExitOnString;
+                &mut exit_on_string
+            }
+            _ if cli_args.stats_only => {
+                discarded = DiscardStrings;
+                &mut discarded
+            }
+            _ if cli_args.tui => collector.insert(StringCollector::new(run_options.decode_wide)),
+            _ if sqlite_output.is_some() => {
+                sqlite_printer.insert(SqlitePrinter::new(writer, &run_options))
+            }
+            _ if cli_args.common_across.is_some() => {
+                common_strings.insert(CommonStrings::new(&current_input))
+            }
+            (Some(template), _) => {
+                template_printer = TemplatePrinter::new(writer, template, &run_options);
+                &mut template_printer
+            }
+            (None, OutputFormat::Text) => {
+                text_printer.insert(StringPrinter::new(writer, &run_options))
+            }
+            (None, OutputFormat::Json) => {
+                json_printer = JsonPrinter::new(writer, &run_options);
+                &mut json_printer
+            }
+            (None, OutputFormat::Csv) => {
+                csv_printer = CsvPrinter::new(writer, &run_options);
+                &mut csv_printer
+            }
+            (None, OutputFormat::Binary(format)) => {
+                binary_printer = BinaryPrinter::new(writer, format, &run_options);
+                &mut binary_printer
+            }
+        };
+        let mut counted_strings = CountingSink { num_found: &num_found, inner: printer };
+        let printer: &mut dyn StringSink = &mut counted_strings;
+
+        let mut escaping;
+        let printer: &mut dyn StringSink = if run_options.escape_output {
+            escaping = EscapingSink::new(printer);
+            &mut escaping
+        } else {
+            printer
+        };
+
+        let mut highlighted;
+        let printer: &mut dyn StringSink = if run_options.color && !highlighter.is_empty()
+            && template.is_none() && matches!(run_options.output_format, OutputFormat::Text)
+            && !cli_args.tui {
+            highlighted = MatchHighlighter::new(&highlighter, printer);
+            &mut highlighted
+        } else {
+            printer
+        };
+
+        // assertions are checked against the original strings
+        let mut redacted = RedactingSink::new(&redactor, printer);
+        let mut demangled;
+        let redacted: &mut dyn StringSink = match demangle_style {
+            Some(style) => {
+                demangled = DemanglingSink::new(style, &mut redacted);
+                &mut demangled
+            }
+            None => &mut redacted,
+        };
+        let mut sorting = None;
+        let redacted: &mut dyn StringSink = match sort_key {
+            Some(key) => {
+                sorting.insert(SortingSink::new(key, cli_args.reverse, sorts_globally, redacted))
+            }
+            None => redacted,
+        };
+        // only the strings passing the filters are remembered as seen
+        let mut unseen;
+        let redacted: &mut dyn StringSink = match seen.as_mut() {
+            Some(seen) => {
+                unseen = SeenFilter::new(seen, redacted);
+                &mut unseen
+            }
+            None => redacted,
+        };
+        // classifiers look at the original strings too
+        let mut classified = ClassifyingSink::new(&mut classifiers, redacted);
+        let mut input_stats = Vec::new();
+        let mut counted;
+        let classified: &mut dyn StringSink = if print_stats {
+            counted = StatsSink::new(&mut input_stats, &mut classified);
+            &mut counted
+        } else {
+            &mut classified
+        };
+        // assertions see the strings dropped by the filters as well
+        let mut categorized = CategorizingSink::new(&categorizer, classified);
+        let mut scoring;
+        let scored: &mut dyn StringSink = if run_options.min_score.is_some() {
+            scoring = ScoringSink::new(&run_options, &mut categorized);
+            &mut scoring
+        } else {
+            &mut categorized
+        };
+        let mut filtered;
+        let entropy_filtered: &mut dyn StringSink = if run_options.filters_entropy() {
+            filtered = EntropyFilter::new(&run_options, scored);
+            &mut filtered
+        } else {
+            scored
+        };
+        let mut without_padding;
+        let output: &mut dyn StringSink = if run_options.min_distinct_chars > 0 {
+            without_padding = DistinctCharsFilter::new(&run_options, entropy_filtered);
+            &mut without_padding
+        } else {
+            entropy_filtered
+        };
+        let mut terminated;
+        let output: &mut dyn StringSink = if run_options.require_terminator {
+            terminated = TerminatorFilter::new(output);
+            &mut terminated
+        } else {
+            output
+        };
+        let mut matched;
+        let output: &mut dyn StringSink = if matcher.is_empty() {
+            output
+        } else {
+            matched = MatchFilter::new(&matcher, output);
+            &mut matched
+        };
+        let mut with_secrets;
+        let output: &mut dyn StringSink = match &secret_detector {
+            Some(detector) => {
+                with_secrets = SecretsFilter::new(detector, &num_secrets, output);
+                &mut with_secrets
+            }
+            None => output,
+        };
+        let mut all_sinks = MultiSink::new(vec![output, &mut assertions]);
+        let mut decoding;
+        let decoded: &mut dyn StringSink = if run_options.decode.is_empty() {
+            &mut all_sinks
+        } else {
+            decoding = DecodingSink::new(&run_options, &mut all_sinks);
+            &mut decoding
+        };
+        let mut transcoded;
+        let sink: &mut dyn StringSink = match run_options.charset {
+            Some(charset) => {
+                transcoded = CharsetSink::new(charset, &run_options, decoded);
+                &mut transcoded
+            }
+            None => decoded
+        };
+        // for --time, cheap enough to be always there
+        let mut counted_bytes = ByteCounter::new(sink);
+
+        for (index, input) in inputs.iter().enumerate() {
+            current_input.set(index);
+            let started = Instant::now();
+            let sink: &mut dyn StringSink = &mut counted_bytes;
+            let input_success = match *input {
+                Input::File(file) if checkpoint.is_some() => {
+                    let checkpoint = checkpoint.as_mut().unwrap();
+                    checkpoint::print_strings_with_checkpoint(file, checkpoint, &run_options, sink)
+                }
+                Input::File(file) => {
+                    let found_in_file = print_strings_for_file(file, &run_options, sink);
+                    match found_in_file {
+                        Some(found_in_file) if cli_args.streams => {
+                            streams::print_strings_for_streams(file, &run_options, sink)
+                                .map(|found_in_streams| found_in_file + found_in_streams)
+                        }
+                        _ => found_in_file,
+                    }
+                }
+                Input::Image(image) => oci::print_strings_for_image(image, &run_options, sink),
+                Input::Process(pid) => process::print_strings_for_process(pid, &run_options, sink),
+                Input::Stdin => print_strings_for_stdin(&cli_args.label, &run_options, sink),
+            };
+            success &= input_success.is_some();
+            if cli_args.time {
+                timings.push(InputTiming {
+                    name: input_name(input, &cli_args.label),
+                    bytes: counted_bytes.take_bytes(),
+                    elapsed: started.elapsed(),
+                });
+            }
+        }
+
+        if let Some(mut sorting) = sorting {
+            sorting.finish();
+        }
+        if let Some(collector) = collector {
+            if let Err(err) = tui::browse(collector.strings) {
+                eprintln!("{}", err);
+                std::process::exit(EXIT_FAILURE)
+            }
+        }
+        if let Some(printer) = sqlite_printer {
+            printer.finish();
+        }
+        if let Some(mut printer) = text_printer {
+            printer.finish();
+        }
+        if let (Some(collected), Some(min_inputs)) = (common_strings, cli_args.common_across) {
+            let names: Vec<String> = inputs.iter()
+                .map(|input| input_name(input, &cli_args.label))
+                .collect();
+            let common = collected.common(min_inputs as usize);
+            match run_options.output_format {
+                OutputFormat::Json => common::print_common_json(&common, &names, writer),
+                _ => common::print_common(&common, &names, writer),
+            }
+        }
+        if print_stats {
+            match run_options.output_format {
+                OutputFormat::Json => stats::print_stats_json(&input_stats, writer),
+                _ => stats::print_stats(&input_stats, writer),
+            }
+        }
+        return (num_found.get(), success);
+    };
+
+    let (num_found, success) = match output {
+        Some(directory) if cli_args.split_per_input => {
+            if let Err(err) = fs::create_dir_all(directory) {
+                eprintln!("{}: {}", Path::new(directory).display(), err);
+                std::process::exit(EXIT_FAILURE)
+            }
+            let mut taken = HashSet::new();
+            let mut num_found = 0u64;
+            let mut success = true;
+            for input in &inputs {
+                let path = split_output_path(directory, *input, &mut taken);
+                let mut writer = create_output(&path);
+                let (found_in_input, input_success) = scan_inputs(&mut writer, &[*input]);
+                diagnostics::check_write(writer.flush());
+                num_found += found_in_input;
+                success &= input_success;
+            }
+            (num_found, success)
+        }
+        // the strings are written as they are found, so that no checkpoint counts the ones
+        // still in a buffer
+        Some(path) if cli_args.checkpoint.is_some() => {
+            let mut writer = open_unbuffered_output(Path::new(path), append_output);
+            scan_inputs(&mut writer, &inputs)
+        }
+        Some(path) => {
+            let mut writer = create_output(Path::new(path));
+            let result = scan_inputs(&mut writer, &inputs);
+            diagnostics::check_write(writer.flush());
+            result
+        }
+        None => match pager {
+            Some(pager) => {
+                let mut writer = BufWriter::new(pager);
+                let result = scan_inputs(&mut writer, &inputs);
+                diagnostics::check_write(writer.flush());
+                if let Ok(pager) = writer.into_inner() {
+                    pager.finish();
+                }
+                result
+            }
+            None => {
+                let stdout = stdout();
+                let mut writer = stdout.lock();
+                let result = scan_inputs(&mut writer, &inputs);
+                diagnostics::check_write(writer.flush());
+                result
+            }
+        },
+    };
+
+    if let (Some(seen), Some(path)) = (seen, &cli_args.seen_db) {
+        if let Err(err) = seen.save(Path::new(path)) {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_FAILURE)
+        }
+    }
+
+    if cli_args.time {
+        timing::print_timings(&timings, run_started.elapsed(), &mut std::io::stderr());
+    }
+
+    // a match would have ended the run
+    if cli_args.quiet_match {
+        std::process::exit(EXIT_FAILURE)
+    }
+
+    if !success || (cli_args.strict && diagnostics::num_warnings() > 0) {
+        std::process::exit(EXIT_FAILURE)
+    }
+
+    if !assertions.is_empty() {
+        let failures = assertions.failures();
+        for failure in &failures {
+            eprintln!("Assertion failed: {}", failure);
+        }
+        if !failures.is_empty() {
+            std::process::exit(EXIT_ASSERTION_FAILED)
+        }
+    }
+
+    if num_secrets.get() > 0 {
+        eprintln!("Found {} string(s) with likely secrets", num_secrets.get());
+        std::process::exit(EXIT_SECRETS_FOUND)
+    }
+
+    if num_found < expected_min_strings {
+        eprintln!("Expected at least {} string(s), found {}", expected_min_strings, num_found);
+        std::process::exit(EXIT_NOT_ENOUGH_STRINGS)
     }
 
-    std::process::exit((!success).into())
+    std::process::exit(0)
 }
@@ -0,0 +1,149 @@
+/*
+ Selection of the strings by regular expressions, see `--match`: only the strings with a match
+ of any of the patterns are printed, with the matches coloured like grep does with `--color`.
+ The patterns are matched against the text of the strings, so they work the same for all the
+ encodings.
+ */
+
+use std::ffi::OsStr;
+use regex::bytes::Regex;
+use super::color;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+pub struct Matcher {
+    patterns: Vec<Regex>,
+}
+
+impl Matcher {
+    /**
+    Compiles the `--match` patterns. Returns an error message for an invalid one.
+     */
+    pub fn new(patterns: &[String]) -> Result<Matcher, String> {
+        let mut compiled = Vec::new();
+        for pattern in patterns {
+            compiled.push(Regex::new(pattern).map_err(
+                |err| format!("invalid argument to --match: {}", err)
+            )?);
+        }
+        return Ok(Matcher { patterns: compiled });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.patterns.is_empty();
+    }
+
+    pub fn is_match(&self, text: &[u8]) -> bool {
+        return self.patterns.iter().any(|pattern| pattern.is_match(text));
+    }
+
+    /**
+    Returns the copy of the text with the matches wrapped in the colour codes, the overlapping
+    matches of different patterns merged.
+     */
+    pub fn highlight(&self, text: &[u8]) -> Vec<u8> {
+        let mut ranges: Vec<(usize, usize)> = self.patterns.iter()
+            .flat_map(|pattern| pattern.find_iter(text))
+            .filter(|found| !found.is_empty())
+            .map(|found| (found.start(), found.end()))
+            .collect();
+        ranges.sort_unstable();
+
+        let mut highlighted = Vec::with_capacity(text.len() + 16);
+        let mut position = 0;
+        for (start, end) in ranges {
+            if end <= position {
+                continue;
+            }
+            let start = start.max(position);
+            highlighted.extend_from_slice(&text[position..start]);
+            highlighted.extend_from_slice(color::MATCH.as_bytes());
+            highlighted.extend_from_slice(&text[start..end]);
+            highlighted.extend_from_slice(color::RESET.as_bytes());
+            position = end;
+        }
+        highlighted.extend_from_slice(&text[position..]);
+        return highlighted;
+    }
+}
+
+/**
+Passes only the strings with a match to the inner sink.
+ */
+pub struct MatchFilter<'a> {
+    matcher: &'a Matcher,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> MatchFilter<'a> {
+    pub fn new(matcher: &'a Matcher, inner: &'a mut dyn StringSink) -> MatchFilter<'a> {
+        return MatchFilter { matcher, inner };
+    }
+}
+
+impl StringSink for MatchFilter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.matcher.is_match(found.text) {
+            self.inner.on_string(found);
+        }
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Colours the matches in the displayed text of the strings. It goes after the redaction, so the
+masked parts are not matched anymore.
+ */
+pub struct MatchHighlighter<'a> {
+    matcher: &'a Matcher,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> MatchHighlighter<'a> {
+    pub fn new(matcher: &'a Matcher, inner: &'a mut dyn StringSink) -> MatchHighlighter<'a> {
+        return MatchHighlighter { matcher, inner };
+    }
+}
+
+impl StringSink for MatchHighlighter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let text = self.matcher.highlight(found.text);
+        self.inner.on_string(&found.with_contents(found.bytes, &text));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_and_highlight() {
+        let matcher = Matcher::new(&["/home/\\w+".to_string(), "[0-9]+".to_string()]).unwrap();
+
+        assert!(matcher.is_match(b"built in /home/ci/src"));
+        assert!(!matcher.is_match(b"plain text"));
+        assert_eq!(
+            format!(
+                "{m}/home/user1{r}/x {m}42{r}",
+                m = color::MATCH, r = color::RESET
+            ).into_bytes(),
+            matcher.highlight(b"/home/user1/x 42")
+        );
+        assert!(Matcher::new(&["(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+/*
+ Scanning of container images (`--oci image.tar`): `docker save` output or an OCI image layout
+ packed in a tarball. The layers are applied in order, honoring the whiteouts, and every regular
+ file of the resulting filesystem is scanned as `image.tar!LAYER:path`, where LAYER is the digest
+ of the layer which provided the file.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tar::{Archive, EntryType};
+use super::decompress::{self, Compression};
+use super::diagnostics;
+use super::limits::{self, ContainerLimits, ExpansionLimiter};
+use super::strings::{self, Options, StringSink};
+
+/// Prefix of the entries hiding the file with the rest of the name in the lower layers.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// Entry hiding all the contents of its directory in the lower layers.
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+/// Limit of the nested image indexes to follow.
+const MAX_INDEX_DEPTH: usize = 4;
+
+/// An entry of `manifest.json` written by `docker save`.
+#[derive(Deserialize)]
+struct DockerManifest {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+/// Either an image index or an image manifest of the OCI layout.
+#[derive(Deserialize)]
+struct OciManifest {
+    #[serde(default)]
+    manifests: Vec<OciDescriptor>,
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct OciDescriptor {
+    digest: String,
+}
+
+/// Location of a file in the image tarball.
+#[derive(Copy, Clone)]
+struct Blob {
+    offset: u64,
+    size: u64,
+}
+
+struct Layer {
+    name: String,
+    blob: Blob,
+}
+
+/**
+Prints the strings of all the files of the image. Returns `None` if the image couldn't be read.
+ */
+pub fn print_strings_for_image(
+    image_path: &OsStr,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let image_name = image_path.to_string_lossy();
+
+    let file = match File::open(Path::new(image_path)) {
+        Ok(file) => file,
+        Err(err) => {
+            diagnostics::error(format_args!("{}: {}", image_name, err));
+            return None;
+        }
+    };
+
+    return match scan_image(image_path, BufReader::new(file), options, sink) {
+        Ok(num_found) => Some(num_found),
+        Err(err) => {
+            diagnostics::error(format_args!("{}: {}", image_name, err));
+            None
+        }
+    };
+}
+
+fn scan_image<R: Read + Seek>(
+    image_path: &OsStr,
+    mut image: R,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Result<u64, String> {
+    let image_name = image_path.to_string_lossy();
+    let container_limits = &options.container_limits;
+    // the image tarball is the first level, the layers are the second one
+    if let Err(limit) = container_limits.check_depth(2) {
+        limits::warn(&image_name, &limit);
+        return Ok(0);
+    }
+
+    let blobs = index_blobs(&mut image)?;
+    let layers = find_layers(&mut image, &blobs)?;
+
+    // path -> index of the layer which provides the file in the final filesystem
+    let mut providers: HashMap<String, usize> = HashMap::new();
+    // layers which hit the expansion limit are left out
+    let mut skipped: HashSet<usize> = HashSet::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let (entries, exceeded) = match open_layer(&mut image, layer, container_limits) {
+            Ok(mut archive) => {
+                let entries = list_entries(&mut archive);
+                (entries, archive.into_inner().exceeded())
+            }
+            Err(err) => (Err(err), None),
+        };
+        // the decompression has a limit of its own, which fails the reads
+        let exceeded = exceeded.or_else(|| entries.as_ref().err().and_then(limits::limit_of));
+        match (entries, exceeded) {
+            (_, Some(limit)) => {
+                limits::warn(&format!("{}!{}", image_name, layer.name), &limit);
+                skipped.insert(i);
+            }
+            (Ok(entries), None) => {
+                for (path, entry_type) in entries {
+                    apply_entry(&mut providers, i, &path, entry_type);
+                }
+            }
+            (Err(err), None) => return Err(layer_error(layer, err)),
+        }
+    }
+
+    let mut num_found = 0u64;
+    for (i, layer) in layers.iter().enumerate() {
+        if skipped.contains(&i) {
+            continue;
+        }
+        let mut archive = open_layer(&mut image, layer, container_limits)
+            .map_err(|err| layer_error(layer, err))?;
+        for entry in archive.entries().map_err(|err| layer_error(layer, err))? {
+            let mut entry = entry.map_err(|err| layer_error(layer, err))?;
+            let path = normalize_path(&entry.path().map_err(|err| layer_error(layer, err))?);
+
+            if providers.get(&path) != Some(&i) || !entry.header().entry_type().is_file() {
+                continue;
+            }
+            // the same path can appear twice in a layer, only the last entry counts
+            providers.remove(&path);
+
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).map_err(|err| layer_error(layer, err))?;
+
+            let mut name = image_path.to_os_string();
+            name.push(format!("!{}:{}", layer.name, path));
+            num_found += strings::print_strings_for_bytes(&name, &data, options, sink);
+        }
+    }
+
+    return Ok(num_found);
+}
+
+/**
+Returns the locations of all the files in the image tarball.
+ */
+fn index_blobs<R: Read + Seek>(image: &mut R) -> Result<HashMap<String, Blob>, String> {
+    let not_tar = |err: std::io::Error| format!("not a container image tarball: {}", err);
+    let mut blobs = HashMap::new();
+
+    let mut archive = Archive::new(image);
+    for entry in archive.entries_with_seek().map_err(not_tar)? {
+        let entry = entry.map_err(not_tar)?;
+        let path = normalize_path(&entry.path().map_err(not_tar)?);
+        blobs.insert(path, Blob { offset: entry.raw_file_position(), size: entry.size() });
+    }
+
+    return Ok(blobs);
+}
+
+/**
+Returns the layers of the image from the bottom one to the top one. If the tarball holds several
+images, only the first one is scanned.
+ */
+fn find_layers<R: Read + Seek>(
+    image: &mut R,
+    blobs: &HashMap<String, Blob>,
+) -> Result<Vec<Layer>, String> {
+    let find_blob = |path: &str| {
+        return blobs.get(&normalize_path(Path::new(path))).copied().ok_or_else(
+            || format!("missing {} in the image", path)
+        );
+    };
+
+    if let Some(manifest) = blobs.get("manifest.json") {
+        let manifests: Vec<DockerManifest> = read_json(image, *manifest, "manifest.json")?;
+        let manifest = manifests.first().ok_or("no images in manifest.json")?;
+
+        let mut layers = Vec::new();
+        for path in &manifest.layers {
+            layers.push(Layer { name: layer_name(path), blob: find_blob(path)? });
+        }
+        return Ok(layers);
+    }
+
+    if let Some(index) = blobs.get("index.json") {
+        let mut manifest: OciManifest = read_json(image, *index, "index.json")?;
+        for _ in 0..MAX_INDEX_DEPTH {
+            if manifest.manifests.is_empty() {
+                break;
+            }
+            let path = digest_path(&manifest.manifests[0].digest);
+            manifest = read_json(image, find_blob(&path)?, &path)?;
+        }
+
+        let mut layers = Vec::new();
+        for layer in &manifest.layers {
+            layers.push(Layer {
+                name: layer.digest.clone(),
+                blob: find_blob(&digest_path(&layer.digest))?,
+            });
+        }
+        return Ok(layers);
+    }
+
+    return Err("not a container image: no manifest.json or index.json found".to_string());
+}
+
+fn read_json<R: Read + Seek, T: DeserializeOwned>(
+    image: &mut R,
+    blob: Blob,
+    name: &str,
+) -> Result<T, String> {
+    image.seek(SeekFrom::Start(blob.offset)).map_err(|err| err.to_string())?;
+    return serde_json::from_reader(image.take(blob.size)).map_err(
+        |err| format!("couldn't parse {}: {}", name, err)
+    );
+}
+
+fn open_layer<'a, R: Read + Seek>(
+    image: &'a mut R,
+    layer: &Layer,
+    limits: &ContainerLimits,
+) -> std::io::Result<Archive<ExpansionLimiter<Box<dyn Read + 'a>>>> {
+    image.seek(SeekFrom::Start(layer.blob.offset))?;
+
+    let mut reader = BufReader::new(image.take(layer.blob.size));
+    let magic = reader.fill_buf()?;
+
+    let data: Box<dyn Read + 'a> = match Compression::detect(magic) {
+        None => Box::new(reader),
+        Some(compression) => {
+            decompress::decompressing_reader(compression, Box::new(reader), limits)?
+        }
+    };
+
+    return Ok(Archive::new(limits.limit_expansion(data, layer.blob.size)));
+}
+
+fn list_entries<R: Read>(archive: &mut Archive<R>) -> std::io::Result<Vec<(String, EntryType)>> {
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        entries.push((normalize_path(&entry.path()?), entry.header().entry_type()));
+    }
+    return Ok(entries);
+}
+
+fn layer_error(layer: &Layer, err: std::io::Error) -> String {
+    return format!("layer {}: {}", layer.name, err);
+}
+
+/**
+Updates the providers of the files with an entry of the layer `layer`.
+ */
+fn apply_entry(
+    providers: &mut HashMap<String, usize>,
+    layer: usize,
+    path: &str,
+    entry_type: EntryType,
+) {
+    let (dir, base) = path.rsplit_once('/').unwrap_or(("", path));
+
+    if base == OPAQUE_WHITEOUT {
+        providers.retain(|file, provider| *provider == layer || !is_inside(file, dir));
+    } else if let Some(hidden) = base.strip_prefix(WHITEOUT_PREFIX) {
+        let hidden_path = if dir.is_empty() { hidden.to_string() } else { format!("{}/{}", dir, hidden) };
+        providers.retain(|file, provider| {
+            return *provider == layer || (*file != hidden_path && !is_inside(file, &hidden_path));
+        });
+    } else if entry_type.is_file() {
+        providers.insert(path.to_string(), layer);
+    } else if !entry_type.is_dir() {
+        // links and special files replace the files of the lower layers
+        providers.remove(path);
+    }
+}
+
+fn is_inside(path: &str, dir: &str) -> bool {
+    return dir.is_empty() || (path.starts_with(dir) && path[dir.len()..].starts_with('/'));
+}
+
+fn normalize_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    return path.trim_start_matches("./").trim_start_matches('/').trim_end_matches('/').to_string();
+}
+
+/**
+Name of a layer of `docker save` output: the digest if the layer is stored as a blob, otherwise
+the path of the layer in the tarball.
+ */
+fn layer_name(path: &str) -> String {
+    let path = normalize_path(Path::new(path));
+    if let Some(digest) = path.strip_prefix("blobs/") {
+        if let Some((algorithm, hash)) = digest.split_once('/') {
+            return format!("{}:{}", algorithm, hash);
+        }
+    }
+    return path;
+}
+
+fn digest_path(digest: &str) -> String {
+    return format!("blobs/{}", digest.replacen(':', "/", 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::fixtures::{gzip, tar, xz, zstd};
+    use crate::strings::FoundString;
+
+    struct Collector {
+        found: Vec<String>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push(format!(
+                "{} {}", found.filename.to_string_lossy(), String::from_utf8_lossy(found.bytes)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_scan_oci_image() {
+        let bottom = gzip(&tar(&[
+            ("etc/passwd", b"root:x:0:0"),
+            ("etc/hosts", b"localhost"),
+            ("opt/app/old.conf", b"old config"),
+        ]));
+        let middle = xz(&tar(&[("usr/share/motd", b"from the xz layer")]));
+        let top = zstd(&tar(&[
+            ("./etc/.wh.passwd", b""),
+            ("opt/app/.wh..wh..opq", b""),
+            ("opt/app/new.conf", b"new config"),
+        ]));
+        let manifest = br#"{"layers": [
+            {"digest": "sha256:bottom"}, {"digest": "sha256:middle"}, {"digest": "sha256:top"}
+        ]}"#;
+        let index = br#"{"manifests": [{"digest": "sha256:manifest"}]}"#;
+
+        let image = tar(&[
+            ("oci-layout", b"{}"),
+            ("index.json", index),
+            ("blobs/sha256/manifest", manifest),
+            ("blobs/sha256/bottom", &bottom),
+            ("blobs/sha256/middle", &middle),
+            ("blobs/sha256/top", &top),
+        ]);
+
+        let mut collector = Collector { found: Vec::new() };
+        let num_found = scan_image(
+            OsStr::new("image.tar"), Cursor::new(image), &Options::default(), &mut collector,
+        ).unwrap();
+
+        assert_eq!(3, num_found);
+        assert_eq!(
+            vec![
+                "image.tar!sha256:bottom:etc/hosts localhost".to_string(),
+                "image.tar!sha256:middle:usr/share/motd from the xz layer".to_string(),
+                "image.tar!sha256:top:opt/app/new.conf new config".to_string(),
+            ],
+            collector.found
+        );
+    }
+
+    #[test]
+    fn test_scan_oci_image_expansion_limit() {
+        let bottom = gzip(&tar(&[("etc/hosts", b"localhost")]));
+        // 64 KiB of zeros, stopped while it is decompressed
+        let zeros = std::fs::read("test-resources/zeros.xz").unwrap();
+        let top = tar(&[("etc/motd", b"welcome")]);
+        let image = tar(&[
+            (
+                "manifest.json",
+                br#"[{"Layers": ["bottom/layer.tar", "zeros/layer.tar", "top/layer.tar"]}]"#,
+            ),
+            ("bottom/layer.tar", &bottom),
+            ("zeros/layer.tar", &zeros),
+            ("top/layer.tar", &top),
+        ]);
+
+        let mut options = Options::default();
+        options.container_limits.max_expansion_ratio = 2;
+        let mut collector = Collector { found: Vec::new() };
+        scan_image(OsStr::new("image.tar"), Cursor::new(image), &options, &mut collector).unwrap();
+
+        assert_eq!(vec!["image.tar!top/layer.tar:etc/motd welcome".to_string()], collector.found);
+    }
+
+    #[test]
+    fn test_layer_name() {
+        assert_eq!("sha256:abcd", layer_name("blobs/sha256/abcd"));
+        assert_eq!("0123/layer.tar", layer_name("0123/layer.tar"));
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ The overlay of PE and ELF files, see `--overlay`: the bytes appended past the end of the image,
+ where installers keep their archives and droppers their payloads. The end of the image is the
+ end of the last of the headers, the section and program header tables and the data of the
+ sections and segments; nothing there is read by the loader. The Authenticode signature of a PE
+ file is appended the same way and so it is a part of the overlay.
+ */
+
+use std::ffi::OsStr;
+use object::elf::{FileHeader32, FileHeader64, SHT_NOBITS};
+use object::pe::{ImageNtHeaders32, ImageNtHeaders64};
+use object::read::elf::{FileHeader, ProgramHeader, SectionHeader};
+use object::read::pe::{ImageNtHeaders, ImageOptionalHeader, PeFile};
+use object::{Endianness, FileKind};
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/**
+Returns the offset where the overlay of a PE or ELF file starts, which is the size of the file
+if it has none. Returns `None` if the data is neither.
+ */
+pub(crate) fn overlay_start(data: &[u8]) -> Option<u64> {
+    let image_end = match FileKind::parse(data).ok()? {
+        FileKind::Pe32 => pe_image_end::<ImageNtHeaders32>(data)?,
+        FileKind::Pe64 => pe_image_end::<ImageNtHeaders64>(data)?,
+        FileKind::Elf32 => elf_image_end::<FileHeader32<Endianness>>(data)?,
+        FileKind::Elf64 => elf_image_end::<FileHeader64<Endianness>>(data)?,
+        _ => return None,
+    };
+    return Some(image_end.min(data.len() as u64));
+}
+
+fn pe_image_end<Pe: ImageNtHeaders>(data: &[u8]) -> Option<u64> {
+    let file = PeFile::<Pe>::parse(data).ok()?;
+    let mut end = file.nt_headers().optional_header().size_of_headers() as u64;
+    for section in file.section_table().iter() {
+        let offset = section.pointer_to_raw_data.get(object::LittleEndian) as u64;
+        let size = section.size_of_raw_data.get(object::LittleEndian) as u64;
+        if size > 0 {
+            end = end.max(offset + size);
+        }
+    }
+    return Some(end);
+}
+
+fn elf_image_end<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Option<u64> {
+    let header = Elf::parse(data).ok()?;
+    let endian = header.endian().ok()?;
+
+    let table_end = |offset: u64, count: u16, entry_size: u16| -> u64 {
+        return if count == 0 { 0 } else { offset + count as u64 * entry_size as u64 };
+    };
+    let mut end = header.e_ehsize(endian) as u64;
+    end = end.max(table_end(
+        header.e_phoff(endian).into(), header.e_phnum(endian), header.e_phentsize(endian)
+    ));
+    end = end.max(table_end(
+        header.e_shoff(endian).into(), header.e_shnum(endian), header.e_shentsize(endian)
+    ));
+    for segment in header.program_headers(endian, data).ok()? {
+        let offset: u64 = segment.p_offset(endian).into();
+        let size: u64 = segment.p_filesz(endian).into();
+        end = end.max(offset + size);
+    }
+    for section in header.section_headers(endian, data).ok()? {
+        if section.sh_type(endian) != SHT_NOBITS {
+            let offset: u64 = section.sh_offset(endian).into();
+            let size: u64 = section.sh_size(endian).into();
+            end = end.max(offset + size);
+        }
+    }
+    return Some(end);
+}
+
+/**
+Tags the strings found in the overlay with their offset from its start, `overlay+0xN`, their
+address staying the offset in the file.
+ */
+pub(crate) struct OverlaySink<'a> {
+    pub start: u64,
+    pub inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for OverlaySink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut tags = found.tags.to_vec();
+        tags.push(format!("overlay+{:#x}", found.address - self.start));
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_start() {
+        for path in ["test-resources/a.out", "test-resources/pe.exe"] {
+            let mut data = std::fs::read(path).unwrap();
+            let size = data.len() as u64;
+            assert_eq!(Some(size), overlay_start(&data), "{}", path);
+            data.extend(b"\0appended payload\0");
+            assert_eq!(Some(size), overlay_start(&data), "{}", path);
+        }
+        assert_eq!(None, overlay_start(b"plain text, not an image"));
+    }
+}
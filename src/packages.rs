@@ -0,0 +1,487 @@
+/*
+ Scanning of the files inside software packages (`--packages`): Debian .deb (ar archive with
+ control and data tarballs), .rpm (headers followed by a compressed cpio payload) and Alpine
+ .apk (concatenated gzipped tar segments). The files are scanned as `package.deb!path/in/package`
+ and the package metadata is reported before them.
+ */
+
+use std::io::Read;
+use std::path::Path;
+use serde::Serialize;
+use super::decompress::{decompress, decompress_if_needed, Compression};
+use super::diagnostics;
+use super::limits::{self, ContainerLimits, ExpandError};
+use super::strings::{self, Options, StringSink};
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const RPM_MAGIC: &[u8] = &[0xed, 0xab, 0xee, 0xdb];
+const RPM_HEADER_MAGIC: &[u8] = &[0x8e, 0xad, 0xe8, 0x01];
+const RPM_LEAD_SIZE: usize = 96;
+
+const RPM_TAG_NAME: u32 = 1000;
+const RPM_TAG_VERSION: u32 = 1001;
+const RPM_TAG_RELEASE: u32 = 1002;
+const RPM_TAG_ARCH: u32 = 1022;
+const RPM_TYPE_STRING: u32 = 6;
+
+const CPIO_NEWC_MAGIC: &[u8] = b"070701";
+const CPIO_CRC_MAGIC: &[u8] = b"070702";
+const CPIO_HEADER_SIZE: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+
+/**
+Metadata of a package, reported before its strings.
+ */
+#[derive(Default, Debug, PartialEq, Serialize)]
+pub struct PackageInfo {
+    /// `deb`, `rpm` or `apk`
+    pub format: &'static str,
+    pub name: String,
+    pub version: String,
+    pub architecture: String,
+}
+
+/**
+A regular file of a package or an archive, with its path inside it.
+ */
+pub(crate) struct PackageFile {
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+struct Package {
+    info: PackageInfo,
+    files: Vec<PackageFile>,
+}
+
+/**
+Prints the strings of all the files in the package. Returns `None` if the file is not a package
+(or a broken one), so that it can be scanned as is.
+ */
+pub fn print_strings_for_package(
+    file_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let filename = file_path.as_os_str();
+
+    let mut magic = [0u8; 8];
+    let num_read = std::fs::File::open(file_path)
+        .and_then(|mut file| file.read(&mut magic))
+        .ok()?;
+    if !might_be_package(&magic[..num_read]) {
+        return None;
+    }
+
+    let data = std::fs::read(file_path).ok()?;
+    let package = match parse_package(&data, &options.container_limits) {
+        Ok(Some(package)) => package,
+        Ok(None) => return None,
+        Err(ExpandError::Limit(limit)) => {
+            limits::warn(&filename.to_string_lossy(), &limit);
+            return None;
+        }
+        Err(ExpandError::Invalid(err)) => {
+            diagnostics::warning(format_args!(
+                "'{}' is a broken package ({}), scanned as is", file_path.display(), err
+            ));
+            return None;
+        }
+    };
+
+    sink.on_package(filename, &package.info);
+
+    let mut num_found = 0u64;
+    for file in &package.files {
+        let mut name = filename.to_os_string();
+        name.push(format!("!{}", file.path));
+        num_found += strings::print_strings_for_bytes(&name, &file.data, options, sink);
+    }
+    return Some(num_found);
+}
+
+fn might_be_package(magic: &[u8]) -> bool {
+    return magic.starts_with(AR_MAGIC)
+        || magic.starts_with(RPM_MAGIC)
+        || Compression::detect(magic) == Some(Compression::Gzip);
+}
+
+/**
+Returns `None` if the data is not a package.
+ */
+fn parse_package(data: &[u8], limits: &ContainerLimits) -> Result<Option<Package>, ExpandError> {
+    limits.check_depth(1)?;
+
+    if data.starts_with(AR_MAGIC) {
+        return parse_deb(data, limits);
+    }
+    if data.starts_with(RPM_MAGIC) {
+        return parse_rpm(data, limits).map(Some);
+    }
+    if Compression::detect(data) == Some(Compression::Gzip) {
+        return parse_apk(data, limits);
+    }
+    return Ok(None);
+}
+
+// region deb
+
+fn parse_deb(data: &[u8], limits: &ContainerLimits) -> Result<Option<Package>, ExpandError> {
+    let mut archive = ar::Archive::new(data);
+    let mut info = PackageInfo { format: "deb", ..Default::default() };
+    let mut files = Vec::new();
+    let mut is_deb = false;
+
+    while let Some(entry) = archive.next_entry() {
+        let mut entry = entry.map_err(|err| err.to_string())?;
+        let name = String::from_utf8_lossy(entry.header().identifier()).to_string();
+
+        let mut member = Vec::new();
+        entry.read_to_end(&mut member).map_err(|err| err.to_string())?;
+
+        if name == "debian-binary" {
+            is_deb = true;
+        } else if name.starts_with("control.tar") {
+            limits.check_depth(2)?;
+            for file in read_tar(&decompress_if_needed(member, limits)?)? {
+                if file.path == "control" {
+                    parse_deb_control(&String::from_utf8_lossy(&file.data), &mut info);
+                }
+            }
+        } else if name.starts_with("data.tar") {
+            limits.check_depth(2)?;
+            files = read_tar(&decompress_if_needed(member, limits)?)?;
+        }
+    }
+
+    if !is_deb {
+        return Ok(None);
+    }
+    return Ok(Some(Package { info, files }));
+}
+
+fn parse_deb_control(control: &str, info: &mut PackageInfo) {
+    for line in control.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            match key {
+                "Package" => info.name = value.trim().to_string(),
+                "Version" => info.version = value.trim().to_string(),
+                "Architecture" => info.architecture = value.trim().to_string(),
+                _ => {}
+            }
+        }
+    }
+}
+
+// endregion
+
+// region rpm
+
+fn parse_rpm(data: &[u8], limits: &ContainerLimits) -> Result<Package, ExpandError> {
+    // the signature header is aligned to 8 bytes, the main header follows it
+    let (_, signature_size) = parse_rpm_header(data, RPM_LEAD_SIZE)?;
+    let header_start = RPM_LEAD_SIZE + ((signature_size + 7) & !7);
+    let (header, header_size) = parse_rpm_header(data, header_start)?;
+
+    let version = header.string(RPM_TAG_VERSION);
+    let release = header.string(RPM_TAG_RELEASE);
+    let info = PackageInfo {
+        format: "rpm",
+        name: header.string(RPM_TAG_NAME),
+        version: if release.is_empty() { version } else { format!("{}-{}", version, release) },
+        architecture: header.string(RPM_TAG_ARCH),
+    };
+
+    let payload = &data[header_start + header_size..];
+    limits.check_depth(2)?;
+    let files = read_cpio(&decompress_if_needed(payload.to_vec(), limits)?)?;
+
+    return Ok(Package { info, files });
+}
+
+struct RpmHeader<'a> {
+    // (tag, type, offset) of every index entry
+    entries: Vec<(u32, u32, usize)>,
+    store: &'a [u8],
+}
+
+impl RpmHeader<'_> {
+    fn string(&self, tag: u32) -> String {
+        for (entry_tag, entry_type, offset) in &self.entries {
+            if *entry_tag == tag && *entry_type == RPM_TYPE_STRING {
+                let value = self.store.get(*offset..).unwrap_or(&[]);
+                let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                return String::from_utf8_lossy(&value[..end]).to_string();
+            }
+        }
+        return String::new();
+    }
+}
+
+/**
+Returns the header at `start` and its size in bytes.
+ */
+fn parse_rpm_header(data: &[u8], start: usize) -> Result<(RpmHeader<'_>, usize), String> {
+    let read_u32 = |offset: usize| -> Result<u32, String> {
+        return data.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| "truncated rpm header".to_string());
+    };
+
+    if data.get(start..start + 4) != Some(RPM_HEADER_MAGIC) {
+        return Err("bad rpm header magic".to_string());
+    }
+    let num_entries = read_u32(start + 8)? as usize;
+    let store_size = read_u32(start + 12)? as usize;
+
+    let index_start = start + 16;
+    let store_start = index_start + num_entries * 16;
+    let store = data.get(store_start..store_start + store_size).ok_or("truncated rpm header")?;
+
+    let mut entries = Vec::with_capacity(num_entries);
+    for i in 0..num_entries {
+        let entry = index_start + i * 16;
+        entries.push((read_u32(entry)?, read_u32(entry + 4)?, read_u32(entry + 8)? as usize));
+    }
+
+    return Ok((RpmHeader { entries, store }, 16 + num_entries * 16 + store_size));
+}
+
+/**
+Returns the regular files of a cpio archive in the "new ASCII" format, used by rpm.
+ */
+fn read_cpio(data: &[u8]) -> Result<Vec<PackageFile>, String> {
+    let mut files = Vec::new();
+    let mut position = 0usize;
+
+    loop {
+        let header = data.get(position..position + CPIO_HEADER_SIZE).ok_or("truncated cpio")?;
+        if !header.starts_with(CPIO_NEWC_MAGIC) && !header.starts_with(CPIO_CRC_MAGIC) {
+            return Err("unsupported cpio format".to_string());
+        }
+
+        let field = |index: usize| -> Result<usize, String> {
+            let hex = std::str::from_utf8(&header[6 + index * 8..6 + (index + 1) * 8])
+                .map_err(|err| err.to_string())?;
+            return usize::from_str_radix(hex, 16).map_err(|err| err.to_string());
+        };
+        let mode = field(1)?;
+        let file_size = field(6)?;
+        let name_size = field(11)?;
+
+        let name_start = position + CPIO_HEADER_SIZE;
+        let name = data.get(name_start..name_start + name_size).ok_or("truncated cpio")?;
+        let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(name)).to_string();
+        if name == CPIO_TRAILER {
+            break;
+        }
+
+        let data_start = align4(name_start + name_size);
+        let contents = data.get(data_start..data_start + file_size).ok_or("truncated cpio")?;
+        // hard links share the data, which is stored only with the last of them
+        if mode & 0o170000 == 0o100000 && file_size > 0 {
+            files.push(PackageFile { path: normalize_path(&name), data: contents.to_vec() });
+        }
+
+        position = align4(data_start + file_size);
+    }
+
+    return Ok(files);
+}
+
+fn align4(position: usize) -> usize {
+    return (position + 3) & !3;
+}
+
+// endregion
+
+// region apk
+
+fn parse_apk(data: &[u8], limits: &ContainerLimits) -> Result<Option<Package>, ExpandError> {
+    // the segments have no end-of-archive blocks, so together they read as a single tar
+    let files = match read_tar(&decompress(Compression::Gzip, data, limits)?) {
+        Ok(files) => files,
+        Err(_) => return Ok(None)
+    };
+
+    let mut info = PackageInfo { format: "apk", ..Default::default() };
+    let mut is_apk = false;
+    let mut payload = Vec::new();
+
+    for file in files {
+        if file.path == ".PKGINFO" {
+            is_apk = true;
+            parse_pkginfo(&String::from_utf8_lossy(&file.data), &mut info);
+        } else if !file.path.starts_with(".SIGN.") {
+            payload.push(file);
+        }
+    }
+
+    if !is_apk {
+        return Ok(None);
+    }
+    return Ok(Some(Package { info, files: payload }));
+}
+
+fn parse_pkginfo(pkginfo: &str, info: &mut PackageInfo) {
+    for line in pkginfo.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            match key.trim() {
+                "pkgname" => info.name = value.trim().to_string(),
+                "pkgver" => info.version = value.trim().to_string(),
+                "arch" => info.architecture = value.trim().to_string(),
+                _ => {}
+            }
+        }
+    }
+}
+
+// endregion
+
+/**
+Returns the regular files of a tar archive.
+ */
+pub(crate) fn read_tar(data: &[u8]) -> Result<Vec<PackageFile>, String> {
+    let mut files = Vec::new();
+
+    let mut archive = tar::Archive::new(data);
+    for entry in archive.entries().map_err(|err| err.to_string())? {
+        let mut entry = entry.map_err(|err| err.to_string())?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = normalize_path(&entry.path().map_err(|err| err.to_string())?.to_string_lossy());
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).map_err(|err| err.to_string())?;
+        files.push(PackageFile { path, data: contents });
+    }
+
+    return Ok(files);
+}
+
+pub(crate) fn normalize_path(path: &str) -> String {
+    return path.trim_start_matches("./").trim_start_matches('/').to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{gzip, tar};
+
+    fn paths(package: &Package) -> Vec<&str> {
+        return package.files.iter().map(|file| file.path.as_str()).collect();
+    }
+
+    #[test]
+    fn test_parse_deb() {
+        let mut deb = ar::Builder::new(Vec::new());
+        let members: [(&str, Vec<u8>); 3] = [
+            ("debian-binary", b"2.0\n".to_vec()),
+            ("control.tar.gz", gzip(&tar(&[("./control", b"Package: hello\nVersion: 2.10-2\nArchitecture: amd64\n")]))),
+            ("data.tar", tar(&[("./usr/bin/hello", b"hello, world")])),
+        ];
+        for (name, data) in &members {
+            let header = ar::Header::new(name.as_bytes().to_vec(), data.len() as u64);
+            deb.append(&header, data.as_slice()).unwrap();
+        }
+
+        let deb = deb.into_inner().unwrap();
+        let package = parse_package(&deb, &ContainerLimits::default()).unwrap().unwrap();
+
+        assert_eq!(
+            PackageInfo {
+                format: "deb",
+                name: "hello".to_string(),
+                version: "2.10-2".to_string(),
+                architecture: "amd64".to_string(),
+            },
+            package.info
+        );
+        assert_eq!(vec!["usr/bin/hello"], paths(&package));
+
+        let limits = ContainerLimits { max_depth: 1, ..Default::default() };
+        assert!(matches!(
+            parse_package(&deb, &limits),
+            Err(ExpandError::Limit(limits::LimitExceeded::Depth { max_depth: 1 }))
+        ));
+    }
+
+    fn cpio_entry(name: &str, mode: usize, data: &[u8]) -> Vec<u8> {
+        let mut entry = format!(
+            "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+            0, mode, 0, 0, 1, 0, data.len(), 0, 0, 0, 0, name.len() + 1, 0
+        ).into_bytes();
+        entry.extend_from_slice(name.as_bytes());
+        entry.push(0);
+        entry.resize(align4(entry.len()), 0);
+        entry.extend_from_slice(data);
+        entry.resize(align4(entry.len()), 0);
+        return entry;
+    }
+
+    fn rpm_header(strings: &[(u32, &str)]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut store = Vec::new();
+        for (tag, value) in strings {
+            for field in [*tag, RPM_TYPE_STRING, store.len() as u32, 1] {
+                index.extend_from_slice(&field.to_be_bytes());
+            }
+            store.extend_from_slice(value.as_bytes());
+            store.push(0);
+        }
+
+        let mut header = RPM_HEADER_MAGIC.to_vec();
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&(strings.len() as u32).to_be_bytes());
+        header.extend_from_slice(&(store.len() as u32).to_be_bytes());
+        header.extend(index);
+        header.extend(store);
+        return header;
+    }
+
+    #[test]
+    fn test_parse_rpm() {
+        let mut rpm = RPM_MAGIC.to_vec();
+        rpm.resize(RPM_LEAD_SIZE, 0);
+        rpm.extend(rpm_header(&[(1004, "sig")]));
+        rpm.resize((rpm.len() + 7) & !7, 0);
+        rpm.extend(rpm_header(&[
+            (RPM_TAG_NAME, "hello"),
+            (RPM_TAG_VERSION, "2.10"),
+            (RPM_TAG_RELEASE, "1.el9"),
+            (RPM_TAG_ARCH, "x86_64"),
+        ]));
+
+        let mut cpio = cpio_entry("./usr/bin", 0o040755, b"");
+        cpio.extend(cpio_entry("./usr/bin/hello", 0o100755, b"hello, world"));
+        cpio.extend(cpio_entry(CPIO_TRAILER, 0, b""));
+        rpm.extend(gzip(&cpio));
+
+        let package = parse_package(&rpm, &ContainerLimits::default()).unwrap().unwrap();
+
+        assert_eq!("hello", package.info.name);
+        assert_eq!("2.10-1.el9", package.info.version);
+        assert_eq!("x86_64", package.info.architecture);
+        assert_eq!(vec!["usr/bin/hello"], paths(&package));
+        assert_eq!(b"hello, world".to_vec(), package.files[0].data);
+    }
+
+    #[test]
+    fn test_parse_apk() {
+        let control = tar(&[(".PKGINFO", b"pkgname = hello\npkgver = 2.12-r1\narch = x86_64\n")]);
+        let data = tar(&[("usr/bin/hello", b"hello, world")]);
+
+        // segments are cut before the end-of-archive blocks, except the last one
+        let mut apk = gzip(&control[..control.len() - 1024]);
+        apk.extend(gzip(&data));
+
+        let package = parse_package(&apk, &ContainerLimits::default()).unwrap().unwrap();
+
+        assert_eq!("apk", package.info.format);
+        assert_eq!("2.12-r1", package.info.version);
+        assert_eq!(vec!["usr/bin/hello"], paths(&package));
+
+        assert!(parse_package(&gzip(&data), &ContainerLimits::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ Paging of the output, see `--pager`: as git does, the text is written to the pager named by
+ `STRINGS_PAGER` or `PAGER`, `less` by default, run with `LESS=FRX` unless `LESS` is set, so that
+ it quits if the text fits on one screen (F), keeps the colours (R), and leaves the text on the
+ screen once it quits (X). A pager named `cat`, or empty, turns the paging off.
+
+ Quitting the pager before the end of the output ends the run, as the signal of the closed pipe
+ ends git: the strings nobody will read are not searched for.
+ */
+
+use std::ffi::OsString;
+use std::io::{self, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// The pager run when none is named by the environment.
+const DEFAULT_PAGER: &str = "less";
+
+/**
+A running pager, writing to it writes to its input.
+ */
+pub struct Pager {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl Pager {
+    /**
+    Starts the pager named by the environment. Returns `None` if the paging is turned off or the
+    pager can't be started, the output is written to stdout then.
+     */
+    pub fn start() -> Option<Pager> {
+        let command = choose_command(
+            std::env::var_os("STRINGS_PAGER"), std::env::var_os("PAGER")
+        )?;
+        let mut command = shell_command(&command);
+        if std::env::var_os("LESS").is_none() {
+            command.env("LESS", "FRX");
+        }
+        if std::env::var_os("LV").is_none() {
+            command.env("LV", "-c");
+        }
+        let mut child = command.stdin(Stdio::piped()).spawn().ok()?;
+        let stdin = child.stdin.take();
+        return Some(Pager { child, stdin });
+    }
+
+    /**
+    Closes the input of the pager and waits for the reader to quit it.
+     */
+    pub fn finish(mut self) {
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+
+    /**
+    Ends the run once the pager has been quit.
+     */
+    fn quit(&mut self) -> ! {
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+        std::process::exit(0)
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(ErrorKind::BrokenPipe.into());
+        };
+        return match stdin.write(buf) {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => self.quit(),
+            result => result,
+        };
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Ok(());
+        };
+        return match stdin.flush() {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => self.quit(),
+            result => result,
+        };
+    }
+}
+
+/**
+Returns the pager command out of the values of `STRINGS_PAGER` and `PAGER`, the first one set,
+or `None` if the paging is turned off.
+ */
+fn choose_command(strings_pager: Option<OsString>, pager: Option<OsString>) -> Option<OsString> {
+    let command = strings_pager.or(pager).unwrap_or_else(|| OsString::from(DEFAULT_PAGER));
+    let trimmed = command.to_string_lossy().trim().to_string();
+    return if trimmed.is_empty() || trimmed == "cat" { None } else { Some(command) };
+}
+
+/**
+Returns the command running the pager with the shell, as the variable may hold options, e.g.
+`less -S`.
+ */
+#[cfg(not(windows))]
+fn shell_command(command: &OsString) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    return shell;
+}
+
+#[cfg(windows)]
+fn shell_command(command: &OsString) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    return shell;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_command() {
+        let some = |command: &str| Some(OsString::from(command));
+        assert_eq!(some("less"), choose_command(None, None));
+        assert_eq!(some("more"), choose_command(None, some("more")));
+        assert_eq!(some("less -S"), choose_command(some("less -S"), some("more")));
+        assert_eq!(None, choose_command(some(""), some("more")));
+        assert_eq!(None, choose_command(None, some(" cat ")));
+    }
+}
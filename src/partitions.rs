@@ -0,0 +1,410 @@
+/*
+ Disk images with a partition table, see `--partitions`: every partition is scanned on its own
+ as `IMAGE!pN`, its offsets relative to its start. N is the number of the partition as Linux
+ gives it: 1 to 4 for the primary partitions of an MBR, 5 and up for the logical ones in its
+ extended partition, the number of the entry for GPT. The strings of a GPT partition are tagged
+ with its unique GUID, `guid:GUID`.
+ */
+
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use super::diagnostics;
+use super::packages::PackageInfo;
+use super::sparse::LARGE_BUFFER_SIZE;
+use super::strings::{self, FoundString, Options, StringSink};
+
+const SECTOR_SIZE: u64 = 512;
+/// Sector sizes a GPT header is looked for with.
+const GPT_SECTOR_SIZES: [u64; 2] = [512, 4096];
+const GPT_SIGNATURE: &[u8] = b"EFI PART";
+/// The MBR partition type of the GPT disks.
+const PROTECTIVE_MBR_TYPE: u8 = 0xee;
+const EXTENDED_TYPES: [u8; 3] = [0x05, 0x0f, 0x85];
+/// Limits of the tables of damaged or crafted images.
+const MAX_LOGICAL_PARTITIONS: u32 = 128;
+const MAX_GPT_ENTRIES: u32 = 1024;
+/// Sizes of a GPT entry: a multiple of 128 bytes, up to a sector.
+const MIN_GPT_ENTRY_SIZE: usize = 128;
+const MAX_GPT_ENTRY_SIZE: usize = 4096;
+
+#[derive(Debug, PartialEq)]
+struct Partition {
+    number: u32,
+    /// Offset and size in bytes.
+    start: u64,
+    size: u64,
+    /// The unique GUID of a GPT partition.
+    guid: Option<String>,
+}
+
+/**
+Prints the strings of every partition of the disk image. Returns `None` if the image has no
+partition table or couldn't be read, so that it is scanned as a whole.
+ */
+pub(crate) fn print_strings_for_partitions(
+    image_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let mut image = File::open(image_path).ok()?;
+    let image_size = image.seek(SeekFrom::End(0)).ok()?;
+    let partitions = match read_partitions(&mut image) {
+        Ok(Some(partitions)) => partitions,
+        Ok(None) => {
+            diagnostics::warning(format_args!(
+                "'{}' has no partition table, scanned as a whole", image_path.display()
+            ));
+            return None;
+        }
+        Err(err) => {
+            diagnostics::error(format_args!("{}: {}", image_path.display(), err));
+            return None;
+        }
+    };
+
+    let mut num_found = 0u64;
+    for partition in partitions {
+        let mut name = OsString::from(image_path.as_os_str());
+        name.push(format!("!p{}", partition.number));
+        if partition.start.checked_add(partition.size).is_none_or(|end| end > image_size) {
+            diagnostics::warning(format_args!(
+                "{}: the partition ends past the end of the image", name.to_string_lossy()
+            ));
+        }
+        let size = partition.size.min(image_size.saturating_sub(partition.start));
+        if let Err(err) = image.seek(SeekFrom::Start(partition.start)) {
+            diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+            continue;
+        }
+
+        let mut reader = BufReader::with_capacity(LARGE_BUFFER_SIZE, (&image).take(size));
+        let mut guid_sink;
+        let sink: &mut dyn StringSink = match &partition.guid {
+            Some(guid) => {
+                guid_sink = GuidSink { guid, inner: sink };
+                &mut guid_sink
+            }
+            None => sink,
+        };
+        let (found, error) = strings::print_strings_for_reader(
+            &name, 0, Some(size), &mut reader, options, sink,
+        );
+        num_found += found;
+        if let Some(err) = error {
+            diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+        }
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the partitions of the GPT, or of the MBR if there is no GPT, or `None` if there is
+neither.
+ */
+fn read_partitions<R: Read + Seek>(image: &mut R) -> io::Result<Option<Vec<Partition>>> {
+    let Some(mbr) = read_sector(image, 0)? else {
+        return Ok(None);
+    };
+    if mbr[510..512] != [0x55, 0xaa] {
+        return Ok(None);
+    }
+    let entries: Vec<&[u8]> = mbr[446..510].chunks(16).collect();
+    if entries.iter().any(|entry| entry[4] == PROTECTIVE_MBR_TYPE) {
+        for sector_size in GPT_SECTOR_SIZES {
+            if let Some(partitions) = read_gpt(image, sector_size)? {
+                return Ok(Some(partitions));
+            }
+        }
+    }
+
+    let mut partitions = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let (partition_type, start, size) = mbr_entry(entry);
+        if partition_type == 0 || size == 0 {
+            continue;
+        }
+        if EXTENDED_TYPES.contains(&partition_type) {
+            read_logical_partitions(image, start, &mut partitions)?;
+        } else if let Some(partition) = sector_partition(i as u32 + 1, start, size, SECTOR_SIZE) {
+            partitions.push(partition);
+        }
+    }
+    partitions.sort_by_key(|partition| partition.number);
+    return Ok(Some(partitions));
+}
+
+/**
+Returns the partition of `num_sectors` from the sector `start`, or `None` if its offsets
+overflow, as in a crafted table.
+ */
+fn sector_partition(
+    number: u32,
+    start: u64,
+    num_sectors: u64,
+    sector_size: u64,
+) -> Option<Partition> {
+    return Some(Partition {
+        number,
+        start: start.checked_mul(sector_size)?,
+        size: num_sectors.checked_mul(sector_size)?,
+        guid: None,
+    });
+}
+
+/**
+Returns the type, the first sector and the number of the sectors of an MBR entry.
+ */
+fn mbr_entry(entry: &[u8]) -> (u8, u64, u64) {
+    let start = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+    let size = u32::from_le_bytes(entry[12..16].try_into().unwrap());
+    return (entry[4], start as u64, size as u64);
+}
+
+/**
+Follows the chain of the extended boot records: each one describes a logical partition,
+relative to itself, and links to the next record, relative to the extended partition.
+ */
+fn read_logical_partitions<R: Read + Seek>(
+    image: &mut R,
+    extended_start: u64,
+    partitions: &mut Vec<Partition>,
+) -> io::Result<()> {
+    let mut record_start = extended_start;
+    for number in 5..5 + MAX_LOGICAL_PARTITIONS {
+        let Some(offset) = record_start.checked_mul(SECTOR_SIZE) else {
+            return Ok(());
+        };
+        let Some(record) = read_sector(image, offset)? else {
+            return Ok(());
+        };
+        if record[510..512] != [0x55, 0xaa] {
+            return Ok(());
+        }
+        let (partition_type, start, size) = mbr_entry(&record[446..462]);
+        if partition_type != 0 && size > 0 {
+            let partition = record_start.checked_add(start)
+                .and_then(|start| sector_partition(number, start, size, SECTOR_SIZE));
+            partitions.extend(partition);
+        }
+        let (next_type, next_start, _) = mbr_entry(&record[462..478]);
+        if next_type == 0 || next_start == 0 {
+            return Ok(());
+        }
+        let Some(next_record_start) = extended_start.checked_add(next_start) else {
+            return Ok(());
+        };
+        record_start = next_record_start;
+    }
+    return Ok(());
+}
+
+/**
+Returns the partitions of the GPT if its header is in the second sector of that size. A header
+of a wrong entry size or with its entries past the end of the image is not a GPT, and the
+entries which overflow the offsets are skipped.
+ */
+fn read_gpt<R: Read + Seek>(image: &mut R, sector_size: u64) -> io::Result<Option<Vec<Partition>>> {
+    let Some(header) = read_sector(image, sector_size)? else {
+        return Ok(None);
+    };
+    if !header.starts_with(GPT_SIGNATURE) {
+        return Ok(None);
+    }
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+    let num_entries = u32::from_le_bytes(header[80..84].try_into().unwrap());
+    let entry_size = u32::from_le_bytes(header[84..88].try_into().unwrap()) as usize;
+    if !(MIN_GPT_ENTRY_SIZE..=MAX_GPT_ENTRY_SIZE).contains(&entry_size)
+        || entry_size % MIN_GPT_ENTRY_SIZE != 0 {
+        return Ok(None);
+    }
+    let Some(entries_offset) = entries_lba.checked_mul(sector_size) else {
+        return Ok(None);
+    };
+
+    let mut entries = vec![0u8; num_entries.min(MAX_GPT_ENTRIES) as usize * entry_size];
+    image.seek(SeekFrom::Start(entries_offset))?;
+    match image.read_exact(&mut entries) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let mut partitions = Vec::new();
+    for (i, entry) in entries.chunks(entry_size).enumerate() {
+        // the unused entries have no type
+        if entry[0..16].iter().all(|byte| *byte == 0) {
+            continue;
+        }
+        let first = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+        if last < first {
+            continue;
+        }
+        let Some(num_sectors) = (last - first).checked_add(1) else {
+            continue;
+        };
+        if let Some(partition) = sector_partition(i as u32 + 1, first, num_sectors, sector_size) {
+            partitions.push(Partition { guid: Some(format_guid(&entry[16..32])), ..partition });
+        }
+    }
+    return Ok(Some(partitions));
+}
+
+/**
+Formats a GUID as it is written, its first three fields being stored little-endian.
+ */
+pub(crate) fn format_guid(bytes: &[u8]) -> String {
+    let hex = |bytes: &[u8]| -> String {
+        return bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    };
+    let reversed = |bytes: &[u8]| -> String {
+        return hex(&bytes.iter().rev().copied().collect::<Vec<u8>>());
+    };
+    return format!(
+        "{}-{}-{}-{}-{}",
+        reversed(&bytes[0..4]), reversed(&bytes[4..6]), reversed(&bytes[6..8]),
+        hex(&bytes[8..10]), hex(&bytes[10..16])
+    );
+}
+
+/**
+Returns the sector of 512 bytes at the offset, or `None` if the image ends before it.
+ */
+fn read_sector<R: Read + Seek>(image: &mut R, offset: u64) -> io::Result<Option<[u8; 512]>> {
+    let mut sector = [0u8; 512];
+    image.seek(SeekFrom::Start(offset))?;
+    return match image.read_exact(&mut sector) {
+        Ok(()) => Ok(Some(sector)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    };
+}
+
+/**
+Tags the strings of a GPT partition with its GUID, `guid:GUID`.
+ */
+struct GuidSink<'a> {
+    guid: &'a str,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for GuidSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut tags = found.tags.to_vec();
+        tags.push(format!("guid:{}", self.guid));
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mbr_entry_bytes(partition_type: u8, start: u32, size: u32) -> Vec<u8> {
+        let mut entry = vec![0u8; 16];
+        entry[4] = partition_type;
+        entry[8..12].copy_from_slice(&start.to_le_bytes());
+        entry[12..16].copy_from_slice(&size.to_le_bytes());
+        return entry;
+    }
+
+    fn boot_record(image: &mut [u8], sector: usize, entries: &[Vec<u8>]) {
+        let record = &mut image[sector * 512..(sector + 1) * 512];
+        for (i, entry) in entries.iter().enumerate() {
+            record[446 + 16 * i..462 + 16 * i].copy_from_slice(entry);
+        }
+        record[510..512].copy_from_slice(&[0x55, 0xaa]);
+    }
+
+    #[test]
+    fn test_read_mbr_partitions() {
+        let mut image = vec![0u8; 64 * 512];
+        boot_record(&mut image, 0, &[
+            mbr_entry_bytes(0x83, 2, 8),
+            mbr_entry_bytes(0x05, 16, 40),
+        ]);
+        // the logical partitions: one at 17 and one at 33
+        boot_record(&mut image, 16, &[mbr_entry_bytes(0x83, 1, 4), mbr_entry_bytes(0x05, 16, 8)]);
+        boot_record(&mut image, 32, &[mbr_entry_bytes(0x07, 1, 6)]);
+
+        let partition = |number: u32, start: u64, size: u64| {
+            return Partition { number, start: start * 512, size: size * 512, guid: None };
+        };
+        assert_eq!(
+            Some(vec![partition(1, 2, 8), partition(5, 17, 4), partition(6, 33, 6)]),
+            read_partitions(&mut Cursor::new(&image)).unwrap()
+        );
+
+        image[510] = 0;
+        assert_eq!(None, read_partitions(&mut Cursor::new(&image)).unwrap());
+        assert_eq!(None, read_partitions(&mut Cursor::new(b"short")).unwrap());
+    }
+
+    #[test]
+    fn test_read_gpt_partitions() {
+        let image = std::fs::read("test-resources/disk.img").unwrap();
+        assert_eq!(
+            Some(vec![
+                Partition {
+                    number: 1,
+                    start: 4 * 512,
+                    size: 2 * 512,
+                    guid: Some("0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9".to_string()),
+                },
+                Partition {
+                    number: 3,
+                    start: 6 * 512,
+                    size: 2 * 512,
+                    guid: Some("11111111-2222-3333-4444-555555555555".to_string()),
+                },
+            ]),
+            read_partitions(&mut Cursor::new(&image)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_crafted_gpt() {
+        let image = std::fs::read("test-resources/disk.img").unwrap();
+        let entries_offset = 2 * 512;
+
+        // the entries of an impossible size are not a GPT, the MBR is used instead
+        let mut huge_entries = image.clone();
+        huge_entries[512 + 84..512 + 88].copy_from_slice(&u32::MAX.to_le_bytes());
+        let partitions = read_partitions(&mut Cursor::new(&huge_entries)).unwrap().unwrap();
+        assert!(partitions.iter().all(|partition| partition.guid.is_none()));
+
+        // the entry which overflows the offsets is skipped
+        let mut overflow = image.clone();
+        overflow[entries_offset + 32..entries_offset + 40]
+            .copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        overflow[entries_offset + 40..entries_offset + 48]
+            .copy_from_slice(&u64::MAX.to_le_bytes());
+        let partitions = read_partitions(&mut Cursor::new(&overflow)).unwrap().unwrap();
+        assert_eq!(vec![3], partitions.iter().map(|partition| partition.number).collect::<Vec<_>>());
+
+        // both at once, as a fuzzer found them
+        huge_entries[entries_offset + 32..entries_offset + 40]
+            .copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        assert!(read_partitions(&mut Cursor::new(&huge_entries)).is_ok());
+
+        // the entries past the end of the image
+        let mut truncated = image.clone();
+        truncated[512 + 72..512 + 80].copy_from_slice(&u64::MAX.to_le_bytes());
+        let partitions = read_partitions(&mut Cursor::new(&truncated)).unwrap().unwrap();
+        assert!(partitions.iter().all(|partition| partition.guid.is_none()));
+        truncated[512 + 72..512 + 80].copy_from_slice(&1000u64.to_le_bytes());
+        let partitions = read_partitions(&mut Cursor::new(&truncated)).unwrap().unwrap();
+        assert!(partitions.iter().all(|partition| partition.guid.is_none()));
+    }
+}
@@ -0,0 +1,278 @@
+/*
+ Detection of the length-prefixed strings, see `--pascal`: the serialized formats and the older
+ binaries store the strings after their length rather than before a NUL, e.g. `\x05hello` with a
+ length byte, as Pascal did, or `\x05\x00hello` with a 16-bit little-endian length. The strings
+ are reported from the offset of their length, tagged with its size, e.g. `pascal:u16`.
+
+ Any byte before a printable run reads as a length, so only the strings which fit in the run are
+ reported, and the length must be at least the minimum length of the strings. Inside a printable run, where the length is itself printable, the string must
+ end the run: otherwise every character of a long text would be the length of the text after it.
+ The narrowest length which fits is chosen, as the big-endian ones can't be told apart when their
+ high bytes are zero.
+ */
+
+use std::ffi::OsStr;
+use super::alphabet::Alphabet;
+use super::scanner::ByteScanner;
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::char_is_printable;
+
+/// The lengths looked for without a value given to `--pascal`.
+pub const DEFAULT_PREFIXES: &str = "u8,u16,u32";
+/// Size of the widest length.
+const MAX_PREFIX_SIZE: usize = 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LengthPrefix {
+    U8,
+    /// 16-bit little-endian
+    U16,
+    U16Be,
+    /// 32-bit little-endian
+    U32,
+    U32Be,
+}
+
+impl LengthPrefix {
+    pub fn parse(name: &str) -> Option<LengthPrefix> {
+        return match name {
+            "u8" => Some(LengthPrefix::U8),
+            "u16" => Some(LengthPrefix::U16),
+            "u16be" => Some(LengthPrefix::U16Be),
+            "u32" => Some(LengthPrefix::U32),
+            "u32be" => Some(LengthPrefix::U32Be),
+            _ => None,
+        };
+    }
+
+    /**
+    Returns the comma-separated lengths, narrowest first, or `None` if one of them is unknown.
+     */
+    pub fn parse_list(names: &str) -> Option<Vec<LengthPrefix>> {
+        let mut prefixes = Vec::new();
+        for name in names.split(',') {
+            let prefix = LengthPrefix::parse(name)?;
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+        prefixes.sort_by_key(|prefix| prefix.num_bytes());
+        return Some(prefixes);
+    }
+
+    pub const fn name(&self) -> &'static str {
+        return match self {
+            LengthPrefix::U8 => "u8",
+            LengthPrefix::U16 => "u16",
+            LengthPrefix::U16Be => "u16be",
+            LengthPrefix::U32 => "u32",
+            LengthPrefix::U32Be => "u32be",
+        };
+    }
+
+    pub const fn num_bytes(&self) -> usize {
+        return match self {
+            LengthPrefix::U8 => 1,
+            LengthPrefix::U16 | LengthPrefix::U16Be => 2,
+            LengthPrefix::U32 | LengthPrefix::U32Be => 4,
+        };
+    }
+
+    fn read(&self, bytes: &[u8]) -> usize {
+        return match self {
+            LengthPrefix::U8 => bytes[0] as usize,
+            LengthPrefix::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+            LengthPrefix::U16Be => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            LengthPrefix::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as usize,
+            LengthPrefix::U32Be => u32::from_be_bytes(bytes.try_into().unwrap()) as usize,
+        };
+    }
+}
+
+/**
+Finds the length-prefixed strings in the printable runs of the data, see the module description.
+ */
+pub(crate) struct PascalScanner {
+    prefixes: Vec<LengthPrefix>,
+    min_length: usize,
+    encoding: EncodingKind,
+    include_all_whitespace: bool,
+    alphabet: Option<Alphabet>,
+    /// The last bytes before the run, as many as the widest length, and the run.
+    data: Vec<u8>,
+    /// Number of the bytes of `data` before the run.
+    run_start: usize,
+    /// Address of the first byte of `data`.
+    address: u64,
+}
+
+impl PascalScanner {
+    pub(crate) fn new(options: &Options, address: u64) -> PascalScanner {
+        return PascalScanner {
+            prefixes: options.pascal.clone(),
+            min_length: options.min_length.max(1) as usize,
+            encoding: options.encoding,
+            include_all_whitespace: options.include_all_whitespace,
+            alphabet: options.alphabet,
+            data: Vec::new(),
+            run_start: 0,
+            address,
+        };
+    }
+
+    fn is_printable(&self, byte: u8) -> bool {
+        return self.alphabet.map_or_else(
+            || char_is_printable(byte as char, self.encoding, self.include_all_whitespace),
+            |alphabet| alphabet.contains(byte as char),
+        );
+    }
+
+    /**
+    Reports the strings of the run, leaving the bytes before the next one in `data`.
+     */
+    fn flush(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        let mut num_found = 0u64;
+        // the bytes up to the end of the last string can't be the length of the next one
+        let mut consumed = 0;
+        let mut start = self.run_start;
+        while start + self.min_length <= self.data.len() {
+            let fitting = self.prefixes.iter().find_map(|prefix| {
+                let prefix_start = start.checked_sub(prefix.num_bytes())?;
+                if prefix_start < consumed {
+                    return None;
+                }
+                let length = prefix.read(&self.data[prefix_start..start]);
+                let end = start.checked_add(length)?;
+                let fits = length >= self.min_length && end <= self.data.len()
+                    && (prefix_start < self.run_start || end == self.data.len());
+                return if fits { Some((*prefix, prefix_start, end)) } else { None };
+            });
+            let Some((prefix, prefix_start, end)) = fitting else {
+                start += 1;
+                continue;
+            };
+
+            let tags = [format!("pascal:{}", prefix.name())];
+            let text = &self.data[start..end];
+            sink.on_string(&FoundString {
+                filename,
+                address: self.address + prefix_start as u64,
+                end_address: self.address + end as u64,
+                bytes: text,
+                text,
+                encoding: self.encoding,
+                tags: &tags,
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Length,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found += 1;
+            consumed = end;
+            start = end;
+        }
+
+        self.run_start = self.data.len();
+        self.trim();
+        return num_found;
+    }
+
+    /**
+    Drops the bytes before the run but the ones which can be its length.
+     */
+    fn trim(&mut self) {
+        let num_dropped = self.run_start.saturating_sub(MAX_PREFIX_SIZE);
+        self.data.drain(..num_dropped);
+        self.address += num_dropped as u64;
+        self.run_start -= num_dropped;
+    }
+}
+
+impl ByteScanner for PascalScanner {
+    fn push(&mut self, byte: u8, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        if self.is_printable(byte) {
+            self.data.push(byte);
+            return 0;
+        }
+        let num_found = self.flush(filename, sink);
+        self.data.push(byte);
+        self.run_start = self.data.len();
+        self.trim();
+        return num_found;
+    }
+
+    fn finish(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        return self.flush(filename, sink);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collector {
+        found: Vec<(u64, u64, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            let text = format!("{} {}", found.tags.join(","), String::from_utf8_lossy(found.text));
+            self.found.push((found.address, found.end_address, text));
+        }
+    }
+
+    fn scan(data: &[u8], prefixes: &str) -> Vec<(u64, u64, String)> {
+        let mut options = Options::default();
+        options.pascal = LengthPrefix::parse_list(prefixes).unwrap();
+        let mut scanner = PascalScanner::new(&options, 0x100);
+        let mut collector = Collector { found: Vec::new() };
+        for byte in data {
+            scanner.push(*byte, OsStr::new("test"), &mut collector);
+        }
+        scanner.finish(OsStr::new("test"), &mut collector);
+        return collector.found;
+    }
+
+    #[test]
+    fn test_scan() {
+        let data = b"\x05hello\x01\x01\x06world!\x00\x00\x00\x09Pascal...\x08\x00\x00\x00ok\xff";
+        assert_eq!(
+            vec![
+                (0x100, 0x106, "pascal:u8 hello".to_string()),
+                (0x108, 0x10f, "pascal:u8 world!".to_string()),
+                (0x112, 0x11c, "pascal:u8 Pascal...".to_string()),
+            ],
+            scan(data, DEFAULT_PREFIXES)
+        );
+        assert_eq!(
+            vec![(0x100, 0x10e, "pascal:u32 serialized".to_string())],
+            scan(b"\x0a\x00\x00\x00serialized\x00", DEFAULT_PREFIXES)
+        );
+        assert_eq!(
+            vec![(0x101, 0x109, "pascal:u16be abcdef".to_string())],
+            scan(b"\xff\x00\x06abcdef\x07", "u32be,u16be")
+        );
+
+        // the string ends the run, the length is its first character
+        assert_eq!(
+            vec![(0x101, 0x122, "pascal:u8 abcdefghijklmnopqrstuvwxyz012345".to_string())],
+            scan(b"\x00 abcdefghijklmnopqrstuvwxyz012345", "u8")
+        );
+        // too short, longer than the run, inside a longer text
+        assert!(scan(b"\x03abc\x00", "u8").is_empty());
+        assert!(scan(b"\x09abcd\x00", "u8").is_empty());
+        assert!(scan(b"\x00&text of a longer sentence with the length inside", "u8").is_empty());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            Some(vec![LengthPrefix::U8, LengthPrefix::U16Be, LengthPrefix::U32]),
+            LengthPrefix::parse_list("u32,u16be,u8,u8")
+        );
+        assert_eq!(None, LengthPrefix::parse_list("u64"));
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ Regular expressions for the well-known kinds of strings, shared by the features which look
+ into the string contents.
+ */
+
+pub(crate) const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+
+pub(crate) const IPV4: &str =
+    r"\b(?:(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\b";
+
+pub(crate) const IPV6: &str =
+    r"(?i)\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b|\b(?:[0-9a-f]{1,4}:){1,6}:(?:[0-9a-f]{1,4}:){0,5}[0-9a-f]{1,4}\b";
+
+pub(crate) const URL: &str = r#"\b[A-Za-z][A-Za-z0-9+.-]*://[^\s"'<>]+"#;
+
+/// Absolute Unix paths at the start of a word, Windows paths with a drive letter and UNC paths.
+pub(crate) const PATH: &str =
+    r#"(?:^|[\s"'=(])(?:~?/[\w.+-]+)+/?|\b[A-Za-z]:\\[^\s"*?<>|:]*|\\\\[\w.-]+\\[^\s"*?<>|:]+"#;
+
+pub(crate) const REGISTRY_KEY: &str =
+    r"(?i)\b(?:HKEY_(?:LOCAL_MACHINE|CURRENT_USER|CLASSES_ROOT|USERS|CURRENT_CONFIG)|HKLM|HKCU|HKCR|HKU|HKCC)\\";
+
+pub(crate) const GUID: &str =
+    r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b";
+
+/// printf-style conversion specifications and the placeholders of the Rust, Python and .NET
+/// format strings: `{}`, `{0}`, `{name}`, `{:>8}`, `{0:x}`.
+pub(crate) const FORMAT_STRING: &str = concat!(
+    r"%[-+#0]*(?:\d+|\*)?(?:\.(?:\d+|\*))?(?:hh|h|ll|l|L|z|j|t|I64|I32)?[diouxXeEfFgGaAcspn]",
+    r"|\{(?:\d+|[A-Za-z_]\w*)?(?:[:,][^{}\s]*)?\}",
+);
+
+/**
+High-signal credential patterns, named by the kind of credential. The secret value itself is
+captured by the first group, if the pattern has one, for the entropy check of `--secrets`.
+ */
+pub(crate) const SECRETS: &[(&str, &str)] = &[
+    ("aws-access-key", r"\b(?:AKIA|ASIA)([0-9A-Z]{16})\b"),
+    ("private-key", r"-----BEGIN (?:[A-Z]+ )*PRIVATE KEY-----"),
+    ("jwt", r"\b(eyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,})"),
+    ("bearer-token", r"(?i)\bbearer\s+([A-Za-z0-9._~+/-]{16,}=*)"),
+    // password in a connection string
+    ("connection-string", r"(?i)\b(?:password|passwd|pwd)\s*=\s*([^;\s]+)"),
+    // credentials in an URL
+    ("url-credentials", r"[A-Za-z][A-Za-z0-9+.-]*://[^/\s:@]+:([^/\s@]+)@"),
+];
@@ -0,0 +1,347 @@
+/*
+ Reading of PDF files, see `--pdf`. The text of a PDF is in the content streams of its objects,
+ nearly always deflated with the FlateDecode filter, so a scan of the file finds little but the
+ dictionaries. The objects are found by their `N G obj` headers, their dictionaries are taken as
+ they are and their streams are inflated; the streams with other filters, such as the images,
+ are skipped. The parts are attributed to the number of their object.
+ */
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Read;
+use flate2::read::ZlibDecoder;
+use super::limits::ContainerLimits;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+const PDF_MAGIC: &[u8] = b"%PDF-";
+/// How far from the start of the file the header may be, as readers allow some junk before it.
+const PDF_MAGIC_SEARCH_SIZE: usize = 1024;
+
+/**
+The dictionary of an object, at its offset in the file, or its stream, inflated or as is.
+ */
+pub(crate) struct Part<'a> {
+    pub object: u32,
+    /// Offset in the file, or in the inflated stream.
+    pub address: u64,
+    pub data: Cow<'a, [u8]>,
+}
+
+pub(crate) struct Document<'a> {
+    pub parts: Vec<Part<'a>>,
+    /// Why the streams which are not in `parts` were skipped.
+    pub problems: Vec<String>,
+}
+
+/**
+Splits a PDF file into the dictionaries and the decoded streams of its objects, in the order of
+the file. Returns `None` if the data is not a PDF file.
+ */
+pub(crate) fn parse<'a>(data: &'a [u8], limits: &ContainerLimits) -> Option<Document<'a>> {
+    find(&data[..data.len().min(PDF_MAGIC_SEARCH_SIZE)], PDF_MAGIC, 0)?;
+
+    let mut document = Document { parts: Vec::new(), problems: Vec::new() };
+    let mut position = 0;
+    while let Some(keyword) = find(data, b"obj", position) {
+        position = keyword + 3;
+        let Some(object) = object_number(data, keyword) else {
+            continue;
+        };
+
+        let end_of_object = find(data, b"endobj", position).unwrap_or(data.len());
+        let stream = find(data, b"stream", position)
+            .filter(|stream| *stream < end_of_object)
+            .and_then(|stream| Some((stream, stream_data_start(data, stream + 6)?)));
+        let Some((keyword, start)) = stream else {
+            document.parts.push(Part {
+                object,
+                address: position as u64,
+                data: Cow::Borrowed(&data[position..end_of_object]),
+            });
+            position = end_of_object;
+            continue;
+        };
+
+        let dictionary = &data[position..keyword];
+        document.parts.push(Part {
+            object,
+            address: position as u64,
+            data: Cow::Borrowed(dictionary),
+        });
+        let end = stream_data_end(data, start, dictionary);
+        let contents = &data[start..end];
+        position = end;
+
+        let filters = filters(dictionary);
+        if filters.is_empty() {
+            document.parts.push(Part {
+                object,
+                address: start as u64,
+                data: Cow::Borrowed(contents),
+            });
+        } else if filters.iter().all(|filter| matches!(*filter, b"FlateDecode" | b"Fl")) {
+            match inflate(contents, filters.len(), limits) {
+                Ok(inflated) => document.parts.push(Part {
+                    object,
+                    address: 0,
+                    data: Cow::Owned(inflated),
+                }),
+                Err(problem) => document.problems.push(format!("object {}: {}", object, problem)),
+            }
+        }
+    }
+    return Some(document);
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    return data.get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position);
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    return matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ');
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    return matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%');
+}
+
+/**
+Returns the number of the object if the `obj` keyword at the position ends an object header,
+`N G obj`.
+ */
+fn object_number(data: &[u8], keyword: usize) -> Option<u32> {
+    match data.get(keyword + 3) {
+        Some(byte) if !is_whitespace(*byte) && !is_delimiter(*byte) => return None,
+        _ => {}
+    }
+    let header = &data[..keyword];
+    let generation = header.trim_ascii_end();
+    if generation.len() == header.len() {
+        return None;
+    }
+    let digits = generation.iter().rev().take_while(|byte| byte.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &generation[..generation.len() - digits];
+    let number = rest.trim_ascii_end();
+    if number.len() == rest.len() {
+        return None;
+    }
+    let digits = number.iter().rev().take_while(|byte| byte.is_ascii_digit()).count();
+    let before = number.len() - digits;
+    if digits == 0 || before > 0 && !is_whitespace(number[before - 1])
+        && !is_delimiter(number[before - 1]) {
+        return None;
+    }
+    return std::str::from_utf8(&number[before..]).ok()?.parse().ok();
+}
+
+/**
+Returns where the data of a stream starts, after the end of line following the `stream`
+keyword, or `None` if the keyword is not followed by one.
+ */
+fn stream_data_start(data: &[u8], after_keyword: usize) -> Option<usize> {
+    return match data.get(after_keyword..)? {
+        [b'\r', b'\n', ..] => Some(after_keyword + 2),
+        [b'\n' | b'\r', ..] => Some(after_keyword + 1),
+        _ => None,
+    };
+}
+
+/**
+Returns where the data of a stream ends: after the number of bytes given by the `/Length` of the
+dictionary if `endstream` follows there, otherwise at the end of line before `endstream`. The
+length is not used if it refers to another object.
+ */
+fn stream_data_end(data: &[u8], start: usize, dictionary: &[u8]) -> usize {
+    if let Some(end) = direct_length(dictionary).and_then(|length| start.checked_add(length)) {
+        if let Some(after) = data.get(end..) {
+            let after = &after[after.len() - after.trim_ascii_start().len()..];
+            if after.starts_with(b"endstream") {
+                return end;
+            }
+        }
+    }
+
+    let Some(keyword) = find(data, b"endstream", start) else {
+        return data.len();
+    };
+    let contents = &data[start..keyword];
+    let contents = contents.strip_suffix(b"\n").unwrap_or(contents);
+    let contents = contents.strip_suffix(b"\r").unwrap_or(contents);
+    return start + contents.len();
+}
+
+/**
+Returns the value following a name in the dictionary, which starts after the whitespace.
+ */
+fn value_of<'a>(dictionary: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let mut position = 0;
+    while let Some(found) = find(dictionary, name, position) {
+        position = found + name.len();
+        match dictionary.get(position) {
+            // the name goes on, e.g. `/Length1`
+            Some(byte) if !is_whitespace(*byte) && !is_delimiter(*byte) => continue,
+            _ => return Some(dictionary[position..].trim_ascii_start()),
+        }
+    }
+    return None;
+}
+
+/**
+Returns the `/Length` of the dictionary if it is a number, not a reference to an object.
+ */
+fn direct_length(dictionary: &[u8]) -> Option<usize> {
+    let value = value_of(dictionary, b"/Length")?;
+    let digits = value.iter().take_while(|byte| byte.is_ascii_digit()).count();
+    let length = std::str::from_utf8(&value[..digits]).ok()?.parse().ok()?;
+
+    // `N G R`
+    let rest = value[digits..].trim_ascii_start();
+    let generation = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+    if generation > 0 && rest[generation..].trim_ascii_start().starts_with(b"R") {
+        return None;
+    }
+    return Some(length);
+}
+
+/**
+Returns the names of the `/Filter` of the dictionary, a single name or an array of them.
+ */
+fn filters(dictionary: &[u8]) -> Vec<&[u8]> {
+    let Some(value) = value_of(dictionary, b"/Filter") else {
+        return Vec::new();
+    };
+    let names = match value.strip_prefix(b"[") {
+        Some(array) => &array[..array.iter().position(|byte| *byte == b']').unwrap_or(0)],
+        None => {
+            let end = value.iter().skip(1)
+                .position(|byte| is_whitespace(*byte) || is_delimiter(*byte))
+                .map_or(value.len(), |position| position + 1);
+            &value[..end]
+        }
+    };
+    return names.split(|byte| *byte == b'/')
+        .skip(1)
+        .map(|name| name.trim_ascii())
+        .collect();
+}
+
+/**
+Inflates the stream as many times as it is deflated. A stream which breaks off is kept up to
+there, as the readers do.
+ */
+fn inflate(data: &[u8], times: usize, limits: &ContainerLimits) -> Result<Vec<u8>, String> {
+    let mut inflated = data.to_vec();
+    for _ in 0..times {
+        let mut decoder = limits.limit_expansion(
+            ZlibDecoder::new(&inflated[..]), data.len() as u64
+        );
+        let mut output = Vec::new();
+        let result = decoder.read_to_end(&mut output);
+        if let Some(limit) = decoder.exceeded() {
+            return Err(format!("not inflated: {}", limit));
+        }
+        if result.is_err() && output.is_empty() {
+            return Err("broken FlateDecode stream".to_string());
+        }
+        inflated = output;
+    }
+    return Ok(inflated);
+}
+
+/**
+Tags the strings with the number of the PDF object they are found in, `obj:N`.
+ */
+pub(crate) struct ObjectSink<'a> {
+    pub object: u32,
+    pub inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for ObjectSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut tags = found.tags.to_vec();
+        tags.push(format!("obj:{}", self.object));
+        self.inner.on_string(&found.with_tags(&tags));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        return encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_parse() {
+        let content = b"BT /F1 12 Tf (Hello from the page) Tj ET";
+        let deflated = deflate(content);
+        let mut data = b"%PDF-1.7\n1 0 obj\n<< /Title (Quarterly report) >>\nendobj\n".to_vec();
+        data.extend(format!("12 0 obj<</Length {}/Filter/FlateDecode>>stream\r\n", deflated.len())
+            .as_bytes());
+        data.extend(&deflated);
+        data.extend(b"\r\nendstream\nendobj\n");
+        data.extend(b"13 0 obj <</Length 14 0 R>> stream\nplain\nendstream endobj\n");
+        data.extend(b"14 0 obj <</Filter [/DCTDecode]>> stream\n\xff\xd8\nendstream endobj\n");
+        data.extend(b"trailer <</Root 1 0 R>>\n%%EOF\n");
+
+        let document = parse(&data, &ContainerLimits::default()).unwrap();
+        let parts: Vec<(u32, &[u8])> = document.parts.iter()
+            .map(|part| (part.object, &*part.data))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, &b"\n<< /Title (Quarterly report) >>\n"[..]),
+                (12, format!("<</Length {}/Filter/FlateDecode>>", deflated.len()).as_bytes()),
+                (12, content),
+                (13, b" <</Length 14 0 R>> "),
+                (13, b"plain"),
+                (14, b" <</Filter [/DCTDecode]>> "),
+            ],
+            parts
+        );
+        assert!(document.problems.is_empty());
+        assert!(parse(b"not a PDF 1 0 obj", &ContainerLimits::default()).is_none());
+    }
+
+    #[test]
+    fn test_object_number() {
+        let number = |data: &[u8]| object_number(data, data.len() - 3);
+        assert_eq!(Some(12), number(b"\n12 0 obj"));
+        assert_eq!(Some(7), number(b">>7 1 obj"));
+        assert_eq!(None, number(b"\nendobj"));
+        assert_eq!(None, number(b"(x12 0 obj"));
+        assert_eq!(None, number(b"12 obj"));
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(vec![&b"FlateDecode"[..]], filters(b"<</Filter/FlateDecode/Length 5>>"));
+        assert_eq!(
+            vec![&b"Fl"[..], b"DCTDecode"],
+            filters(b"<< /Filter [ /Fl /DCTDecode ] >>")
+        );
+        assert!(filters(b"<</Length 5>>").is_empty());
+        assert_eq!(Some(5), direct_length(b"<</Length1 9 /Length 5>>"));
+        assert_eq!(None, direct_length(b"<</Length 5 0 R>>"));
+    }
+}
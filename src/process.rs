@@ -0,0 +1,171 @@
+/*
+ Scanning of the memory of a running process on Linux (`--pid PID`): the readable mappings listed
+ in /proc/PID/maps are read from /proc/PID/mem one by one and scanned as `PID!MAPPING`, where
+ MAPPING is the path of the mapped file, a pseudo-path like `[heap]` or `[stack]`, or `[anon]`
+ for the anonymous memory. The strings are addressed with their virtual addresses. Reading the
+ memory of another process needs the permission to trace it, see ptrace(2).
+ */
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use super::diagnostics;
+use super::strings::{self, Options, StringSink};
+
+/// Mappings which are listed as readable, but can't be read through /proc/PID/mem.
+const UNREADABLE_MAPPINGS: [&str; 3] = ["[vvar]", "[vvar_vclock]", "[vsyscall]"];
+
+/// A readable mapping of /proc/PID/maps.
+#[derive(Debug, PartialEq)]
+struct Mapping {
+    start: u64,
+    end: u64,
+    name: String,
+}
+
+/**
+Prints the strings of the readable memory of the process. Returns `None` if its memory couldn't
+be read at all.
+ */
+pub fn print_strings_for_process(
+    pid: u32,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let maps = match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(maps) => maps,
+        Err(err) => {
+            diagnostics::error(format_args!("process {}: {}", pid, err));
+            return None;
+        }
+    };
+    let mut memory = match File::open(format!("/proc/{}/mem", pid)) {
+        Ok(memory) => memory,
+        Err(err) => {
+            diagnostics::error(format_args!("process {}: {}", pid, err));
+            return None;
+        }
+    };
+
+    let mut num_found = 0u64;
+    for mapping in parse_maps(&maps) {
+        if UNREADABLE_MAPPINGS.contains(&mapping.name.as_str()) {
+            continue;
+        }
+        let name = OsString::from(format!("{}!{}", pid, mapping.name));
+        let size = mapping.end - mapping.start;
+        let result = memory.seek(SeekFrom::Start(mapping.start)).map(|_| {
+            return strings::print_strings_for_reader(
+                &name, mapping.start, Some(size), &mut BufReader::new((&memory).take(size)),
+                options, sink,
+            );
+        });
+        // the process may unmap the memory while it is read, the rest is read all the same
+        match result {
+            Ok((found, None)) => num_found += found,
+            Ok((found, Some(err))) => {
+                num_found += found;
+                diagnostics::warning(format_args!("{}: {}", name.to_string_lossy(), err));
+            }
+            Err(err) => diagnostics::warning(format_args!("{}: {}", name.to_string_lossy(), err)),
+        }
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the readable mappings of the process, in the order of their addresses. The lines are
+`START-END PERMS OFFSET DEVICE INODE [PATH]`, the path padded with spaces.
+ */
+fn parse_maps(maps: &str) -> Vec<Mapping> {
+    let mut mappings = Vec::new();
+    for line in maps.lines() {
+        let mut fields = line.splitn(6, ' ');
+        let (Some(range), Some(permissions)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((start, end)) = range.split_once('-') else {
+            continue;
+        };
+        let (Ok(start), Ok(end)) = (u64::from_str_radix(start, 16), u64::from_str_radix(end, 16))
+        else {
+            continue;
+        };
+        if !permissions.starts_with('r') || end <= start {
+            continue;
+        }
+        let name = fields.nth(3).map(str::trim_start).unwrap_or("");
+        mappings.push(Mapping {
+            start,
+            end,
+            name: if name.is_empty() { "[anon]".to_string() } else { name.to_string() },
+        });
+    }
+    return mappings;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::FoundString;
+
+    #[test]
+    fn test_parse_maps() {
+        let maps = "\
+55d0c8a00000-55d0c8a02000 r--p 00000000 08:01 1311 /usr/bin/cat
+55d0c8a02000-55d0c8a06000 r-xp 00002000 08:01 1311                       /usr/bin/cat
+55d0c9e1c000-55d0c9e3d000 rw-p 00000000 00:00 0                          [heap]
+7f1b2c000000-7f1b2c021000 rw-p 00000000 00:00 0
+7f1b2c021000-7f1b30000000 ---p 00000000 00:00 0
+7f1b30000000-7f1b30001000 r--p 00000000 08:01 42                         /tmp/a file (deleted)
+";
+        let mapping = |start: u64, end: u64, name: &str| {
+            return Mapping { start, end, name: name.to_string() };
+        };
+        assert_eq!(
+            vec![
+                mapping(0x55d0c8a00000, 0x55d0c8a02000, "/usr/bin/cat"),
+                mapping(0x55d0c8a02000, 0x55d0c8a06000, "/usr/bin/cat"),
+                mapping(0x55d0c9e1c000, 0x55d0c9e3d000, "[heap]"),
+                mapping(0x7f1b2c000000, 0x7f1b2c021000, "[anon]"),
+                mapping(0x7f1b30000000, 0x7f1b30001000, "/tmp/a file (deleted)"),
+            ],
+            parse_maps(maps)
+        );
+    }
+
+    struct Collector {
+        found: Vec<(String, u64, String)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((
+                found.filename.to_string_lossy().into_owned(),
+                found.address,
+                String::from_utf8_lossy(found.text).into_owned(),
+            ));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_scan_own_process() {
+        let pid = std::process::id();
+        // on the heap, so that it is only found at its address
+        let needle = format!("the needle of the process {}", pid);
+        let mut collector = Collector { found: Vec::new() };
+        let options = Options { min_length: 10, ..Options::default() };
+        assert!(print_strings_for_process(pid, &options, &mut collector).is_some());
+
+        let address = needle.as_ptr() as u64;
+        assert!(collector.found.iter().any(|(name, found_address, text)| {
+            let Some(position) = text.find(&needle) else {
+                return false;
+            };
+            return name.starts_with(&format!("{}!", pid))
+                && found_address + position as u64 == address;
+        }));
+        assert!(print_strings_for_process(u32::MAX, &options, &mut collector).is_none());
+    }
+}
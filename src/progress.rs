@@ -0,0 +1,144 @@
+/*
+ Progress bar of the scan of a large file, see `--progress`. It is drawn on stderr from the
+ bytes read so far and the size of the file, so it needs a regular file: stdin and pipes have no
+ size to compare with. Nothing is drawn for the files scanned in less than half a second, and
+ the bar is erased when the file is done, so that it doesn't mix with the output.
+ */
+
+use std::io::{self, Read, Stderr, Write};
+use std::time::{Duration, Instant};
+
+const FIRST_DRAW_DELAY: Duration = Duration::from_millis(500);
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: u64 = 30;
+/// Clears the line from the cursor to its end.
+const CLEAR_LINE: &str = "\x1B[K";
+
+/**
+Counts the bytes read through it and redraws the bar every `REDRAW_INTERVAL`.
+ */
+pub(crate) struct ProgressReader<R, W: Write = Stderr> {
+    inner: R,
+    output: W,
+    name: String,
+    total: u64,
+    done: u64,
+    started: Instant,
+    first_draw_delay: Duration,
+    last_drawn: Option<Instant>,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub(crate) fn new(inner: R, name: String, total: u64) -> ProgressReader<R> {
+        return ProgressReader {
+            inner,
+            output: io::stderr(),
+            name,
+            total,
+            done: 0,
+            started: Instant::now(),
+            first_draw_delay: FIRST_DRAW_DELAY,
+            last_drawn: None,
+        };
+    }
+}
+
+impl<R, W: Write> ProgressReader<R, W> {
+    fn draw(&mut self) {
+        let now = Instant::now();
+        let due = match self.last_drawn {
+            Some(last_drawn) => now.duration_since(last_drawn) >= REDRAW_INTERVAL,
+            None => now.duration_since(self.started) >= self.first_draw_delay,
+        };
+        if !due {
+            return;
+        }
+        self.last_drawn = Some(now);
+
+        // the file may grow while it is scanned
+        let done = self.done.min(self.total);
+        let filled = (done * BAR_WIDTH).checked_div(self.total).unwrap_or(BAR_WIDTH);
+        let percent = (done * 100).checked_div(self.total).unwrap_or(100);
+        // a failure to draw is not worth stopping the scan
+        let _ = write!(
+            self.output, "\r[{}{}] {:>3}% {} of {} {}{}",
+            "=".repeat(filled as usize), " ".repeat((BAR_WIDTH - filled) as usize), percent,
+            format_size(done), format_size(self.total), self.name, CLEAR_LINE
+        );
+        let _ = self.output.flush();
+    }
+}
+
+impl<R: Read, W: Write> Read for ProgressReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.inner.read(buf)?;
+        self.done += num_read as u64;
+        self.draw();
+        return Ok(num_read);
+    }
+}
+
+impl<R, W: Write> Drop for ProgressReader<R, W> {
+    fn drop(&mut self) {
+        if self.last_drawn.is_some() {
+            let _ = write!(self.output, "\r{}", CLEAR_LINE);
+            let _ = self.output.flush();
+        }
+    }
+}
+
+/**
+Formats the size with a binary unit, e.g. `1.5 GiB`.
+ */
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut scaled = size as f64 / 1024.0;
+    let mut unit = 0;
+    while scaled >= 1024.0 && unit + 1 < UNITS.len() {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+    return format!("{:.1} {}", scaled, UNITS[unit]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("1000 B", format_size(1000));
+        assert_eq!("1.5 KiB", format_size(1536));
+        assert_eq!("2.6 GiB", format_size(2_800_000_000));
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let data = [0u8; 100];
+        let mut output = Vec::new();
+        {
+            let mut reader = ProgressReader {
+                inner: &data[..],
+                output: &mut output,
+                name: "a.bin".to_string(),
+                total: 100,
+                done: 0,
+                started: Instant::now(),
+                first_draw_delay: Duration::ZERO,
+                last_drawn: None,
+            };
+            let mut buffer = [0u8; 40];
+            assert_eq!(40, reader.read(&mut buffer).unwrap());
+            // too soon to be redrawn
+            assert_eq!(40, reader.read(&mut buffer).unwrap());
+        }
+        assert_eq!(
+            "\r[============                  ]  40% 40 B of 100 B a.bin\x1B[K\r\x1B[K",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ The Python extension module of the `pyo3` feature, `binutils_strings`:
+
+     >>> import binutils_strings
+     >>> [found.text for found in binutils_strings.scan_bytes(b"\x01hello\x00world!\x02")]
+     ['hello', 'world!']
+
+ `maturin build --release` in `python/` builds the wheel. The data is scanned in memory, with
+ the options the command line gives with -n, -e and -w, as the C interface does.
+ */
+
+use std::ffi::OsStr;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use super::ffi;
+use super::strings::{self, FoundString, StringSink};
+
+/**
+A string found by `scan_bytes`.
+ */
+#[pyclass(name = "FoundString", frozen, get_all, module = "binutils_strings")]
+pub struct PyFoundString {
+    /// Offset of the string in the data.
+    offset: u64,
+    /// Number of the bytes the string occupies in the data.
+    length: u64,
+    text: String,
+    /// Name of the encoding, e.g. "7bit" or "16le".
+    encoding: &'static str,
+}
+
+#[pymethods]
+impl PyFoundString {
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        return Ok(format!(
+            "FoundString(offset={}, length={}, text={}, encoding='{}')",
+            self.offset, self.length, PyString::new(py, &self.text).repr()?, self.encoding
+        ));
+    }
+}
+
+struct Collector {
+    found: Vec<PyFoundString>,
+}
+
+impl StringSink for Collector {
+    fn on_string(&mut self, found: &FoundString) {
+        self.found.push(PyFoundString {
+            offset: found.address,
+            length: found.end_address - found.address,
+            text: String::from_utf8_lossy(found.text).into_owned(),
+            encoding: ffi::encoding_name(found.encoding).to_str().unwrap(),
+        });
+    }
+}
+
+/**
+Returns the strings found in the data, as `strings -n MIN_LEN -e ENCODING` prints them. The
+encoding is one of 's', 'S', 'b', 'l', 'B', 'L', 'cp037' and 'cp500'.
+ */
+#[pyfunction]
+#[pyo3(signature = (data, min_len = 4, encoding = "s", include_all_whitespace = false))]
+fn scan_bytes(
+    py: Python<'_>,
+    data: &[u8],
+    min_len: u16,
+    encoding: &str,
+    include_all_whitespace: bool,
+) -> PyResult<Vec<PyFoundString>> {
+    let options = ffi::scan_options(min_len, Some(encoding), include_all_whitespace)
+        .ok_or_else(|| PyValueError::new_err(format!(
+            "invalid options: min_len={}, encoding='{}'", min_len, encoding
+        )))?;
+    // the data is immutable bytes, so it can be scanned without holding the interpreter
+    let found = py.detach(|| {
+        let mut collector = Collector { found: Vec::new() };
+        strings::print_strings_for_bytes(OsStr::new(""), data, &options, &mut collector);
+        return collector.found;
+    });
+    return Ok(found);
+}
+
+#[pymodule]
+fn binutils_strings(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyFoundString>()?;
+    module.add_function(wrap_pyfunction!(scan_bytes, module)?)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_bytes() {
+        Python::initialize();
+        Python::attach(|py| {
+            let data = b"\x01abc\0hello\0w\0o\0r\0l\0d\0\0\x02";
+            let found = scan_bytes(py, data, 4, "s", false).unwrap();
+            assert_eq!(1, found.len());
+            assert_eq!(
+                (5, 5, "hello", "7bit"),
+                (found[0].offset, found[0].length, found[0].text.as_str(), found[0].encoding)
+            );
+            assert_eq!(
+                "FoundString(offset=5, length=5, text='hello', encoding='7bit')",
+                found[0].__repr__(py).unwrap()
+            );
+
+            let found = scan_bytes(py, data, 3, "l", false).unwrap();
+            assert_eq!(vec!["oworld"], found.iter().map(|found| &found.text).collect::<Vec<_>>());
+
+            assert!(scan_bytes(py, b"", 4, "x", false).is_err());
+            assert!(scan_bytes(py, b"", 0, "s", false).is_err());
+        });
+    }
+}
@@ -0,0 +1,223 @@
+/*
+ Decoding of the firmware images in Intel HEX and Motorola S-record files, see `--hex-records`.
+ Both are text, one record per line: the address of the record, its bytes in hex and a
+ checksum. The bytes are put back at their addresses, so the strings are reported at the
+ addresses of the target memory. The records which continue one another make a block, and the
+ blocks are scanned one by one, so that no string runs across a gap between them.
+ */
+
+/**
+A run of bytes at consecutive addresses.
+ */
+#[derive(PartialEq, Debug)]
+pub(crate) struct Block {
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum RecordFormat {
+    IntelHex,
+    SRecord,
+}
+
+impl RecordFormat {
+    fn detect(data: &[u8]) -> Option<RecordFormat> {
+        let start = data.iter().position(|byte| !byte.is_ascii_whitespace())?;
+        return match &data[start..] {
+            [b':', ..] => Some(RecordFormat::IntelHex),
+            [b'S', b'0'..=b'9', ..] => Some(RecordFormat::SRecord),
+            _ => None,
+        };
+    }
+}
+
+/**
+Decodes the records into the blocks of the image, ordered by address. Returns `None` if the
+data is neither Intel HEX nor S-records, and an error naming the line of a malformed record.
+ */
+pub(crate) fn decode(data: &[u8]) -> Option<Result<Vec<Block>, String>> {
+    let format = RecordFormat::detect(data)?;
+    let mut blocks: Vec<Block> = Vec::new();
+    // the upper bits of the Intel HEX addresses, set by the extended address records
+    let mut base = 0u64;
+
+    for (index, line) in data.split(|byte| *byte == b'\n').enumerate() {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+        let record = match format {
+            RecordFormat::IntelHex => parse_intel_hex(line, &mut base),
+            RecordFormat::SRecord => parse_s_record(line),
+        };
+        match record {
+            Ok(Some((address, bytes))) => add_record(&mut blocks, address, bytes),
+            Ok(None) => {}
+            Err(err) => return Some(Err(format!("line {}: {}", index + 1, err))),
+        }
+    }
+
+    blocks.sort_by_key(|block| block.address);
+    let mut merged: Vec<Block> = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        match merged.last_mut() {
+            Some(last) if last.address + last.data.len() as u64 == block.address => {
+                last.data.extend(block.data);
+            }
+            _ => merged.push(block),
+        }
+    }
+    return Some(Ok(merged));
+}
+
+fn add_record(blocks: &mut Vec<Block>, address: u64, bytes: Vec<u8>) {
+    if bytes.is_empty() {
+        return;
+    }
+    if let Some(last) = blocks.last_mut() {
+        if last.address + last.data.len() as u64 == address {
+            last.data.extend(bytes);
+            return;
+        }
+    }
+    blocks.push(Block { address, data: bytes });
+}
+
+/**
+Decodes the hex digits of a record into its bytes.
+ */
+fn decode_hex(digits: &[u8]) -> Result<Vec<u8>, String> {
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+    return digits.chunks_exact(2)
+        .map(|pair| {
+            let text = std::str::from_utf8(pair).map_err(|_| "invalid hex digit".to_string())?;
+            return u8::from_str_radix(text, 16)
+                .map_err(|_| format!("invalid hex digit in {}", text));
+        })
+        .collect();
+}
+
+/**
+Parses a record of Intel HEX: `:`, the byte count, the 16-bit address, the type, the data and
+the checksum, which makes the sum of all the bytes zero. Returns the address and the bytes of
+a data record.
+ */
+fn parse_intel_hex(line: &[u8], base: &mut u64) -> Result<Option<(u64, Vec<u8>)>, String> {
+    const DATA: u8 = 0x00;
+    const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+    const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+
+    let Some(digits) = line.strip_prefix(b":") else {
+        return Err("record doesn't start with ':'".to_string());
+    };
+    let bytes = decode_hex(digits)?;
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+        return Err("wrong record length".to_string());
+    }
+    if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return Err("bad checksum".to_string());
+    }
+
+    let address = u16::from_be_bytes([bytes[1], bytes[2]]) as u64;
+    let data = &bytes[4..bytes.len() - 1];
+    let value = || -> Result<u64, String> {
+        return match data {
+            [high, low] => Ok(u16::from_be_bytes([*high, *low]) as u64),
+            _ => Err("wrong length of an extended address".to_string()),
+        };
+    };
+    match bytes[3] {
+        DATA => return Ok(Some((*base + address, data.to_vec()))),
+        EXTENDED_SEGMENT_ADDRESS => *base = value()? << 4,
+        EXTENDED_LINEAR_ADDRESS => *base = value()? << 16,
+        // the end of file and the start addresses
+        0x01 | 0x03 | 0x05 => {}
+        kind => return Err(format!("unknown record type {:02x}", kind)),
+    }
+    return Ok(None);
+}
+
+/**
+Parses an S-record: `S`, the type, the byte count, the address of 2, 3 or 4 bytes depending on
+the type, the data and the checksum, the complement of the sum of the other bytes. Returns the
+address and the bytes of a data record, S1, S2 or S3.
+ */
+fn parse_s_record(line: &[u8]) -> Result<Option<(u64, Vec<u8>)>, String> {
+    let [b'S', kind, digits @ ..] = line else {
+        return Err("record doesn't start with 'S'".to_string());
+    };
+    let bytes = decode_hex(digits)?;
+    if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+        return Err("wrong record length".to_string());
+    }
+    let (checksum, counted) = bytes.split_last().unwrap();
+    if !counted.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != *checksum {
+        return Err("bad checksum".to_string());
+    }
+
+    let address_size = match kind {
+        b'1' => 2,
+        b'2' => 3,
+        b'3' => 4,
+        // the header, the record counts and the start addresses
+        b'0' | b'5'..=b'9' => return Ok(None),
+        _ => return Err(format!("unknown record type S{}", *kind as char)),
+    };
+    let Some((address, data)) = counted[1..].split_at_checked(address_size) else {
+        return Err("wrong record length".to_string());
+    };
+    let address = address.iter().fold(0u64, |address, byte| (address << 8) | *byte as u64);
+    return Ok(Some((address, data.to_vec())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_intel_hex() {
+        let data = concat!(
+            ":020000040800F2\n",
+            ":0500000048656C6C6F07\n",
+            ":0400050020484558F2\n",
+            ":0210000041426B\n",
+            ":00000001FF\n",
+        );
+        assert_eq!(
+            Some(Ok(vec![
+                Block { address: 0x0800_0000, data: b"Hello HEX".to_vec() },
+                Block { address: 0x0800_1000, data: b"AB".to_vec() },
+            ])),
+            decode(data.as_bytes())
+        );
+        assert_eq!(
+            Some(Err("line 2: bad checksum".to_string())),
+            decode(b":020000040800F2\r\n:0500000048656C6C6F08\r\n")
+        );
+        assert_eq!(None, decode(b"plain text"));
+    }
+
+    #[test]
+    fn test_decode_s_record() {
+        let data = concat!(
+            "S00600004844521B\n",
+            "S2080100004869210024\n",
+            "S1050020414257\n",
+            "S9030000FC\n",
+        );
+        assert_eq!(
+            Some(Ok(vec![
+                Block { address: 0x20, data: b"AB".to_vec() },
+                Block { address: 0x01_0000, data: b"Hi!\0".to_vec() },
+            ])),
+            decode(data.as_bytes())
+        );
+        assert_eq!(
+            Some(Err("line 1: unknown record type S4".to_string())),
+            decode(b"S4030000FC")
+        );
+    }
+}
@@ -0,0 +1,327 @@
+/*
+ Strings of the resources of PE files, see `--pe-resources`. The string tables (RT_STRING) and
+ the version information (RT_VERSION) keep their text in UTF-16 with length prefixes and
+ without NULs between the strings, so the scan of the bytes joins neighbouring strings and
+ splits them at the prefixes. They are read from the resource directory instead, and reported
+ with the ID of the string, or the name of the version field, and the language of the resource.
+ */
+
+use std::ffi::OsStr;
+use object::pe;
+use object::read::pe::{PeFile32, PeFile64, ResourceDirectory, ResourceDirectoryTable, SectionTable};
+use object::FileKind;
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::read_u16_le;
+
+/// Number of strings in every block of a string table.
+const STRINGS_PER_BLOCK: u32 = 16;
+
+/**
+A string read from a resource, at its offset in the file.
+ */
+#[derive(PartialEq, Debug)]
+struct ResourceString {
+    offset: usize,
+    /// Offset right after the last character.
+    end: usize,
+    text: String,
+    /// `string:ID` or `version:KEY`
+    label: String,
+    termination: Termination,
+}
+
+/**
+Scans the string tables and the version information of a PE file. Returns `None` if the data is
+not a PE file or has neither of them.
+ */
+pub(crate) fn print_strings_for_pe_resources(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let (directory, sections) = resource_directory(data)?;
+    let mut found_any = false;
+    let mut num_found = 0u64;
+
+    for type_entry in directory.root().ok()?.entries {
+        let kind = type_entry.name_or_id().id();
+        if kind != Some(pe::RT_STRING) && kind != Some(pe::RT_VERSION) {
+            continue;
+        }
+        for (id, language, offset, resource) in resources(directory, &sections, data, type_entry) {
+            found_any = true;
+            let strings = match kind {
+                Some(pe::RT_STRING) => parse_string_table(resource, offset, id),
+                _ => parse_version_info(resource, offset),
+            };
+            let tags_language = format!("lang:{:04x}", language);
+
+            for string in strings {
+                if string.text.chars().count() < options.min_length as usize {
+                    continue;
+                }
+                let tags = [string.label, tags_language.clone()];
+                sink.on_string(&FoundString {
+                    filename,
+                    address: string.offset as u64,
+                    end_address: string.end as u64,
+                    bytes: string.text.as_bytes(),
+                    text: string.text.as_bytes(),
+                    encoding: EncodingKind::LittleEndian16,
+                    tags: &tags,
+                    xor_key: None,
+                    decoded_from: None,
+                    termination: string.termination,
+                    score: None,
+                    context_before: &[],
+                    context_after: &[],
+                    section: None,
+                });
+                num_found += 1;
+            }
+        }
+    }
+
+    return found_any.then_some(num_found);
+}
+
+fn resource_directory(data: &[u8]) -> Option<(ResourceDirectory<'_>, SectionTable<'_>)> {
+    let (sections, directories) = match FileKind::parse(data).ok()? {
+        FileKind::Pe32 => {
+            let file = PeFile32::parse(data).ok()?;
+            (file.section_table(), file.data_directories())
+        }
+        FileKind::Pe64 => {
+            let file = PeFile64::parse(data).ok()?;
+            (file.section_table(), file.data_directories())
+        }
+        _ => return None,
+    };
+    let directory = directories.resource_directory(data, &sections).ok()??;
+    return Some((directory, sections));
+}
+
+/**
+Returns the resources of a type: their ID, language, offset in the file and data. The resources
+named instead of numbered, and the broken entries, are skipped.
+ */
+fn resources<'data>(
+    directory: ResourceDirectory<'data>,
+    sections: &SectionTable<'data>,
+    data: &'data [u8],
+    type_entry: &pe::ImageResourceDirectoryEntry,
+) -> Vec<(u16, u16, usize, &'data [u8])> {
+    let subtable = |entry: &pe::ImageResourceDirectoryEntry| -> Option<ResourceDirectoryTable> {
+        return entry.data(directory).ok()?.table();
+    };
+
+    let mut found = Vec::new();
+    let Some(ids) = subtable(type_entry) else {
+        return found;
+    };
+    for id_entry in ids.entries {
+        let (Some(id), Some(languages)) = (id_entry.name_or_id().id(), subtable(id_entry)) else {
+            continue;
+        };
+        for language_entry in languages.entries {
+            let Some(language) = language_entry.name_or_id().id() else {
+                continue;
+            };
+            let Some(entry) = language_entry.data(directory).ok().and_then(|entry| entry.data())
+            else {
+                continue;
+            };
+            let address = entry.offset_to_data.get(object::LittleEndian);
+            let size = entry.size.get(object::LittleEndian) as usize;
+            let (Some((offset, _)), Some(resource)) = (
+                sections.pe_file_range_at(address), sections.pe_data_at(data, address)
+            ) else {
+                continue;
+            };
+            found.push((id, language, offset as usize, &resource[..size.min(resource.len())]));
+        }
+    }
+    return found;
+}
+
+/**
+Decodes the UTF-16LE text from `start` up to `end` or to the first NUL. Returns it with the
+offset right after its last character.
+ */
+fn read_utf16(data: &[u8], start: usize, end: usize) -> (String, usize) {
+    let units: Vec<u16> = (start..end.min(data.len()).saturating_sub(1)).step_by(2)
+        .map_while(|position| read_u16_le(data, position).filter(|unit| *unit != 0))
+        .collect();
+    return (String::from_utf16_lossy(&units), start + units.len() * 2);
+}
+
+/**
+Reads a block of a string table: the strings with the IDs `(block_id - 1) * 16` to
+`block_id * 16 - 1`, each one the number of its UTF-16 code units followed by them. The
+strings which are not defined are empty.
+ */
+fn parse_string_table(data: &[u8], offset: usize, block_id: u16) -> Vec<ResourceString> {
+    let mut strings = Vec::new();
+    if block_id == 0 {
+        return strings;
+    }
+
+    let mut position = 0;
+    for index in 0..STRINGS_PER_BLOCK {
+        let Some(length) = read_u16_le(data, position) else {
+            break;
+        };
+        let start = position + 2;
+        position = start + length as usize * 2;
+        if position > data.len() {
+            break;
+        }
+        if length == 0 {
+            continue;
+        }
+
+        let units: Vec<u16> = data[start..position].chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        strings.push(ResourceString {
+            offset: offset + start,
+            end: offset + position,
+            text: String::from_utf16_lossy(&units),
+            label: format!("string:{}", (block_id as u32 - 1) * STRINGS_PER_BLOCK + index),
+            termination: Termination::Length,
+        });
+    }
+    return strings;
+}
+
+/**
+Reads the text fields of the version information, e.g. `CompanyName`. It is a tree of blocks,
+each one its length, the length and the type of its value, its key as a NUL-terminated UTF-16
+string, the value and the child blocks, all of them aligned to 4 bytes.
+ */
+fn parse_version_info(data: &[u8], offset: usize) -> Vec<ResourceString> {
+    let mut strings = Vec::new();
+    parse_version_block(data, 0, data.len(), offset, &mut strings);
+    return strings;
+}
+
+fn parse_version_block(
+    data: &[u8],
+    start: usize,
+    limit: usize,
+    offset: usize,
+    strings: &mut Vec<ResourceString>,
+) -> Option<usize> {
+    const TEXT_VALUE: u16 = 1;
+    let align = |position: usize| (position + 3) & !3;
+
+    let length = read_u16_le(data, start)? as usize;
+    let value_length = read_u16_le(data, start + 2)? as usize;
+    let value_type = read_u16_le(data, start + 4)?;
+    let end = start + length;
+    if length < 6 || end > limit {
+        return None;
+    }
+
+    let (key, key_end) = read_utf16(data, start + 6, end);
+    let value_start = align(key_end + 2);
+    let mut children_start = value_start;
+    if value_length > 0 && value_type == TEXT_VALUE {
+        // the length is in characters, but some compilers put the number of bytes there
+        let (text, text_end) = read_utf16(data, value_start, end);
+        children_start = text_end;
+        if !text.is_empty() {
+            strings.push(ResourceString {
+                offset: offset + value_start,
+                end: offset + text_end,
+                text,
+                label: format!("version:{}", key),
+                termination: Termination::Nul,
+            });
+        }
+    } else {
+        children_start += value_length;
+    }
+
+    let mut child = align(children_start);
+    while child < end {
+        match parse_version_block(data, child, end, offset, strings) {
+            Some(child_end) if child_end > child => child = align(child_end),
+            _ => break,
+        }
+    }
+    return Some(end);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        return text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+    }
+
+    /**
+    Builds a version block with a text value, or with the given children.
+     */
+    fn version_block(key: &str, value: Option<&str>, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut block = vec![0u8; 6];
+        block.extend(utf16(key));
+        block.extend([0, 0]);
+        block.resize((block.len() + 3) & !3, 0);
+        if let Some(value) = value {
+            block[2..4].copy_from_slice(&(value.len() as u16 + 1).to_le_bytes());
+            block[4] = 1;
+            block.extend(utf16(value));
+            block.extend([0, 0]);
+        }
+        for child in children {
+            block.resize((block.len() + 3) & !3, 0);
+            block.extend(child);
+        }
+        let length = block.len() as u16;
+        block[0..2].copy_from_slice(&length.to_le_bytes());
+        return block;
+    }
+
+    #[test]
+    fn test_parse_string_table() {
+        let mut data = vec![0u8; 2];
+        data.extend(2u16.to_le_bytes());
+        data.extend(utf16("OK"));
+        data.extend(5u16.to_le_bytes());
+        data.extend(utf16("Hello"));
+        data.extend([0u8; 26]);
+
+        let strings = parse_string_table(&data, 0x400, 7);
+        assert_eq!(
+            vec![("OK", "string:97", 0x404, 0x408), ("Hello", "string:98", 0x40a, 0x414)],
+            strings.iter()
+                .map(|s| (s.text.as_str(), s.label.as_str(), s.offset, s.end))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_version_info() {
+        let strings = version_block("StringFileInfo", None, &[
+            version_block("040904b0", None, &[
+                version_block("CompanyName", Some("Acme"), &[]),
+                version_block("FileVersion", Some("1.2.3"), &[]),
+            ]),
+        ]);
+        let data = version_block("VS_VERSION_INFO", None, &[strings]);
+
+        let found = parse_version_info(&data, 0);
+        assert_eq!(
+            vec![("Acme", "version:CompanyName"), ("1.2.3", "version:FileVersion")],
+            found.iter().map(|s| (s.text.as_str(), s.label.as_str())).collect::<Vec<_>>()
+        );
+        assert_eq!("Acme", String::from_utf16_lossy(
+            &data[found[0].offset..found[0].end].chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect::<Vec<_>>()
+        ));
+    }
+}
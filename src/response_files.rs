@@ -0,0 +1,128 @@
+/*
+ Response files, as GNU binutils reads them: an argument `@FILE` is replaced with the arguments
+ in FILE, so that the build systems can pass more files than the command line holds. The
+ arguments are separated by whitespace, and quotes and backslashes work as in a shell: `'a b'`,
+ `"a b"` and `a\ b` are the same argument. A response file may refer to other ones. If FILE
+ can't be read, `@FILE` is kept as an argument.
+ */
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use super::utils::{os_str_bytes, os_string_from_bytes};
+
+/// Response files referring to each other deeper than this are taken for a loop.
+const MAX_DEPTH: usize = 32;
+
+/**
+Returns the command line with the response files expanded. The first argument is the program
+name and is kept as is. Returns an error message if the response files refer to each other in
+a loop.
+ */
+pub fn expand_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Vec<OsString>, String> {
+    let mut args = args.into_iter();
+    let mut expanded: Vec<OsString> = args.next().into_iter().collect();
+    for arg in args {
+        expand_arg(arg, 0, &mut expanded)?;
+    }
+    return Ok(expanded);
+}
+
+fn expand_arg(arg: OsString, depth: usize, expanded: &mut Vec<OsString>) -> Result<(), String> {
+    let path = match os_str_bytes(&arg).strip_prefix(b"@") {
+        Some(path) => os_string_from_bytes(path.to_vec()),
+        None => {
+            expanded.push(arg);
+            return Ok(());
+        }
+    };
+
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(_) => {
+            expanded.push(arg);
+            return Ok(());
+        }
+    };
+    if depth >= MAX_DEPTH {
+        return Err(format!("{}: response files nested too deeply", Path::new(&path).display()));
+    }
+
+    for nested in split_args(&data) {
+        expand_arg(os_string_from_bytes(nested), depth + 1, expanded)?;
+    }
+    return Ok(());
+}
+
+/**
+Splits the contents of a response file into the arguments.
+ */
+pub(crate) fn split_args(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut args = Vec::new();
+    // `Some` once an argument is started, even an empty one like ''
+    let mut current: Option<Vec<u8>> = None;
+    let mut quote: Option<u8> = None;
+    let mut bytes = data.iter().copied();
+
+    while let Some(byte) = bytes.next() {
+        match (quote, byte) {
+            (_, b'\\') => {
+                let arg = current.get_or_insert_with(Vec::new);
+                arg.extend(bytes.next());
+            }
+            (Some(open), _) if byte == open => quote = None,
+            (Some(_), _) => current.get_or_insert_with(Vec::new).push(byte),
+            (None, b'\'' | b'"') => {
+                quote = Some(byte);
+                current.get_or_insert_with(Vec::new);
+            }
+            (None, _) if byte.is_ascii_whitespace() => args.extend(current.take()),
+            (None, _) => current.get_or_insert_with(Vec::new).push(byte),
+        }
+    }
+    args.extend(current);
+    return args;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        let split = |data: &[u8]| {
+            return split_args(data).into_iter()
+                .map(|arg| String::from_utf8(arg).unwrap())
+                .collect::<Vec<String>>();
+        };
+
+        assert_eq!(vec!["-n", "8", "a.out"], split(b"  -n 8\n\ta.out\r\n"));
+        assert_eq!(
+            vec!["my file", "it's", "a\\b", "", "x y"],
+            split(br#"'my file' "it's" a\\b '' x\ y"#)
+        );
+        assert!(split(b" \n ").is_empty());
+    }
+
+    #[test]
+    fn test_expand_args() {
+        let directory = std::env::temp_dir().join(format!("strings-rsp-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let outer = directory.join("outer.rsp");
+        let inner = directory.join("inner.rsp");
+        let looped = directory.join("loop.rsp");
+        fs::write(&outer, format!("-f @{}\n'b c.bin'", inner.display())).unwrap();
+        fs::write(&inner, "-t x a.bin").unwrap();
+        fs::write(&looped, format!("@{}", looped.display())).unwrap();
+
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<OsString>>();
+        let outer_arg = format!("@{}", outer.display());
+        assert_eq!(
+            Ok(args(&["strings", "-f", "-t", "x", "a.bin", "b c.bin", "-a", "@missing"])),
+            expand_args(args(&["strings", &outer_arg, "-a", "@missing"]))
+        );
+        assert!(expand_args(args(&["strings", &format!("@{}", looped.display())])).is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
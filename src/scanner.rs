@@ -0,0 +1,802 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use super::alphabet::Alphabet;
+use super::ebcdic;
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::*;
+
+/**
+Scanner which is fed with the data byte by byte. It's `Send`, so that a `StreamScanner` can be
+kept across the awaits of a task on a multithreaded runtime.
+ */
+pub(crate) trait ByteScanner: Send {
+    /**
+    Returns the number of strings found.
+     */
+    fn push(&mut self, byte: u8, filename: &OsStr, sink: &mut dyn StringSink) -> u64;
+
+    /**
+    Flushes the string which is in progress, if any. Returns the number of strings found.
+     */
+    fn finish(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64;
+}
+
+pub(crate) fn new_scanner(
+    encoding: EncodingKind,
+    options: &Options,
+    address: u64,
+) -> Box<dyn ByteScanner> {
+    return match encoding {
+        EncodingKind::Auto16 => Box::new(AutoEndianScanner::new(
+            EncodingKind::LittleEndian16, EncodingKind::BigEndian16, options, address,
+        )),
+        EncodingKind::Auto32 => Box::new(AutoEndianScanner::new(
+            EncodingKind::LittleEndian32, EncodingKind::BigEndian32, options, address,
+        )),
+        _ => Box::new(IncrementalScanner::new(encoding, options, address))
+    };
+}
+
+/**
+Incremental scanner for a single encoding. Bytes are pushed one by one and found strings are
+passed to the sink as soon as they end, so several scanners can share one pass over the data.
+
+It follows the same rules as the pull-based scanner: once a non-graphic symbol is found, the
+search restarts at the byte following the start of that symbol (or at the next code unit
+with `--decode-wide`).
+ */
+pub(crate) struct IncrementalScanner {
+    encoding: EncodingKind,
+    min_length: u16,
+    include_all_whitespace: bool,
+    alphabet: Option<Alphabet>,
+    decode_wide: bool,
+    strict_units: bool,
+    // bytes which are not yet decoded into symbols
+    pending: VecDeque<u8>,
+    // address of the first pending byte
+    address: u64,
+    run: Vec<u8>,
+    // number of symbols in the run, differs from its length with `--decode-wide`
+    run_num_symbols: usize,
+    run_start_address: u64,
+}
+
+impl IncrementalScanner {
+    pub(crate) fn new(encoding: EncodingKind, options: &Options, address: u64) -> IncrementalScanner {
+        return IncrementalScanner {
+            encoding,
+            min_length: options.min_length,
+            include_all_whitespace: options.include_all_whitespace,
+            alphabet: options.alphabet,
+            decode_wide: options.decode_wide && encoding.num_bytes() > 1,
+            strict_units: options.strict_units,
+            pending: VecDeque::with_capacity(4),
+            address,
+            run: Vec::new(),
+            run_num_symbols: 0,
+            run_start_address: address,
+        };
+    }
+
+    /**
+    Returns the start address of the string in progress, if any.
+     */
+    fn current_run_start_address(&self) -> Option<u64> {
+        if self.run_num_symbols > 0 {
+            return Some(self.run_start_address);
+        }
+        return None;
+    }
+
+    /**
+    Lowest address a string found later can start at.
+     */
+    fn next_string_min_address(&self) -> u64 {
+        return self.current_run_start_address().unwrap_or(self.address);
+    }
+
+    /**
+    Decodes the symbol starting at the given index of the pending bytes.
+     */
+    fn decode_symbol(&self, index: usize) -> u32 {
+        let mut symbol = 0u32;
+        for i in index..index + self.encoding.num_bytes() as usize {
+            symbol = (symbol << 8) | (self.pending[i] as u32);
+        }
+
+        return match self.encoding {
+            EncodingKind::LittleEndian16 => to_little_endian_16(symbol),
+            EncodingKind::LittleEndian32 => to_little_endian_32(symbol),
+            _ => symbol
+        };
+    }
+
+    fn append_if_printable(&mut self, symbol: u32) -> bool {
+        if self.decode_wide {
+            match char::from_u32(symbol) {
+                Some(c) if self.alphabet.map_or_else(
+                    || wide_char_is_printable(c, self.include_all_whitespace),
+                    |alphabet| alphabet.contains(c),
+                ) => {
+                    let mut utf8 = [0u8; 4];
+                    self.run.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                _ => return false
+            }
+        } else if self.encoding.is_ebcdic() {
+            let c = ebcdic::decode(self.encoding, symbol as u8);
+            let printable = self.alphabet.map_or_else(
+                || ebcdic::is_printable(c, self.include_all_whitespace),
+                |alphabet| alphabet.contains(c),
+            );
+            if !printable {
+                return false;
+            }
+            let mut utf8 = [0u8; 4];
+            self.run.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        } else {
+            if symbol > 255 || !self.alphabet.map_or_else(
+                || char_is_printable(symbol as u8 as char, self.encoding,
+                                     self.include_all_whitespace),
+                |alphabet| alphabet.contains(symbol as u8 as char),
+            ) {
+                return false;
+            }
+            self.run.push(symbol as u8);
+        }
+
+        if self.run_num_symbols == 0 {
+            self.run_start_address = self.address;
+        }
+        self.run_num_symbols += 1;
+        return true;
+    }
+
+    fn end_run(
+        &mut self,
+        filename: &OsStr,
+        termination: Termination,
+        sink: &mut dyn StringSink,
+    ) -> u64 {
+        let mut num_found = 0u64;
+
+        if self.run_num_symbols >= self.min_length as usize {
+            sink.on_string(&FoundString {
+                filename,
+                address: self.run_start_address,
+                end_address: self.address,
+                bytes: &self.run,
+                text: &self.run,
+                encoding: self.encoding,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found = 1;
+        }
+
+        self.run.clear();
+        self.run_num_symbols = 0;
+        return num_found;
+    }
+}
+
+impl ByteScanner for IncrementalScanner {
+    fn push(&mut self, byte: u8, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        let num_bytes = self.encoding.num_bytes() as usize;
+        let mut num_found = 0u64;
+
+        self.pending.push_back(byte);
+
+        while self.pending.len() >= num_bytes {
+            let mut symbol = self.decode_symbol(0);
+            let mut symbol_num_bytes = num_bytes;
+
+            if self.decode_wide && num_bytes == 2 && is_high_surrogate(symbol) {
+                if self.pending.len() < 2 * num_bytes {
+                    // wait for the low surrogate
+                    break;
+                }
+                let low = self.decode_symbol(num_bytes);
+                if is_low_surrogate(low) {
+                    symbol = decode_surrogate_pair(symbol, low);
+                    symbol_num_bytes += num_bytes;
+                }
+            }
+
+            if !self.append_if_printable(symbol) {
+                let termination = Termination::after(symbol, self.encoding);
+                num_found += self.end_run(filename, termination, sink);
+                // stay aligned when decoding, as any misaligned unit is likely to be valid
+                let skip = if self.decode_wide { num_bytes } else { 1 };
+                self.pending.drain(..skip);
+                self.address += skip as u64;
+            } else {
+                self.pending.drain(..symbol_num_bytes);
+                self.address += symbol_num_bytes as u64;
+            }
+        }
+
+        return num_found;
+    }
+
+    /**
+    Incomplete trailing symbols are dropped, with `--strict-units` along with the string they
+    follow.
+     */
+    fn finish(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        let num_bytes = self.encoding.num_bytes() as usize;
+        if self.strict_units && !self.pending.len().is_multiple_of(num_bytes) {
+            self.run.clear();
+            self.run_num_symbols = 0;
+        }
+        self.pending.clear();
+        return self.end_run(filename, Termination::Eof, sink);
+    }
+}
+
+struct Candidate {
+    address: u64,
+    end_address: u64,
+    bytes: Vec<u8>,
+    encoding: EncodingKind,
+    termination: Termination,
+    preceded_by_bom: bool,
+}
+
+struct CandidateCollector {
+    found: Vec<Candidate>,
+}
+
+impl StringSink for CandidateCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        self.found.push(Candidate {
+            address: found.address,
+            end_address: found.end_address,
+            bytes: found.bytes.to_vec(),
+            encoding: found.encoding,
+            termination: found.termination,
+            preceded_by_bom: false,
+        });
+    }
+}
+
+const HISTORY_SIZE: usize = 8;
+
+/**
+Scans for both little- and big-endian strings (`--encoding auto16/auto32`). When strings of
+different endianness overlap, only one of them is reported: the one preceded by its BOM or,
+failing that, the one aligned to the code unit size. When both are aligned, which happens with
+`--decode-wide`, the text is expected to keep to a few blocks of 256 characters: the reading
+with the zero high bytes (for ASCII text, the one with the NUL bytes at the expected positions)
+or, failing that, with the fewest different high bytes wins.
+ */
+struct AutoEndianScanner {
+    // little-endian first, big-endian second
+    scanners: [IncrementalScanner; 2],
+    boms: [&'static [u8]; 2],
+    // start address of the string in progress of each scanner and if it is preceded by BOM
+    run_boms: [Option<(u64, bool)>; 2],
+    // last pushed bytes, to check for BOM before the strings
+    history: VecDeque<u8>,
+    // address right after the last pushed byte
+    history_end_address: u64,
+    candidates: Vec<Candidate>,
+    unit_size: u64,
+}
+
+impl AutoEndianScanner {
+    fn new(
+        little_endian: EncodingKind,
+        big_endian: EncodingKind,
+        options: &Options,
+        address: u64,
+    ) -> AutoEndianScanner {
+        let boms: [&'static [u8]; 2] = if little_endian.num_bytes() == 2 {
+            [&[0xff, 0xfe], &[0xfe, 0xff]]
+        } else {
+            [&[0xff, 0xfe, 0x00, 0x00], &[0x00, 0x00, 0xfe, 0xff]]
+        };
+
+        return AutoEndianScanner {
+            scanners: [
+                IncrementalScanner::new(little_endian, options, address),
+                IncrementalScanner::new(big_endian, options, address),
+            ],
+            boms,
+            run_boms: [None, None],
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            history_end_address: address,
+            candidates: Vec::new(),
+            unit_size: little_endian.num_bytes() as u64,
+        };
+    }
+
+    fn is_preceded_by(&self, address: u64, bom: &[u8]) -> bool {
+        let history_start_address = self.history_end_address - self.history.len() as u64;
+        if address < history_start_address + bom.len() as u64
+            || address > self.history_end_address {
+            return false;
+        }
+
+        let start = (address - history_start_address) as usize - bom.len();
+        return bom.iter().enumerate().all(|(i, byte)| self.history[start + i] == *byte);
+    }
+
+    /**
+    Returns the number of characters of the candidate with a zero high byte, that is below
+    U+0100, the number of different high bytes and the number of characters. The characters above
+    U+FFFF count with the high byte of their plane and block.
+     */
+    fn high_bytes(&self, candidate: &Candidate) -> (usize, usize, usize) {
+        let chars: Vec<u32> = if self.scanners[0].decode_wide {
+            String::from_utf8_lossy(&candidate.bytes).chars().map(|c| c as u32).collect()
+        } else {
+            candidate.bytes.iter().map(|byte| *byte as u32).collect()
+        };
+
+        let mut high_bytes: Vec<u32> = chars.iter().map(|c| c >> 8).collect();
+        let num_zero = high_bytes.iter().filter(|high| **high == 0).count();
+        high_bytes.sort_unstable();
+        high_bytes.dedup();
+        return (num_zero, high_bytes.len(), chars.len());
+    }
+
+    /**
+    Returns true if `a` should be reported rather than the overlapping `b`.
+     */
+    fn prefers(&self, a: &Candidate, b: &Candidate) -> bool {
+        if a.preceded_by_bom != b.preceded_by_bom {
+            return a.preceded_by_bom;
+        }
+
+        // unit size is a power of two
+        let a_aligned = a.address & (self.unit_size - 1) == 0;
+        let b_aligned = b.address & (self.unit_size - 1) == 0;
+        if a_aligned != b_aligned {
+            return a_aligned;
+        }
+
+        let (a_zero, a_distinct, a_len) = self.high_bytes(a);
+        let (b_zero, b_distinct, b_len) = self.high_bytes(b);
+        // compare the shares, the strings can differ in length
+        if a_zero * b_len != b_zero * a_len {
+            return a_zero * b_len > b_zero * a_len;
+        }
+        if a_distinct * b_len != b_distinct * a_len {
+            return a_distinct * b_len < b_distinct * a_len;
+        }
+
+        let a_span = a.end_address - a.address;
+        let b_span = b.end_address - b.address;
+        if a_span != b_span {
+            return a_span > b_span;
+        }
+
+        // little-endian
+        return a.encoding.name() == self.scanners[0].encoding.name();
+    }
+
+    /**
+    Reports the candidates which can't be overlapped by the strings found later.
+     */
+    fn resolve(&mut self, filename: &OsStr, sink: &mut dyn StringSink, is_final: bool) -> u64 {
+        let mut num_found = 0u64;
+
+        loop {
+            let first = match (0..self.candidates.len())
+                .min_by_key(|i| self.candidates[*i].address) {
+                Some(index) => index,
+                None => return num_found
+            };
+
+            let candidate = &self.candidates[first];
+            let overlapping = self.candidates.iter().position(|other| {
+                other.encoding.name() != candidate.encoding.name()
+                    && other.address < candidate.end_address
+                    && candidate.address < other.end_address
+            });
+
+            if let Some(other) = overlapping {
+                let loser = if self.prefers(candidate, &self.candidates[other]) {
+                    other
+                } else {
+                    first
+                };
+                self.candidates.remove(loser);
+                continue;
+            }
+
+            let other_scanner = if candidate.encoding.name() == self.scanners[0].encoding.name() {
+                &self.scanners[1]
+            } else {
+                &self.scanners[0]
+            };
+            if !is_final && other_scanner.next_string_min_address() < candidate.end_address {
+                // wait, the other scanner may still find an overlapping string
+                return num_found;
+            }
+
+            let candidate = self.candidates.remove(first);
+            sink.on_string(&FoundString {
+                filename,
+                address: candidate.address,
+                end_address: candidate.end_address,
+                bytes: &candidate.bytes,
+                text: &candidate.bytes,
+                encoding: candidate.encoding,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: candidate.termination,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found += 1;
+        }
+    }
+
+    fn collect(&mut self, index: usize, collector: CandidateCollector) {
+        for mut candidate in collector.found {
+            candidate.preceded_by_bom = matches!(
+                self.run_boms[index],
+                Some((address, true)) if address == candidate.address
+            );
+            self.candidates.push(candidate);
+        }
+    }
+}
+
+impl ByteScanner for AutoEndianScanner {
+    fn push(&mut self, byte: u8, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(byte);
+        self.history_end_address += 1;
+
+        for index in 0..2 {
+            let mut collector = CandidateCollector { found: Vec::new() };
+            self.scanners[index].push(byte, filename, &mut collector);
+            self.collect(index, collector);
+
+            if let Some(address) = self.scanners[index].current_run_start_address() {
+                if !matches!(self.run_boms[index], Some((known, _)) if known == address) {
+                    let has_bom = self.is_preceded_by(address, self.boms[index]);
+                    self.run_boms[index] = Some((address, has_bom));
+                }
+            }
+        }
+
+        return self.resolve(filename, sink, false);
+    }
+
+    fn finish(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        for index in 0..2 {
+            let mut collector = CandidateCollector { found: Vec::new() };
+            self.scanners[index].finish(filename, &mut collector);
+            self.collect(index, collector);
+        }
+
+        return self.resolve(filename, sink, true);
+    }
+}
+
+/**
+Returns the scanner of `--detect-utf16`, which finds the UTF-16 strings of a byte scan.
+ */
+pub(crate) fn new_utf16_detector(options: &Options, address: u64) -> Box<dyn ByteScanner> {
+    return Box::new(Utf16Detector::new(options, address));
+}
+
+const WINDOW_SIZE: u64 = 16;
+
+/**
+Scans for both little- and big-endian 16-bit strings and picks the endianness by the NUL bytes
+around them. The NUL bytes are counted at the even and at the odd addresses of every window of
+`WINDOW_SIZE` bytes; the text mostly below U+0100 puts them at the high bytes of its code units.
+A string is reported if the windows it spans have more NUL bytes at the odd addresses for
+UTF-16LE, or at the even ones for UTF-16BE, so that a string is found once, in the endianness
+of its code units aligned to 2 bytes. A tie is taken for little-endian, as it's the usual one.
+ */
+struct Utf16Detector {
+    // little-endian first, big-endian second
+    scanners: [IncrementalScanner; 2],
+    // start address of the first window kept
+    windows_start_address: u64,
+    // number of the NUL bytes of every window at the even and at the odd addresses
+    windows: VecDeque<[usize; 2]>,
+    // address right after the last pushed byte
+    end_address: u64,
+    candidates: VecDeque<Candidate>,
+}
+
+impl Utf16Detector {
+    fn new(options: &Options, address: u64) -> Utf16Detector {
+        return Utf16Detector {
+            scanners: [
+                IncrementalScanner::new(EncodingKind::LittleEndian16, options, address),
+                IncrementalScanner::new(EncodingKind::BigEndian16, options, address),
+            ],
+            windows_start_address: address - address % WINDOW_SIZE,
+            windows: VecDeque::new(),
+            end_address: address,
+            candidates: VecDeque::new(),
+        };
+    }
+
+    fn window_index(&self, address: u64) -> usize {
+        return ((address - self.windows_start_address) / WINDOW_SIZE) as usize;
+    }
+
+    /**
+    Returns the endianness of the code units in the windows the candidate spans.
+     */
+    fn endianness(&self, candidate: &Candidate) -> EncodingKind {
+        let first = self.window_index(candidate.address);
+        let last = self.window_index(candidate.end_address - 1);
+        let (mut even, mut odd) = (0usize, 0usize);
+        for window in self.windows.range(first..=last.min(self.windows.len() - 1)) {
+            even += window[0];
+            odd += window[1];
+        }
+        return if even > odd { EncodingKind::BigEndian16 } else { EncodingKind::LittleEndian16 };
+    }
+
+    /**
+    Reports the candidates whose windows are complete, in the order they were found.
+     */
+    fn resolve(&mut self, filename: &OsStr, sink: &mut dyn StringSink, is_final: bool) -> u64 {
+        let mut num_found = 0u64;
+
+        while let Some(candidate) = self.candidates.front() {
+            let last_window_end = (candidate.end_address - 1) / WINDOW_SIZE * WINDOW_SIZE
+                + WINDOW_SIZE;
+            if !is_final && self.end_address < last_window_end {
+                break;
+            }
+
+            if self.endianness(candidate).name() == candidate.encoding.name() {
+                sink.on_string(&FoundString {
+                    filename,
+                    address: candidate.address,
+                    end_address: candidate.end_address,
+                    bytes: &candidate.bytes,
+                    text: &candidate.bytes,
+                    encoding: candidate.encoding,
+                    tags: &[],
+                    xor_key: None,
+                    decoded_from: None,
+                    termination: candidate.termination,
+                    score: None,
+                    context_before: &[],
+                    context_after: &[],
+                    section: None,
+                });
+                num_found += 1;
+            }
+            self.candidates.pop_front();
+        }
+
+        // the windows before any string which can still be reported aren't needed
+        let needed_address = self.candidates.iter()
+            .map(|candidate| candidate.address)
+            .chain(self.scanners.iter().map(|scanner| scanner.next_string_min_address()))
+            .min()
+            .unwrap_or(self.end_address);
+        while self.windows.len() > 1
+            && self.windows_start_address + WINDOW_SIZE <= needed_address {
+            self.windows.pop_front();
+            self.windows_start_address += WINDOW_SIZE;
+        }
+        return num_found;
+    }
+}
+
+impl ByteScanner for Utf16Detector {
+    fn push(&mut self, byte: u8, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        let index = self.window_index(self.end_address);
+        while self.windows.len() <= index {
+            self.windows.push_back([0, 0]);
+        }
+        if byte == 0 {
+            self.windows[index][(self.end_address % 2) as usize] += 1;
+        }
+        self.end_address += 1;
+
+        for scanner in self.scanners.iter_mut() {
+            let mut collector = CandidateCollector { found: Vec::new() };
+            scanner.push(byte, filename, &mut collector);
+            self.candidates.extend(collector.found);
+        }
+
+        return self.resolve(filename, sink, false);
+    }
+
+    fn finish(&mut self, filename: &OsStr, sink: &mut dyn StringSink) -> u64 {
+        for scanner in self.scanners.iter_mut() {
+            let mut collector = CandidateCollector { found: Vec::new() };
+            scanner.finish(filename, &mut collector);
+            self.candidates.extend(collector.found);
+        }
+
+        return self.resolve(filename, sink, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::StringPrinter;
+
+    struct Collector {
+        found: Vec<(u64, String, &'static str)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            self.found.push((
+                found.address,
+                String::from_utf8(found.text.to_vec()).unwrap(),
+                found.encoding.name(),
+            ));
+        }
+    }
+
+    fn scan(data: &[u8], encodings: &[EncodingKind], options: &Options, sink: &mut dyn StringSink) {
+        let mut scanners: Vec<Box<dyn ByteScanner>> = encodings.iter()
+            .map(|encoding| new_scanner(*encoding, options, 0))
+            .collect();
+
+        for byte in data {
+            for scanner in scanners.iter_mut() {
+                scanner.push(*byte, OsStr::new("test"), sink);
+            }
+        }
+        for scanner in scanners.iter_mut() {
+            scanner.finish(OsStr::new("test"), sink);
+        }
+    }
+
+    #[test]
+    fn test_incremental_scanner_matches_default_output() {
+        let data = std::fs::read("test-resources/a.out").unwrap();
+        let expected = std::fs::read("test-resources/default-output.txt").unwrap();
+
+        let options = Options::default();
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+
+        scan(&data, &[EncodingKind::Bit7], &options, &mut printer);
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_incremental_scanner_several_encodings() {
+        let data = b"\x01abcd\x00\x00h\x00e\x00l\x00l\x00o\x00\x00\x00\x00w\x00o\x00r\x00d";
+
+        let mut collector = Collector { found: Vec::new() };
+        scan(
+            data,
+            &[EncodingKind::Bit7, EncodingKind::LittleEndian16, EncodingKind::BigEndian16],
+            &Options::default(),
+            &mut collector,
+        );
+
+        assert_eq!(
+            vec![
+                (1, "abcd".to_string(), "7bit"),
+                // a NUL before a 16le string makes it a valid 16be string as well
+                (6, "hello".to_string(), "16be"),
+                (7, "hello".to_string(), "16le"),
+                (19, "word".to_string(), "16be"),
+            ],
+            collector.found
+        );
+    }
+
+    #[test]
+    fn test_incremental_scanner_decode_wide() {
+        let mut data = vec![0u8, 0u8];
+        for unit in "ℓôřè₥ 𐍈".encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        // unpaired high surrogate ends the string
+        data.extend_from_slice(&[0x00, 0xd8, b'a', 0x00]);
+
+        let mut options = Options::default();
+        options.decode_wide = true;
+
+        let mut collector = Collector { found: Vec::new() };
+        scan(&data, &[EncodingKind::LittleEndian16], &options, &mut collector);
+
+        assert_eq!(vec![(2, "ℓôřè₥ 𐍈".to_string(), "16le")], collector.found);
+    }
+
+    #[test]
+    fn test_auto_endian_scanner_alignment() {
+        // "hello" in UTF-16LE at an even address is also "hello" in UTF-16BE at an odd one
+        let data = b"\x01\x01\x00\x00h\x00e\x00l\x00l\x00o\x00\x01\x01\x00w\x00o\x00r\x00d\x00";
+
+        let mut collector = Collector { found: Vec::new() };
+        scan(data, &[EncodingKind::Auto16], &Options::default(), &mut collector);
+
+        assert_eq!(
+            vec![
+                (4, "hello".to_string(), "16le"),
+                (16, "word".to_string(), "16be"),
+            ],
+            collector.found
+        );
+    }
+
+    #[test]
+    fn test_auto_endian_scanner_bom() {
+        // BOM wins over the alignment
+        let data = b"\x01\xfe\xff\x00h\x00e\x00l\x00l\x00o\x00\x00";
+
+        let mut collector = Collector { found: Vec::new() };
+        scan(data, &[EncodingKind::Auto16], &Options::default(), &mut collector);
+
+        assert_eq!(vec![(3, "hello".to_string(), "16be")], collector.found);
+    }
+
+    #[test]
+    fn test_auto_endian_scanner_decode_wide() {
+        let mut options = Options::default();
+        options.decode_wide = true;
+
+        // read as big-endian, the ASCII text is CJK text of the same length
+        let mut collector = Collector { found: Vec::new() };
+        scan(b"t\0a\0i\0l\0", &[EncodingKind::Auto16], &options, &mut collector);
+        assert_eq!(vec![(0, "tail".to_string(), "16le")], collector.found);
+
+        let mut collector = Collector { found: Vec::new() };
+        scan(b"\0t\0a\0i\0l", &[EncodingKind::Auto16], &options, &mut collector);
+        assert_eq!(vec![(0, "tail".to_string(), "16be")], collector.found);
+
+        // none of the characters has a zero high byte either way
+        for (text, encoding) in [("Привет мир", "16le"), ("Ελληνικά", "16be")] {
+            let data: Vec<u8> = text.encode_utf16()
+                .flat_map(|unit| match encoding {
+                    "16le" => unit.to_le_bytes(),
+                    _ => unit.to_be_bytes(),
+                })
+                .collect();
+            let mut collector = Collector { found: Vec::new() };
+            scan(&data, &[EncodingKind::Auto16], &options, &mut collector);
+            assert_eq!(vec![(0, text.to_string(), encoding)], collector.found);
+        }
+    }
+    #[test]
+    fn test_utf16_detector() {
+        // the 16be reading of the 16le string and the 16le reading of the 16be one are misaligned
+        let mut data = b"\x01\x01h\0e\0l\0l\0o\0\x01\x02".to_vec();
+        data.extend_from_slice(b"\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10");
+        data.extend_from_slice(b"\0w\0o\0r\0d\0\0\x01");
+
+        let mut collector = Collector { found: Vec::new() };
+        let mut detector = new_utf16_detector(&Options::default(), 0);
+        for byte in &data {
+            detector.push(*byte, OsStr::new("test"), &mut collector);
+        }
+        detector.finish(OsStr::new("test"), &mut collector);
+
+        assert_eq!(
+            vec![
+                (2, "hello".to_string(), "16le"),
+                (28, "word".to_string(), "16be"),
+            ],
+            collector.found
+        );
+    }
+}
@@ -0,0 +1,106 @@
+/*
+ English-likeness scoring of the strings (`--min-score`), to suppress the printable garbage
+ found in code and compressed data. The score combines the share of letters and spaces in the
+ string with the share of its letter pairs which are among the most common English bigrams.
+ */
+
+use std::ffi::OsStr;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, StringSink};
+
+/// The most common letter pairs of English text, they make up about half of all its pairs.
+const COMMON_BIGRAMS: &[&[u8; 2]] = &[
+    b"th", b"he", b"in", b"er", b"an", b"re", b"on", b"at", b"en", b"nd",
+    b"ti", b"es", b"or", b"te", b"of", b"ed", b"is", b"it", b"al", b"ar",
+    b"st", b"to", b"nt", b"ng", b"se", b"ha", b"as", b"ou", b"io", b"le",
+    b"ve", b"co", b"me", b"de", b"hi", b"ri", b"ro", b"ic", b"ne", b"ea",
+    b"ra", b"ce", b"li", b"ch", b"ll", b"be", b"ma", b"si", b"om", b"ur",
+];
+
+/// Share of the common bigrams above which a string scores as well as English text.
+const ENGLISH_BIGRAM_SHARE: f64 = 0.5;
+
+/**
+Returns how much the string looks like English text, from 0 to 1.
+ */
+pub fn english_score(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let num_letters = bytes.iter().filter(|byte| byte.is_ascii_alphabetic() || **byte == b' ').count();
+    let letter_share = num_letters as f64 / bytes.len() as f64;
+
+    let mut num_pairs = 0usize;
+    let mut num_common = 0usize;
+    for pair in bytes.windows(2) {
+        if !pair[0].is_ascii_alphabetic() || !pair[1].is_ascii_alphabetic() {
+            continue;
+        }
+        num_pairs += 1;
+        let pair = [pair[0].to_ascii_lowercase(), pair[1].to_ascii_lowercase()];
+        if COMMON_BIGRAMS.contains(&&pair) {
+            num_common += 1;
+        }
+    }
+    if num_pairs == 0 {
+        return 0.0;
+    }
+    let bigram_share = num_common as f64 / num_pairs as f64;
+
+    return letter_share * (bigram_share / ENGLISH_BIGRAM_SHARE).min(1.0);
+}
+
+/**
+Scores the strings and passes on only the ones scoring at least the minimum.
+ */
+pub struct ScoringSink<'a> {
+    min_score: f64,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> ScoringSink<'a> {
+    pub fn new(options: &Options, inner: &'a mut dyn StringSink) -> ScoringSink<'a> {
+        return ScoringSink {
+            min_score: options.min_score.unwrap_or(0.0),
+            inner,
+        };
+    }
+}
+
+impl StringSink for ScoringSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let score = english_score(found.bytes);
+        if score < self.min_score {
+            return;
+        }
+        let mut found = found.with_tags(found.tags);
+        found.score = Some(score);
+        self.inner.on_string(&found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_score() {
+        let english = english_score(b"Hello, this is the configuration of the printer");
+        assert!(english > 0.8, "{}", english);
+
+        for garbage in [&b"xQ7zP9kLw2vB"[..], b"H\x89\xe5AWAVAUATI", b"GLIBC_2.2.5", b"1234567890"] {
+            let score = english_score(garbage);
+            assert!(score < 0.5, "{} {}", String::from_utf8_lossy(garbage), score);
+        }
+        assert_eq!(0.0, english_score(b""));
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ Deduplication of the strings across runs, see `--seen-db PATH`: the strings printed by the
+ previous runs are kept in a Bloom filter saved to PATH, and only the ones not in it are printed,
+ so that the nightly scans of a corpus surface only the new strings. The filter takes a fixed
+ size whatever the number of strings, at the cost of a few false positives, new strings taken for
+ seen ones: about 1% with the default size once it holds 14 million strings.
+
+ The file is the magic number, the number of hashes per string and the base 2 logarithm of the
+ number of bits as 32-bit little-endian integers, then the bits in 64-bit little-endian words.
+ */
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use xxhash_rust::xxh3;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+use super::utils::read_u32_le;
+
+const MAGIC: &[u8] = b"STRSEEN1";
+const HEADER_SIZE: usize = MAGIC.len() + 8;
+/// 2^27 bits, a file of 16 MiB.
+const DEFAULT_BITS_LOG2: u32 = 27;
+const DEFAULT_NUM_HASHES: u32 = 7;
+/// The smallest filter is a single word.
+const MIN_BITS_LOG2: u32 = 6;
+const MAX_BITS_LOG2: u32 = 36;
+
+/**
+A Bloom filter of the strings seen so far.
+ */
+pub struct SeenSet {
+    bits: Vec<u64>,
+    bits_log2: u32,
+    num_hashes: u32,
+}
+
+impl SeenSet {
+    pub fn new() -> SeenSet {
+        return SeenSet::with_size(DEFAULT_BITS_LOG2, DEFAULT_NUM_HASHES);
+    }
+
+    fn with_size(bits_log2: u32, num_hashes: u32) -> SeenSet {
+        return SeenSet { bits: vec![0; 1 << (bits_log2 - 6)], bits_log2, num_hashes };
+    }
+
+    /**
+    Reads the filter from the file, or returns an empty one if there is no file yet.
+     */
+    pub fn load(path: &Path) -> Result<SeenSet, String> {
+        return match fs::read(path) {
+            Ok(data) => SeenSet::from_bytes(&data)
+                .ok_or_else(|| format!("{}: not a database of seen strings", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(SeenSet::new()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        };
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<SeenSet> {
+        if !data.starts_with(MAGIC) {
+            return None;
+        }
+        let num_hashes = read_u32_le(data, MAGIC.len())?;
+        let bits_log2 = read_u32_le(data, MAGIC.len() + 4)?;
+        if num_hashes == 0 || !(MIN_BITS_LOG2..=MAX_BITS_LOG2).contains(&bits_log2)
+            || data.len() != HEADER_SIZE + (1 << (bits_log2 - 3)) {
+            return None;
+        }
+        let bits = data[HEADER_SIZE..].chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        return Some(SeenSet { bits, bits_log2, num_hashes });
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.bits.len() * 8);
+        data.extend(MAGIC);
+        data.extend(self.num_hashes.to_le_bytes());
+        data.extend(self.bits_log2.to_le_bytes());
+        for word in &self.bits {
+            data.extend(word.to_le_bytes());
+        }
+        return data;
+    }
+
+    /**
+    Writes the filter to the file. It is written next to it first and renamed over it, so that
+    an interrupted run leaves the previous filter.
+     */
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut temporary = path.as_os_str().to_os_string();
+        temporary.push(".tmp");
+        return fs::write(&temporary, self.to_bytes())
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|err| format!("{}: {}", path.display(), err));
+    }
+
+    /**
+    Adds the string, returning false if it was probably added before.
+     */
+    pub fn insert(&mut self, text: &[u8]) -> bool {
+        // the bits are picked by double hashing, from the two halves of a 128-bit hash
+        let hash = xxh3::xxh3_128(text);
+        let first = hash as u64;
+        let step = (hash >> 64) as u64 | 1;
+        let mask = (1u64 << self.bits_log2) - 1;
+
+        let mut seen = true;
+        for index in 0..self.num_hashes as u64 {
+            let bit = first.wrapping_add(index.wrapping_mul(step)) & mask;
+            let word = &mut self.bits[(bit >> 6) as usize];
+            let flag = 1 << (bit & 63);
+            seen &= *word & flag != 0;
+            *word |= flag;
+        }
+        return !seen;
+    }
+}
+
+impl Default for SeenSet {
+    fn default() -> Self {
+        return SeenSet::new();
+    }
+}
+
+/**
+Passes to the inner sink only the strings which are not in the set, adding them to it.
+ */
+pub struct SeenFilter<'a> {
+    seen: &'a mut SeenSet,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> SeenFilter<'a> {
+    pub fn new(seen: &'a mut SeenSet, inner: &'a mut dyn StringSink) -> SeenFilter<'a> {
+        return SeenFilter { seen, inner };
+    }
+}
+
+impl StringSink for SeenFilter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.seen.insert(found.text) {
+            self.inner.on_string(found);
+        }
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seen_set() {
+        let mut seen = SeenSet::with_size(16, 4);
+        assert!(seen.insert(b"first"));
+        assert!(seen.insert(b"second"));
+        assert!(!seen.insert(b"first"));
+
+        let mut loaded = SeenSet::from_bytes(&seen.to_bytes()).unwrap();
+        assert!(!loaded.insert(b"second"));
+        assert!(loaded.insert(b"third"));
+
+        let mut truncated = seen.to_bytes();
+        truncated.pop();
+        assert!(SeenSet::from_bytes(&truncated).is_none());
+        assert!(SeenSet::from_bytes(b"STRSEEN1").is_none());
+    }
+}
@@ -0,0 +1,342 @@
+/*
+ HTTP service mode: `strings serve --http ADDR`.
+
+ `POST /strings` scans the request body (the contents of a file, sent as is or as a chunked
+ stream) and responds with `{"file": NAME, "count": N, "strings": [...]}`, where every string is
+ a `StringRecord`. The options are passed as query parameters named after the command line
+ options: `name`, `bytes`, `encoding`, `data`, `segments`, `include-all-whitespace` and
+ `decode-wide`.
+ `GET /strings?path=PATH` scans a file of the server instead, if it is started with `--root`:
+ PATH is relative to the root and can't lead out of it.
+ `GET /health` can be used as a liveness probe.
+ With `--grpc ADDR` the scan is served over gRPC too, see `grpc.rs`.
+ */
+
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use super::json::StringRecord;
+use super::strings::{self, EncodingKind, FoundString, Options, StringSink};
+
+/// Name of the scanned data in the report, if the request doesn't give one.
+const DEFAULT_NAME: &str = "<upload>";
+
+#[derive(Clone)]
+pub struct ServeOptions {
+    pub address: String,
+    /// Requests with a larger body, or for a larger file, are rejected.
+    pub max_body_bytes: u64,
+    /// Number of requests handled at the same time, the rest wait in the queue.
+    pub max_concurrent: usize,
+    /// Directory of the files which can be scanned by path, none if `None`.
+    pub root: Option<PathBuf>,
+    /// Address of the gRPC service, if it is served.
+    pub grpc_address: Option<String>,
+}
+
+/**
+The parameters of a request to scan.
+ */
+struct Query {
+    name: Option<String>,
+    /// File to scan, relative to the root.
+    path: Option<String>,
+    options: Options,
+}
+
+/**
+Runs the server until the process is stopped. Fails only if the server couldn't start.
+ */
+pub fn serve(options: &ServeOptions) -> Result<(), String> {
+    let server = Arc::new(Server::http(&options.address).map_err(
+        |err| format!("couldn't listen on {}: {}", options.address, err)
+    )?);
+    eprintln!("Listening on http://{}", server.server_addr());
+
+    let mut workers = Vec::new();
+    if let Some(address) = &options.grpc_address {
+        workers.push(serve_grpc(address, options)?);
+    }
+    for _ in 0..options.max_concurrent.max(1) {
+        let server = server.clone();
+        let options = options.clone();
+        workers.push(thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &options);
+            }
+        }));
+    }
+
+    for worker in workers {
+        worker.join().expect("Server thread panicked");
+    }
+
+    return Ok(());
+}
+
+#[cfg(feature = "grpc")]
+fn serve_grpc(address: &str, options: &ServeOptions) -> Result<thread::JoinHandle<()>, String> {
+    use super::grpc;
+
+    let listener = grpc::bind(address)?;
+    eprintln!("Listening on grpc://{}", listener.local_addr().map_err(|err| err.to_string())?);
+    let (max_body_bytes, max_concurrent) = (options.max_body_bytes, options.max_concurrent);
+    return Ok(thread::spawn(move || {
+        if let Err(err) = grpc::run(listener, max_body_bytes, max_concurrent) {
+            eprintln!("{}", err);
+        }
+    }));
+}
+
+#[cfg(not(feature = "grpc"))]
+fn serve_grpc(_address: &str, _options: &ServeOptions) -> Result<thread::JoinHandle<()>, String> {
+    return Err("strings was built without the grpc feature".to_string());
+}
+
+fn handle_request(mut request: Request, serve_options: &ServeOptions) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let max_body_bytes = serve_options.max_body_bytes;
+
+    let (status, body) = match (request.method(), path) {
+        (Method::Get, "/health") => (200, json!({"status": "ok"})),
+        (Method::Post, "/strings") => {
+            match parse_query(query) {
+                Ok(Query { path: Some(_), .. }) => {
+                    (400, json!({"error": "path is for GET, POST sends the data"}))
+                }
+                Ok(Query { name, options, .. }) => {
+                    let name = name.unwrap_or_else(|| DEFAULT_NAME.to_string());
+                    match read_body(&mut request, max_body_bytes) {
+                        Ok(data) => (200, report(&name, &data, &options)),
+                        Err((status, message)) => (status, json!({"error": message}))
+                    }
+                }
+                Err(message) => (400, json!({"error": message}))
+            }
+        }
+        (Method::Get, "/strings") => {
+            match parse_query(query) {
+                Ok(Query { name, path: Some(path), options }) => {
+                    match read_file(serve_options.root.as_deref(), &path, max_body_bytes) {
+                        Ok(data) => (200, report(&name.unwrap_or(path), &data, &options)),
+                        Err((status, message)) => (status, json!({"error": message}))
+                    }
+                }
+                Ok(_) => (400, json!({"error": "missing path"})),
+                Err(message) => (400, json!({"error": message}))
+            }
+        }
+        (_, "/health") | (_, "/strings") => (405, json!({"error": "method not allowed"})),
+        _ => (404, json!({"error": "not found"}))
+    };
+
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    if let Err(err) = request.respond(response) {
+        eprintln!("Warning: couldn't send the response: {}", err);
+    }
+}
+
+fn read_body(request: &mut Request, max_body_bytes: u64) -> Result<Vec<u8>, (u16, String)> {
+    let too_large = (413, format!("body is larger than {} bytes", max_body_bytes));
+
+    if let Some(length) = request.body_length() {
+        if length as u64 > max_body_bytes {
+            return Err(too_large);
+        }
+    }
+
+    // the length is unknown for chunked bodies, so read one byte more than allowed to notice it
+    let mut data = Vec::new();
+    request.as_reader().take(max_body_bytes + 1).read_to_end(&mut data).map_err(
+        |err| (400, format!("couldn't read the body: {}", err))
+    )?;
+    if data.len() as u64 > max_body_bytes {
+        return Err(too_large);
+    }
+
+    return Ok(data);
+}
+
+/**
+Reads a file under the root. The path is resolved with the links followed, so that neither
+`..` nor a link can lead out of the root.
+ */
+fn read_file(root: Option<&Path>, path: &str, max_bytes: u64) -> Result<Vec<u8>, (u16, String)> {
+    let Some(root) = root else {
+        return Err((403, "scanning by path is disabled, see --root".to_string()));
+    };
+    let not_found = || (404, format!("no such file: {}", path));
+    let root = root.canonicalize().map_err(|_| not_found())?;
+    let file_path = root.join(path.trim_start_matches('/')).canonicalize()
+        .map_err(|_| not_found())?;
+    if !file_path.starts_with(&root) {
+        return Err((403, format!("outside of the root: {}", path)));
+    }
+    if !file_path.is_file() {
+        return Err(not_found());
+    }
+
+    let mut data = Vec::new();
+    std::fs::File::open(&file_path)
+        .and_then(|file| file.take(max_bytes + 1).read_to_end(&mut data))
+        .map_err(|err| (500, format!("couldn't read {}: {}", path, err)))?;
+    if data.len() as u64 > max_bytes {
+        return Err((413, format!("file is larger than {} bytes", max_bytes)));
+    }
+    return Ok(data);
+}
+
+/**
+Returns the parameters given in the query string.
+ */
+fn parse_query(query: &str) -> Result<Query, String> {
+    let mut name = None;
+    let mut path = None;
+    let mut options = Options::default();
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)?;
+
+        match key {
+            "name" => { name = Some(value); }
+            "path" => { path = Some(value); }
+            "bytes" => {
+                options.min_length = match value.parse() {
+                    Ok(min_length) if min_length > 0 => min_length,
+                    _ => return Err(format!("invalid minimum string length: {}", value))
+                };
+            }
+            "encoding" => {
+                options.encoding = EncodingKind::parse(&value).ok_or_else(
+                    || format!("invalid encoding: {}", value)
+                )?;
+            }
+            "data" => { options.datasection_only = parse_flag(key, &value)?; }
+            "segments" => { options.segments_only = parse_flag(key, &value)?; }
+            "include-all-whitespace" => { options.include_all_whitespace = parse_flag(key, &value)?; }
+            "decode-wide" => { options.decode_wide = parse_flag(key, &value)?; }
+            wrong => {
+                return Err(format!("unknown parameter: {}", wrong));
+            }
+        }
+    }
+
+    return Ok(Query { name, path, options });
+}
+
+fn parse_flag(key: &str, value: &str) -> Result<bool, String> {
+    return match value {
+        "" | "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        wrong => Err(format!("invalid value of {}: {}", key, wrong))
+    };
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("invalid escape in: {}", value))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    return String::from_utf8(decoded).map_err(|_| format!("invalid UTF-8 in: {}", value));
+}
+
+struct RecordCollector {
+    records: Vec<Value>,
+}
+
+impl StringSink for RecordCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        self.records.push(serde_json::to_value(StringRecord::new(found)).unwrap());
+    }
+}
+
+fn report(name: &str, data: &[u8], options: &Options) -> Value {
+    let mut collector = RecordCollector { records: Vec::new() };
+    let count = strings::print_strings_for_bytes(OsStr::new(name), data, options, &mut collector);
+
+    return json!({
+        "file": name,
+        "count": count,
+        "strings": collector.records,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("name=my%20lib.so&bytes=6&encoding=l&data").unwrap();
+
+        assert_eq!(Some("my lib.so".to_string()), query.name);
+        assert_eq!(None, query.path);
+        assert_eq!(6, query.options.min_length);
+        assert!(query.options.encoding == EncodingKind::LittleEndian16);
+        assert!(query.options.datasection_only);
+        assert_eq!(Some("lib/a.so".to_string()), parse_query("path=lib%2Fa.so").unwrap().path);
+
+        assert!(parse_query("bytes=0").is_err());
+        assert!(parse_query("encoding=x").is_err());
+        assert!(parse_query("radix=x").is_err());
+    }
+
+    #[test]
+    fn test_read_file() {
+        let root = Some(Path::new("test-resources"));
+        let size = std::fs::metadata("test-resources/utf16.bin").unwrap().len();
+        assert_eq!(size as usize, read_file(root, "utf16.bin", size).unwrap().len());
+        assert_eq!(size as usize, read_file(root, "/utf16.bin", size).unwrap().len());
+        assert_eq!(413, read_file(root, "utf16.bin", size - 1).unwrap_err().0);
+        assert_eq!(403, read_file(root, "../Cargo.toml", 1 << 20).unwrap_err().0);
+        assert_eq!(404, read_file(root, "missing.bin", 1 << 20).unwrap_err().0);
+        assert_eq!(404, read_file(root, ".", 1 << 20).unwrap_err().0);
+        assert_eq!(403, read_file(None, "utf16.bin", size).unwrap_err().0);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report("test", b"\x01hello\x00ab\x00world!\x02", &Options::default());
+
+        assert_eq!(
+            json!({
+                "file": "test",
+                "count": 2,
+                "strings": [
+                    {"file": "test", "offset": 1, "length": 5, "encoding": "7bit", "termination": "nul", "string": "hello"},
+                    {"file": "test", "offset": 10, "length": 6, "encoding": "7bit", "termination": "non-printable", "string": "world!"},
+                ],
+            }),
+            report
+        );
+    }
+}
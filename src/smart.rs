@@ -0,0 +1,85 @@
+/*
+ Heuristic choice of the encoding (`--smart-encoding`): the data is split into regions, and
+ every region is scanned with the encoding most of its text seems to be in, ASCII, UTF-16LE or
+ UTF-16BE. The wide strings are assumed to be aligned to 2 bytes, as compilers lay them out.
+ */
+
+use super::strings::EncodingKind;
+
+/// Size of the regions the encoding is chosen for.
+pub const REGION_SIZE: usize = 64 * 1024;
+
+/// Shortest run of characters counted as text.
+const MIN_RUN_LENGTH: usize = 4;
+
+fn is_text(byte: u8) -> bool {
+    return matches!(byte, b'\t' | b'\n' | b'\r' | 0x20..=0x7e);
+}
+
+/**
+Returns the number of characters in the runs of at least `MIN_RUN_LENGTH` units matching
+`is_unit`, the units are `unit_size` bytes long and aligned to it.
+ */
+fn count_text(region: &[u8], unit_size: usize, is_unit: impl Fn(&[u8]) -> bool) -> usize {
+    let mut num_chars = 0usize;
+    let mut run_length = 0usize;
+    for unit in region.chunks_exact(unit_size) {
+        if is_unit(unit) {
+            run_length += 1;
+            continue;
+        }
+        if run_length >= MIN_RUN_LENGTH {
+            num_chars += run_length;
+        }
+        run_length = 0;
+    }
+    if run_length >= MIN_RUN_LENGTH {
+        num_chars += run_length;
+    }
+    return num_chars;
+}
+
+/**
+Returns the encoding the most characters of text in the region are in, ASCII if there is no
+text at all.
+ */
+pub fn guess_encoding(region: &[u8]) -> EncodingKind {
+    let ascii = count_text(region, 1, |unit| is_text(unit[0]));
+    let little_endian = count_text(region, 2, |unit| is_text(unit[0]) && unit[1] == 0);
+    let big_endian = count_text(region, 2, |unit| unit[0] == 0 && is_text(unit[1]));
+
+    if little_endian > ascii && little_endian >= big_endian {
+        return EncodingKind::LittleEndian16;
+    }
+    if big_endian > ascii {
+        return EncodingKind::BigEndian16;
+    }
+    return EncodingKind::Bit7;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        return text.encode_utf16()
+            .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+            .collect();
+    }
+
+    #[test]
+    fn test_guess_encoding() {
+        let mut region = vec![0u8; 16];
+        region.extend(b"some ASCII text\0");
+        region.extend(utf16("wide text", false));
+        region.extend(utf16("and some more wide text", false));
+        assert!(guess_encoding(&region) == EncodingKind::LittleEndian16);
+
+        let mut region = utf16("big endian wide text", true);
+        region.extend(b"\x01\x02ASCII\0");
+        assert!(guess_encoding(&region) == EncodingKind::BigEndian16);
+
+        assert!(guess_encoding(b"\x7fELF\x02\x01\x01\0\0\0 just ASCII") == EncodingKind::Bit7);
+        assert!(guess_encoding(&[0u8; 32]) == EncodingKind::Bit7);
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ Sorting of the output, see `--sort`: the strings of every input, or of all the inputs with
+ `--sort-scope=global`, are held until the input ends and printed in the order of the key. It
+ works with any separator and output format, unlike piping the output through `sort`.
+
+ Sorting by count prints every distinct string once, where it is first found, tagged with the
+ number of times it is found, e.g. `count:12`, the most frequent first.
+ */
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use super::decode::DecodeKind;
+use super::packages::PackageInfo;
+use super::strings::{EncodingKind, FoundString, StringSink, Termination};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SortKey {
+    /// The text, byte by byte
+    String,
+    /// The number of characters, the shortest first
+    Length,
+    /// The number of times the text is found, the most frequent first
+    Count,
+    /// The offset in the input
+    Offset,
+}
+
+impl SortKey {
+    pub fn parse(name: &str) -> Option<SortKey> {
+        return match name {
+            "string" => Some(SortKey::String),
+            "length" => Some(SortKey::Length),
+            "count" => Some(SortKey::Count),
+            "offset" => Some(SortKey::Offset),
+            _ => None,
+        };
+    }
+}
+
+/**
+A found string held until it is sorted, with copies of the borrowed contents.
+ */
+struct HeldString {
+    filename: OsString,
+    address: u64,
+    end_address: u64,
+    bytes: Vec<u8>,
+    text: Vec<u8>,
+    encoding: EncodingKind,
+    tags: Vec<String>,
+    xor_key: Option<u8>,
+    decoded_from: Option<DecodeKind>,
+    termination: Termination,
+    score: Option<f64>,
+    context_before: Vec<u8>,
+    context_after: Vec<u8>,
+    section: Option<String>,
+    /// Number of times the text is found, with `SortKey::Count`.
+    count: u64,
+}
+
+impl HeldString {
+    fn new(found: &FoundString) -> HeldString {
+        return HeldString {
+            filename: found.filename.to_os_string(),
+            address: found.address,
+            end_address: found.end_address,
+            bytes: found.bytes.to_vec(),
+            text: found.text.to_vec(),
+            encoding: found.encoding,
+            tags: found.tags.to_vec(),
+            xor_key: found.xor_key,
+            decoded_from: found.decoded_from,
+            termination: found.termination,
+            score: found.score,
+            context_before: found.context_before.to_vec(),
+            context_after: found.context_after.to_vec(),
+            section: found.section.map(str::to_string),
+            count: 1,
+        };
+    }
+
+    fn as_found(&self) -> FoundString<'_> {
+        return FoundString {
+            filename: &self.filename,
+            address: self.address,
+            end_address: self.end_address,
+            bytes: &self.bytes,
+            text: &self.text,
+            encoding: self.encoding,
+            tags: &self.tags,
+            xor_key: self.xor_key,
+            decoded_from: self.decoded_from,
+            termination: self.termination,
+            score: self.score,
+            context_before: &self.context_before,
+            context_after: &self.context_after,
+            section: self.section.as_deref(),
+        };
+    }
+
+    fn length(&self) -> usize {
+        return String::from_utf8_lossy(&self.text).chars().count();
+    }
+}
+
+/**
+Holds the strings and passes them to the inner sink sorted, at the start of the next input or,
+with `global`, once `finish` is called.
+ */
+pub struct SortingSink<'a> {
+    key: SortKey,
+    reverse: bool,
+    global: bool,
+    /// The strings in the order they are found, the distinct ones only with `SortKey::Count`.
+    held: Vec<HeldString>,
+    positions: HashMap<Vec<u8>, usize>,
+    /// With `global`, the last input and the largest size of the inputs, passed on before the
+    /// strings so that the addresses are as wide as the largest needs.
+    input: Option<(OsString, Option<u64>)>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> SortingSink<'a> {
+    pub fn new(
+        key: SortKey,
+        reverse: bool,
+        global: bool,
+        inner: &'a mut dyn StringSink,
+    ) -> SortingSink<'a> {
+        return SortingSink {
+            key, reverse, global, held: Vec::new(), positions: HashMap::new(), input: None, inner,
+        };
+    }
+
+    fn compare(&self, a: &HeldString, b: &HeldString) -> Ordering {
+        return match self.key {
+            SortKey::String => a.text.cmp(&b.text),
+            SortKey::Length => a.length().cmp(&b.length()),
+            SortKey::Count => b.count.cmp(&a.count),
+            SortKey::Offset => a.address.cmp(&b.address),
+        };
+    }
+
+    /**
+    Passes the held strings to the inner sink in order. The sort is stable, the strings of the
+    same key stay in the order they are found.
+     */
+    pub fn finish(&mut self) {
+        if let Some((filename, size)) = self.input.take() {
+            self.inner.on_input(&filename, size);
+        }
+        let mut held = std::mem::take(&mut self.held);
+        self.positions.clear();
+        held.sort_by(|a, b| {
+            let ordering = self.compare(a, b);
+            return if self.reverse { ordering.reverse() } else { ordering };
+        });
+        for string in &mut held {
+            if self.key == SortKey::Count {
+                string.tags.push(format!("count:{}", string.count));
+            }
+            self.inner.on_string(&string.as_found());
+        }
+    }
+}
+
+impl StringSink for SortingSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.key == SortKey::Count {
+            if let Some(position) = self.positions.get(found.text) {
+                self.held[*position].count += 1;
+                return;
+            }
+            self.positions.insert(found.text.to_vec(), self.held.len());
+        }
+        self.held.push(HeldString::new(found));
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        if !self.global {
+            self.finish();
+        }
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        if !self.global {
+            self.finish();
+            self.inner.on_input(filename, size);
+            return;
+        }
+        let largest = match &self.input {
+            Some((_, Some(largest))) => size.map(|size| size.max(*largest)),
+            _ => size,
+        };
+        self.input = Some((filename.to_os_string(), largest));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collector {
+        found: Vec<String>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            let text = String::from_utf8_lossy(found.text);
+            self.found.push(match found.tags.is_empty() {
+                true => text.into_owned(),
+                false => format!("{{{}}} {}", found.tags.join(","), text),
+            });
+        }
+    }
+
+    fn found(text: &str, address: u64) -> FoundString<'_> {
+        return FoundString {
+            filename: OsStr::new("input"),
+            address,
+            end_address: address + text.len() as u64,
+            bytes: text.as_bytes(),
+            text: text.as_bytes(),
+            encoding: EncodingKind::Bit7,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        };
+    }
+
+    fn sort(key: SortKey, reverse: bool, global: bool) -> Vec<String> {
+        let mut collector = Collector { found: Vec::new() };
+        let mut sorting = SortingSink::new(key, reverse, global, &mut collector);
+        sorting.on_input(OsStr::new("first"), Some(100));
+        for (text, address) in [("zeta", 40), ("alpha", 10), ("beta", 30), ("alpha", 20)] {
+            sorting.on_string(&found(text, address));
+        }
+        sorting.on_input(OsStr::new("second"), Some(100));
+        sorting.on_string(&found("alpha", 0));
+        sorting.on_string(&found("gamma ray", 5));
+        sorting.finish();
+        return collector.found;
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(
+            vec!["alpha", "alpha", "beta", "zeta", "alpha", "gamma ray"],
+            sort(SortKey::String, false, false)
+        );
+        assert_eq!(
+            vec!["alpha", "alpha", "alpha", "beta", "gamma ray", "zeta"],
+            sort(SortKey::String, false, true)
+        );
+        assert_eq!(
+            vec!["gamma ray", "alpha", "alpha", "alpha", "zeta", "beta"],
+            sort(SortKey::Length, true, true)
+        );
+        assert_eq!(
+            vec!["alpha", "alpha", "beta", "zeta", "alpha", "gamma ray"],
+            sort(SortKey::Offset, false, false)
+        );
+        assert_eq!(
+            vec!["{count:3} alpha", "{count:1} zeta", "{count:1} beta", "{count:1} gamma ray"],
+            sort(SortKey::Count, false, true)
+        );
+    }
+}
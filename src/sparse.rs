@@ -0,0 +1,132 @@
+/*
+ Sparse files and block devices, the disk images. The holes of a sparse file are read as zeros,
+ which can't be a part of a string, so only its data regions are read: the file system tells
+ where they are with SEEK_DATA and SEEK_HOLE. Block devices have no size in their metadata, it
+ is taken by seeking to their end. Both are read in large chunks.
+ */
+
+use std::fs::{File, Metadata};
+use std::io::{self, Seek, SeekFrom};
+
+/// Size of the reads of the disk images.
+pub(crate) const LARGE_BUFFER_SIZE: usize = 1 << 20;
+
+/**
+Returns if the file is a regular one with fewer blocks allocated than its size needs.
+ */
+pub(crate) fn is_sparse(metadata: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        return metadata.is_file() && metadata.blocks().saturating_mul(512) < metadata.len();
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        return false;
+    }
+}
+
+/**
+Returns if the file is a block device, e.g. a disk or a partition.
+ */
+pub(crate) fn is_block_device(metadata: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        return metadata.file_type().is_block_device();
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        return false;
+    }
+}
+
+/**
+Returns the size of a block device, leaving it positioned at its start.
+ */
+pub(crate) fn device_size(mut file: &File) -> io::Result<u64> {
+    let size = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    return Ok(size);
+}
+
+/**
+Returns the ranges of the file which hold data, in order, or `None` if the file system can't
+tell them apart from the holes.
+ */
+#[cfg(target_os = "linux")]
+pub(crate) fn data_regions(file: &File, size: u64) -> Option<Vec<(u64, u64)>> {
+    use std::ffi::c_int;
+    use std::os::unix::io::AsRawFd;
+
+    extern "C" {
+        fn lseek64(fd: c_int, offset: i64, whence: c_int) -> i64;
+    }
+    const SEEK_DATA: c_int = 3;
+    const SEEK_HOLE: c_int = 4;
+    // no data past the offset
+    const ENXIO: i32 = 6;
+
+    let fd = file.as_raw_fd();
+    let mut regions = Vec::new();
+    let mut position = 0u64;
+    while position < size {
+        let start = unsafe { lseek64(fd, position as i64, SEEK_DATA) };
+        if start < 0 {
+            if io::Error::last_os_error().raw_os_error() == Some(ENXIO) {
+                break;
+            }
+            return None;
+        }
+        let end = unsafe { lseek64(fd, start, SEEK_HOLE) };
+        if end < 0 {
+            return None;
+        }
+        // the file may be cut while it is scanned
+        let end = (end as u64).min(size);
+        if end > start as u64 {
+            regions.push((start as u64, end));
+        }
+        position = end.max(start as u64 + 1);
+    }
+    return Some(regions);
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn data_regions(_file: &File, _size: u64) -> Option<Vec<(u64, u64)>> {
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_data_regions() {
+        let path = std::env::temp_dir().join(format!("strings-sparse-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"head").unwrap();
+        file.seek(SeekFrom::Start(8 << 20)).unwrap();
+        file.write_all(b"tail").unwrap();
+        drop(file);
+
+        let file = File::open(&path).unwrap();
+        let metadata = file.metadata().unwrap();
+        let size = metadata.len();
+        let regions = data_regions(&file, size).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // the file systems without holes have a single region
+        assert_eq!(0, regions[0].0);
+        assert_eq!(size, regions.last().unwrap().1);
+        if is_sparse(&metadata) {
+            assert!(regions.len() > 1);
+            assert!(regions.iter().map(|(start, end)| end - start).sum::<u64>() < size);
+        }
+        assert!(!is_block_device(&metadata));
+    }
+}
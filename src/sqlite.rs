@@ -0,0 +1,410 @@
+/*
+ Output to an SQLite database, see `-O sqlite:PATH`. The database file is written directly in
+ the SQLite file format, without the library: a single table, `strings`, with a row per string.
+ The table is a B-tree built bottom-up as the rows come in: the leaf pages are filled in rowid
+ order, then the interior pages over them, and its root takes page 2, right after the schema.
+ The pages are kept in memory and written out at the end.
+ */
+
+use std::io::Write;
+use super::diagnostics::check_write;
+use super::hash::HashKind;
+use super::json::StringRecord;
+use super::strings::{FoundString, Options, StringSink};
+
+const PAGE_SIZE: usize = 4096;
+const FILE_HEADER_SIZE: usize = 100;
+const LEAF_TABLE_PAGE: u8 = 0x0d;
+const INTERIOR_TABLE_PAGE: u8 = 0x05;
+const LEAF_HEADER_SIZE: usize = 8;
+const INTERIOR_HEADER_SIZE: usize = 12;
+/// The largest payload kept in a leaf cell whole, a larger one spills to overflow pages.
+const MAX_LOCAL_PAYLOAD: usize = PAGE_SIZE - 35;
+const MIN_LOCAL_PAYLOAD: usize = (PAGE_SIZE - 12) * 32 / 255 - 23;
+/// Children of an interior page: the cells take up to 15 bytes with their pointers, and the
+/// last child is in the header.
+const MAX_CHILDREN: usize = (PAGE_SIZE - INTERIOR_HEADER_SIZE) / 15 + 1;
+const ROOT_PAGE: u32 = 2;
+const TABLE_SQL: &str = "CREATE TABLE strings(file TEXT, offset INTEGER, length INTEGER, \
+    encoding TEXT, termination TEXT, section TEXT, string TEXT, tags TEXT, xor_key INTEGER, \
+    decoded_from TEXT, score REAL, hash TEXT)";
+
+/**
+A value of a column.
+ */
+enum Value<'a> {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(&'a str),
+}
+
+/**
+A table of a database being written, along with the pages of the whole file.
+ */
+struct Table {
+    /// The pages by number from 1; the first two are filled in at the end, with the schema and
+    /// the root of the table.
+    pages: Vec<Vec<u8>>,
+    /// Cells of the leaf page being filled.
+    cells: Vec<Vec<u8>>,
+    cells_size: usize,
+    /// The full leaf pages, with the largest rowid in each.
+    leaves: Vec<(u32, i64)>,
+    last_rowid: i64,
+}
+
+impl Table {
+    fn new() -> Table {
+        return Table {
+            pages: vec![Vec::new(), Vec::new()],
+            cells: Vec::new(),
+            cells_size: 0,
+            leaves: Vec::new(),
+            last_rowid: 0,
+        };
+    }
+
+    fn insert(&mut self, values: &[Value]) {
+        self.last_rowid += 1;
+        let payload = encode_record(values);
+
+        let local_size = local_payload_size(payload.len());
+        let mut cell = Vec::with_capacity(local_size + 22);
+        put_varint(&mut cell, payload.len() as u64);
+        put_varint(&mut cell, self.last_rowid as u64);
+        cell.extend(&payload[..local_size]);
+        if local_size < payload.len() {
+            let first_overflow = self.pages.len() as u32 + 1;
+            cell.extend(first_overflow.to_be_bytes());
+            self.add_overflow_pages(&payload[local_size..]);
+        }
+
+        if LEAF_HEADER_SIZE + self.cells_size + cell.len() + 2 > PAGE_SIZE {
+            self.flush_leaf(self.last_rowid - 1);
+        }
+        self.cells_size += cell.len() + 2;
+        self.cells.push(cell);
+    }
+
+    fn add_overflow_pages(&mut self, data: &[u8]) {
+        let mut chunks = data.chunks(PAGE_SIZE - 4).peekable();
+        while let Some(chunk) = chunks.next() {
+            let next = if chunks.peek().is_some() { self.pages.len() as u32 + 2 } else { 0 };
+            let mut page = Vec::with_capacity(PAGE_SIZE);
+            page.extend(next.to_be_bytes());
+            page.extend(chunk);
+            page.resize(PAGE_SIZE, 0);
+            self.pages.push(page);
+        }
+    }
+
+    fn flush_leaf(&mut self, max_rowid: i64) {
+        let page = build_page(LEAF_TABLE_PAGE, 0, &self.cells, None);
+        self.pages.push(page);
+        self.leaves.push((self.pages.len() as u32, max_rowid));
+        self.cells.clear();
+        self.cells_size = 0;
+    }
+
+    /**
+    Finishes the B-tree of the table and returns the whole database file.
+     */
+    fn into_database(mut self) -> Vec<u8> {
+        if self.leaves.is_empty() {
+            self.pages[ROOT_PAGE as usize - 1] = build_page(LEAF_TABLE_PAGE, 0, &self.cells, None);
+        } else {
+            if !self.cells.is_empty() {
+                self.flush_leaf(self.last_rowid);
+            }
+            self.build_interior_pages();
+        }
+
+        let mut schema_cell = Vec::new();
+        let schema = encode_record(&[
+            Value::Text("table"),
+            Value::Text("strings"),
+            Value::Text("strings"),
+            Value::Integer(ROOT_PAGE as i64),
+            Value::Text(TABLE_SQL),
+        ]);
+        put_varint(&mut schema_cell, schema.len() as u64);
+        put_varint(&mut schema_cell, 1);
+        schema_cell.extend(schema);
+        let mut first_page = build_page(LEAF_TABLE_PAGE, FILE_HEADER_SIZE, &[schema_cell], None);
+        first_page[..FILE_HEADER_SIZE].copy_from_slice(&file_header(self.pages.len() as u32));
+        self.pages[0] = first_page;
+
+        return self.pages.concat();
+    }
+
+    /**
+    Builds the levels of interior pages over the leaves, up to the root.
+     */
+    fn build_interior_pages(&mut self) {
+        let mut level = std::mem::take(&mut self.leaves);
+        loop {
+            let groups: Vec<&[(u32, i64)]> = level.chunks(MAX_CHILDREN).collect();
+            if groups.len() == 1 {
+                self.pages[ROOT_PAGE as usize - 1] = build_interior_page(groups[0]);
+                return;
+            }
+            let mut next_level = Vec::with_capacity(groups.len());
+            for group in groups {
+                self.pages.push(build_interior_page(group));
+                next_level.push((self.pages.len() as u32, group[group.len() - 1].1));
+            }
+            level = next_level;
+        }
+    }
+}
+
+fn local_payload_size(payload_size: usize) -> usize {
+    if payload_size <= MAX_LOCAL_PAYLOAD {
+        return payload_size;
+    }
+    let size = MIN_LOCAL_PAYLOAD + (payload_size - MIN_LOCAL_PAYLOAD) % (PAGE_SIZE - 4);
+    return if size <= MAX_LOCAL_PAYLOAD { size } else { MIN_LOCAL_PAYLOAD };
+}
+
+/**
+Builds an interior page over the children, given with their page and their largest rowid.
+ */
+fn build_interior_page(children: &[(u32, i64)]) -> Vec<u8> {
+    let (last, others) = children.split_last().unwrap();
+    let cells: Vec<Vec<u8>> = others.iter()
+        .map(|(page, rowid)| {
+            let mut cell = page.to_be_bytes().to_vec();
+            put_varint(&mut cell, *rowid as u64);
+            return cell;
+        })
+        .collect();
+    return build_page(INTERIOR_TABLE_PAGE, 0, &cells, Some(last.0));
+}
+
+/**
+Lays out a B-tree page: the header at `start` (past the file header on page 1), the pointers to
+the cells after it and the cells at the end of the page.
+ */
+fn build_page(kind: u8, start: usize, cells: &[Vec<u8>], right_most: Option<u32>) -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    let header_size = if right_most.is_some() { INTERIOR_HEADER_SIZE } else { LEAF_HEADER_SIZE };
+
+    let mut content_start = PAGE_SIZE;
+    for (index, cell) in cells.iter().enumerate() {
+        content_start -= cell.len();
+        page[content_start..content_start + cell.len()].copy_from_slice(cell);
+        let pointer = start + header_size + index * 2;
+        page[pointer..pointer + 2].copy_from_slice(&(content_start as u16).to_be_bytes());
+    }
+
+    page[start] = kind;
+    page[start + 3..start + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+    page[start + 5..start + 7].copy_from_slice(&(content_start as u16).to_be_bytes());
+    if let Some(right_most) = right_most {
+        page[start + 8..start + 12].copy_from_slice(&right_most.to_be_bytes());
+    }
+    return page;
+}
+
+fn file_header(num_pages: u32) -> [u8; FILE_HEADER_SIZE] {
+    let mut header = [0u8; FILE_HEADER_SIZE];
+    header[..16].copy_from_slice(b"SQLite format 3\0");
+    header[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+    // the legacy journal for writing and reading
+    header[18] = 1;
+    header[19] = 1;
+    // the fractions of the payload kept in the cells, which are fixed
+    header[21] = 64;
+    header[22] = 32;
+    header[23] = 32;
+    // the change counter, which the size is valid for
+    header[24..28].copy_from_slice(&1u32.to_be_bytes());
+    header[28..32].copy_from_slice(&num_pages.to_be_bytes());
+    // the schema cookie and the schema format
+    header[40..44].copy_from_slice(&1u32.to_be_bytes());
+    header[44..48].copy_from_slice(&4u32.to_be_bytes());
+    // UTF-8
+    header[56..60].copy_from_slice(&1u32.to_be_bytes());
+    header[92..96].copy_from_slice(&1u32.to_be_bytes());
+    header[96..100].copy_from_slice(&3_040_000u32.to_be_bytes());
+    return header;
+}
+
+/**
+Appends a variable-length integer: 7 bits per byte, most significant first, the high bit set
+on all but the last byte. The ninth byte, if there is one, holds 8 bits.
+ */
+fn put_varint(output: &mut Vec<u8>, value: u64) {
+    if value > 0x00ff_ffff_ffff_ffff {
+        for index in (0..8).rev() {
+            output.push(((value >> 8 >> (7 * index)) & 0x7f) as u8 | 0x80);
+        }
+        output.push(value as u8);
+        return;
+    }
+    let num_bytes = (1..=8).find(|num_bytes| value >> (7 * num_bytes) == 0).unwrap();
+    for index in (0..num_bytes).rev() {
+        let byte = ((value >> (7 * index)) & 0x7f) as u8;
+        output.push(if index > 0 { byte | 0x80 } else { byte });
+    }
+}
+
+fn varint_size(value: u64) -> usize {
+    let mut output = Vec::new();
+    put_varint(&mut output, value);
+    return output.len();
+}
+
+/**
+Encodes the values as a record: the size of the header, the serial types of the values, then
+the values, the integers in as few bytes as they fit in.
+ */
+fn encode_record(values: &[Value]) -> Vec<u8> {
+    let mut types = Vec::new();
+    let mut body = Vec::new();
+    for value in values {
+        let serial_type = match value {
+            Value::Null => 0,
+            Value::Integer(0) => 8,
+            Value::Integer(1) => 9,
+            Value::Integer(value) => {
+                let (serial_type, size) = match *value {
+                    -0x80..=0x7f => (1, 1),
+                    -0x8000..=0x7fff => (2, 2),
+                    -0x80_0000..=0x7f_ffff => (3, 3),
+                    -0x8000_0000..=0x7fff_ffff => (4, 4),
+                    -0x8000_0000_0000..=0x7fff_ffff_ffff => (5, 6),
+                    _ => (6, 8),
+                };
+                body.extend(&value.to_be_bytes()[8 - size..]);
+                serial_type
+            }
+            Value::Real(value) => {
+                body.extend(value.to_be_bytes());
+                7
+            }
+            Value::Text(text) => {
+                body.extend(text.as_bytes());
+                text.len() as u64 * 2 + 13
+            }
+        };
+        put_varint(&mut types, serial_type);
+    }
+
+    // the size of the header counts the bytes of the size too
+    let mut header_size = types.len() + 1;
+    while varint_size(header_size as u64) + types.len() != header_size {
+        header_size = varint_size(header_size as u64) + types.len();
+    }
+    let mut record = Vec::with_capacity(header_size + body.len());
+    put_varint(&mut record, header_size as u64);
+    record.extend(types);
+    record.extend(body);
+    return record;
+}
+
+/**
+Collects the found strings into an SQLite database and writes it with `finish`. The tags are
+joined with `;`, as in the CSV output, and the missing values are NULL, as the hashes are
+without `--hash`. Package metadata is not stored.
+ */
+pub struct SqlitePrinter<'a> {
+    writer: &'a mut dyn Write,
+    table: Table,
+    hash: Option<HashKind>,
+}
+
+impl<'a> SqlitePrinter<'a> {
+    pub fn new(writer: &'a mut dyn Write, options: &Options) -> SqlitePrinter<'a> {
+        return SqlitePrinter { writer, table: Table::new(), hash: options.string_hash };
+    }
+
+    /**
+    Writes the database with the strings found so far.
+     */
+    pub fn finish(self) {
+        check_write(self.writer.write_all(&self.table.into_database()));
+    }
+}
+
+impl StringSink for SqlitePrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let record = StringRecord::new(found).with_hash(self.hash);
+        let tags = record.tags.join(";");
+        self.table.insert(&[
+            Value::Text(&record.file),
+            Value::Integer(record.offset as i64),
+            Value::Integer(record.length as i64),
+            Value::Text(record.encoding),
+            Value::Text(record.termination),
+            found.section.map_or(Value::Null, Value::Text),
+            Value::Text(&record.string),
+            if tags.is_empty() { Value::Null } else { Value::Text(&tags) },
+            record.xor_key.map_or(Value::Null, |key| Value::Integer(key as i64)),
+            record.decoded_from.map_or(Value::Null, Value::Text),
+            record.score.map_or(Value::Null, Value::Real),
+            record.hash.as_deref().map_or(Value::Null, Value::Text),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_varint() {
+        let varint = |value: u64| {
+            let mut output = Vec::new();
+            put_varint(&mut output, value);
+            return output;
+        };
+        assert_eq!(vec![0x00], varint(0));
+        assert_eq!(vec![0x7f], varint(0x7f));
+        assert_eq!(vec![0x81, 0x00], varint(0x80));
+        assert_eq!(vec![0xff, 0x7f], varint(0x3fff));
+        assert_eq!(vec![0xff; 9], varint(u64::MAX));
+    }
+
+    #[test]
+    fn test_encode_record() {
+        assert_eq!(
+            vec![5, 0, 9, 2, 19, 0x01, 0x00, b'a', b'b', b'c'],
+            encode_record(&[
+                Value::Null, Value::Integer(1), Value::Integer(256), Value::Text("abc"),
+            ])
+        );
+        let text = "x".repeat(100);
+        let record = encode_record(&[Value::Text(&text)]);
+        // 213 takes two bytes
+        assert_eq!(vec![3, 0x81, 0x55], record[..3].to_vec());
+        assert_eq!(103, record.len());
+    }
+
+    #[test]
+    fn test_database_pages() {
+        let database = Table::new().into_database();
+        assert_eq!(2 * PAGE_SIZE, database.len());
+        assert_eq!(b"SQLite format 3\0", &database[..16]);
+        assert_eq!([0, 0, 0, 2], database[28..32]);
+        assert_eq!([LEAF_TABLE_PAGE, 0, 0, 0, 0], database[PAGE_SIZE..PAGE_SIZE + 5]);
+
+        // a row spilling to three overflow pages, then enough rows for a few leaf pages
+        let mut table = Table::new();
+        let long = "y".repeat(3 * PAGE_SIZE);
+        table.insert(&[Value::Text(&long)]);
+        for index in 0..1000 {
+            table.insert(&[Value::Integer(index), Value::Text("some string")]);
+        }
+        let database = table.into_database();
+        let num_pages = database.len() / PAGE_SIZE;
+        assert_eq!(num_pages as u32, u32::from_be_bytes(database[28..32].try_into().unwrap()));
+        assert_eq!(INTERIOR_TABLE_PAGE, database[PAGE_SIZE]);
+        // the overflow pages come first, linked one to the next
+        assert_eq!([0, 0, 0, 4], database[2 * PAGE_SIZE..2 * PAGE_SIZE + 4]);
+        assert_eq!([0, 0, 0, 5], database[3 * PAGE_SIZE..3 * PAGE_SIZE + 4]);
+        assert_eq!([0, 0, 0, 0], database[4 * PAGE_SIZE..4 * PAGE_SIZE + 4]);
+        assert_eq!(LEAF_TABLE_PAGE, database[5 * PAGE_SIZE]);
+    }
+}
@@ -0,0 +1,458 @@
+/*
+ SquashFS images, the read-only file systems of the embedded Linux systems, see `--firmware`.
+ The image is walked from its root directory and its regular files are read whole: the data
+ blocks, the sparse ones as zeros, and the tail of the file packed into a fragment block. Only
+ version 4 is read, compressed with gzip, lzma, xz or zstd; lzo and lz4 are not supported.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use flate2::read::ZlibDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use super::limits::{ContainerLimits, ExpandError, ExpansionBudget};
+use super::packages::PackageFile;
+use super::utils::{read_u16_le, read_u32_le, read_u64_le};
+
+pub(crate) const SQUASHFS_MAGIC: &[u8] = b"hsqs";
+const SUPERBLOCK_SIZE: usize = 96;
+/// Uncompressed size of a metadata block.
+const METADATA_SIZE: usize = 8192;
+/// The size of a metadata block has this bit set if it is stored as is.
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+/// The size of a data block has this bit set if it is stored as is.
+const BLOCK_UNCOMPRESSED: u32 = 1 << 24;
+const BLOCK_SIZE_MASK: u32 = BLOCK_UNCOMPRESSED - 1;
+const NO_FRAGMENT: u32 = u32::MAX;
+const FRAGMENT_ENTRY_SIZE: usize = 16;
+const INODE_HEADER_SIZE: usize = 16;
+const BASIC_DIRECTORY: u16 = 1;
+const BASIC_FILE: u16 = 2;
+const EXTENDED_DIRECTORY: u16 = 8;
+const EXTENDED_FILE: u16 = 9;
+/// Limit of the nesting of the directories of crafted images.
+const MAX_DIRECTORY_DEPTH: usize = 256;
+
+#[derive(Copy, Clone)]
+enum Compressor {
+    Gzip,
+    Lzma,
+    Xz,
+    Zstd,
+}
+
+enum Inode {
+    Directory {
+        block: u32,
+        offset: u16,
+        /// Size of the listing, 3 bytes more than it takes.
+        size: u32,
+    },
+    File {
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: u32,
+        block_sizes: Vec<u32>,
+    },
+    Other,
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    compressor: Compressor,
+    block_size: u32,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+    /// The metadata blocks read, by their position, with the position of the next one.
+    metadata: HashMap<u64, (Vec<u8>, u64)>,
+    /// The fragment blocks read, by their position.
+    fragments: HashMap<u64, Vec<u8>>,
+    /// How many more bytes the files may take, see `--max-expansion-ratio`.
+    budget: ExpansionBudget,
+}
+
+/**
+Returns the regular files of the SquashFS image, with their paths from its root.
+ */
+pub(crate) fn read_squashfs(
+    data: &[u8],
+    limits: &ContainerLimits,
+) -> Result<Vec<PackageFile>, ExpandError> {
+    let superblock = data.get(..SUPERBLOCK_SIZE).ok_or("truncated superblock")?;
+    if !superblock.starts_with(SQUASHFS_MAGIC) {
+        return Err(ExpandError::Invalid("not a SquashFS image".to_string()));
+    }
+    let version = read_u16_le(superblock, 28).unwrap();
+    if version != 4 {
+        return Err(ExpandError::Invalid(format!("SquashFS {} is not supported", version)));
+    }
+    let compressor = match read_u16_le(superblock, 20).unwrap() {
+        1 => Compressor::Gzip,
+        2 => Compressor::Lzma,
+        4 => Compressor::Xz,
+        6 => Compressor::Zstd,
+        3 => return Err(ExpandError::Invalid("lzo compression is not supported".to_string())),
+        5 => return Err(ExpandError::Invalid("lz4 compression is not supported".to_string())),
+        id => return Err(ExpandError::Invalid(format!("unknown compression {}", id))),
+    };
+    let block_size = read_u32_le(superblock, 12).unwrap();
+    if block_size == 0 || block_size > BLOCK_SIZE_MASK {
+        return Err(ExpandError::Invalid("broken superblock".to_string()));
+    }
+
+    let mut image = Image {
+        data,
+        compressor,
+        block_size,
+        inode_table: read_u64_le(superblock, 64).unwrap(),
+        directory_table: read_u64_le(superblock, 72).unwrap(),
+        fragment_table: read_u64_le(superblock, 80).unwrap(),
+        metadata: HashMap::new(),
+        fragments: HashMap::new(),
+        budget: limits.expansion_budget(data.len() as u64),
+    };
+    let mut files = Vec::new();
+    let root = read_u64_le(superblock, 32).unwrap();
+    image.read_directory(root, "", 0, &mut HashSet::new(), &mut files)?;
+    return Ok(files);
+}
+
+impl<'a> Image<'a> {
+    /**
+    Adds the files in the directory and its subdirectories. The directories already read are
+    skipped, so that the loops of crafted images end.
+     */
+    fn read_directory(
+        &mut self,
+        reference: u64,
+        path: &str,
+        depth: usize,
+        visited: &mut HashSet<u64>,
+        files: &mut Vec<PackageFile>,
+    ) -> Result<(), ExpandError> {
+        if !visited.insert(reference) {
+            return Ok(());
+        }
+        if depth > MAX_DIRECTORY_DEPTH {
+            return Err(ExpandError::Invalid("directories nested too deep".to_string()));
+        }
+        let Inode::Directory { block, offset, size } = self.read_inode(reference)? else {
+            return Err(ExpandError::Invalid(format!("{} is not a directory", path)));
+        };
+        let listing = self.read_metadata(
+            add_offset(self.directory_table, block as u64)?,
+            offset as usize,
+            (size as usize).saturating_sub(3),
+        )?;
+
+        // headers of the inode block and the number of the entries, followed by the entries
+        let mut position = 0;
+        while position + 12 <= listing.len() {
+            let count = read_u32_le(&listing, position).unwrap() as u64 + 1;
+            let inode_block = read_u32_le(&listing, position + 4).unwrap() as u64;
+            position += 12;
+            for _ in 0..count {
+                let entry = listing.get(position..position + 8).ok_or("truncated directory")?;
+                let inode_offset = read_u16_le(entry, 0).unwrap() as u64;
+                let kind = read_u16_le(entry, 4).unwrap();
+                let name_end = position + 8 + read_u16_le(entry, 6).unwrap() as usize + 1;
+                let name = listing.get(position + 8..name_end).ok_or("truncated directory")?;
+                position = name_end;
+
+                let name = String::from_utf8_lossy(name);
+                let entry_path = if path.is_empty() {
+                    name.into_owned()
+                } else {
+                    format!("{}/{}", path, name)
+                };
+                let entry_reference = (inode_block << 16) | inode_offset;
+                match kind {
+                    BASIC_DIRECTORY | EXTENDED_DIRECTORY => {
+                        self.read_directory(
+                            entry_reference, &entry_path, depth + 1, visited, files,
+                        )?;
+                    }
+                    BASIC_FILE | EXTENDED_FILE => {
+                        let data = self.read_file(entry_reference)?;
+                        files.push(PackageFile { path: entry_path, data });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn read_file(&mut self, reference: u64) -> Result<Vec<u8>, ExpandError> {
+        let Inode::File { blocks_start, size, fragment, fragment_offset, block_sizes } =
+            self.read_inode(reference)?
+        else {
+            return Err(ExpandError::Invalid("broken file inode".to_string()));
+        };
+        self.budget.take(size)?;
+
+        let mut contents = Vec::with_capacity(size as usize);
+        let mut position = blocks_start;
+        for block_size in block_sizes {
+            let stored_size = (block_size & BLOCK_SIZE_MASK) as u64;
+            if stored_size == 0 {
+                // a sparse block
+                contents.resize(contents.len() + self.block_size as usize, 0);
+                continue;
+            }
+            contents.extend(self.read_block(position, block_size)?);
+            position = add_offset(position, stored_size)?;
+        }
+        if fragment != NO_FRAGMENT {
+            let tail_size = (size % self.block_size as u64) as usize;
+            let fragment_block = self.read_fragment(fragment)?;
+            let tail_start = fragment_offset as usize;
+            contents.extend(
+                fragment_block.get(tail_start..tail_start + tail_size).ok_or("broken fragment")?
+            );
+        }
+        contents.truncate(size as usize);
+        return Ok(contents);
+    }
+
+    /**
+    Returns the fragment block, read once for all the files whose tails it holds.
+     */
+    fn read_fragment(&mut self, index: u32) -> Result<&[u8], ExpandError> {
+        let entries_per_block = (METADATA_SIZE / FRAGMENT_ENTRY_SIZE) as u64;
+        let lookup = add_offset(self.fragment_table, index as u64 / entries_per_block * 8)?;
+        let table_block = usize::try_from(lookup).ok()
+            .and_then(|lookup| read_u64_le(self.data, lookup))
+            .ok_or("truncated fragment table")?;
+        let entry_offset = (index as u64 % entries_per_block) as usize * FRAGMENT_ENTRY_SIZE;
+        let entry = self.read_metadata(table_block, entry_offset, FRAGMENT_ENTRY_SIZE)?;
+        let start = read_u64_le(&entry, 0).unwrap();
+        let block_size = read_u32_le(&entry, 8).unwrap();
+
+        if !self.fragments.contains_key(&start) {
+            let block = self.read_block(start, block_size)?;
+            self.fragments.insert(start, block);
+        }
+        return Ok(&self.fragments[&start]);
+    }
+
+    /**
+    Returns a data block, `block_size` being its stored size and whether it is compressed.
+     */
+    fn read_block(&self, position: u64, block_size: u32) -> Result<Vec<u8>, ExpandError> {
+        let stored = self.slice(position, (block_size & BLOCK_SIZE_MASK) as u64)
+            .ok_or("truncated data block")?;
+        if block_size & BLOCK_UNCOMPRESSED != 0 {
+            return Ok(stored.to_vec());
+        }
+        return self.decompress(stored, self.block_size as usize);
+    }
+
+    fn read_inode(&mut self, reference: u64) -> Result<Inode, ExpandError> {
+        // the position of the metadata block in the table and the offset in the block
+        let position = add_offset(self.inode_table, reference >> 16)?;
+        let offset = (reference & 0xffff) as usize;
+        let header = self.read_metadata(position, offset, INODE_HEADER_SIZE)?;
+        let body_offset = offset + INODE_HEADER_SIZE;
+
+        let (blocks_start, size, fragment, fragment_offset, body_size) =
+            match read_u16_le(&header, 0).unwrap() {
+                BASIC_DIRECTORY => {
+                    let body = self.read_metadata(position, body_offset, 16)?;
+                    return Ok(Inode::Directory {
+                        block: read_u32_le(&body, 0).unwrap(),
+                        offset: read_u16_le(&body, 10).unwrap(),
+                        size: read_u16_le(&body, 8).unwrap() as u32,
+                    });
+                }
+                EXTENDED_DIRECTORY => {
+                    let body = self.read_metadata(position, body_offset, 24)?;
+                    return Ok(Inode::Directory {
+                        block: read_u32_le(&body, 8).unwrap(),
+                        offset: read_u16_le(&body, 18).unwrap(),
+                        size: read_u32_le(&body, 4).unwrap(),
+                    });
+                }
+                BASIC_FILE => {
+                    let body = self.read_metadata(position, body_offset, 16)?;
+                    (
+                        read_u32_le(&body, 0).unwrap() as u64,
+                        read_u32_le(&body, 12).unwrap() as u64,
+                        read_u32_le(&body, 4).unwrap(),
+                        read_u32_le(&body, 8).unwrap(),
+                        16,
+                    )
+                }
+                EXTENDED_FILE => {
+                    let body = self.read_metadata(position, body_offset, 40)?;
+                    (
+                        read_u64_le(&body, 0).unwrap(),
+                        read_u64_le(&body, 8).unwrap(),
+                        read_u32_le(&body, 28).unwrap(),
+                        read_u32_le(&body, 32).unwrap(),
+                        40,
+                    )
+                }
+                _ => return Ok(Inode::Other),
+            };
+
+        // the tail of a file with a fragment has no block of its own
+        let block_size = self.block_size as u64;
+        let num_blocks = if fragment == NO_FRAGMENT {
+            size.div_ceil(block_size)
+        } else {
+            size / block_size
+        };
+        if num_blocks.saturating_mul(4) > self.data.len() as u64 {
+            return Err(ExpandError::Invalid("broken file inode".to_string()));
+        }
+        let sizes = self.read_metadata(position, body_offset + body_size, num_blocks as usize * 4)?;
+        let block_sizes = sizes.chunks_exact(4)
+            .map(|size| u32::from_le_bytes(size.try_into().unwrap()))
+            .collect();
+        return Ok(Inode::File { blocks_start, size, fragment, fragment_offset, block_sizes });
+    }
+
+    /**
+    Returns `length` bytes of the metadata starting at `offset` in the block at `position`,
+    running on into the blocks which follow it.
+     */
+    fn read_metadata(
+        &mut self,
+        mut position: u64,
+        mut offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, ExpandError> {
+        let mut bytes = Vec::with_capacity(length);
+        while bytes.len() < length {
+            let (block, next) = self.metadata_block(position)?;
+            if offset < block.len() {
+                let end = block.len().min(offset + length - bytes.len());
+                bytes.extend(&block[offset..end]);
+                offset = 0;
+            } else {
+                offset -= block.len();
+            }
+            position = next;
+        }
+        return Ok(bytes);
+    }
+
+    fn metadata_block(&mut self, position: u64) -> Result<(&[u8], u64), ExpandError> {
+        if !self.metadata.contains_key(&position) {
+            let header = usize::try_from(position).ok()
+                .and_then(|position| read_u16_le(self.data, position))
+                .ok_or("truncated metadata")?;
+            let stored_size = (header & !METADATA_UNCOMPRESSED) as u64;
+            let stored_start = add_offset(position, 2)?;
+            let stored = self.slice(stored_start, stored_size).ok_or("truncated metadata")?;
+            let block = if header & METADATA_UNCOMPRESSED != 0 {
+                stored.to_vec()
+            } else {
+                self.decompress(stored, METADATA_SIZE)?
+            };
+            self.metadata.insert(position, (block, add_offset(stored_start, stored_size)?));
+        }
+        let (block, next) = &self.metadata[&position];
+        return Ok((block, *next));
+    }
+
+    /**
+    Decompresses a block, which can't be larger than `max_size`.
+     */
+    fn decompress(&self, stored: &[u8], max_size: usize) -> Result<Vec<u8>, ExpandError> {
+        let block_limits = ContainerLimits { max_depth: 0, max_expansion_ratio: 1 };
+        let mut output = block_limits.limit_expansion(Vec::new(), max_size as u64);
+        let mut input = stored;
+        let result = match self.compressor {
+            Compressor::Gzip => io::copy(&mut ZlibDecoder::new(input), &mut output)
+                .map(drop)
+                .map_err(|err| err.to_string()),
+            Compressor::Lzma => lzma_rs::lzma_decompress(&mut input, &mut output)
+                .map_err(|err| err.to_string()),
+            Compressor::Xz => lzma_rs::xz_decompress(&mut input, &mut output)
+                .map_err(|err| err.to_string()),
+            Compressor::Zstd => StreamingDecoder::new(&mut input)
+                .map_err(|err| err.to_string())
+                .and_then(|mut decoder| {
+                    return io::copy(&mut decoder, &mut output)
+                        .map(drop)
+                        .map_err(|err| err.to_string());
+                }),
+        };
+        if output.exceeded().is_some() {
+            return Err(ExpandError::Invalid("a block decompresses past its size".to_string()));
+        }
+        if let Err(err) = result {
+            return Err(ExpandError::Invalid(format!("broken compressed block: {}", err)));
+        }
+        return Ok(output.into_inner());
+    }
+
+    fn slice(&self, position: u64, size: u64) -> Option<&'a [u8]> {
+        let start = usize::try_from(position).ok()?;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+        return self.data.get(start..end);
+    }
+}
+
+/**
+Returns the position `offset` bytes after `base`, both read from the image, failing if a crafted
+image makes it overflow.
+ */
+fn add_offset(base: u64, offset: u64) -> Result<u64, ExpandError> {
+    return base.checked_add(offset)
+        .ok_or_else(|| ExpandError::Invalid("broken superblock".to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_squashfs() {
+        let data = std::fs::read("test-resources/rootfs.squashfs").unwrap();
+        let limits = ContainerLimits::default();
+        let files = read_squashfs(&data, &limits).unwrap();
+        assert_eq!(
+            vec!["bin/busybox", "etc/banner", "etc/init.d/rcS"],
+            files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>()
+        );
+        // two blocks, the second one sparse, and the tail in a fragment
+        assert_eq!(2 * 4096 + 100, files[0].data.len());
+        assert!(files[0].data.starts_with(b"\x7fELF"));
+        assert!(files[0].data[4096..8192].iter().all(|byte| *byte == 0));
+        assert_eq!(b"Welcome to the router\n".to_vec(), files[1].data);
+
+        let limits = ContainerLimits { max_expansion_ratio: 1, ..limits };
+        assert!(matches!(read_squashfs(&data, &limits), Err(ExpandError::Limit(_))));
+        let mut lz4 = data.clone();
+        lz4[20] = 5;
+        assert!(matches!(read_squashfs(&lz4, &limits), Err(ExpandError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_read_squashfs_broken_tables() {
+        let data = std::fs::read("test-resources/rootfs.squashfs").unwrap();
+        let limits = ContainerLimits::default();
+        let corrupted = |offset: usize, value: u64| {
+            let mut corrupted = data.clone();
+            corrupted[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            return read_squashfs(&corrupted, &limits);
+        };
+
+        // the root inode reference, and the inode, directory and fragment tables
+        let mut root_past_the_end = data.clone();
+        root_past_the_end[32..40].copy_from_slice(&0xffff0000u64.to_le_bytes());
+        root_past_the_end[64..72].copy_from_slice(&u64::MAX.to_le_bytes());
+        let result = read_squashfs(&root_past_the_end, &limits);
+        assert!(matches!(result, Err(ExpandError::Invalid(_))));
+        for offset in [64, 72, 80] {
+            for value in [u64::MAX, u64::MAX - 1] {
+                let result = corrupted(offset, value);
+                assert!(matches!(result, Err(ExpandError::Invalid(_))), "{} {}", offset, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+/*
+ Reconstruction of the stack strings, see `--stack-strings`: the malware and the obfuscated
+ binaries build their strings on the stack, a few characters at a time, with the immediates of
+ `mov` instructions such as `mov byte ptr [rbp-0x10], 0x68`, so that the strings are never in
+ the data. Like FLOSS does, the x86 and x86-64 code is searched for the runs of such stores
+ relative to the stack or the frame pointer, the bytes are put at their places in the frame,
+ and the printable runs of the frame are reported as the strings, UTF-16LE ones included.
+
+ Only the runs of consecutive stores are seen, not the strings whose stores are interleaved with
+ other instructions, nor the ones built in a loop or with the registers: the emulation of the
+ code FLOSS does is out of reach.
+ */
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use object::{Architecture, Object, ObjectSection, SectionKind};
+use super::strings::{EncodingKind, FoundString, Options, StringSink, Termination};
+use super::utils::char_is_printable;
+
+/// Fewest stores in a run, a single one is rather the initialization of a variable.
+const MIN_STORES: usize = 2;
+
+/**
+The register an address in the frame is relative to.
+ */
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Base {
+    /// `rsp` or `esp`
+    Stack,
+    /// `rbp` or `ebp`
+    Frame,
+}
+
+/**
+A `mov` of an immediate to the memory at a displacement from the stack or the frame pointer.
+ */
+#[derive(PartialEq, Debug)]
+struct Store {
+    /// Number of bytes of the instruction.
+    length: usize,
+    base: Base,
+    displacement: i64,
+    /// The bytes written, in memory order.
+    value: Vec<u8>,
+}
+
+/**
+A string put together by a run of stores.
+ */
+#[derive(PartialEq, Debug)]
+pub(crate) struct StackString {
+    /// Offsets of the first store of the run and past the last one in the code.
+    pub start: usize,
+    pub end: usize,
+    pub encoding: EncodingKind,
+    /// The characters, one byte each for the UTF-16LE strings as well.
+    pub text: Vec<u8>,
+    pub termination: Termination,
+}
+
+/**
+Reports the stack strings of the code sections of an x86 or x86-64 object file. Returns `None`
+if the data is not an object file of these architectures or has no code.
+ */
+pub(crate) fn print_strings_for_stack_strings(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let object = object::File::parse(data).ok()?;
+    let is_64 = match object.architecture() {
+        Architecture::X86_64 | Architecture::X86_64_X32 => true,
+        Architecture::I386 => false,
+        _ => return None,
+    };
+
+    let tags = ["stack".to_string()];
+    let mut has_code = false;
+    let mut num_found = 0u64;
+    for section in object.sections() {
+        if section.kind() != SectionKind::Text {
+            continue;
+        }
+        let (Ok(name), Ok(code), Some((file_offset, _))) =
+            (section.name(), section.data(), section.file_range()) else {
+            continue;
+        };
+        has_code = true;
+
+        for string in find_stack_strings(code, is_64, options) {
+            sink.on_string(&FoundString {
+                filename,
+                address: file_offset + string.start as u64,
+                end_address: file_offset + string.end as u64,
+                bytes: &string.text,
+                text: &string.text,
+                encoding: string.encoding,
+                tags: &tags,
+                xor_key: None,
+                decoded_from: None,
+                termination: string.termination,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: Some(name),
+            });
+            num_found += 1;
+        }
+    }
+    return if has_code { Some(num_found) } else { None };
+}
+
+/**
+Returns the strings put together by the runs of stores in the code.
+ */
+pub(crate) fn find_stack_strings(code: &[u8], is_64: bool, options: &Options) -> Vec<StackString> {
+    let mut strings = Vec::new();
+    let mut position = 0;
+    while position < code.len() {
+        let start = position;
+        let mut frame = BTreeMap::new();
+        let mut num_stores = 0;
+        while let Some(store) = decode_store(&code[position..], is_64) {
+            for (i, byte) in store.value.iter().enumerate() {
+                frame.insert((store.base, store.displacement + i as i64), *byte);
+            }
+            position += store.length;
+            num_stores += 1;
+        }
+        if num_stores == 0 {
+            position += 1;
+            continue;
+        }
+        if num_stores < MIN_STORES {
+            continue;
+        }
+
+        // the runs of adjacent bytes of the frame
+        let mut block = Vec::new();
+        let mut last = None;
+        for ((base, displacement), byte) in frame {
+            if last.is_some() && last != Some((base, displacement - 1)) {
+                strings.extend(split_block(&block, start, position, options));
+                block.clear();
+            }
+            block.push(byte);
+            last = Some((base, displacement));
+        }
+        strings.extend(split_block(&block, start, position, options));
+    }
+    return strings;
+}
+
+/**
+Returns the store the code starts with, if any: `mov` of an 8, 16 or 32-bit immediate, the
+latter sign-extended to 64 bits with `REX.W`, to `[rsp+disp]` or `[rbp+disp]`.
+ */
+fn decode_store(code: &[u8], is_64: bool) -> Option<Store> {
+    let (operand_size, prefix_size) = match code.first()? {
+        0x66 => (2, 1),
+        // a `dec eax` in 32-bit code
+        0x48 if is_64 => (8, 1),
+        _ => (4, 0),
+    };
+    let opcode = *code.get(prefix_size)?;
+    let (immediate_size, value_size) = match opcode {
+        0xc6 if prefix_size == 0 => (1, 1),
+        0xc7 => (operand_size.min(4), operand_size),
+        _ => return None,
+    };
+
+    let mut position = prefix_size + 1;
+    let modrm = *code.get(position)?;
+    position += 1;
+    let (base, displacement_size) = match modrm {
+        // followed by the SIB byte of `rsp` alone
+        0x44 | 0x84 if code.get(position) == Some(&0x24) => {
+            position += 1;
+            (Base::Stack, if modrm == 0x44 { 1 } else { 4 })
+        }
+        0x45 => (Base::Frame, 1),
+        0x85 => (Base::Frame, 4),
+        _ => return None,
+    };
+
+    let displacement = code.get(position..position + displacement_size)?;
+    let displacement = match displacement_size {
+        1 => displacement[0] as i8 as i64,
+        _ => i32::from_le_bytes(displacement.try_into().unwrap()) as i64,
+    };
+    position += displacement_size;
+
+    let immediate = code.get(position..position + immediate_size)?;
+    let mut value = immediate.to_vec();
+    let sign = if immediate[immediate_size - 1] & 0x80 != 0 { 0xff } else { 0 };
+    value.resize(value_size, sign);
+    position += immediate_size;
+
+    return Some(Store { length: position, base, displacement, value });
+}
+
+/**
+Returns the printable runs of adjacent bytes of the frame, written by the stores from `start` to
+`end`: the ones of the characters alternating with NULs as UTF-16LE, the others as ASCII.
+ */
+fn split_block(block: &[u8], start: usize, end: usize, options: &Options) -> Vec<StackString> {
+    let min_length = options.min_length.max(1) as usize;
+    let is_printable = |byte: u8| {
+        return char_is_printable(byte as char, EncodingKind::Bit7, options.include_all_whitespace);
+    };
+    let new_string = |encoding, text: Vec<u8>, next: Option<u32>| {
+        let termination = match next {
+            Some(symbol) => Termination::after(symbol, encoding),
+            None => Termination::Eof,
+        };
+        return StackString { start, end, encoding, text, termination };
+    };
+
+    let mut strings = Vec::new();
+    let mut i = 0;
+    while i < block.len() {
+        let num_wide = block[i..].chunks_exact(2)
+            .take_while(|pair| is_printable(pair[0]) && pair[1] == 0)
+            .count();
+        if num_wide >= min_length {
+            let text = block[i..i + 2 * num_wide].iter().step_by(2).copied().collect();
+            i += 2 * num_wide;
+            let next = block.get(i..i + 2).map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+            strings.push(new_string(EncodingKind::LittleEndian16, text, next.map(u32::from)));
+            continue;
+        }
+
+        let num_narrow = block[i..].iter().take_while(|byte| is_printable(**byte)).count();
+        if num_narrow >= min_length {
+            let text = block[i..i + num_narrow].to_vec();
+            let next = block.get(i + num_narrow).map(|byte| *byte as u32);
+            strings.push(new_string(EncodingKind::Bit7, text, next));
+        }
+        i += num_narrow.max(1);
+    }
+    return strings;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_store() {
+        // mov byte ptr [rbp-0x10], 0x68
+        assert_eq!(
+            Some(Store { length: 4, base: Base::Frame, displacement: -0x10, value: vec![0x68] }),
+            decode_store(&[0xc6, 0x45, 0xf0, 0x68], true)
+        );
+        // mov dword ptr [rsp+0x120], 0x6c6c6548
+        assert_eq!(
+            Some(Store {
+                length: 11, base: Base::Stack, displacement: 0x120, value: b"Hell".to_vec(),
+            }),
+            decode_store(&[0xc7, 0x84, 0x24, 0x20, 0x01, 0x00, 0x00, 0x48, 0x65, 0x6c, 0x6c], true)
+        );
+        // mov word ptr [rbp-2], 0x6f; mov qword ptr [rbp-8], -1
+        assert_eq!(
+            Some(Store { length: 6, base: Base::Frame, displacement: -2, value: vec![0x6f, 0] }),
+            decode_store(&[0x66, 0xc7, 0x45, 0xfe, 0x6f, 0x00], true)
+        );
+        assert_eq!(
+            Some(Store { length: 8, base: Base::Frame, displacement: -8, value: vec![0xff; 8] }),
+            decode_store(&[0x48, 0xc7, 0x45, 0xf8, 0xff, 0xff, 0xff, 0xff], true)
+        );
+        assert_eq!(None, decode_store(&[0x48, 0xc7, 0x45, 0xf8, 0xff, 0xff, 0xff, 0xff], false));
+        // mov byte ptr [rax+0x10], 0x68; truncated
+        assert_eq!(None, decode_store(&[0xc6, 0x40, 0x10, 0x68], true));
+        assert_eq!(None, decode_store(&[0xc6, 0x45, 0xf0], true));
+    }
+
+    #[test]
+    fn test_find_stack_strings() {
+        let mut code = vec![0x55, 0x48, 0x89, 0xe5];
+        // "secret" backwards, then the NUL
+        for (displacement, c) in [(0xf5u8, b't'), (0xf4, b'e'), (0xf3, b'r'), (0xf2, b'c')] {
+            code.extend([0xc6, 0x45, displacement, c]);
+        }
+        code.extend([0x66, 0xc7, 0x45, 0xf0, b's', b'e']);
+        code.extend([0xc6, 0x45, 0xf6, 0x00]);
+        code.extend([0xe8, 0x00, 0x00, 0x00, 0x00]);
+        // L"cmd.exe" at [rsp+0x20]
+        let wide: Vec<u8> = "cmd.exe\0".encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        let wide_start = code.len();
+        for (i, pair) in wide.chunks(4).enumerate() {
+            code.extend([0xc7, 0x44, 0x24, 0x20 + 4 * i as u8]);
+            code.extend(pair);
+        }
+        // a lone store
+        code.extend([0xc3, 0xc6, 0x45, 0xff, b'x']);
+
+        let strings = find_stack_strings(&code, true, &Options::default());
+        assert_eq!(
+            vec![
+                StackString {
+                    start: 4,
+                    end: 30,
+                    encoding: EncodingKind::Bit7,
+                    text: b"secret".to_vec(),
+                    termination: Termination::Nul,
+                },
+                StackString {
+                    start: wide_start,
+                    end: wide_start + 4 * 8,
+                    encoding: EncodingKind::LittleEndian16,
+                    text: b"cmd.exe".to_vec(),
+                    termination: Termination::Nul,
+                },
+            ],
+            strings
+        );
+    }
+}
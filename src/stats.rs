@@ -0,0 +1,218 @@
+/*
+ Summary of the found strings, see `--stats`: per input, the number of strings, the size of the
+ input, a histogram of the string lengths and the counts per encoding and per section. It is
+ printed after the strings, or instead of them with `--stats-only`, to compare builds at a
+ glance.
+ */
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::Write;
+use serde::Serialize;
+use super::diagnostics::check_write;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/**
+A bucket of the length histogram: the strings from `min` to `max` bytes long.
+ */
+#[derive(Serialize, PartialEq, Debug)]
+pub struct LengthBucket {
+    pub min: u64,
+    pub max: u64,
+    pub count: u64,
+}
+
+#[derive(Serialize, Default, PartialEq, Debug)]
+pub struct InputStats {
+    pub strings: u64,
+    /// the size of the input, unknown for stdin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// the powers of two between which the lengths fall, the empty buckets left out
+    pub lengths: Vec<LengthBucket>,
+    pub encodings: BTreeMap<&'static str, u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sections: BTreeMap<String, u64>,
+}
+
+impl InputStats {
+    fn add(&mut self, found: &FoundString) {
+        self.strings += 1;
+
+        let length = found.end_address - found.address;
+        // 0 for the lengths of 1, 1 for 2-3, 2 for 4-7 and so on
+        let bucket = length.max(1).ilog2() as u64;
+        match self.lengths.iter_mut().find(|existing| existing.min == 1 << bucket) {
+            Some(existing) => existing.count += 1,
+            None => {
+                self.lengths.push(LengthBucket {
+                    min: 1 << bucket,
+                    max: (2 << bucket) - 1,
+                    count: 1,
+                });
+                self.lengths.sort_by_key(|existing| existing.min);
+            }
+        }
+
+        *self.encodings.entry(found.encoding.name()).or_insert(0) += 1;
+        if let Some(section) = found.section {
+            *self.sections.entry(section.to_string()).or_insert(0) += 1;
+        }
+    }
+}
+
+/**
+Collects the statistics of the strings passed to the inner sink into `inputs`, an entry per
+input in the order they are scanned.
+ */
+pub struct StatsSink<'a> {
+    inputs: &'a mut Vec<(String, InputStats)>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> StatsSink<'a> {
+    pub fn new(
+        inputs: &'a mut Vec<(String, InputStats)>,
+        inner: &'a mut dyn StringSink,
+    ) -> StatsSink<'a> {
+        return StatsSink { inputs, inner };
+    }
+}
+
+impl StringSink for StatsSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let filename = found.filename.to_string_lossy();
+        // in case an input is scanned without `on_input`
+        if self.inputs.last().map(|(name, _)| name.as_str()) != Some(&*filename) {
+            self.inputs.push((filename.into_owned(), InputStats::default()));
+        }
+        self.inputs.last_mut().unwrap().1.add(found);
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        let stats = InputStats { bytes: size, ..InputStats::default() };
+        self.inputs.push((filename.to_string_lossy().into_owned(), stats));
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Prints the statistics as text: a line with the totals of every input, followed by the indented
+counts, and the totals of all the inputs if there are several.
+ */
+pub fn print_stats(inputs: &[(String, InputStats)], writer: &mut dyn Write) {
+    for (filename, stats) in inputs {
+        let bytes = stats.bytes.map(|bytes| bytes.to_string()).unwrap_or("?".to_string());
+        check_write(writeln!(
+            writer, "{}: {} strings in {} bytes", filename, stats.strings, bytes
+        ));
+        for bucket in &stats.lengths {
+            check_write(writeln!(
+                writer, "  length {}-{}: {}", bucket.min, bucket.max, bucket.count
+            ));
+        }
+        for (encoding, count) in &stats.encodings {
+            check_write(writeln!(writer, "  encoding {}: {}", encoding, count));
+        }
+        for (section, count) in &stats.sections {
+            check_write(writeln!(writer, "  section {}: {}", section, count));
+        }
+    }
+
+    if inputs.len() > 1 {
+        let num_strings: u64 = inputs.iter().map(|(_, stats)| stats.strings).sum();
+        let num_bytes: u64 = inputs.iter().filter_map(|(_, stats)| stats.bytes).sum();
+        check_write(writeln!(
+            writer, "total: {} strings in {} bytes of {} inputs",
+            num_strings, num_bytes, inputs.len()
+        ));
+    }
+}
+
+/**
+The statistics of an input as a JSON line, next to the string records.
+ */
+#[derive(Serialize)]
+struct StatsRecord<'a> {
+    file: &'a str,
+    stats: &'a InputStats,
+}
+
+/**
+Prints the statistics as JSON lines, one per input.
+ */
+pub fn print_stats_json(inputs: &[(String, InputStats)], writer: &mut dyn Write) {
+    for (filename, stats) in inputs {
+        check_write(serde_json::to_writer(&mut *writer, &StatsRecord { file: filename, stats }));
+        check_write(writer.write_all(b"\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    struct Discard;
+
+    impl StringSink for Discard {
+        fn on_string(&mut self, _found: &FoundString) {}
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut discard = Discard;
+        let mut inputs = Vec::new();
+        let mut sink = StatsSink::new(&mut inputs, &mut discard);
+        let string = |address: u64, length: u64, encoding: EncodingKind| {
+            return FoundString {
+                filename: OsStr::new("a.out"),
+                address,
+                end_address: address + length,
+                bytes: b"",
+                text: b"",
+                encoding,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Nul,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: Some(".rodata"),
+            };
+        };
+        sink.on_input(OsStr::new("a.out"), Some(100));
+        sink.on_string(&string(0, 4, EncodingKind::Bit7));
+        sink.on_string(&string(10, 20, EncodingKind::Bit7));
+        sink.on_string(&string(40, 7, EncodingKind::LittleEndian16));
+        sink.on_input(OsStr::new("empty"), None);
+
+        let mut output = Vec::new();
+        print_stats(&inputs, &mut output);
+        assert_eq!(
+            "a.out: 3 strings in 100 bytes\n\
+             \x20 length 4-7: 2\n\
+             \x20 length 16-31: 1\n\
+             \x20 encoding 16le: 1\n\
+             \x20 encoding 7bit: 2\n\
+             \x20 section .rodata: 3\n\
+             empty: 0 strings in ? bytes\n\
+             total: 3 strings in 100 bytes of 2 inputs\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        let mut output = Vec::new();
+        print_stats_json(&inputs[1..], &mut output);
+        assert_eq!(
+            "{\"file\":\"empty\",\"stats\":{\"strings\":0,\"lengths\":[],\"encodings\":{}}}\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,149 @@
+/*
+ The other data streams of the files, see `--streams`: the alternate data streams of NTFS on
+ Windows and the resource forks on macOS. Every stream is scanned as `FILE:STREAM`, e.g.
+ `setup.exe:Zone.Identifier` or `Icon:rsrc`; the main data is the file itself. Other systems
+ keep no such streams, so nothing more is scanned there.
+ */
+
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use super::diagnostics;
+use super::strings::{self, Options, StringSink};
+
+/**
+Prints the strings of the other streams of the file. Returns `None` if any of them couldn't be
+read.
+ */
+pub fn print_strings_for_streams(
+    file_path: &OsStr,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let mut num_found = 0u64;
+    let mut success = true;
+    for (stream, stream_path) in other_streams(Path::new(file_path)) {
+        let mut name = OsString::from(file_path);
+        name.push(format!(":{}", stream));
+        let file = match File::open(&stream_path) {
+            Ok(file) => file,
+            Err(err) => {
+                diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+                success = false;
+                continue;
+            }
+        };
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        let (found, error) = strings::print_strings_for_reader(
+            &name, 0, size, &mut BufReader::new(file), options, sink,
+        );
+        num_found += found;
+        if let Some(err) = error {
+            diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+            success = false;
+        }
+    }
+    return if success { Some(num_found) } else { None };
+}
+
+/**
+Returns the name of a stream as FindFirstStreamW and FindNextStreamW list it, `:NAME:$DATA`,
+or `None` for the main stream and the streams of other types.
+ */
+#[cfg(any(windows, test))]
+fn stream_name(listed: &str) -> Option<&str> {
+    let name = listed.strip_prefix(':')?.strip_suffix(":$DATA")?;
+    return if name.is_empty() { None } else { Some(name) };
+}
+
+/**
+Returns the names of the other streams of the file and the paths they are opened with.
+ */
+#[cfg(windows)]
+fn other_streams(file_path: &Path) -> Vec<(String, PathBuf)> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStrExt;
+
+    /// WIN32_FIND_STREAM_DATA, its name is MAX_PATH + 36 characters.
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct FindStreamData {
+        stream_size: i64,
+        stream_name: [u16; 296],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn FindFirstStreamW(
+            file_name: *const u16, info_level: i32, data: *mut c_void, flags: u32,
+        ) -> isize;
+        fn FindNextStreamW(find_stream: isize, data: *mut c_void) -> i32;
+        fn FindClose(find_file: isize) -> i32;
+    }
+    const INVALID_HANDLE_VALUE: isize = -1;
+    const FIND_STREAM_INFO_STANDARD: i32 = 0;
+
+    let wide_path: Vec<u16> = file_path.as_os_str().encode_wide().chain([0]).collect();
+    let mut data = FindStreamData { stream_size: 0, stream_name: [0; 296] };
+    let data_pointer = &mut data as *mut FindStreamData as *mut c_void;
+    let handle = unsafe {
+        FindFirstStreamW(wide_path.as_ptr(), FIND_STREAM_INFO_STANDARD, data_pointer, 0)
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Vec::new();
+    }
+
+    let mut streams = Vec::new();
+    loop {
+        let length = data.stream_name.iter().position(|unit| *unit == 0).unwrap_or(296);
+        let listed = String::from_utf16_lossy(&data.stream_name[..length]);
+        if let Some(name) = stream_name(&listed) {
+            let mut stream_path = file_path.as_os_str().to_os_string();
+            stream_path.push(format!(":{}", name));
+            streams.push((name.to_string(), PathBuf::from(stream_path)));
+        }
+        if unsafe { FindNextStreamW(handle, data_pointer) } == 0 {
+            break;
+        }
+    }
+    unsafe { FindClose(handle) };
+    return streams;
+}
+
+#[cfg(target_os = "macos")]
+fn other_streams(file_path: &Path) -> Vec<(String, PathBuf)> {
+    let fork_path = file_path.join("..namedfork").join("rsrc");
+    return match std::fs::metadata(&fork_path) {
+        Ok(metadata) if metadata.len() > 0 => vec![("rsrc".to_string(), fork_path)],
+        _ => Vec::new(),
+    };
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn other_streams(_file_path: &Path) -> Vec<(String, PathBuf)> {
+    return Vec::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_name() {
+        assert_eq!(Some("Zone.Identifier"), stream_name(":Zone.Identifier:$DATA"));
+        assert_eq!(None, stream_name("::$DATA"));
+        assert_eq!(None, stream_name(":index:$INDEX_ALLOCATION"));
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    #[test]
+    fn test_no_other_streams() {
+        let options = Options::default();
+        let mut output = Vec::new();
+        let mut printer = strings::StringPrinter::new(&mut output, &options);
+        let path = OsStr::new("test-resources/a.out");
+        assert_eq!(Some(0), print_strings_for_streams(path, &options, &mut printer));
+        assert!(output.is_empty());
+    }
+}
@@ -1,16 +1,53 @@
 use std::cmp::min;
 use std::collections::VecDeque;
 use std::ffi::OsStr;
-use std::fs::File;
+use std::fs::{self, File};
 use std::path::Path;
-use object::{Object, ObjectSection, Section, SectionFlags};
-use atty::Stream;
-use std::io::{Write, stdin, stdout, Read, BufReader, StdinLock};
+use std::sync::Arc;
+use encoding_rs::Encoding;
+use object::{macho, Object, ObjectSection, ObjectSegment, Section, SectionFlags};
+use std::io::{Write, stdin, Read, BufRead, BufReader, ErrorKind, Seek, SeekFrom, StdinLock};
+use super::alphabet::Alphabet;
+use super::archives;
+use super::binary::BinaryFormat;
+use super::color;
+use super::coredump::{self, MappedFileSink};
+use super::decode::DecodeKind;
+use super::decompress::{self, Compression};
+use super::diagnostics::check_write;
+use super::diagnostics;
+use super::dotnet;
+use super::dwarf;
+use super::golang;
+use super::hash::HashKind;
+use super::java;
+use super::overlay::{self, OverlaySink};
+use super::macho as macho_literals;
+use super::ebcdic;
+use super::limits::{self, ContainerLimits, ExpandError};
+use super::follow::FollowReader;
+use super::formats;
+use super::packages::{self, PackageInfo};
+use super::partitions;
+use super::pascal::{LengthPrefix, PascalScanner};
+use super::carve;
+use super::firmware;
+use super::pdf::{self, ObjectSink};
+use super::progress::ProgressReader;
+use super::records;
+use super::resources;
+use super::scanner::{new_scanner, new_utf16_detector, ByteScanner};
+
+use super::smart;
+use super::stack;
+use super::sparse::{self, LARGE_BUFFER_SIZE};
+use super::utf8::{self, Utf8Decoder, Utf8Unit};
 use super::utils::*;
+use super::xor::XorKeySink;
 
-macro_rules! write_or_panic {
+macro_rules! write_or_exit {
     ($dst:expr, $($arg:tt)*) => ({
-        write!($dst, $($arg)*).expect("Couldn't write data");
+        check_write(write!($dst, $($arg)*));
     })
 }
 
@@ -19,14 +56,22 @@ macro_rules! write_or_panic {
 #[derive(Copy, Clone)]
 pub enum UnicodeDisplayKind {
     Default,
+    /// The characters as they are, for the terminal to render.
     Show,
+    /// Like `Show`, but the bytes which are not valid UTF-8 are kept in the strings as U+FFFD
+    /// instead of ending them.
+    Replace,
+    /// `\uXXXX` escapes, the characters outside of the BMP as UTF-16 surrogate pairs.
     Escape,
+    /// The escapes of GNU strings, which get the characters outside of the BMP wrong: U+10348
+    /// comes out as `\u040348`.
+    EscapeGnu,
     Hex,
     Highlight,
     Invalid,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum EncodingKind {
     Bit7,
     Bit8,
@@ -34,18 +79,84 @@ pub enum EncodingKind {
     LittleEndian16,
     BigEndian32,
     LittleEndian32,
+    /// 16-bit, endianness is detected for every string
+    Auto16,
+    /// 32-bit, endianness is detected for every string
+    Auto32,
+    /// EBCDIC code page 037, the strings are transcoded to UTF-8
+    Ebcdic037,
+    /// EBCDIC code page 500, the strings are transcoded to UTF-8
+    Ebcdic500,
 }
 
 impl EncodingKind {
-    const fn num_bytes(&self) -> u8 {
+    pub(crate) const fn num_bytes(&self) -> u8 {
         return match self {
             EncodingKind::Bit7 | EncodingKind::Bit8 => 1,
-            EncodingKind::BigEndian16 | EncodingKind::LittleEndian16 => 2,
-            EncodingKind::BigEndian32 | EncodingKind::LittleEndian32 => 4
+            EncodingKind::Ebcdic037 | EncodingKind::Ebcdic500 => 1,
+            EncodingKind::BigEndian16 | EncodingKind::LittleEndian16 | EncodingKind::Auto16 => 2,
+            EncodingKind::BigEndian32 | EncodingKind::LittleEndian32 | EncodingKind::Auto32 => 4
+        };
+    }
+
+    /**
+    Short name used to tag the strings in the output.
+     */
+    pub const fn name(&self) -> &'static str {
+        return match self {
+            EncodingKind::Bit7 => "7bit",
+            EncodingKind::Bit8 => "8bit",
+            EncodingKind::BigEndian16 => "16be",
+            EncodingKind::LittleEndian16 => "16le",
+            EncodingKind::BigEndian32 => "32be",
+            EncodingKind::LittleEndian32 => "32le",
+            EncodingKind::Auto16 => "auto16",
+            EncodingKind::Auto32 => "auto32",
+            EncodingKind::Ebcdic037 => "cp037",
+            EncodingKind::Ebcdic500 => "cp500",
+        };
+    }
+
+    pub(crate) const fn is_auto(&self) -> bool {
+        return matches!(self, EncodingKind::Auto16 | EncodingKind::Auto32);
+    }
+
+    pub(crate) const fn is_ebcdic(&self) -> bool {
+        return matches!(self, EncodingKind::Ebcdic037 | EncodingKind::Ebcdic500);
+    }
+
+    /**
+    Parses the value of `-e`: one of {s,S,b,l,B,L,auto16,auto32,cp037,cp500}.
+     */
+    pub fn parse(value: &str) -> Option<EncodingKind> {
+        return match value {
+            "s" => Some(EncodingKind::Bit7),
+            "S" => Some(EncodingKind::Bit8),
+            "b" => Some(EncodingKind::BigEndian16),
+            "l" => Some(EncodingKind::LittleEndian16),
+            "B" => Some(EncodingKind::BigEndian32),
+            "L" => Some(EncodingKind::LittleEndian32),
+            "auto16" => Some(EncodingKind::Auto16),
+            "auto32" => Some(EncodingKind::Auto32),
+            "cp037" => Some(EncodingKind::Ebcdic037),
+            "cp500" => Some(EncodingKind::Ebcdic500),
+            _ => None
         };
     }
 }
 
+#[derive(Copy, Clone)]
+pub enum OutputFormat {
+    /// GNU `strings` output
+    Text,
+    /// one JSON object per string
+    Json,
+    /// comma-separated values with a header row
+    Csv,
+    /// the JSON records in MessagePack or CBOR
+    Binary(BinaryFormat),
+}
+
 #[derive(Copy, Clone)]
 pub enum RadixKind {
     Oct,
@@ -55,49 +166,276 @@ pub enum RadixKind {
 
 pub struct Options {
     pub datasection_only: bool,
+    /// Scan only the loadable segments of object files, see `--segments`.
+    pub segments_only: bool,
+    /// Read the string tables and the version information of PE files instead of scanning
+    /// them, see `--pe-resources`.
+    pub pe_resources: bool,
+    /// Read the `#Strings` and `#US` heaps of .NET assemblies instead of scanning them, see
+    /// `--dotnet`.
+    pub dotnet_metadata: bool,
+    /// Scan only the DWARF string sections of object files, see `--debug-strings`.
+    pub debug_strings: bool,
+    /// Cut the strings of Go binaries at the places the binary refers to, see `--go`.
+    pub go_strings: bool,
+    /// Read the string pools of Java class and DEX files instead of scanning them, see
+    /// `--java`.
+    pub java_strings: bool,
+    /// Split the `__cstring` and `__ustring` sections of Mach-O files at their literals instead
+    /// of scanning them, see `--macho-literals`.
+    pub macho_literals: bool,
+    /// Scan only the data appended past the image of PE and ELF files, see `--overlay`.
+    pub overlay_only: bool,
+    /// Decode Intel HEX and S-record files and scan the image, see `--hex-records`.
+    pub hex_records: bool,
+    /// Scan the objects of PDF files with their streams inflated, see `--pdf`.
+    pub pdf_objects: bool,
+    /// Reconstruct the strings built on the stack by the x86 code, see `--stack-strings`.
+    pub stack_strings: bool,
+    /// Scan the partitions of disk images one by one, see `--partitions`.
+    pub partitions: bool,
+    /// Scan the files embedded in the input on their own, see `--carve`.
+    pub carve: bool,
+    /// Scan the modules of UEFI firmware volumes and the files of SquashFS images one by one,
+    /// see `--firmware`.
+    pub firmware: bool,
     pub print_filenames: bool,
     pub min_length: u16,
     pub include_all_whitespace: bool,
     pub print_addresses: bool,
     pub address_radix: RadixKind,
     pub encoding: EncodingKind,
-    pub output_separator: Option<String>,
+    /// Encodings scanned in the same pass as `encoding`. If not empty, every string is tagged
+    /// with the encoding it was found in.
+    pub extra_encodings: Vec<EncodingKind>,
+    /// Also scan for the UTF-16 strings in the endianness the NUL bytes around them suggest,
+    /// see `--detect-utf16`.
+    pub detect_utf16: bool,
+    /// Bytes written after every string instead of a newline, see `-s`.
+    pub output_separator: Option<Vec<u8>>,
+    /// Escape the control characters of the printed strings, see `--escape-output`.
+    pub escape_output: bool,
+    /// Print the bytes the strings occupy in the input instead of their text, see `--raw`.
+    pub raw_output: bool,
+    /// Colour the file names, the offsets and the characters highlighted with `-u highlight`,
+    /// see `--color`.
+    pub color: bool,
     pub unicode_display: UnicodeDisplayKind,
+    /// Decode 16/32-bit encodings as UTF-16/UTF-32 and output them as UTF-8, instead of
+    /// accepting only the symbols which fit a single byte. The scan stays aligned to the code
+    /// unit size in this mode.
+    pub decode_wide: bool,
+    pub output_format: OutputFormat,
+    /// Hash of the strings included in the structured output, see `--hash`.
+    pub string_hash: Option<HashKind>,
+    /// Code page the strings found by the 8-bit scan are transcoded from, see `--charset`.
+    pub charset: Option<&'static Encoding>,
+    /// Scan the files inside deb, rpm and apk packages instead of the packages themselves.
+    pub expand_packages: bool,
+    /// Scan the files inside tar and zip archives instead of the archives themselves.
+    pub expand_archives: bool,
+    /// Scan the decompressed data of the gzip, xz, zstd and bzip2 inputs, see `--decompress`.
+    pub decompress: bool,
+    /// Limits of the expansion of packages, images and compressed data.
+    pub container_limits: ContainerLimits,
+    /// Single-byte keys the data is XOR-ed with before the scan, see `--xor`. Empty to scan the
+    /// data as is.
+    pub xor_keys: Vec<u8>,
+    /// Print the addresses exactly as GNU strings does, padded to 7 characters, instead of
+    /// padding them to the width of the largest offset in the input (but at least 7 as well).
+    pub compat_address_format: bool,
+    /// Pad the addresses to exactly this width (longer ones are not truncated) instead of
+    /// choosing it per input, see `--address-width`.
+    pub address_width: Option<usize>,
+    /// Pad the addresses with zeros instead of spaces, see `--address-pad`.
+    pub address_zero_pad: bool,
+    /// Encodings of the runs inside the strings which are decoded and reported, see `--decode`.
+    pub decode: Vec<DecodeKind>,
+    /// Drop the strings followed by an incomplete code unit at the end of the data. Such a tail
+    /// is never a part of a string, but it may mean that the string is misaligned.
+    pub strict_units: bool,
+    /// Print the offset just past the end of the string after its address, in the address
+    /// radix.
+    pub print_end_offset: bool,
+    /// Print the number of bytes the string occupies in the input, in decimal.
+    pub print_length: bool,
+    /// Print the strings of every input after a header with its name, size and format, and the
+    /// number of its strings after them, see `--group-by-file`.
+    pub group_by_file: bool,
+    /// Number of bytes of the input to dump before and after every string, see `-B` and `-A`.
+    pub context_before: usize,
+    pub context_after: usize,
+    /// Lengths the strings are prefixed with, see `--pascal`. Empty to look for the strings
+    /// ending with a non-printable character instead.
+    pub pascal: Vec<LengthPrefix>,
+    /// Report only the strings followed by a NUL or a newline, see `--require-terminator`.
+    pub require_terminator: bool,
+    /// Choose the encoding for every region of the data, see `--smart-encoding`. `encoding`
+    /// and `extra_encodings` are ignored.
+    pub smart_encoding: bool,
+    /// Bounds of the Shannon entropy of the printed strings in bits per character, see
+    /// `--min-entropy` and `--max-entropy`.
+    pub min_entropy: Option<f64>,
+    pub max_entropy: Option<f64>,
+    /// Minimum English-likeness score of the printed strings, from 0 to 1. If set, the strings
+    /// are scored and the score is included in the structured output.
+    pub min_score: Option<f64>,
+    /// Minimum number of distinct characters of the printed strings, 0 to print all of them.
+    pub min_distinct_chars: usize,
+    /// Draw a progress bar on stderr while the regular files are read, see `--progress`.
+    pub progress: bool,
+    /// Wait for more data at the end of the files instead of ending the scan, see `--follow`.
+    pub follow: bool,
+    /// Characters which make up the strings, instead of the printable ones, see `--chars`.
+    pub alphabet: Option<Alphabet>,
+    /// Told of the progress of the scans and asked whether to stop them, for the embedders of
+    /// the library.
+    pub observer: Option<Arc<dyn ScanObserver>>,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Options {
             datasection_only: false,
+            segments_only: false,
+            pe_resources: false,
+            dotnet_metadata: false,
+            debug_strings: false,
+            go_strings: false,
+            java_strings: false,
+            macho_literals: false,
+            overlay_only: false,
+            hex_records: false,
+            pdf_objects: false,
+            stack_strings: false,
+            partitions: false,
+            carve: false,
+            firmware: false,
             print_filenames: false,
             min_length: 4,
             include_all_whitespace: false,
             print_addresses: false,
             address_radix: RadixKind::Hex,
             output_separator: None,
+            escape_output: false,
+            raw_output: false,
+            color: false,
             encoding: EncodingKind::Bit7,
+            extra_encodings: Vec::new(),
+            detect_utf16: false,
             unicode_display: UnicodeDisplayKind::Default,
+            decode_wide: false,
+            output_format: OutputFormat::Text,
+            string_hash: None,
+            charset: None,
+            expand_packages: false,
+            expand_archives: false,
+            decompress: false,
+            container_limits: ContainerLimits::default(),
+            xor_keys: Vec::new(),
+            compat_address_format: false,
+            address_width: None,
+            address_zero_pad: false,
+            decode: Vec::new(),
+            strict_units: false,
+            print_end_offset: false,
+            print_length: false,
+            group_by_file: false,
+            context_before: 0,
+            context_after: 0,
+            pascal: Vec::new(),
+            require_terminator: false,
+            smart_encoding: false,
+            min_entropy: None,
+            max_entropy: None,
+            min_score: None,
+            min_distinct_chars: 0,
+            progress: false,
+            follow: false,
+            alphabet: None,
+            observer: None,
         }
     }
 }
 
+impl Options {
+    /**
+    If more than one encoding is scanned, strings are tagged with the encoding they were
+    found in.
+     */
+    pub fn scans_multiple_encodings(&self) -> bool {
+        return !self.extra_encodings.is_empty() || self.detect_utf16 || self.encoding.is_auto()
+            || self.smart_encoding;
+    }
+
+    pub fn filters_entropy(&self) -> bool {
+        return self.min_entropy.is_some() || self.max_entropy.is_some();
+    }
+
+    /**
+    If the context of the strings is printed, the whole input is read to memory to take it from.
+     */
+    pub fn prints_context(&self) -> bool {
+        return self.context_before > 0 || self.context_after > 0;
+    }
+
+    /**
+    If the whole input is needed in memory: to take the context of the strings from it, or to
+    read the structures of its format.
+     */
+    fn scans_in_memory(&self) -> bool {
+        return self.prints_context() || self.reads_format() || self.datasection_only
+            || self.segments_only || self.debug_strings;
+    }
+
+    /**
+    If the strings are read from the structures of a file format instead of scanning for them:
+    the PE resources, the .NET metadata, the Go string references, the Java string pools or the
+    Mach-O literal sections. The overlay is found from the structures too, the firmware image
+    is decoded from the records, the streams of a PDF are inflated and the stack strings are
+    put together from the code.
+     */
+    pub fn reads_format(&self) -> bool {
+        return self.pe_resources || self.dotnet_metadata || self.go_strings || self.java_strings
+            || self.macho_literals || self.overlay_only || self.hex_records || self.pdf_objects
+            || self.stack_strings;
+    }
+}
+
 // endregion
 
 const SEC_ALLOC: u64 = 0x1;
 const SEC_LOAD: u64 = 0x2;
 const SEC_HAS_CONTENTS: u64 = 0x100;
 
+/// Minimum number of the last read bytes `ReaderWithSeek` keeps to seek back to.
 const MAX_KEEP_BACK_SIZE: usize = 1024;
 
+/// Width of the addresses printed by GNU strings.
+const GNU_ADDRESS_WIDTH: usize = 7;
+
+/// Number of bytes per line of the context hex dump.
+const CONTEXT_LINE_SIZE: usize = 16;
+
+/// Number of bytes of the hole read after each data region of a sparse file, enough to end the
+/// strings of every encoding.
+const HOLE_PADDING: u64 = 8;
+
+/// Number of bytes scanned between the calls of `ScanObserver`.
+const OBSERVE_INTERVAL: u64 = 64 * 1024;
+
 const DATA_FLAGS: u64 = SEC_ALLOC | SEC_LOAD | SEC_HAS_CONTENTS;
 
 // region internal data structures
 
 trait DataSource {
-    fn read_unicode(&mut self) -> Option<Vec<u8>>;
     fn read_byte(&mut self) -> Option<u8>;
     fn read_symbol(&mut self, encoding: &EncodingKind) -> Option<(u32, u8)>;
-    fn seek_back(&mut self, num_bytes: u8);
+    /**
+    Steps back over the last `num_bytes` read, so that they are read again. The source keeps
+    at least `MAX_KEEP_BACK_SIZE` of them.
+     */
+    fn seek_back(&mut self, num_bytes: usize);
 }
 
 struct ByteArrayHolder<'a> {
@@ -106,18 +444,6 @@ struct ByteArrayHolder<'a> {
 }
 
 impl DataSource for ByteArrayHolder<'_> {
-    fn read_unicode(&mut self) -> Option<Vec<u8>> {
-        if self.position >= self.inner.len() {
-            return None;
-        }
-
-        let until = min(self.position + 4, self.inner.len());
-        let read = &self.inner[self.position..until];
-        self.position = until;
-
-        return Some(read.to_vec());
-    }
-
     fn read_byte(&mut self) -> Option<u8> {
         return match self.read_symbol(&EncodingKind::Bit8) {
             Some(x) => Some(x.0 as u8),
@@ -163,75 +489,104 @@ impl DataSource for ByteArrayHolder<'_> {
         return Some((result, num_read));
     }
 
-    fn seek_back(&mut self, num_bytes: u8) {
-        self.position -= num_bytes as usize;
+    fn seek_back(&mut self, num_bytes: usize) {
+        self.position -= num_bytes;
     }
 }
 
 struct ReaderWithSeek<'a> {
-    inner: Box<(dyn Read + 'a)>,
+    inner: Box<dyn Read + 'a>,
+    /// the last read bytes, the ones to be read again at the end
     back_buf: VecDeque<u8>,
     back_pos: usize,
+    /// the error which ended the reading early, the scan sees it as the end of the data
+    error: Option<std::io::Error>,
 }
 
-impl<'a> Into<ReaderWithSeek<'a>> for BufReader<File> {
-    fn into(self) -> ReaderWithSeek<'a> {
+impl<'a> ReaderWithSeek<'a> {
+    fn new(inner: Box<dyn Read + 'a>) -> ReaderWithSeek<'a> {
         return ReaderWithSeek {
-            inner: Box::new(self),
-            back_buf: VecDeque::with_capacity(MAX_KEEP_BACK_SIZE),
+            inner,
+            back_buf: VecDeque::with_capacity(2 * MAX_KEEP_BACK_SIZE),
             back_pos: 0,
+            error: None,
         };
     }
-}
 
-impl<'a> Into<ReaderWithSeek<'a>> for BufReader<StdinLock<'a>> {
-    fn into(self) -> ReaderWithSeek<'a> {
-        return ReaderWithSeek {
-            inner: Box::new(self),
-            back_buf: VecDeque::with_capacity(MAX_KEEP_BACK_SIZE),
-            back_pos: 0,
-        };
+    /**
+    Drops the oldest bytes once there are twice as many as needed, so that it happens rarely.
+     */
+    fn trim_back_buf(&mut self) {
+        if self.back_buf.len() > 2 * MAX_KEEP_BACK_SIZE {
+            let num_kept = MAX_KEEP_BACK_SIZE.max(self.back_pos);
+            self.back_buf.drain(..self.back_buf.len() - num_kept);
+        }
     }
 }
 
-impl DataSource for ReaderWithSeek<'_> {
-    fn read_unicode(&mut self) -> Option<Vec<u8>> {
-        let mut vec = Vec::<u8>::new();
+/**
+Reports the bytes read through it to the observer and ends the data once it cancels the scan.
+ */
+struct ObservedSource<'a> {
+    inner: &'a mut dyn DataSource,
+    observer: &'a dyn ScanObserver,
+    position: u64,
+    next_check: u64,
+    cancelled: bool,
+}
 
-        let mut buffer = [0u8; 4];
-        loop {
-            if self.back_pos > 0 {
-                vec.push(self.back_buf[self.back_buf.len() - self.back_pos]);
-                self.back_pos -= 1;
-                if vec.len() == 4 {
-                    break;
-                }
-            } else {
-                match self.inner.read(&mut buffer[..(4 - vec.len())]) {
-                    Ok(read) => {
-                        if read == 0 {
-                            return None;
-                        }
-                        for byte in &buffer[0..read] {
-                            vec.push(*byte);
-                            self.back_buf.push_back(*byte);
-                        }
-                    }
-                    Err(_) => {
-                        return None;
-                    }
-                };
-                break;
-            }
-        }
+impl DataSource for ObservedSource<'_> {
+    fn read_byte(&mut self) -> Option<u8> {
+        return self.read_symbol(&EncodingKind::Bit8).map(|(symbol, _)| symbol as u8);
+    }
 
-        if self.back_buf.len() > MAX_KEEP_BACK_SIZE {
-            self.back_buf = self.back_buf.split_off(MAX_KEEP_BACK_SIZE / 2);
+    fn read_symbol(&mut self, encoding: &EncodingKind) -> Option<(u32, u8)> {
+        if self.position >= self.next_check && !self.cancelled {
+            self.next_check = self.position + OBSERVE_INTERVAL;
+            self.observer.progress(self.position);
+            self.cancelled = self.observer.should_cancel();
         }
+        if self.cancelled {
+            return None;
+        }
+        let symbol = self.inner.read_symbol(encoding);
+        if let Some((_, num_read)) = symbol {
+            self.position += num_read as u64;
+        }
+        return symbol;
+    }
+
+    fn seek_back(&mut self, num_bytes: usize) {
+        self.inner.seek_back(num_bytes);
+        self.position -= num_bytes as u64;
+    }
+}
+
+impl<'a> Into<ReaderWithSeek<'a>> for BufReader<File> {
+    fn into(self) -> ReaderWithSeek<'a> {
+        return ReaderWithSeek::new(Box::new(self));
+    }
+}
+
+impl<'a> From<BufReader<ProgressReader<File>>> for ReaderWithSeek<'a> {
+    fn from(reader: BufReader<ProgressReader<File>>) -> ReaderWithSeek<'a> {
+        return ReaderWithSeek::new(Box::new(reader));
+    }
+}
+
+impl<'a> From<BufReader<FollowReader>> for ReaderWithSeek<'a> {
+    fn from(reader: BufReader<FollowReader>) -> ReaderWithSeek<'a> {
+        return ReaderWithSeek::new(Box::new(reader));
+    }
+}
 
-        return Some(vec);
+impl<'a> Into<ReaderWithSeek<'a>> for BufReader<StdinLock<'a>> {
+    fn into(self) -> ReaderWithSeek<'a> {
+        return ReaderWithSeek::new(Box::new(self));
     }
+}
 
+impl DataSource for ReaderWithSeek<'_> {
     fn read_byte(&mut self) -> Option<u8> {
         return match self.read_symbol(&EncodingKind::Bit8) {
             Some(x) => Some(x.0 as u8),
@@ -254,7 +609,10 @@ impl DataSource for ReaderWithSeek<'_> {
                     Ok(_) => {
                         buf[0]
                     }
-                    Err(_) => {
+                    Err(err) => {
+                        if err.kind() != ErrorKind::UnexpectedEof {
+                            self.error = Some(err);
+                        }
                         break;
                     }
                 };
@@ -265,9 +623,7 @@ impl DataSource for ReaderWithSeek<'_> {
             num_read += 1;
         }
 
-        if self.back_buf.len() > MAX_KEEP_BACK_SIZE {
-            self.back_buf = self.back_buf.split_off(MAX_KEEP_BACK_SIZE / 2);
-        }
+        self.trim_back_buf();
 
         if num_read == 0 {
             return None;
@@ -288,444 +644,2003 @@ impl DataSource for ReaderWithSeek<'_> {
         return Some((result, num_read));
     }
 
-    fn seek_back(&mut self, num_bytes: u8) {
-        self.back_pos += num_bytes as usize;
+    fn seek_back(&mut self, num_bytes: usize) {
+        self.back_pos += num_bytes;
         if self.back_pos > self.back_buf.len() {
-            panic!("Cannot seek back more than {} bytes", MAX_KEEP_BACK_SIZE)
+            panic!("Cannot seek back more than {} bytes", self.back_buf.len())
         }
     }
+
 }
 
 // endregion
 
-pub fn print_strings_for_file(file_path_str: &OsStr, options: &Options) -> bool {
-    let file_path = Path::new(file_path_str);
+// region Output
 
-    if !file_path.exists() {
-        eprintln!("{:?}: No such file", file_path_str);
-        return false;
-    }
+/**
+How the run of printable symbols ended.
+ */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Termination {
+    Nul,
+    /// A line feed or a carriage return
+    Newline,
+    NonPrintable,
+    /// End of the data, or an incomplete symbol at its end
+    Eof,
+    /// End of the scanned object file section
+    SectionEnd,
+    /// End given by the length written before the string, e.g. in a PE string table
+    Length,
+}
 
-    if file_path.is_dir() {
-        eprintln!("Warning: '{:?}' is a directory", file_path_str);
-        return false;
+impl Termination {
+    /**
+    Returns how a string ends if it is followed by the given non-printable symbol.
+     */
+    pub fn after(symbol: u32, encoding: EncodingKind) -> Termination {
+        let symbol = if encoding.is_ebcdic() {
+            ebcdic::decode(encoding, symbol as u8) as u32
+        } else {
+            symbol
+        };
+        return match symbol {
+            0 => Termination::Nul,
+            0x0a | 0x0d => Termination::Newline,
+            _ => Termination::NonPrintable
+        };
     }
 
-    if !options.datasection_only || !print_strings_for_object_file(file_path, options) {
-        let stdout = stdout();
-        let mut writer = stdout.lock();
-
-        let mut reader: ReaderWithSeek = BufReader::new(
-            File::open(file_path).expect("Couldn't open the file.")
-        ).into();
-
-        print_strings(file_path_str.to_str().expect("Couldn't convert file path to string"),
-                      0, &mut reader, options, &mut writer);
+    pub const fn name(&self) -> &'static str {
+        return match self {
+            Termination::Nul => "nul",
+            Termination::Newline => "newline",
+            Termination::NonPrintable => "non-printable",
+            Termination::Eof => "eof",
+            Termination::SectionEnd => "section-end",
+            Termination::Length => "length",
+        };
+    }
 
-        writer.flush();
-        return true;
+    /**
+    Returns true if the string ends the way GNU strings documents: with a NUL or a newline. The
+    strings with a length prefix end where it says, which is as good.
+     */
+    pub const fn is_terminator(&self) -> bool {
+        return matches!(self, Termination::Nul | Termination::Newline | Termination::Length);
     }
-    return true;
 }
 
-pub fn print_strings_for_stdin(options: &Options) {
-    let stdin = stdin();
-    let stdout = stdout();
-    let mut writer = stdout.lock();
-    let mut reader: ReaderWithSeek = BufReader::new(stdin.lock()).into();
-    print_strings("<stdin>", 0, &mut reader, options, &mut writer);
-    writer.flush();
+/**
+A string found by the scanner. `bytes` are the string as decoded from the data, `text` is what
+should be displayed (they differ only when unicode characters are escaped or highlighted).
+ */
+pub struct FoundString<'a> {
+    pub filename: &'a OsStr,
+    pub address: u64,
+    /// Address right after the last symbol of the string.
+    pub end_address: u64,
+    pub bytes: &'a [u8],
+    pub text: &'a [u8],
+    pub encoding: EncodingKind,
+    /// Labels attached to the string by the classifiers, e.g. `url`.
+    pub tags: &'a [String],
+    /// Key the data was XOR-ed with to find the string, see `--xor`.
+    pub xor_key: Option<u8>,
+    /// Encoding of the string this one was decoded from, see `--decode`.
+    pub decoded_from: Option<DecodeKind>,
+    pub termination: Termination,
+    /// English-likeness of the string, if it is scored, see `--min-score`.
+    pub score: Option<f64>,
+    /// Bytes of the data right before and right after the string, see `--before-context` and
+    /// `--after-context`. Empty unless the context is requested.
+    pub context_before: &'a [u8],
+    pub context_after: &'a [u8],
+    /// Name of the object file section the string is found in, with `-d`.
+    pub section: Option<&'a str>,
 }
 
-fn print_strings_for_object_file(file_path: &Path, options: &Options) -> bool {
-    return match std::fs::read(file_path) {
-        Ok(data) => {
-            if let Ok(object) = object::File::parse(&*data) {
-                let mut got_section = false;
-                for section in object.sections() {
-                    got_section |= print_strings_for_object_section(
-                        file_path.as_os_str(), &section, options,
-                    );
+impl FoundString<'_> {
+    /**
+    Returns the same string with different contents, e.g. after some transformation.
+     */
+    pub fn with_contents<'b>(&'b self, bytes: &'b [u8], text: &'b [u8]) -> FoundString<'b> {
+        return FoundString {
+            filename: self.filename,
+            address: self.address,
+            end_address: self.end_address,
+            bytes,
+            text,
+            encoding: self.encoding,
+            tags: self.tags,
+            xor_key: self.xor_key,
+            decoded_from: self.decoded_from,
+            termination: self.termination,
+            score: self.score,
+            context_before: self.context_before,
+            context_after: self.context_after,
+            section: self.section,
+        };
+    }
+
+    /**
+    Returns the bytes the string occupies in the input, `end_address - address` of them: the
+    bytes of the 8-bit strings as they are, the characters of the other encodings encoded back.
+    `decode_wide` tells that the wide strings are decoded to UTF-8, see `--decode-wide`. The
+    XOR-ed strings and the decoded payloads are returned as found.
+     */
+    pub fn original_bytes(&self, decode_wide: bool) -> std::borrow::Cow<'_, [u8]> {
+        if self.xor_key.is_some() || self.decoded_from.is_some() {
+            return self.bytes.into();
+        }
+        let text = String::from_utf8_lossy(self.bytes);
+        let units: Vec<u32> = match self.encoding {
+            EncodingKind::Bit7 | EncodingKind::Bit8 => return self.bytes.into(),
+            EncodingKind::Ebcdic037 | EncodingKind::Ebcdic500 => {
+                return text.chars()
+                    .map(|c| ebcdic::encode(self.encoding, c).unwrap_or(b'?'))
+                    .collect::<Vec<u8>>()
+                    .into();
+            }
+            _ if !decode_wide => self.bytes.iter().map(|byte| *byte as u32).collect(),
+            EncodingKind::BigEndian16 | EncodingKind::LittleEndian16 | EncodingKind::Auto16 => {
+                text.encode_utf16().map(u32::from).collect()
+            }
+            _ => text.chars().map(u32::from).collect(),
+        };
+
+        let mut bytes = Vec::with_capacity(units.len() * self.encoding.num_bytes() as usize);
+        for unit in units {
+            match self.encoding {
+                EncodingKind::BigEndian16 => bytes.extend((unit as u16).to_be_bytes()),
+                EncodingKind::BigEndian32 => bytes.extend(unit.to_be_bytes()),
+                EncodingKind::LittleEndian32 | EncodingKind::Auto32 => {
+                    bytes.extend(unit.to_le_bytes());
                 }
-                got_section
-            } else {
-                println!("File is not an object");
-                false
+                _ => bytes.extend((unit as u16).to_le_bytes()),
             }
         }
-        Err(err) => {
-            println!("Warning: could not open '{:?}'.  reason: {}", file_path, err);
-            false
-        }
-    };
+        return bytes.into();
+    }
+
+    /**
+    Returns the same string with different tags.
+     */
+    pub fn with_tags<'b>(&'b self, tags: &'b [String]) -> FoundString<'b> {
+        return FoundString {
+            filename: self.filename,
+            address: self.address,
+            end_address: self.end_address,
+            bytes: self.bytes,
+            text: self.text,
+            encoding: self.encoding,
+            tags,
+            xor_key: self.xor_key,
+            decoded_from: self.decoded_from,
+            termination: self.termination,
+            score: self.score,
+            context_before: self.context_before,
+            context_after: self.context_after,
+            section: self.section,
+        };
+    }
 }
 
-fn print_strings_for_object_section(
-    filename: &OsStr,
-    section: &Section,
-    options: &Options,
-) -> bool {
-    if !is_data_section(section) || section.size() == 0 {
+/**
+Watches the scans from the outside, so that a GUI or a server embedding the library can show
+their progress and stop the long ones, see `Options::observer`. It may be called from any thread
+the scans run in.
+ */
+pub trait ScanObserver: Send + Sync {
+    /**
+    Called every 64 KiB and at the end of an input with the number of its bytes scanned so far.
+    The members of the containers and the decompressed data are inputs of their own.
+     */
+    fn progress(&self, _bytes: u64) {}
+
+    /**
+    Checked as often as the progress is reported. Once it returns `true`, the input ends where
+    the scan is, so the strings found so far are still passed to the sink.
+     */
+    fn should_cancel(&self) -> bool {
         return false;
     }
+}
 
-    if let Ok(compressed_data) = section.compressed_data() {
-        let stdout = stdout();
-        let mut writer = stdout.lock();
-        let mut byte_holder = ByteArrayHolder {
-            inner: compressed_data.data,
-            position: 0,
+pub trait StringSink {
+    fn on_string(&mut self, found: &FoundString);
+
+    /**
+    Called with the metadata of a package before the strings of its files, see `--packages`.
+     */
+    fn on_package(&mut self, _filename: &OsStr, _package: &PackageInfo) {}
+
+    /**
+    Called before the strings of every input (a file or a member of a container), with its size
+    if it is known.
+     */
+    fn on_input(&mut self, _filename: &OsStr, _size: Option<u64>) {}
+}
+
+/**
+Prints found strings in the GNU `strings` format.
+ */
+pub struct StringPrinter<'a> {
+    writer: &'a mut dyn Write,
+    options: &'a Options,
+    // minimum width of the addresses of the current input
+    address_width: usize,
+    // with `--group-by-file`, the number of strings of the current input, `None` before the
+    // first input, and the section of the last string
+    group_size: Option<u64>,
+    group_section: Option<String>,
+}
+
+impl<'a> StringPrinter<'a> {
+    pub fn new(writer: &'a mut dyn Write, options: &'a Options) -> StringPrinter<'a> {
+        let address_width = options.address_width.unwrap_or(GNU_ADDRESS_WIDTH);
+        return StringPrinter {
+            writer, options, address_width, group_size: None, group_section: None,
         };
-        print_strings(
-            filename.to_str().unwrap(),
-            section.address(),
-            &mut byte_holder, options,
-            &mut writer,
-        );
-        writer.flush();
-        return true;
     }
 
-    return false;
-}
+    /**
+    Prints the header of the group of the input's strings: its name, then its size and format
+    if they are known.
+     */
+    fn start_group(&mut self, filename: &OsStr, size: Option<u64>) {
+        if self.group_size.is_some() {
+            self.finish();
+            write_or_exit!(self.writer, "\n");
+        }
+        self.group_size = Some(0);
+        self.group_section = None;
 
-fn is_data_section(section: &Section) -> bool {
-    let flags = match section.flags() {
-        SectionFlags::Elf { sh_flags } => {
-            sh_flags
+        let mut details = Vec::new();
+        if let Some(size) = size {
+            details.push(format!("{} bytes", size));
         }
-        SectionFlags::MachO { flags } => {
-            flags as u64
+        if let Some(format) = formats::describe_file(Path::new(filename)) {
+            details.push(format.to_string());
         }
-        SectionFlags::Coff { characteristics } => {
-            characteristics as u64
+        write_or_exit!(self.writer, "==> ");
+        if self.options.color {
+            write_or_exit!(self.writer, "{}", color::FILENAME);
         }
-        _ => 0
-    };
-
-    if flags == 0 {
-        return false;
+        check_write(self.writer.write_all(&os_str_bytes(filename)));
+        if self.options.color {
+            write_or_exit!(self.writer, "{}", color::RESET);
+        }
+        if !details.is_empty() {
+            write_or_exit!(self.writer, " ({})", details.join(", "));
+        }
+        write_or_exit!(self.writer, " <==\n");
     }
 
-    // TODO check here, use flags maybe? Elf() type? is it complete match?
-    return matches!(section.kind(), object::SectionKind::Metadata)
-        || matches!(section.kind(), object::SectionKind::ReadOnlyData)
-        || matches!(section.kind(), object::SectionKind::Text);
-}
+    /**
+    Prints the line before the strings of a new section of the group.
+     */
+    fn start_section(&mut self, section: Option<&str>) {
+        if let Some(section) = section {
+            if self.group_section.as_deref() != Some(section) {
+                write_or_exit!(self.writer, "[{}]\n", section);
+                self.group_section = Some(section.to_string());
+            }
+        }
+    }
 
-fn print_strings(
-    filename: &str,
-    address: u64,
-    data: &mut dyn DataSource,
-    options: &Options,
-    writer: &mut dyn Write,
-) {
-    if !matches!(options.unicode_display, UnicodeDisplayKind::Default) {
-        print_unicode_buffer(filename, address, data, options, writer);
-        return;
+    /**
+    With `--group-by-file`, prints the number of strings of the last input. Called after the
+    last input.
+     */
+    pub fn finish(&mut self) {
+        if let Some(group_size) = self.group_size {
+            let plural = if group_size == 1 { "" } else { "s" };
+            write_or_exit!(self.writer, "-- {} string{}\n", group_size, plural);
+        }
     }
 
-    let mut search_start_address = address;
-    let mut buffer = Vec::<u8>::new();
+    /**
+    Prints the bytes around a string as `hexdump -C` does, 16 bytes per line. The lines are
+    marked with `-` after the address, like the context lines of grep.
+     */
+    fn print_context(&mut self, filename: &OsStr, address: u64, bytes: &[u8]) {
+        for (i, line) in bytes.chunks(CONTEXT_LINE_SIZE).enumerate() {
+            if self.options.print_filenames {
+                print_filename(filename, self.options, self.writer);
+            }
+            let line_address = address + (i * CONTEXT_LINE_SIZE) as u64;
+            print_address(line_address, self.address_width, self.options, self.writer);
+            write_or_exit!(self.writer, "- ");
 
-    // TODO split this giant method.
-    // current logic of this big loop:
-    // * Search for a matching sequence. Once found, we will have a sequence (content
-    // + start address + end address).
-    // * Print sequence start address
-    // * Print sequence content and continue to scan until wrong char found.
-    loop {
-        let mut current_address: u64;
+            for byte in line {
+                write_or_exit!(self.writer, "{:02x} ", byte);
+            }
+            let padding = 3 * (CONTEXT_LINE_SIZE - line.len());
+            write_or_exit!(self.writer, "{:padding$}|", "", padding = padding);
+            for byte in line {
+                let c = if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' };
+                write_or_exit!(self.writer, "{}", c);
+            }
+            write_or_exit!(self.writer, "|\n");
+        }
+    }
+}
 
-        if let Some(address) = find_matching_ascii_sequence(
-            search_start_address, data, &mut buffer, options,
-        ) {
-            search_start_address = address;
-            current_address = address + buffer.len() as u64;
-        } else {
-            return;
+impl StringSink for StringPrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if self.options.group_by_file {
+            self.start_section(found.section);
+            self.group_size = self.group_size.map(|group_size| group_size + 1);
         }
 
-        /* We found a run of `string_min' graphic characters.  Print up
-         to the next non-graphic character.  */
-        print_filename_and_address(filename, search_start_address, options, writer);
+        let context_address = found.address - found.context_before.len() as u64;
+        self.print_context(found.filename, context_address, found.context_before);
 
-        // continue until we find non-valid char
-        loop {
-            let (character, read) = match data.read_symbol(&options.encoding) {
-                Some(x) => x,
-                None => break
-            };
-            current_address += read as u64;
-            if character > 255 || !char_is_printable(character as u8 as char,
-                                                     options.encoding,
-                                                     options.include_all_whitespace) {
-                current_address -= read as u64;
-                data.seek_back(read);
-                break;
-            }
-            buffer.push(character as u8);
+        print_filename_and_address(
+            found.filename, found.address, self.address_width, self.options, self.writer,
+        );
+
+        if self.options.print_end_offset {
+            print_address(found.end_address, self.address_width, self.options, self.writer);
         }
 
-        if let Some(separator) = &options.output_separator {
-            buffer.extend_from_slice(separator.as_bytes());
-        } else {
-            buffer.push('\n' as u8);
+        if self.options.print_length {
+            write_or_exit!(self.writer, "{:5} ", found.end_address - found.address);
         }
 
-        std::io::copy(&mut buffer.as_slice(), writer);
-        buffer.clear();
+        if self.options.scans_multiple_encodings() {
+            write_or_exit!(self.writer, "[{}] ", found.encoding.name());
+        }
 
-        search_start_address = current_address;
-    }
-}
+        if let Some(key) = found.xor_key {
+            write_or_exit!(self.writer, "[xor:{:#04x}] ", key);
+        }
 
-/*
- Finds an ASCII sequence which is matching the min length criteria. It will be written to
- the buffer and start address will be returned.
- */
-fn find_matching_ascii_sequence(
-    start_address: u64,
-    data: &mut dyn DataSource,
-    buffer: &mut Vec<u8>,
-    options: &Options,
-) -> Option<u64> {
-    let mut search_start_address = start_address;
-    let mut current_address = start_address;
+        if let Some(kind) = found.decoded_from {
+            write_or_exit!(self.writer, "[{}] ", kind.name());
+        }
 
-    /* See if the next `string_min' chars are all graphic chars.  */
-    let mut should_retry = true;
+        if !found.tags.is_empty() {
+            write_or_exit!(self.writer, "{{{}}} ", found.tags.join(","));
+        }
 
-    while should_retry {
-        current_address = search_start_address;
-        should_retry = false;
+        if self.options.raw_output {
+            let bytes = found.original_bytes(self.options.decode_wide);
+            check_write(self.writer.write_all(&bytes));
+        } else {
+            check_write(self.writer.write_all(found.text));
+        }
 
-        if !buffer.is_empty() {
-            buffer.clear();
+        if let Some(separator) = &self.options.output_separator {
+            check_write(self.writer.write_all(separator));
+        } else {
+            write_or_exit!(self.writer, "\n");
         }
 
-        let mut i = 0u16;
-        while i < options.min_length {
-            let (character, read) = data.read_symbol(&options.encoding)?;
-            current_address += read as u64;
+        self.print_context(found.filename, found.end_address, found.context_after);
+    }
 
-            if character > 255 || !char_is_printable(character as u8 as char, options.encoding,
-                                                     options.include_all_whitespace) {
-                /* Found a non-graphic.  Try again starting with next byte.  */
-                search_start_address =
-                    current_address - (options.encoding.num_bytes() as u64 - 1);
-                data.seek_back(read - 1);
-                should_retry = true;
-                break;
-            }
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        check_write(self.writer.write_all(&os_str_bytes(filename)));
+        write_or_exit!(
+            self.writer, ": {} package {} {} {}\n",
+            package.format, package.name, package.version, package.architecture
+        );
+    }
+
+    /**
+    Unless `--compat-address-format` is used, the addresses are padded to the width of the
+    largest offset in the input, so that the strings stay aligned in big files. With
+    `--group-by-file`, the header of the input is printed.
+     */
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        if self.options.group_by_file {
+            self.start_group(filename, size);
+        }
 
-            // TODO wrong cast, symbol can be up to 4 bytes
-            buffer.push(character as u8);
+        if let Some(width) = self.options.address_width {
+            self.address_width = width;
+            return;
+        }
 
-            i += 1;
+        self.address_width = GNU_ADDRESS_WIDTH;
+        if let (Some(size), false) = (size, self.options.compat_address_format) {
+            let last_offset = size.saturating_sub(1);
+            let num_digits = match self.options.address_radix {
+                RadixKind::Oct => format!("{:o}", last_offset).len(),
+                RadixKind::Dec => format!("{}", last_offset).len(),
+                RadixKind::Hex => format!("{:x}", last_offset).len(),
+            };
+            self.address_width = self.address_width.max(num_digits);
         }
     }
+}
 
-    return Some(current_address - buffer.len() as u64);
+/**
+Passes every found string to all the inner sinks, in order.
+ */
+pub struct MultiSink<'a> {
+    sinks: Vec<&'a mut dyn StringSink>,
 }
 
-/*
-UTF-8 structure
+impl<'a> MultiSink<'a> {
+    pub fn new(sinks: Vec<&'a mut dyn StringSink>) -> MultiSink<'a> {
+        return MultiSink { sinks };
+    }
+}
 
-First code point 	Last code point 	Byte 1 	    Byte 2 	    Byte 3 	    Byte 4
-U+0000 	            U+007F 	            0xxxxxxx
-U+0080 	            U+07FF 	            110xxxxx 	10xxxxxx
-U+0800 	            U+FFFF 	            1110xxxx 	10xxxxxx 	10xxxxxx
-U+10000             U+10FFFF 	        11110xxx 	10xxxxxx 	10xxxxxx 	10xxxxxx
+impl StringSink for MultiSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        for sink in self.sinks.iter_mut() {
+            sink.on_string(found);
+        }
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        for sink in self.sinks.iter_mut() {
+            sink.on_package(filename, package);
+        }
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        for sink in self.sinks.iter_mut() {
+            sink.on_input(filename, size);
+        }
+    }
+}
+
+// endregion
+
+/**
+A found string which owns its contents, returned by `scan_bytes` and `scan_path`.
+ */
+#[derive(Clone, Debug)]
+pub struct ScannedString {
+    /// Offset of the string in the input.
+    pub offset: u64,
+    /// Number of bytes the string occupies in the input.
+    pub length: u64,
+    /// The string as decoded from the input, UTF-8 unless a legacy code page is scanned.
+    pub bytes: Vec<u8>,
+    pub encoding: EncodingKind,
+    pub termination: Termination,
+}
+
+impl ScannedString {
+    /**
+    Returns the string with the bytes which are not valid UTF-8 replaced by U+FFFD.
+     */
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        return String::from_utf8_lossy(&self.bytes);
+    }
+}
+
+impl From<&FoundString<'_>> for ScannedString {
+    fn from(found: &FoundString) -> Self {
+        return ScannedString {
+            offset: found.address,
+            length: found.end_address - found.address,
+            bytes: found.bytes.to_vec(),
+            encoding: found.encoding,
+            termination: found.termination,
+        };
+    }
+}
+
+/**
+A found string which borrows its contents from the scanned data, returned by `scan_slice`.
+ */
+#[derive(Copy, Clone, Debug)]
+pub struct StringMatch<'a> {
+    /// Offset of the string in the data.
+    pub offset: u64,
+    /// The string, the range of the data at `offset`.
+    pub bytes: &'a [u8],
+    pub encoding: EncodingKind,
+    pub termination: Termination,
+}
+
+impl<'a> StringMatch<'a> {
+    /**
+    Returns the string with the bytes which are not valid UTF-8 replaced by U+FFFD.
+     */
+    pub fn text(&self) -> std::borrow::Cow<'a, str> {
+        return String::from_utf8_lossy(self.bytes);
+    }
+
+    /**
+    Copies the string, e.g. to keep it after the data is gone.
+     */
+    pub fn into_owned(self) -> ScannedString {
+        return ScannedString {
+            offset: self.offset,
+            length: self.bytes.len() as u64,
+            bytes: self.bytes.to_vec(),
+            encoding: self.encoding,
+            termination: self.termination,
+        };
+    }
+}
+
+/**
+Collects the found strings.
+ */
+struct ScannedStringCollector {
+    found: Vec<ScannedString>,
+}
+
+impl StringSink for ScannedStringCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        self.found.push(ScannedString::from(found));
+    }
+}
+
+/**
+Collects the positions of the found strings, without their contents.
+ */
+struct RangeCollector {
+    found: Vec<(u64, u64, EncodingKind, Termination)>,
+}
+
+impl StringSink for RangeCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        self.found.push((found.address, found.end_address, found.encoding, found.termination));
+    }
+}
+
+/**
+Returns the strings found in the data with the default options: strings of at least 4 7-bit
+characters, anywhere in the data.
+ */
+pub fn scan_bytes(data: &[u8]) -> Vec<ScannedString> {
+    return scan_slice(data).into_iter().map(StringMatch::into_owned).collect();
+}
+
+/**
+Same as `scan_bytes`, but the strings are not copied: they are slices of the data. The data
+may be a memory-mapped file.
+ */
+pub fn scan_slice(data: &[u8]) -> Vec<StringMatch<'_>> {
+    let mut collector = RangeCollector { found: Vec::new() };
+    let mut byte_holder = ByteArrayHolder {
+        inner: data,
+        position: 0,
+    };
+    scan_strings(OsStr::new(""), 0, &mut byte_holder, &Options::default(), &mut collector);
+
+    // 7-bit strings are the same as their bytes in the data
+    return collector.found.into_iter().map(|(address, end_address, encoding, termination)| {
+        return StringMatch {
+            offset: address,
+            bytes: &data[address as usize..end_address as usize],
+            encoding,
+            termination,
+        };
+    }).collect();
+}
+
+/**
+Returns the strings found in the file with the default options, see `scan_bytes`. The file is
+read as a stream, so it may be larger than the memory.
+ */
+pub fn scan_path<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<ScannedString>> {
+    let path = path.as_ref();
+    let mut reader: ReaderWithSeek = BufReader::new(File::open(path)?).into();
+    let mut collector = ScannedStringCollector { found: Vec::new() };
+    scan_strings(path.as_os_str(), 0, &mut reader, &Options::default(), &mut collector);
+    return Ok(collector.found);
+}
+
+/**
+Scanner of a stream which arrives in chunks, for the sources which can't be read in a blocking
+loop, e.g. the async ones: the caller reads the chunks however it likes and feeds them one by
+one. It doesn't depend on a runtime; `extract_strings_async` drives it with a tokio reader.
+
+The strings are the same as the ones of `scan_bytes` with the options, wherever the chunks end.
+Only the encodings and the XOR keys of the options select the scanners; the expansion of the
+containers needs the whole input and isn't done.
+ */
+pub struct StreamScanner {
+    scanners: Vec<(Option<u8>, Box<dyn ByteScanner>)>,
+}
+
+impl StreamScanner {
+    pub fn new(options: &Options) -> StreamScanner {
+        let mut encodings = vec![options.encoding];
+        encodings.extend_from_slice(&options.extra_encodings);
+        let keys: Vec<Option<u8>> = if options.xor_keys.is_empty() {
+            vec![None]
+        } else {
+            options.xor_keys.iter().map(|key| Some(*key)).collect()
+        };
+
+        let mut scanners = Vec::new();
+        for key in &keys {
+            for encoding in &encodings {
+                scanners.push((*key, new_scanner(*encoding, options, 0)));
+            }
+            if options.detect_utf16 {
+                scanners.push((*key, new_utf16_detector(options, 0)));
+            }
+        }
+        return StreamScanner { scanners };
+    }
+
+    /**
+    Scans the next chunk of the stream and returns the strings which end in it.
+     */
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<ScannedString> {
+        let mut collector = ScannedStringCollector { found: Vec::new() };
+        for &byte in chunk {
+            for (key, scanner) in self.scanners.iter_mut() {
+                match key {
+                    None => scanner.push(byte, OsStr::new(""), &mut collector),
+                    Some(key) => scanner.push(
+                        if byte == 0 { 0 } else { byte ^ *key },
+                        OsStr::new(""),
+                        &mut XorKeySink::new(*key, &mut collector),
+                    ),
+                };
+            }
+        }
+        return collector.found;
+    }
+
+    /**
+    Ends the stream and returns the strings which were still in progress.
+     */
+    pub fn finish(mut self) -> Vec<ScannedString> {
+        let mut collector = ScannedStringCollector { found: Vec::new() };
+        for (key, scanner) in self.scanners.iter_mut() {
+            match key {
+                None => scanner.finish(OsStr::new(""), &mut collector),
+                Some(key) => scanner.finish(
+                    OsStr::new(""), &mut XorKeySink::new(*key, &mut collector)
+                ),
+            };
+        }
+        return collector.found;
+    }
+}
+
+/**
+Reads the async source to its end and returns its strings, as `StreamScanner` finds them with
+the options. The source is read in chunks, so the runtime thread is blocked only while a chunk
+is scanned.
+ */
+#[cfg(feature = "tokio")]
+pub async fn extract_strings_async<R: tokio::io::AsyncRead + Unpin>(
+    mut reader: R,
+    options: &Options,
+) -> std::io::Result<Vec<ScannedString>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut scanner = StreamScanner::new(options);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut found = Vec::new();
+    loop {
+        let size = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        found.extend(scanner.feed(&buffer[..size]));
+    }
+    found.extend(scanner.finish());
+    return Ok(found);
+}
+
+/**
+Returns the number of strings found, or `None` if the file couldn't be scanned.
+ */
+pub fn print_strings_for_file(
+    file_path_str: &OsStr,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let file_path = Path::new(file_path_str);
+
+    if !file_path.exists() {
+        diagnostics::error(format_args!("{:?}: No such file", file_path_str));
+        return None;
+    }
+
+    if file_path.is_dir() {
+        diagnostics::warning(format_args!("'{}' is a directory", file_path.display()));
+        return None;
+    }
+
+    if options.expand_packages {
+        if let Some(num_found) = packages::print_strings_for_package(file_path, options, sink) {
+            return Some(num_found);
+        }
+    }
+
+    if options.expand_archives {
+        if let Some(num_found) = archives::print_strings_for_archive(file_path, options, sink) {
+            return Some(num_found);
+        }
+    }
+
+    if options.decompress {
+        if let Some(compression) = compression_of_file(file_path) {
+            return match File::open(file_path) {
+                Ok(file) => print_strings_for_compressed(
+                    file_path_str, compression, Box::new(BufReader::new(file)), options, sink,
+                ),
+                Err(err) => {
+                    diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+                    None
+                }
+            };
+        }
+    }
+
+    let filename = file_path_str;
+    sink.on_input(filename, file_path.metadata().ok().map(|metadata| metadata.len()));
+
+    if options.partitions {
+        let found_in_partitions = partitions::print_strings_for_partitions(file_path, options, sink);
+        if let Some(num_found) = found_in_partitions {
+            return Some(num_found);
+        }
+    }
+
+    if options.firmware {
+        if let Some(num_found) = firmware::print_strings_for_firmware(file_path, options, sink) {
+            return Some(num_found);
+        }
+    }
+
+    if options.reads_format() {
+        if let Some(num_found) = print_strings_for_format_file(file_path, options, sink) {
+            return Some(num_found);
+        }
+    }
+
+    if options.datasection_only || options.segments_only || options.debug_strings {
+        if let Some(num_found) = print_strings_for_object_file(file_path, options, sink) {
+            return Some(num_found);
+        }
+    }
+
+    if options.carve {
+        return match fs::read(file_path) {
+            Ok(data) => Some(carve::print_strings_for_blob(filename, &data, options, sink)),
+            Err(err) => {
+                diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+                None
+            }
+        };
+    }
+
+    if options.prints_context() {
+        return match fs::read(file_path) {
+            Ok(data) => Some(scan_strings_in_memory(filename, 0, &data, options, sink)),
+            Err(err) => {
+                diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+                None
+            }
+        };
+    }
+
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(err) => {
+            diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+            return None;
+        }
+    };
+    if let Ok(metadata) = file.metadata() {
+        if !options.follow && sparse::is_sparse(&metadata) {
+            if let Some(regions) = sparse::data_regions(&file, metadata.len()) {
+                return scan_sparse_file(file_path, &file, metadata.len(), &regions, options, sink);
+            }
+        }
+    }
+
+    let mut reader: ReaderWithSeek = match file.metadata() {
+        _ if options.follow => {
+            BufReader::new(FollowReader::new(file, file_path.display().to_string())).into()
+        }
+        // devices have no size in the metadata, and are read in large chunks
+        Ok(metadata) if sparse::is_block_device(&metadata) => {
+            match sparse::device_size(&file) {
+                Ok(size) if options.progress => {
+                    let name = file_path.display().to_string();
+                    let progress = ProgressReader::new(file, name, size);
+                    BufReader::with_capacity(LARGE_BUFFER_SIZE, progress).into()
+                }
+                _ => BufReader::with_capacity(LARGE_BUFFER_SIZE, file).into(),
+            }
+        }
+        // pipes and devices have no size to show the progress against
+        Ok(metadata) if options.progress && metadata.is_file() => {
+            let name = file_path.display().to_string();
+            BufReader::new(ProgressReader::new(file, name, metadata.len())).into()
+        }
+        _ => BufReader::new(file).into(),
+    };
+
+    let num_found = scan_strings(filename, 0, &mut reader, options, sink);
+
+    // the strings found before the error are printed, but the file counts as failed
+    if let Some(err) = reader.error {
+        diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+        return None;
+    }
+    return Some(num_found);
+}
+
+/**
+Scans the data regions of a sparse file, skipping its holes. A few bytes of the hole after
+every region are read too, so that the strings running up to it end as in the whole file.
+ */
+fn scan_sparse_file(
+    file_path: &Path,
+    file: &File,
+    size: u64,
+    regions: &[(u64, u64)],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let filename = file_path.as_os_str();
+    let report = |err: &std::io::Error| {
+        diagnostics::error(format_args!("{}: {}", file_path.display(), err));
+    };
+    // the progress is the one of the data, the holes take no time
+    let data_size = regions.iter().map(|(start, end)| end - start).sum();
+    let mut source: Box<dyn Read + '_> = if options.progress {
+        Box::new(ProgressReader::new(file, file_path.display().to_string(), data_size))
+    } else {
+        Box::new(file)
+    };
+
+    let mut num_found = 0u64;
+    for (start, end) in regions {
+        let padded_end = (end + HOLE_PADDING).min(size);
+        if let Err(err) = (&*file).seek(SeekFrom::Start(*start)) {
+            report(&err);
+            return None;
+        }
+        let region = (&mut source).take(padded_end - start);
+        let region = BufReader::with_capacity(LARGE_BUFFER_SIZE, region);
+        let mut reader = ReaderWithSeek::new(Box::new(region));
+        num_found += scan_strings(filename, *start, &mut reader, options, sink);
+        if let Some(err) = &reader.error {
+            report(err);
+            return None;
+        }
+    }
+    return Some(num_found);
+}
+
+/**
+Returns the number of strings found, or `None` if stdin couldn't be read. `name` is used in
+place of the file name, `<stdin>` on the command line unless `--label` sets it.
+ */
+pub fn print_strings_for_stdin(
+    name: &OsStr,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let stdin = stdin();
+    let filename = name;
+    // the bytes peeked at for the compression stay in the buffer, so the scan reads them again
+    let mut input = BufReader::new(stdin.lock());
+
+    if options.decompress {
+        let compression = match input.fill_buf() {
+            Ok(magic) => Compression::detect(magic),
+            Err(err) => {
+                diagnostics::error(format_args!("{}: {}", filename.to_string_lossy(), err));
+                return None;
+            }
+        };
+        if let Some(compression) = compression {
+            return print_strings_for_compressed(
+                filename, compression, Box::new(input), options, sink,
+            );
+        }
+    }
+
+    sink.on_input(filename, None);
+
+    if options.prints_context() || options.carve {
+        let mut data = Vec::<u8>::new();
+        if let Err(err) = input.read_to_end(&mut data) {
+            diagnostics::error(format_args!("{}: {}", filename.to_string_lossy(), err));
+            return None;
+        }
+        if options.carve {
+            return Some(carve::print_strings_for_blob(filename, &data, options, sink));
+        }
+        return Some(scan_strings_in_memory(filename, 0, &data, options, sink));
+    }
+
+    let mut reader: ReaderWithSeek = input.into();
+    return finish_stream_scan(filename, &mut reader, options, sink);
+}
+
+/**
+Returns the compression of the file if it starts with a known magic number.
+ */
+fn compression_of_file(file_path: &Path) -> Option<Compression> {
+    let mut magic = [0u8; 8];
+    let num_read = File::open(file_path).and_then(|mut file| file.read(&mut magic)).ok()?;
+    return Compression::detect(&magic[..num_read]);
+}
+
+/**
+Scans the decompressed data, see `--decompress`, as `name[compression]`. The data is
+decompressed while it is scanned, unless the scan needs it in memory as a whole.
+ */
+fn print_strings_for_compressed<'a>(
+    name: &OsStr,
+    compression: Compression,
+    mut input: Box<dyn BufRead + 'a>,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let mut decompressed_name = name.to_os_string();
+    decompressed_name.push(format!("[{}]", compression.name()));
+    let name = decompressed_name.as_os_str();
+
+    if options.scans_in_memory() {
+        let mut compressed = Vec::new();
+        if let Err(err) = input.read_to_end(&mut compressed) {
+            diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+            return None;
+        }
+        return match decompress::decompress(compression, &compressed, &options.container_limits) {
+            Ok(data) => Some(print_strings_for_bytes(name, &data, options, sink)),
+            Err(ExpandError::Limit(limit)) => {
+                limits::warn(&name.to_string_lossy(), &limit);
+                None
+            }
+            Err(ExpandError::Invalid(err)) => {
+                diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err));
+                None
+            }
+        };
+    }
+
+    let decompressed = match decompress::decompressing_reader(
+        compression, input, &options.container_limits,
+    ) {
+        Ok(decompressed) => decompressed,
+        Err(err) => {
+            match limits::limit_of(&err) {
+                Some(limit) => limits::warn(&name.to_string_lossy(), &limit),
+                None => diagnostics::error(format_args!(
+                    "{}: {} decompression failed: {}",
+                    name.to_string_lossy(), compression.name(), err
+                )),
+            }
+            return None;
+        }
+    };
+    sink.on_input(name, None);
+    let mut reader = ReaderWithSeek::new(Box::new(BufReader::new(decompressed)));
+    return finish_stream_scan(name, &mut reader, options, sink);
+}
+
+/**
+Scans a stream to the end, reporting the error which stopped the reading, as a warning if it is
+a limit of the decompression. Returns `None` if there was one, the strings found before it are
+printed all the same.
+ */
+fn finish_stream_scan(
+    name: &OsStr,
+    reader: &mut ReaderWithSeek,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let num_found = scan_strings(name, 0, reader, options, sink);
+    if let Some(err) = &reader.error {
+        match limits::limit_of(err) {
+            Some(limit) => limits::warn(&name.to_string_lossy(), &limit),
+            None => diagnostics::error(format_args!("{}: {}", name.to_string_lossy(), err)),
+        }
+        return None;
+    }
+    return Some(num_found);
+}
+
+/**
+Scans the data of the reader, e.g. a region of the memory of a process, with the strings
+addressed from `address`. Returns the number of the strings found and the error which ended the
+reading early, if any; the strings found before it are printed all the same.
+ */
+pub(crate) fn print_strings_for_reader(
+    name: &OsStr,
+    address: u64,
+    size: Option<u64>,
+    reader: &mut dyn Read,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> (u64, Option<std::io::Error>) {
+    sink.on_input(name, size);
+
+    if options.prints_context() {
+        let mut data = Vec::<u8>::new();
+        let error = reader.read_to_end(&mut data).err();
+        return (scan_strings_in_memory(name, address, &data, options, sink), error);
+    }
+
+    let mut reader = ReaderWithSeek::new(Box::new(reader));
+    let num_found = scan_strings(name, address, &mut reader, options, sink);
+    return (num_found, reader.error);
+}
+
+/**
+Scans the data which is already in memory, e.g. received over the network. `name` is used
+in place of the file name.
+ */
+pub fn print_strings_for_bytes(
+    name: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    sink.on_input(name, Some(data.len() as u64));
+
+    if options.reads_format() {
+        if let Some(num_found) = print_strings_for_format_data(name, data, options, sink) {
+            return num_found;
+        }
+    }
+
+    if options.datasection_only || options.segments_only || options.debug_strings {
+        if let Some(num_found) = print_strings_for_object_data(name, data, options, sink) {
+            return num_found;
+        }
+    }
+
+    if options.carve {
+        return carve::print_strings_for_blob(name, data, options, sink);
+    }
+
+    return scan_strings_in_memory(name, 0, data, options, sink);
+}
+
+/**
+Scans the data which is in memory, attaching the bytes around the strings to them if the
+context is printed.
+ */
+pub(crate) fn scan_strings_in_memory(
+    filename: &OsStr,
+    address: u64,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let mut byte_holder = ByteArrayHolder {
+        inner: data,
+        position: 0,
+    };
+    if !options.prints_context() {
+        return scan_strings(filename, address, &mut byte_holder, options, sink);
+    }
+
+    let mut with_context = ContextSink {
+        data,
+        address,
+        before: options.context_before,
+        after: options.context_after,
+        inner: sink,
+    };
+    return scan_strings(filename, address, &mut byte_holder, options, &mut with_context);
+}
+
+/**
+Returns the number of strings found in the data sections, or `None` if the file is not an
+object or has no data sections.
+ */
+fn print_strings_for_object_file(
+    file_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    return match std::fs::read(file_path) {
+        Ok(data) => {
+            if object::File::parse(&*data).is_ok() {
+                print_strings_for_object_data(file_path.as_os_str(), &data, options, sink)
+            } else {
+                diagnostics::warning(format_args!(
+                    "'{}' is not an object, scanned as a whole", file_path.display()
+                ));
+                None
+            }
+        }
+        Err(err) => {
+            diagnostics::warning(format_args!(
+                "could not open '{}'.  reason: {}", file_path.display(), err
+            ));
+            None
+        }
+    };
+}
+
+/**
+Returns the number of strings read from the structures of the file format, or `None` if the file
+is not of the format or doesn't have them.
+ */
+fn print_strings_for_format_file(
+    file_path: &Path,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let data = match std::fs::read(file_path) {
+        Ok(data) => data,
+        Err(err) => {
+            diagnostics::warning(format_args!(
+                "could not open '{}'.  reason: {}", file_path.display(), err
+            ));
+            return None;
+        }
+    };
+    let num_found = print_strings_for_format_data(file_path.as_os_str(), &data, options, sink);
+    if num_found.is_none() {
+        let missing = if options.dotnet_metadata {
+            ".NET metadata"
+        } else if options.go_strings {
+            "Go strings"
+        } else if options.java_strings {
+            "Java class or DEX strings"
+        } else if options.macho_literals {
+            "Mach-O literal sections"
+        } else if options.overlay_only {
+            "PE or ELF image"
+        } else if options.hex_records {
+            "Intel HEX or S-records"
+        } else if options.pdf_objects {
+            "PDF objects"
+        } else if options.stack_strings {
+            "x86 code"
+        } else {
+            "PE string resources"
+        };
+        diagnostics::warning(format_args!(
+            "'{}' has no {}, scanned as a whole", file_path.display(), missing
+        ));
+    }
+    return num_found;
+}
+
+/**
+Reads the strings of a PE file from its resources, see `--pe-resources`, or from its .NET
+metadata, see `--dotnet`, the strings a Go binary refers to, see `--go`, the string pool of a
+Java class or DEX file, see `--java`, or the literals of a Mach-O file, see `--macho-literals`.
+Scans the overlay of a PE or ELF file with `--overlay`, the image of Intel HEX or S-records
+with `--hex-records` and the objects of a PDF file with `--pdf`. Puts together the stack strings
+of x86 code with `--stack-strings`.
+ */
+fn print_strings_for_format_data(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    if options.dotnet_metadata {
+        return dotnet::print_strings_for_dotnet_metadata(filename, data, options, sink);
+    }
+    if options.stack_strings {
+        return stack::print_strings_for_stack_strings(filename, data, options, sink);
+    }
+    if options.go_strings {
+        return golang::print_strings_for_go_binary(filename, data, options, sink);
+    }
+    if options.java_strings {
+        return java::print_strings_for_java(filename, data, options, sink);
+    }
+    if options.macho_literals {
+        return macho_literals::print_strings_for_macho_literals(filename, data, options, sink);
+    }
+    if options.overlay_only {
+        let start = overlay::overlay_start(data)?;
+        return Some(scan_strings_in_memory(
+            filename,
+            start,
+            &data[start as usize..], options,
+            &mut OverlaySink { start, inner: sink },
+        ));
+    }
+    if options.hex_records {
+        let blocks = match records::decode(data)? {
+            Ok(blocks) => blocks,
+            Err(err) => {
+                diagnostics::warning(format_args!(
+                    "'{}': {}", Path::new(filename).display(), err
+                ));
+                return None;
+            }
+        };
+        let mut num_found = 0u64;
+        for block in blocks {
+            num_found += scan_strings_in_memory(
+                filename, block.address, &block.data, options, sink,
+            );
+        }
+        return Some(num_found);
+    }
+    if options.pdf_objects {
+        let document = pdf::parse(data, &options.container_limits)?;
+        for problem in &document.problems {
+            diagnostics::warning(format_args!(
+                "'{}': {}", Path::new(filename).display(), problem
+            ));
+        }
+        let mut num_found = 0u64;
+        for part in document.parts {
+            num_found += scan_strings_in_memory(
+                filename, part.address, &part.data, options,
+                &mut ObjectSink { object: part.object, inner: sink },
+            );
+        }
+        return Some(num_found);
+    }
+    return resources::print_strings_for_pe_resources(filename, data, options, sink);
+}
+
+/**
+Scans the data sections of an object file, its segments with `--segments` or its DWARF strings
+with `--debug-strings`. Returns `None` if the data is not an object file or has no data
+sections.
+ */
+fn print_strings_for_object_data(
+    filename: &OsStr,
+    data: &[u8],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let object = object::File::parse(data).ok()?;
+    // a core dump has no sections, its segments are the memory of the process
+    let core_mappings = coredump::file_mappings(data);
+    if options.segments_only || options.datasection_only && core_mappings.is_some() {
+        let mappings = core_mappings.unwrap_or_default();
+        return print_strings_for_object_segments(filename, &object, &mappings, options, sink);
+    }
+    if options.debug_strings {
+        return Some(dwarf::print_strings_for_debug_sections(filename, &object, options, sink));
+    }
+
+    let mut num_found: Option<u64> = None;
+    for section in object.sections() {
+        if let Some(found_in_section) = print_strings_for_object_section(
+            filename, &section, options, sink,
+        ) {
+            num_found = Some(num_found.unwrap_or(0) + found_in_section);
+        }
+    }
+    return num_found;
+}
+
+/**
+Scans the loadable segments of an object file, the data which ends up in memory: the PT_LOAD
+segments of an ELF, the segments of a Mach-O. Unlike the sections, they are kept in the
+stripped binaries. The strings of a core dump are tagged with the files mapped at their
+segments. Returns `None` if no segment has data in the file.
+ */
+fn print_strings_for_object_segments(
+    filename: &OsStr,
+    object: &object::File,
+    mappings: &[coredump::FileMapping],
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    let mut num_found: Option<u64> = None;
+    for segment in object.segments() {
+        // the segments of the zero-initialized data have nothing in the file
+        let data = match segment.data() {
+            Ok(data) if !data.is_empty() => data,
+            _ => continue,
+        };
+        let mapping = mappings.iter().find(|mapping| {
+            return (mapping.start..mapping.end).contains(&segment.address());
+        });
+        let mut mapped_file;
+        let sink: &mut dyn StringSink = match mapping {
+            Some(mapping) => {
+                mapped_file = MappedFileSink { path: &mapping.path, inner: sink };
+                &mut mapped_file
+            }
+            None => sink,
+        };
+        let found_in_segment = scan_strings_in_memory(
+            filename,
+            segment.address(),
+            data, options,
+            &mut SectionSink { section: segment.name().ok().flatten(), inner: sink },
+        );
+        num_found = Some(num_found.unwrap_or(0) + found_in_segment);
+    }
+    return num_found;
+}
+
+fn print_strings_for_object_section(
+    filename: &OsStr,
+    section: &Section,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> Option<u64> {
+    if !is_data_section(section) || section.size() == 0 {
+        return None;
+    }
+
+    if let Ok(compressed_data) = section.compressed_data() {
+        let num_found = scan_strings_in_memory(
+            filename,
+            section.address(),
+            compressed_data.data, options,
+            &mut SectionSink { section: section.name().ok(), inner: sink },
+        );
+        return Some(num_found);
+    }
+
+    return None;
+}
+
+/**
+Attaches the name of the section to the strings found in it, and reports the strings running
+up to the end of the data as ending with the section.
+ */
+struct SectionSink<'a> {
+    section: Option<&'a str>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for SectionSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut found = found.with_tags(found.tags);
+        found.section = self.section;
+        if found.termination == Termination::Eof {
+            found.termination = Termination::SectionEnd;
+        }
+        self.inner.on_string(&found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Passes on only the strings followed by a NUL or a newline, which drops most of the printable
+runs inside the machine code.
+ */
+pub struct TerminatorFilter<'a> {
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> TerminatorFilter<'a> {
+    pub fn new(inner: &'a mut dyn StringSink) -> TerminatorFilter<'a> {
+        return TerminatorFilter { inner };
+    }
+}
+
+impl StringSink for TerminatorFilter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        if !found.termination.is_terminator() {
+            return;
+        }
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+/**
+Attaches the bytes around every string to it, taking them from `data` which starts at
+`address`.
+ */
+struct ContextSink<'a> {
+    data: &'a [u8],
+    address: u64,
+    before: usize,
+    after: usize,
+    inner: &'a mut dyn StringSink,
+}
+
+impl StringSink for ContextSink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let end = min((found.end_address - self.address) as usize, self.data.len());
+        let start = min((found.address - self.address) as usize, end);
+
+        let mut found = found.with_tags(found.tags);
+        found.context_before = &self.data[start.saturating_sub(self.before)..start];
+        found.context_after = &self.data[end..min(end + self.after, self.data.len())];
+        self.inner.on_string(&found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+fn is_data_section(section: &Section) -> bool {
+    let flags = match section.flags() {
+        SectionFlags::Elf { sh_flags } => {
+            sh_flags
+        }
+        SectionFlags::MachO { flags } => {
+            return macho_section_flags(flags) & DATA_FLAGS == DATA_FLAGS;
+        }
+        SectionFlags::Coff { characteristics } => {
+            characteristics as u64
+        }
+        _ => 0
+    };
+
+    if flags == 0 {
+        return false;
+    }
+
+    // TODO check here, use flags maybe? Elf() type? is it complete match?
+    return matches!(section.kind(), object::SectionKind::Metadata)
+        || matches!(section.kind(), object::SectionKind::ReadOnlyData)
+        || matches!(section.kind(), object::SectionKind::Text);
+}
+
+/**
+Translates the flags of a Mach-O section into the ones BFD gives it: the sections of the debug
+segment are not loaded and the zero-filled ones have nothing in the file. All the others are
+scanned with `-d`, the code and the data alike, as GNU strings does.
+ */
+fn macho_section_flags(flags: u32) -> u64 {
+    let mut section_flags = SEC_ALLOC;
+    if flags & macho::S_ATTR_DEBUG != 0 {
+        section_flags &= !SEC_ALLOC;
+    }
+    let is_zero_filled = matches!(
+        flags & macho::SECTION_TYPE,
+        macho::S_ZEROFILL | macho::S_GB_ZEROFILL | macho::S_THREAD_LOCAL_ZEROFILL
+    );
+    if !is_zero_filled {
+        section_flags |= SEC_HAS_CONTENTS;
+        if section_flags & SEC_ALLOC != 0 {
+            section_flags |= SEC_LOAD;
+        }
+    }
+    return section_flags;
+}
+
+/**
+Returns the number of strings found. The data is watched by the observer of the options, if any.
  */
-fn print_unicode_buffer(
-    filename: &str,
+fn scan_strings(
+    filename: &OsStr,
     address: u64,
     data: &mut dyn DataSource,
     options: &Options,
-    writer: &mut dyn Write,
-) {
-    if !matches!(options.encoding, EncodingKind::Bit8) {
-        eprintln!("ICE: bad arguments to print_unicode_buffer");
-        return;
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let Some(observer) = &options.observer else {
+        return scan_data(filename, address, data, options, sink);
+    };
+
+    let mut observed = ObservedSource {
+        inner: data,
+        observer: observer.as_ref(),
+        position: 0,
+        next_check: 0,
+        cancelled: false,
+    };
+    let num_found = scan_data(filename, address, &mut observed, options, sink);
+    observer.progress(observed.position);
+    return num_found;
+}
+
+fn scan_data(
+    filename: &OsStr,
+    address: u64,
+    data: &mut dyn DataSource,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    if !matches!(options.unicode_display, UnicodeDisplayKind::Default) {
+        return scan_unicode_buffer(filename, address, data, options, sink);
     }
 
-    let mut current_address = address;
+    if !options.pascal.is_empty() {
+        let mut scanner = PascalScanner::new(options, address);
+        let mut num_found = 0u64;
+        while let Some(byte) = data.read_byte() {
+            num_found += scanner.push(byte, filename, sink);
+        }
+        return num_found + scanner.finish(filename, sink);
+    }
+
+    if options.smart_encoding {
+        return scan_strings_smart_encoding(filename, address, data, options, sink);
+    }
+
+    if options.scans_multiple_encodings() || !options.xor_keys.is_empty() {
+        return scan_strings_multiple_encodings(filename, address, data, options, sink);
+    }
+
+    let mut search_start_address = address;
+    let mut buffer = Vec::<u8>::new();
+    let mut num_found = 0u64;
 
+    // TODO split this giant method.
+    // current logic of this big loop:
+    // * Search for a matching sequence. Once found, we will have a sequence (content
+    // + start address + end address).
+    // * Continue to scan until wrong char found.
+    // * Pass the sequence to the sink.
     loop {
+        let mut current_address: u64;
 
-        let sequence_start_address_offset = match find_matching_unicode_sequence(
-            data, options
+        if let Some((start_address, end_address)) = find_matching_ascii_sequence(
+            search_start_address, data, &mut buffer, options,
         ) {
-            Some(offset) => offset,
-            None => return
-        };
+            search_start_address = start_address;
+            current_address = end_address;
+        } else {
+            return num_found;
+        }
 
-        print_filename_and_address(
+        /* We found a run of `string_min' graphic characters.  Collect up
+         to the next non-graphic character.  */
+        let mut termination = Termination::Eof;
+        let mut truncated = false;
+        while let Some(symbol) = read_printable_symbol(data, &mut buffer, options) {
+            match symbol {
+                Ok(read) => current_address += read as u64,
+                Err((read, terminator)) => {
+                    data.seek_back(read as usize);
+                    termination = terminator;
+                    truncated = read < options.encoding.num_bytes();
+                    break;
+                }
+            }
+        }
+
+        if truncated && options.strict_units {
+            buffer.clear();
+            search_start_address = current_address;
+            continue;
+        }
+
+        sink.on_string(&FoundString {
             filename,
-            current_address + sequence_start_address_offset as u64,
-            options,
-            writer,
+            address: search_start_address,
+            end_address: current_address,
+            bytes: &buffer,
+            text: &buffer,
+            encoding: options.encoding,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        });
+        buffer.clear();
+        num_found += 1;
+
+        search_start_address = current_address;
+    }
+}
+
+/**
+Scans every region of the data with the encoding guessed for it, the adjacent regions in the
+same encoding are scanned as one. The choices are reported on stderr.
+Returns the number of strings found.
+ */
+fn scan_strings_smart_encoding(
+    filename: &OsStr,
+    address: u64,
+    data: &mut dyn DataSource,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let report = |encoding: EncodingKind, start: u64, end: u64| {
+        eprintln!(
+            "{}: {:#x}-{:#x}: scanned as {}",
+            Path::new(filename).display(), start, end, encoding.name()
         );
+    };
 
-        /* We have found string_min characters.  Display them and any
-       more that follow.  */
-        let mut offset = sequence_start_address_offset;
-        loop {
-            let c = match data.read_byte() {
-                Some(x) => x,
-                None => return
-            };
+    let mut num_found = 0u64;
+    let mut region = Vec::with_capacity(smart::REGION_SIZE);
+    let mut region_address = address;
+    // scanner of the current span of regions in the same encoding, with its encoding and start
+    let mut current: Option<(Box<dyn ByteScanner>, EncodingKind, u64)> = None;
 
-            let mut char_len = 1;
+    loop {
+        region.clear();
+        while region.len() < smart::REGION_SIZE {
+            match data.read_byte() {
+                Some(byte) => region.push(byte),
+                None => break
+            }
+        }
+        if region.is_empty() {
+            break;
+        }
 
-            if !char_is_printable(c as char, options.encoding, options.include_all_whitespace) {
-                data.seek_back(1);
-                break;
-            } else if c < 127 {
-                write_or_panic!(writer, "{}", c as char);
-            } else {
-                data.seek_back(1);
-                let maybe_utf8 = match data.read_unicode() {
-                    Some(x) => x,
-                    None => return
-                };
-                if is_valid_utf8(&maybe_utf8) == 0 {
-                    data.seek_back(maybe_utf8.len() as u8);
-                    break;
-                } else if matches!(options.unicode_display, UnicodeDisplayKind::Invalid) {
-                    data.seek_back(maybe_utf8.len() as u8);
-                    break;
-                } else {
-                    char_len = display_utf8_char(
-                        &maybe_utf8,
-                        options.unicode_display,
-                        writer,
-                    );
-                    if char_len != maybe_utf8.len() as u8 {
-                        data.seek_back(maybe_utf8.len() as u8 - char_len);
-                    }
+        let encoding = smart::guess_encoding(&region);
+        match &current {
+            Some((_, current_encoding, _)) if *current_encoding == encoding => {}
+            _ => {
+                if let Some((mut scanner, current_encoding, start)) = current.take() {
+                    num_found += scanner.finish(filename, sink);
+                    report(current_encoding, start, region_address);
                 }
+                current = Some((new_scanner(encoding, options, region_address), encoding, region_address));
             }
-            offset += char_len as usize;
         }
 
-        if let Some(separator) = &options.output_separator {
-            write_or_panic!(writer, "{}", separator.as_str());
-        } else {
-            write_or_panic!(writer, "\n");
+        let (scanner, _, _) = current.as_mut().unwrap();
+        for byte in &region {
+            num_found += scanner.push(*byte, filename, sink);
         }
+        region_address += region.len() as u64;
+    }
 
-        current_address += offset as u64;
+    if let Some((mut scanner, encoding, start)) = current {
+        num_found += scanner.finish(filename, sink);
+        report(encoding, start, region_address);
     }
+
+    return num_found;
 }
 
-fn find_matching_unicode_sequence(
+/**
+Scans the data for all the requested encodings and XOR keys in a single pass.
+Returns the number of strings found.
+ */
+fn scan_strings_multiple_encodings(
+    filename: &OsStr,
+    address: u64,
     data: &mut dyn DataSource,
     options: &Options,
-) -> Option<usize> {
-    /* We must only display strings that are at least string_min *characters*
-   long.  So we scan the buffer in two stages.  First we locate the start
-   of a potential string.  Then we walk along it until we have found
-   string_min characters.  Then we go back to the start point and start
-   displaying characters according to the unicode_display setting.  */
+    sink: &mut dyn StringSink,
+) -> u64 {
+    let mut encodings = vec![options.encoding];
+    encodings.extend_from_slice(&options.extra_encodings);
+
+    // a scanner per encoding and XOR key, if any
+    let keys: Vec<Option<u8>> = if options.xor_keys.is_empty() {
+        vec![None]
+    } else {
+        options.xor_keys.iter().map(|key| Some(*key)).collect()
+    };
+    let mut scanners = Vec::new();
+    for key in &keys {
+        for encoding in &encodings {
+            scanners.push((*key, new_scanner(*encoding, options, address)));
+        }
+        if options.detect_utf16 {
+            scanners.push((*key, new_utf16_detector(options, address)));
+        }
+    }
 
-    let mut sequence_start_address_offset = 0usize;
-    let mut address_offset = 0usize;
-    let mut num_found = 0u16;
+    let mut num_found = 0u64;
+
+    while let Some(byte) = data.read_byte() {
+        for (key, scanner) in scanners.iter_mut() {
+            num_found += match key {
+                None => scanner.push(byte, filename, sink),
+                // NUL bytes are padding rather than a part of the encoded data
+                Some(key) => scanner.push(
+                    if byte == 0 { 0 } else { byte ^ *key },
+                    filename,
+                    &mut XorKeySink::new(*key, sink),
+                ),
+            };
+        }
+    }
 
-    loop {
-        let c = data.read_byte()?;
+    for (key, scanner) in scanners.iter_mut() {
+        num_found += match key {
+            None => scanner.finish(filename, sink),
+            Some(key) => scanner.finish(filename, &mut XorKeySink::new(*key, sink)),
+        };
+    }
 
-        let mut char_len = 1;
+    return num_found;
+}
 
-        /* Find the first potential character of a string.  */
-        if !char_is_printable(c as char, options.encoding, options.include_all_whitespace) {
-            num_found = 0;
-            address_offset += 1 as usize;
-            continue;
+/*
+ Finds an ASCII sequence which is matching the min length criteria. It will be written to
+ the buffer and its start and end addresses will be returned.
+ */
+fn find_matching_ascii_sequence(
+    start_address: u64,
+    data: &mut dyn DataSource,
+    buffer: &mut Vec<u8>,
+    options: &Options,
+) -> Option<(u64, u64)> {
+    let mut search_start_address = start_address;
+    let mut current_address = start_address;
+
+    /* See if the next `string_min' chars are all graphic chars.  */
+    let mut should_retry = true;
+
+    while should_retry {
+        current_address = search_start_address;
+        should_retry = false;
+
+        if !buffer.is_empty() {
+            buffer.clear();
         }
 
-        if c > 126 {
-            if c < 0xc0 {
-                num_found = 0;
-                address_offset += 1 as usize;
-                continue;
+        let mut i = 0u16;
+        while i < options.min_length {
+            let (printable, read) = match read_printable_symbol(data, buffer, options)? {
+                Ok(read) => (true, read),
+                Err((read, _)) => (false, read),
+            };
+            current_address += read as u64;
+
+            if !printable {
+                if options.decode_wide && options.encoding.num_bytes() > 1 {
+                    /* Found a non-graphic.  Try again starting with next code unit, as
+                     any misaligned unit is likely to be a valid character.  */
+                    search_start_address = current_address;
+                } else {
+                    /* Found a non-graphic.  Try again starting with next byte.  */
+                    search_start_address = current_address - (read as u64 - 1);
+                    data.seek_back(read as usize - 1);
+                }
+                should_retry = true;
+                break;
             }
 
-            data.seek_back(1);
+            i += 1;
+        }
+    }
 
-            let maybe_utf8 = data.read_unicode()?;
+    return Some((search_start_address, current_address));
+}
 
-            char_len = is_valid_utf8(&maybe_utf8);
-            if char_len == 0 {
-                num_found = 0;
-                address_offset += 1;
-                data.seek_back(maybe_utf8.len() as u8 - 1);
-                continue;
-            }
+/**
+Reads the next symbol and appends it to the buffer if it is printable. Returns the number of
+bytes read, as an error with the way the string ends if the symbol is not printable.
+ */
+fn read_printable_symbol(
+    data: &mut dyn DataSource,
+    buffer: &mut Vec<u8>,
+    options: &Options,
+) -> Option<Result<u8, (u8, Termination)>> {
+    let (mut character, mut read) = data.read_symbol(&options.encoding)?;
+    let num_bytes = options.encoding.num_bytes();
+    if read < num_bytes {
+        // incomplete code unit at the end of data, the bytes would make a bogus symbol
+        return Some(Err((read, Termination::Eof)));
+    }
+    let termination = Termination::after(character, options.encoding);
 
-            if matches!(options.unicode_display, UnicodeDisplayKind::Invalid) {
-                /* We have found a valid UTF-8 character, but we treat it as non-graphic.  */
-                num_found = 0;
-                data.seek_back(maybe_utf8.len() as u8 - 1);
-                address_offset += char_len as usize;
-                continue;
-            }
+    if options.encoding.is_ebcdic() {
+        let c = ebcdic::decode(options.encoding, character as u8);
+        let printable = options.alphabet.map_or_else(
+            || ebcdic::is_printable(c, options.include_all_whitespace),
+            |alphabet| alphabet.contains(c),
+        );
+        if !printable {
+            return Some(Err((read, termination)));
+        }
+        let mut utf8 = [0u8; 4];
+        buffer.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        return Some(Ok(read));
+    }
+
+    if !options.decode_wide || num_bytes == 1 {
+        if character > 255 || !options.alphabet.map_or_else(
+            || char_is_printable(character as u8 as char, options.encoding,
+                                 options.include_all_whitespace),
+            |alphabet| alphabet.contains(character as u8 as char),
+        ) {
+            return Some(Err((read, termination)));
+        }
+        buffer.push(character as u8);
+        return Some(Ok(read));
+    }
 
-            if char_len as usize != maybe_utf8.len() && num_found < options.min_length - 1 {
-                data.seek_back(maybe_utf8.len() as u8 - char_len)
+    if num_bytes == 2 && is_high_surrogate(character) {
+        if let Some((low, low_read)) = data.read_symbol(&options.encoding) {
+            if low_read == 2 && is_low_surrogate(low) {
+                character = decode_surrogate_pair(character, low);
+                read += low_read;
+            } else {
+                data.seek_back(low_read as usize);
             }
         }
+    }
 
-        if num_found == 0 {
-            /* We have found a potential starting point for a string.  */
-            sequence_start_address_offset = address_offset;
+    return match char::from_u32(character) {
+        Some(c) if options.alphabet.map_or_else(
+            || wide_char_is_printable(c, options.include_all_whitespace),
+            |alphabet| alphabet.contains(c),
+        ) => {
+            let mut utf8 = [0u8; 4];
+            buffer.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            Some(Ok(read))
         }
+        _ => Some(Err((read, termination)))
+    };
+}
 
-        num_found += 1;
+/*
+UTF-8 structure
 
-        if num_found >= options.min_length {
-            if char_len == 1 {
-                data.seek_back(address_offset as u8 + char_len - sequence_start_address_offset as u8);
-            } else {
-                // TODO fix that. We need to go back taking into account last read, and we
-                // don't know if it was unicode or not
-                data.seek_back(address_offset as u8 + 4 - sequence_start_address_offset as u8);
+First code point 	Last code point 	Byte 1 	    Byte 2 	    Byte 3 	    Byte 4
+U+0000 	            U+007F 	            0xxxxxxx
+U+0080 	            U+07FF 	            110xxxxx 	10xxxxxx
+U+0800 	            U+FFFF 	            1110xxxx 	10xxxxxx 	10xxxxxx
+U+10000             U+10FFFF 	        11110xxx 	10xxxxxx 	10xxxxxx 	10xxxxxx
+ */
+fn scan_unicode_buffer(
+    filename: &OsStr,
+    address: u64,
+    data: &mut dyn DataSource,
+    options: &Options,
+    sink: &mut dyn StringSink,
+) -> u64 {
+    if !matches!(options.encoding, EncodingKind::Bit8) {
+        eprintln!("ICE: bad arguments to scan_unicode_buffer");
+        return 0;
+    }
+
+    let mut current_address = address;
+    let mut num_found = 0u64;
+    let mut bytes = Vec::<u8>::new();
+    let mut text = Vec::<u8>::new();
+    // the number of characters in `bytes`, the string is reported if there are enough of them
+    let mut num_chars = 0usize;
+    // the highlighting is the escaping in colour
+    let unicode_display = match options.unicode_display {
+        UnicodeDisplayKind::Highlight if !options.color => UnicodeDisplayKind::Escape,
+        display => display
+    };
+    let mut decoder = Utf8Decoder::new();
+    let mut read_byte = || data.read_byte();
+
+    loop {
+        let unit = decoder.next(&mut read_byte);
+        let termination = match unit {
+            Some(Utf8Unit::Ascii(c)) if options.alphabet.map_or_else(
+                || char_is_printable(c as char, options.encoding, options.include_all_whitespace),
+                |alphabet| alphabet.contains(c as char),
+            ) => {
+                bytes.push(c);
+                text.push(c);
+                num_chars += 1;
+                continue;
+            }
+            Some(Utf8Unit::Ascii(c)) => Termination::after(c as u32, options.encoding),
+            // GNU strings treats the valid characters as non-graphic in this mode
+            Some(Utf8Unit::Char { .. }) if matches!(
+                options.unicode_display, UnicodeDisplayKind::Invalid
+            ) => Termination::NonPrintable,
+            Some(Utf8Unit::Char { bytes: char_bytes, len }) => {
+                display_utf8_char(&char_bytes[..len], unicode_display, &mut text);
+                bytes.extend_from_slice(&char_bytes[..len]);
+                num_chars += 1;
+                continue;
+            }
+            // only inside a string, the broken bytes don't start one
+            Some(Utf8Unit::Invalid(byte)) if matches!(
+                options.unicode_display, UnicodeDisplayKind::Replace
+            ) && !bytes.is_empty() => {
+                bytes.push(byte);
+                write_or_exit!(text, "{}", char::REPLACEMENT_CHARACTER);
+                continue;
             }
-            return Some(sequence_start_address_offset);
+            Some(Utf8Unit::Invalid(_)) => Termination::NonPrintable,
+            None => Termination::Eof,
+        };
+
+        if num_chars >= options.min_length as usize {
+            sink.on_string(&FoundString {
+                filename,
+                address: current_address,
+                end_address: current_address + bytes.len() as u64,
+                bytes: &bytes,
+                text: &text,
+                encoding: options.encoding,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            num_found += 1;
         }
 
-        address_offset += char_len as usize;
+        let Some(unit) = unit else {
+            return num_found;
+        };
+        // the unit which ended the string is not a part of the next one
+        current_address += (bytes.len() + unit.len()) as u64;
+        bytes.clear();
+        text.clear();
+        num_chars = 0;
     }
 }
 
 fn print_filename_and_address(
-    filename: &str,
+    filename: &OsStr,
     address: u64,
+    width: usize,
     options: &Options,
     writer: &mut dyn Write,
 ) {
     if options.print_filenames {
-        write_or_panic!(writer, "{}: ", filename);
+        print_filename(filename, options, writer);
+    }
+
+    if options.print_addresses {
+        print_address(address, width, options, writer);
+    }
+}
+
+fn print_filename(filename: &OsStr, options: &Options, writer: &mut dyn Write) {
+    if options.color {
+        write_or_exit!(writer, "{}", color::FILENAME);
     }
+    check_write(writer.write_all(&os_str_bytes(filename)));
+    if options.color {
+        write_or_exit!(writer, "{}", color::RESET);
+    }
+    write_or_exit!(writer, ": ");
+}
 
-    if !options.print_addresses {
-        return;
+fn print_address(address: u64, width: usize, options: &Options, writer: &mut dyn Write) {
+    if options.color {
+        write_or_exit!(writer, "{}", color::OFFSET);
     }
 
-    // TODO should support longer addresses?
-    match options.address_radix {
-        RadixKind::Oct => {
-            write_or_panic!(writer, "{:7o} ", address);
+    // same as GNU "%7lx" with the default width, the longer addresses are not truncated
+    match (options.address_radix, options.address_zero_pad) {
+        (RadixKind::Oct, false) => {
+            write_or_exit!(writer, "{:width$o}", address, width = width);
+        }
+        (RadixKind::Oct, true) => {
+            write_or_exit!(writer, "{:0width$o}", address, width = width);
+        }
+        (RadixKind::Dec, false) => {
+            write_or_exit!(writer, "{:width$}", address, width = width);
         }
-        RadixKind::Dec => {
-            write_or_panic!(writer, "{:7} ", address);
+        (RadixKind::Dec, true) => {
+            write_or_exit!(writer, "{:0width$}", address, width = width);
         }
-        RadixKind::Hex => {
-            write_or_panic!(writer, "{:7x} ", address);
+        (RadixKind::Hex, false) => {
+            write_or_exit!(writer, "{:width$x}", address, width = width);
         }
+        (RadixKind::Hex, true) => {
+            write_or_exit!(writer, "{:0width$x}", address, width = width);
+        }
+    }
+
+    if options.color {
+        write_or_exit!(writer, "{}", color::RESET);
     }
+    write_or_exit!(writer, " ");
 }
 
 fn display_utf8_char(buffer: &[u8], display: UnicodeDisplayKind, writer: &mut dyn Write) -> u8 {
@@ -737,12 +2652,25 @@ fn display_utf8_char(buffer: &[u8], display: UnicodeDisplayKind, writer: &mut dy
 
     match display {
         UnicodeDisplayKind::Escape | UnicodeDisplayKind::Highlight => {
-            if matches!(display, UnicodeDisplayKind::Highlight) && atty::is(Stream::Stdout) {
-                write_or_panic!(writer, "\x1B[31;47m"); /* Red.  */
+            if matches!(display, UnicodeDisplayKind::Highlight) {
+                write_or_exit!(writer, "{}", color::HIGHLIGHT);
+            }
+            match utf8::code_point(&buffer[..utf8_len as usize]) {
+                code_point @ 0x1_0000..=0x10_ffff => {
+                    let (high, low) = encode_surrogate_pair(code_point);
+                    write_or_exit!(writer, "\\u{:04x}\\u{:04x}", high, low);
+                }
+                // the lead bytes past U+10FFFF are accepted as GNU strings does
+                code_point => write_or_exit!(writer, "\\u{:04x}", code_point),
+            }
+            if matches!(display, UnicodeDisplayKind::Highlight) {
+                write_or_exit!(writer, "{}", color::RESET);
             }
+        }
+        UnicodeDisplayKind::EscapeGnu => {
             match utf8_len {
                 2 => {
-                    write_or_panic!(
+                    write_or_exit!(
                         writer,
                         "\\u{:02x}{:02x}",
                         ((buffer[0] & 0x1c) >> 2),
@@ -750,7 +2678,7 @@ fn display_utf8_char(buffer: &[u8], display: UnicodeDisplayKind, writer: &mut dy
                 }
 
                 3 => {
-                    write_or_panic!(
+                    write_or_exit!(
                         writer,
                         "\\u{:02x}{:02x}",
                         ((buffer[0] & 0x0f) << 4) | ((buffer[1] & 0x3c) >> 2),
@@ -758,7 +2686,7 @@ fn display_utf8_char(buffer: &[u8], display: UnicodeDisplayKind, writer: &mut dy
                 }
 
                 4 => {
-                    write_or_panic!(
+                    write_or_exit!(
                         writer,
                         "\\u{:02x}{:02x}{:02x}",
                         ((buffer[0] & 0x07) << 6) | ((buffer[1] & 0x3c) >> 2),
@@ -769,21 +2697,25 @@ fn display_utf8_char(buffer: &[u8], display: UnicodeDisplayKind, writer: &mut dy
                     panic!("Unknown utf8_len")
                 }
             }
-
-            if matches!(display, UnicodeDisplayKind::Highlight) && atty::is(Stream::Stdout) {
-                write_or_panic!(writer, "\033[0m"); /* Default colour.  */
-            }
         }
         UnicodeDisplayKind::Hex => {
-            write_or_panic!(writer, "<");
-            write_or_panic!(writer, "0x");
+            write_or_exit!(writer, "<");
+            write_or_exit!(writer, "0x");
             for j in 0usize..utf8_len as usize {
-                write_or_panic!(writer, "{:02x}", buffer[j]);
+                write_or_exit!(writer, "{:02x}", buffer[j]);
             }
-            write_or_panic!(writer, ">");
+            write_or_exit!(writer, ">");
         }
         UnicodeDisplayKind::Show => {
-            write_or_panic!(writer, "{:01?}", buffer);
+            check_write(writer.write_all(&buffer[..utf8_len as usize]));
+        }
+        UnicodeDisplayKind::Replace => {
+            // the overlong forms and the surrogates are accepted by the scan, but not by the
+            // terminals
+            match std::str::from_utf8(&buffer[..utf8_len as usize]) {
+                Ok(char) => write_or_exit!(writer, "{}", char),
+                Err(_) => write_or_exit!(writer, "{}", char::REPLACEMENT_CHARACTER),
+            }
         }
         _ => {
             eprintln!("ICE: unexpected unicode display type");
@@ -799,6 +2731,28 @@ mod tests {
 
     const TEST_OBJECT_FILE_PATH: &str = "test-resources/a.out";
 
+    struct Collector {
+        found: Vec<(EncodingKind, String, Termination)>,
+    }
+
+    impl StringSink for Collector {
+        fn on_string(&mut self, found: &FoundString) {
+            let text = String::from_utf8(found.bytes.to_vec()).unwrap();
+            self.found.push((found.encoding, text, found.termination));
+        }
+    }
+
+    fn print_strings(
+        filename: &OsStr,
+        address: u64,
+        data: &mut dyn DataSource,
+        options: &Options,
+        writer: &mut dyn Write,
+    ) -> u64 {
+        let mut printer = StringPrinter::new(writer, options);
+        return scan_strings(filename, address, data, options, &mut printer);
+    }
+
     #[test]
     fn test_display_utf8_char_escape_2bytes() {
         let mut output = Vec::new();
@@ -820,8 +2774,17 @@ mod tests {
         let mut output = Vec::new();
         display_utf8_char("𐍈".as_bytes(), UnicodeDisplayKind::Escape, &mut output);
 
+        assert_eq!("\\ud800\\udf48", String::from_utf8(output).expect("Not valid UTF8"))
+    }
+
+    #[test]
+    fn test_display_utf8_char_escape_gnu() {
+        let mut output = Vec::new();
+        display_utf8_char("ह".as_bytes(), UnicodeDisplayKind::EscapeGnu, &mut output);
+        display_utf8_char("𐍈".as_bytes(), UnicodeDisplayKind::EscapeGnu, &mut output);
+
         // should be 10348, but strings.c produces the same
-        assert_eq!("\\u040348", String::from_utf8(output).expect("Not valid UTF8"))
+        assert_eq!("\\u0939\\u040348", String::from_utf8(output).expect("Not valid UTF8"))
     }
 
     #[test]
@@ -837,8 +2800,17 @@ mod tests {
         let mut output = Vec::new();
         display_utf8_char("𐍈".as_bytes(), UnicodeDisplayKind::Show, &mut output);
 
-        // TODO recheck this
-        assert_eq!("[240, 144, 141, 136]", String::from_utf8(output).expect("Not valid UTF8"))
+        assert_eq!("𐍈", String::from_utf8(output).expect("Not valid UTF8"))
+    }
+
+    #[test]
+    fn test_display_utf8_char_replace() {
+        let mut output = Vec::new();
+        display_utf8_char("𐍈".as_bytes(), UnicodeDisplayKind::Replace, &mut output);
+        // an overlong '/'
+        display_utf8_char(b"\xc0\xaf", UnicodeDisplayKind::Replace, &mut output);
+
+        assert_eq!("𐍈\u{fffd}", String::from_utf8(output).expect("Not valid UTF8"))
     }
 
     #[test]
@@ -852,10 +2824,29 @@ mod tests {
             std::fs::read("test-resources/default-output.txt").unwrap()
         ).unwrap();
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &Options::default(), &mut output);
+        print_strings(
+            OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &Options::default(), &mut output,
+        );
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
+    #[test]
+    fn test_print_strings_returns_number_of_strings() {
+        let mut data: ReaderWithSeek = BufReader::new(
+            File::open(TEST_OBJECT_FILE_PATH).unwrap()
+        ).into();
+        let mut output = Vec::new();
+
+        let expected = String::from_utf8(
+            std::fs::read("test-resources/default-output.txt").unwrap()
+        ).unwrap();
+
+        let num_found = print_strings(
+            OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &Options::default(), &mut output,
+        );
+        assert_eq!(expected.lines().count() as u64, num_found)
+    }
+
     #[test]
     fn test_print_strings_with_address_hex() {
         let mut data: ReaderWithSeek = BufReader::new(
@@ -871,7 +2862,7 @@ mod tests {
         options.print_addresses = true;
         options.address_radix = RadixKind::Hex;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
@@ -890,7 +2881,7 @@ mod tests {
         options.print_addresses = true;
         options.address_radix = RadixKind::Oct;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
@@ -906,9 +2897,9 @@ mod tests {
         ).unwrap();
 
         let mut options = Options::default();
-        options.output_separator = Some("\n\n".to_string());
+        options.output_separator = Some(b"\n\n".to_vec());
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
@@ -926,7 +2917,7 @@ mod tests {
         let mut options = Options::default();
         options.min_length = 8;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
@@ -943,7 +2934,7 @@ mod tests {
         let mut options = Options::default();
         options.encoding = EncodingKind::Bit8;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, output)
     }
 
@@ -961,7 +2952,7 @@ mod tests {
         let mut options = Options::default();
         options.print_filenames = true;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
@@ -980,10 +2971,39 @@ mod tests {
         options.unicode_display = UnicodeDisplayKind::Escape;
         options.encoding = EncodingKind::Bit8;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
+    #[test]
+    fn test_print_strings_with_long_unicode_sequences() {
+        let long = "é".repeat(1500);
+        let mut buffer = vec![1u8; 300];
+        buffer.extend_from_slice(b"short \xC3\xA9\x00");
+        buffer.extend_from_slice(long.as_bytes());
+        buffer.push(0);
+
+        let mut options = Options::default();
+        options.unicode_display = UnicodeDisplayKind::Escape;
+        options.encoding = EncodingKind::Bit8;
+        let scan = |data: &mut dyn DataSource, options: &Options| {
+            let mut collector = Collector { found: Vec::new() };
+            scan_strings(OsStr::new("test"), 0, data, options, &mut collector);
+            return collector.found.into_iter().map(|(_, text, _)| text).collect::<Vec<_>>();
+        };
+        for (min_length, expected) in [
+            (4, vec!["short é".to_string(), long.clone()]),
+            (1000, vec![long.clone()]),
+        ] {
+            options.min_length = min_length;
+
+            let mut data = ReaderWithSeek::new(Box::new(&buffer[..]));
+            assert_eq!(expected, scan(&mut data, &options), "{}", min_length);
+            let mut data = ByteArrayHolder { inner: &buffer, position: 0 };
+            assert_eq!(expected, scan(&mut data, &options), "{}", min_length);
+        }
+    }
+
     #[test]
     fn test_print_strings_with_unicode_escape_and_address_hex() {
         let mut data: ReaderWithSeek = BufReader::new(
@@ -1001,10 +3021,516 @@ mod tests {
         options.print_addresses = true;
         options.address_radix = RadixKind::Hex;
 
-        print_strings(TEST_OBJECT_FILE_PATH, 0, &mut data, &options, &mut output);
+        print_strings(OsStr::new(TEST_OBJECT_FILE_PATH), 0, &mut data, &options, &mut output);
         assert_eq!(expected, String::from_utf8(output).unwrap())
     }
 
+    #[test]
+    fn test_print_strings_decode_wide() {
+        let mut buffer = vec![0u8, 0u8];
+        for unit in "ℓôřè₥ 𐍈".encode_utf16() {
+            buffer.extend_from_slice(&unit.to_be_bytes());
+        }
+        buffer.extend_from_slice(&[0xd8, 0x00, 0x00, b'a']);
+
+        let mut data = ByteArrayHolder {
+            inner: &buffer,
+            position: 0,
+        };
+        let mut output = Vec::<u8>::new();
+
+        let mut options = Options::default();
+        options.encoding = EncodingKind::BigEndian16;
+        options.decode_wide = true;
+        options.print_addresses = true;
+
+        print_strings(OsStr::new("test"), 0, &mut data, &options, &mut output);
+        assert_eq!("      2 ℓôřè₥ 𐍈\n", String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_print_strings_ebcdic() {
+        // "Cafe [1]" in cp500, then "ASCII" in ASCII
+        let buffer = b"\x00\xc3\x81\x86\x85\x40\x4a\xf1\x5a\x00ASCII";
+
+        let mut data = ByteArrayHolder {
+            inner: buffer,
+            position: 0,
+        };
+        let mut output = Vec::<u8>::new();
+
+        let mut options = Options::default();
+        options.encoding = EncodingKind::Ebcdic500;
+        options.print_addresses = true;
+
+        print_strings(OsStr::new("test"), 0, &mut data, &options, &mut output);
+        assert_eq!("      1 Cafe [1]\n", String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_address_width() {
+        let print = |compat_address_format: bool, radix: RadixKind| {
+            let mut options = Options::default();
+            options.print_addresses = true;
+            options.address_radix = radix;
+            options.compat_address_format = compat_address_format;
+
+            let mut output = Vec::<u8>::new();
+            let mut printer = StringPrinter::new(&mut output, &options);
+            printer.on_input(OsStr::new("big"), Some(0x1_0000_0000));
+            for address in [0x10, 0xffff_fff0] {
+                printer.on_string(&FoundString {
+                    filename: OsStr::new("big"),
+                    address,
+                    end_address: address + 4,
+                    bytes: b"text",
+                    text: b"text",
+                    encoding: EncodingKind::Bit7,
+                    tags: &[],
+                    xor_key: None,
+                    decoded_from: None,
+                    termination: Termination::Nul,
+                    score: None,
+                    context_before: &[],
+                    context_after: &[],
+                    section: None,
+                });
+            }
+            printer.on_input(OsStr::new("small"), Some(0x100));
+            printer.on_string(&FoundString {
+                filename: OsStr::new("small"),
+                address: 0x20,
+                end_address: 0x24,
+                bytes: b"text",
+                text: b"text",
+                encoding: EncodingKind::Bit7,
+                tags: &[],
+                xor_key: None,
+                decoded_from: None,
+                termination: Termination::Nul,
+                score: None,
+                context_before: &[],
+                context_after: &[],
+                section: None,
+            });
+            return String::from_utf8(output).unwrap();
+        };
+
+        assert_eq!(
+            "      10 text\nfffffff0 text\n     20 text\n",
+            print(false, RadixKind::Hex)
+        );
+        assert_eq!(
+            "     10 text\nfffffff0 text\n     20 text\n",
+            print(true, RadixKind::Hex)
+        );
+        assert_eq!(
+            "        16 text\n4294967280 text\n     32 text\n",
+            print(false, RadixKind::Dec)
+        );
+        assert_eq!(
+            "     16 text\n4294967280 text\n     32 text\n",
+            print(true, RadixKind::Dec)
+        );
+    }
+
+    #[test]
+    fn test_print_context() {
+        let mut options = Options::default();
+        options.context_before = 3;
+        options.context_after = 18;
+
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+        let data = b"\x01\x02text\0\x03ABCDEFGHIJKLMNO";
+        print_strings_for_bytes(OsStr::new("test"), data, &options, &mut printer);
+        assert_eq!(
+            concat!(
+                "      0 - 01 02                                           |..|\n",
+                "text\n",
+                "      6 - 00 03 41 42 43 44 45 46 47 48 49 4a 4b 4c 4d 4e |..ABCDEFGHIJKLMN|\n",
+                "     16 - 4f                                              |O|\n",
+                "      5 - 74 00 03                                        |t..|\n",
+                "ABCDEFGHIJKLMNO\n",
+            ),
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_address_width_and_pad() {
+        let mut options = Options::default();
+        options.print_addresses = true;
+        options.address_width = Some(10);
+        options.address_zero_pad = true;
+
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+        print_strings_for_bytes(OsStr::new("test"), b"\x01text\0", &options, &mut printer);
+        // a width narrower than the offsets doesn't truncate them
+        options.address_width = Some(2);
+        options.address_radix = RadixKind::Dec;
+        let mut printer = StringPrinter::new(&mut output, &options);
+        let mut data = vec![1u8; 200];
+        data.extend_from_slice(b"text");
+        print_strings_for_bytes(OsStr::new("test"), &data, &options, &mut printer);
+
+        assert_eq!("0000000001 text\n200 text\n", String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn test_print_color() {
+        let mut options = Options::default();
+        options.print_filenames = true;
+        options.print_addresses = true;
+        options.color = true;
+
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+        print_strings_for_bytes(OsStr::new("test"), b"\x01text\0", &options, &mut printer);
+        assert_eq!(
+            "\x1B[35mtest\x1B[0m: \x1B[32m      1\x1B[0m text\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_print_non_utf8_filename() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut options = Options::default();
+        options.print_filenames = true;
+
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+        let name = OsStr::from_bytes(b"caf\xe9.bin");
+        print_strings_for_bytes(name, b"\x01text\0", &options, &mut printer);
+        assert_eq!(b"caf\xe9.bin: text\n".to_vec(), output);
+    }
+
+    #[test]
+    fn test_print_end_offset_and_length() {
+        let mut options = Options::default();
+        options.print_addresses = true;
+        options.address_radix = RadixKind::Dec;
+        options.print_end_offset = true;
+        options.print_length = true;
+
+        let mut output = Vec::<u8>::new();
+        print_strings(OsStr::new("test"), 0, &mut ByteArrayHolder {
+            inner: b"\x01first\0\x02\x03second string\n",
+            position: 0,
+        }, &options, &mut output);
+        assert_eq!(
+            "      1       6     5 first\n      9      22    13 second string\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_print_strings_xor() {
+        // the NUL bytes are not decoded, otherwise they would be "ZZZZZZ" with the key 0x5a
+        let mut buffer = vec![0u8; 6];
+        buffer.extend(b"password".iter().map(|byte| byte ^ 0x5a));
+
+        let mut data = ByteArrayHolder {
+            inner: &buffer,
+            position: 0,
+        };
+        let mut output = Vec::<u8>::new();
+
+        let mut options = Options::default();
+        options.xor_keys = vec![0x20, 0x5a];
+        options.print_addresses = true;
+
+        print_strings(OsStr::new("test"), 0, &mut data, &options, &mut output);
+        assert_eq!("      6 [xor:0x5a] password\n", String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_termination() {
+        let scan = |options: &Options| {
+            let buffer = b"first\0second\nthird\x01fourth";
+            let mut data = ByteArrayHolder {
+                inner: buffer,
+                position: 0,
+            };
+            let mut collector = Collector { found: Vec::new() };
+            let mut sink = SectionSink { section: None, inner: &mut collector };
+            scan_strings(OsStr::new("test"), 0, &mut data, options, &mut sink);
+            return collector.found.into_iter()
+                .map(|(_, text, termination)| (text, termination))
+                .collect::<Vec<(String, Termination)>>();
+        };
+        let expected = vec![
+            ("first".to_string(), Termination::Nul),
+            ("second".to_string(), Termination::Newline),
+            ("third".to_string(), Termination::NonPrintable),
+            ("fourth".to_string(), Termination::SectionEnd),
+        ];
+
+        let mut options = Options::default();
+        assert_eq!(expected, scan(&options));
+
+        // the incremental scanners
+        options.extra_encodings = vec![EncodingKind::LittleEndian16];
+        assert_eq!(expected, scan(&options));
+
+        options.extra_encodings = vec![];
+        options.encoding = EncodingKind::Bit8;
+        options.unicode_display = UnicodeDisplayKind::Escape;
+        assert_eq!(expected, scan(&options));
+    }
+
+    #[test]
+    fn test_terminator_filter() {
+        let buffer = b"first\0second\nthird\x01fourth";
+        let mut data = ByteArrayHolder {
+            inner: buffer,
+            position: 0,
+        };
+        let mut collector = Collector { found: Vec::new() };
+        let mut filter = TerminatorFilter::new(&mut collector);
+        scan_strings(OsStr::new("test"), 0, &mut data, &Options::default(), &mut filter);
+        let found: Vec<&str> = collector.found.iter().map(|(_, text, _)| text.as_str()).collect();
+        assert_eq!(vec!["first", "second"], found);
+    }
+
+    #[test]
+    fn test_scan_bytes_and_path() {
+        let data = b"\x01\x02text\0\xffmore text".to_vec();
+        let found = scan_slice(&data);
+        assert_eq!(2, found.len());
+        assert!(std::ptr::eq(&data[2..6], found[0].bytes));
+        assert_eq!(b"more text", found[1].bytes);
+        assert_eq!(9, found[1].into_owned().length);
+
+        let found = scan_bytes(b"\x01\x02text\0\xffmore text");
+        assert_eq!(2, found.len());
+        assert_eq!((2, 4, "text"), (found[0].offset, found[0].length, &*found[0].text()));
+        assert_eq!(Termination::Nul, found[0].termination);
+        assert_eq!((8, 9, "more text"), (found[1].offset, found[1].length, &*found[1].text()));
+        assert_eq!(Termination::Eof, found[1].termination);
+
+        let found = scan_path(TEST_OBJECT_FILE_PATH).unwrap();
+        assert!(found.iter().any(|string| string.text() == "Some text with "));
+        assert!(scan_path("test-resources/missing").is_err());
+    }
+
+    #[test]
+    fn test_stream_scanner() {
+        let data = b"\x01\x02text\0\xffmore text\x03wide\0\0".to_vec();
+        let expected: Vec<(u64, String)> = scan_bytes(&data).iter()
+            .map(|string| (string.offset, string.text().to_string()))
+            .collect();
+
+        for chunk_size in [1, 3, data.len()] {
+            let mut scanner = StreamScanner::new(&Options::default());
+            let mut found = Vec::new();
+            for chunk in data.chunks(chunk_size) {
+                found.extend(scanner.feed(chunk));
+            }
+            found.extend(scanner.finish());
+            let found: Vec<(u64, String)> = found.iter()
+                .map(|string| (string.offset, string.text().to_string()))
+                .collect();
+            assert_eq!(expected, found);
+        }
+
+        let options = Options {
+            encoding: EncodingKind::LittleEndian16,
+            xor_keys: vec![0x20],
+            ..Options::default()
+        };
+        let mut scanner = StreamScanner::new(&options);
+        let data: Vec<u8> = "ABCD".encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .map(|byte| if byte == 0 { 0 } else { byte ^ 0x20 })
+            .collect();
+        let mut found = scanner.feed(&data);
+        found.extend(scanner.finish());
+        assert_eq!(vec!["ABCD"], found.iter().map(|string| string.text()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sparse_file() {
+        let name = format!("strings-sparse-scan-{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"\x01first region").unwrap();
+        file.seek(SeekFrom::Start(4 << 20)).unwrap();
+        let wide: Vec<u8> = "wide\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        file.write_all(&wide).unwrap();
+        file.set_len(8 << 20).unwrap();
+        drop(file);
+
+        let mut output = Vec::new();
+        let options = Options {
+            encoding: EncodingKind::LittleEndian16,
+            extra_encodings: vec![EncodingKind::Bit7],
+            print_addresses: true,
+            ..Options::default()
+        };
+        let mut printer = StringPrinter::new(&mut output, &options);
+        let num_found = print_strings_for_file(path.as_os_str(), &options, &mut printer);
+        std::fs::remove_file(&path).unwrap();
+
+        // the strings end before the holes as before the zeros of a whole file
+        assert_eq!(Some(2), num_found);
+        assert_eq!(
+            "      1 [7bit] first region\n 400000 [16le] wide\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scan_observer() {
+        struct Observer {
+            reported: std::sync::Mutex<Vec<u64>>,
+            cancel_at: u64,
+        }
+
+        impl ScanObserver for Observer {
+            fn progress(&self, bytes: u64) {
+                self.reported.lock().unwrap().push(bytes);
+            }
+
+            fn should_cancel(&self) -> bool {
+                return self.reported.lock().unwrap().last() >= Some(&self.cancel_at);
+            }
+        }
+
+        let data = b"some text\0".repeat(20_000);
+        let scan = |cancel_at: u64, encoding: EncodingKind| {
+            let observer = Arc::new(Observer { reported: Default::default(), cancel_at });
+            let options = Options {
+                encoding,
+                observer: Some(observer.clone()),
+                ..Options::default()
+            };
+            let mut collector = Collector { found: Vec::new() };
+            let mut byte_holder = ByteArrayHolder { inner: &data, position: 0 };
+            let num_found = scan_strings(
+                OsStr::new("test"), 0, &mut byte_holder, &options, &mut collector
+            );
+            let reported = observer.reported.lock().unwrap().clone();
+            return (num_found, reported);
+        };
+
+        let (num_found, reported) = scan(u64::MAX, EncodingKind::Bit7);
+        assert_eq!(20_000, num_found);
+        assert_eq!(vec![0, 65536, 131072, 196608, 200_000], reported);
+
+        // the strings found until the cancellation are kept
+        let (num_found, reported) = scan(OBSERVE_INTERVAL, EncodingKind::Bit7);
+        assert_eq!(6554, num_found);
+        assert_eq!(vec![0, 65536, 65536], reported);
+        let (num_found, reported) = scan(0, EncodingKind::Auto16);
+        assert_eq!((0, vec![0, 0]), (num_found, reported));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_extract_strings_async() {
+        let data = b"\x01\x02text\0\xffmore text\x03wide\0\0".repeat(10_000);
+        let options = Options::default();
+        let future = extract_strings_async(&data[..], &options);
+        // it may be spawned on a multithreaded runtime
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&future);
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let found = runtime.block_on(future).unwrap();
+        let expected: Vec<(u64, String)> = scan_bytes(&data).iter()
+            .map(|string| (string.offset, string.text().to_string()))
+            .collect();
+        let found: Vec<(u64, String)> = found.iter()
+            .map(|string| (string.offset, string.text().to_string()))
+            .collect();
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn test_smart_encoding() {
+        let mut buffer = b"ASCII text\0".repeat(smart::REGION_SIZE / 11 + 1);
+        buffer.truncate(smart::REGION_SIZE);
+        buffer.extend("wide text\0".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+
+        let mut data = ByteArrayHolder {
+            inner: &buffer,
+            position: 0,
+        };
+        let mut collector = Collector { found: Vec::new() };
+        let mut options = Options::default();
+        options.smart_encoding = true;
+        scan_strings(OsStr::new("test"), 0, &mut data, &options, &mut collector);
+
+        // the last ASCII string is cut by the end of the region
+        let found: Vec<(&str, &str)> = collector.found.iter()
+            .map(|(encoding, text, _)| (encoding.name(), text.as_str()))
+            .collect();
+        assert_eq!(smart::REGION_SIZE / 11 + 2, found.len());
+        assert!(found[..found.len() - 2].iter().all(|string| *string == ("7bit", "ASCII text")));
+        assert_eq!(vec![("7bit", "ASCII tex"), ("16le", "wide text")], found[found.len() - 2..]);
+    }
+
+    #[test]
+    fn test_incomplete_code_unit_tail() {
+        // "text" in every encoding
+        let encodings: [(EncodingKind, &[u8]); 7] = [
+            (EncodingKind::Bit7, b"text"),
+            (EncodingKind::Bit8, b"text"),
+            (EncodingKind::Ebcdic037, b"\xa3\x85\xa7\xa3"),
+            (EncodingKind::LittleEndian16, b"t\0e\0x\0t\0"),
+            (EncodingKind::BigEndian16, b"\0t\0e\0x\0t"),
+            (EncodingKind::LittleEndian32, b"t\0\0\0e\0\0\0x\0\0\0t\0\0\0"),
+            (EncodingKind::BigEndian32, b"\0\0\0t\0\0\0e\0\0\0x\0\0\0t"),
+        ];
+
+        let scan = |options: &Options, buffer: &[u8]| {
+            let mut data = ByteArrayHolder {
+                inner: buffer,
+                position: 0,
+            };
+            let mut collector = Collector { found: Vec::new() };
+            scan_strings(OsStr::new("test"), 0, &mut data, options, &mut collector);
+            // only the strings of the tested encoding
+            return collector.found.into_iter()
+                .filter(|(encoding, _, _)| *encoding == options.encoding)
+                .map(|(_, text, termination)| (text, termination))
+                .collect::<Vec<(String, Termination)>>();
+        };
+
+        for (encoding, text) in encodings {
+            // the incremental scanners are used if there are several encodings
+            let other = if encoding == EncodingKind::Bit7 { EncodingKind::Bit8 } else { EncodingKind::Bit7 };
+            for extra_encodings in [vec![], vec![other]] {
+                for decode_wide in [false, true] {
+                    let mut options = Options::default();
+                    options.encoding = encoding;
+                    options.extra_encodings = extra_encodings.clone();
+                    options.decode_wide = decode_wide;
+                    let context = format!("{} {} {}", encoding.name(), extra_encodings.len(), decode_wide);
+
+                    let expected = vec![("text".to_string(), Termination::Eof)];
+                    assert_eq!(expected, scan(&options, text), "{}", context);
+
+                    for tail_size in 1..encoding.num_bytes() as usize {
+                        // printable bytes, which would make a symbol in one of the endiannesses
+                        let mut buffer = text.to_vec();
+                        buffer.extend(vec![b'A'; tail_size]);
+
+                        options.strict_units = false;
+                        assert_eq!(expected, scan(&options, &buffer), "{} {}", context, tail_size);
+                        options.strict_units = true;
+                        assert!(scan(&options, &buffer).is_empty(), "{} {}", context, tail_size);
+                        assert_eq!(expected, scan(&options, text), "{} {}", context, tail_size);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_data_source_backed_by_array() {
         let buffer = [0x12u8, 0x23, 0x34, 0x45, 0x56, 0x67, 0x78, 0x89, 0xFF, 0xAA];
@@ -1045,11 +3571,7 @@ mod tests {
     fn test_data_source_backed_by_reader_with_seek() {
         let buffer = [0x12u8, 0x23, 0x34, 0x45, 0x56, 0x67, 0x78, 0x89, 0xFF, 0xAA];
 
-        let mut source = ReaderWithSeek {
-            inner: Box::new(&buffer[..]),
-            back_buf: VecDeque::with_capacity(MAX_KEEP_BACK_SIZE),
-            back_pos: 0,
-        };
+        let mut source = ReaderWithSeek::new(Box::new(&buffer[..]));
 
         assert_eq!(0x12, source.read_byte().unwrap());
 
@@ -1079,109 +3601,114 @@ mod tests {
     }
 
     #[test]
-    fn test_data_source_backed_by_reader_with_seek_unicode() {
-        let buffer = [0x12u8, 0x23, 0x34, 0x45, 0x56, 0x67, 0x78, 0x89, 0xFF, 0xAA];
-
-        let mut source = ReaderWithSeek {
-            inner: Box::new(&buffer[..]),
-            back_buf: VecDeque::with_capacity(MAX_KEEP_BACK_SIZE),
-            back_pos: 0,
-        };
-
-        assert_eq!(0x12, source.read_byte().unwrap());
-
-        let vec = source.read_unicode().unwrap();
-
-        assert_eq!(4, vec.len());
-        assert_eq!(0x23, vec[0]);
-        assert_eq!(0x34, vec[1]);
-        assert_eq!(0x45, vec[2]);
-        assert_eq!(0x56, vec[3]);
-
-        source.seek_back(3);
-
-        let vec = source.read_unicode().unwrap();
-
-        assert_eq!(4, vec.len());
-        assert_eq!(0x34, vec[0]);
-        assert_eq!(0x45, vec[1]);
-        assert_eq!(0x56, vec[2]);
-        assert_eq!(0x67, vec[3]);
-
-        source.seek_back(5);
-
-        let vec = source.read_unicode().unwrap();
-
-        assert_eq!(4, vec.len());
-        assert_eq!(0x23, vec[0]);
-        assert_eq!(0x34, vec[1]);
-        assert_eq!(0x45, vec[2]);
-        assert_eq!(0x56, vec[3]);
-
-        let vec = source.read_unicode().unwrap();
+    fn test_reader_with_seek_keeps_error() {
+        struct FailingReader;
 
-        assert_eq!(4, vec.len());
-        assert_eq!(0x67, vec[0]);
-        assert_eq!(0x78, vec[1]);
-        assert_eq!(0x89, vec[2]);
-        assert_eq!(0xFF, vec[3]);
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                return Err(std::io::Error::other("bad sector"));
+            }
+        }
 
-        let vec = source.read_unicode().unwrap();
+        let mut source = ReaderWithSeek::new(Box::new((&b"text"[..]).chain(FailingReader)));
+        let mut output = Vec::<u8>::new();
+        print_strings(OsStr::new("test"), 0, &mut source, &Options::default(), &mut output);
 
-        assert_eq!(1, vec.len());
-        assert_eq!(0xAA, vec[0]);
+        assert_eq!("text\n", String::from_utf8(output).unwrap());
+        assert_eq!("bad sector", source.error.unwrap().to_string());
     }
 
     #[test]
-    fn test_data_source_backed_by_array_unicode() {
-        let buffer = [0x12u8, 0x23, 0x34, 0x45, 0x56, 0x67, 0x78, 0x89, 0xFF, 0xAA];
-
-        let mut source = ByteArrayHolder {
-            inner: &buffer,
-            position: 0,
-        };
-
-        assert_eq!(0x12, source.read_byte().unwrap());
-
-        let vec = source.read_unicode().unwrap();
-
-        assert_eq!(4, vec.len());
-        assert_eq!(0x23, vec[0]);
-        assert_eq!(0x34, vec[1]);
-        assert_eq!(0x45, vec[2]);
-        assert_eq!(0x56, vec[3]);
-
-        source.seek_back(3);
-
-        let vec = source.read_unicode().unwrap();
-
-        assert_eq!(4, vec.len());
-        assert_eq!(0x34, vec[0]);
-        assert_eq!(0x45, vec[1]);
-        assert_eq!(0x56, vec[2]);
-        assert_eq!(0x67, vec[3]);
-
-        source.seek_back(5);
+    fn test_overlay() {
+        let mut options = Options::default();
+        options.overlay_only = true;
+        options.print_addresses = true;
+        options.address_radix = RadixKind::Dec;
 
-        let vec = source.read_unicode().unwrap();
+        let mut data = fs::read(TEST_OBJECT_FILE_PATH).unwrap();
+        let image_size = data.len();
+        data.extend(b"\x01PK\x03\x04archive.zip\0\x02setup.ini\0");
+        let mut output = Vec::<u8>::new();
+        let mut printer = StringPrinter::new(&mut output, &options);
+        print_strings_for_bytes(OsStr::new("test"), &data, &options, &mut printer);
+        assert_eq!(
+            format!(
+                "{:7} {{overlay+0x5}} archive.zip\n{:7} {{overlay+0x12}} setup.ini\n",
+                image_size + 5, image_size + 18
+            ),
+            String::from_utf8(output).unwrap()
+        );
+    }
 
-        assert_eq!(4, vec.len());
-        assert_eq!(0x23, vec[0]);
-        assert_eq!(0x34, vec[1]);
-        assert_eq!(0x45, vec[2]);
-        assert_eq!(0x56, vec[3]);
+    #[test]
+    fn test_macho_section_flags() {
+        let is_data = |flags: u32| macho_section_flags(flags) & DATA_FLAGS == DATA_FLAGS;
+        // __TEXT,__text, __TEXT,__cstring, __DATA,__data
+        assert!(is_data(macho::S_REGULAR | macho::S_ATTR_PURE_INSTRUCTIONS
+            | macho::S_ATTR_SOME_INSTRUCTIONS));
+        assert!(is_data(macho::S_CSTRING_LITERALS));
+        assert!(is_data(macho::S_REGULAR));
+        // __DATA,__bss, __DWARF,__debug_str
+        assert!(!is_data(macho::S_ZEROFILL));
+        assert!(!is_data(macho::S_REGULAR | macho::S_ATTR_DEBUG));
+    }
 
-        let vec = source.read_unicode().unwrap();
+    #[test]
+    fn test_original_bytes() {
+        struct RangeChecker<'a> {
+            buffer: &'a [u8],
+            decode_wide: bool,
+            num_found: usize,
+        }
 
-        assert_eq!(4, vec.len());
-        assert_eq!(0x67, vec[0]);
-        assert_eq!(0x78, vec[1]);
-        assert_eq!(0x89, vec[2]);
-        assert_eq!(0xFF, vec[3]);
+        impl StringSink for RangeChecker<'_> {
+            fn on_string(&mut self, found: &FoundString) {
+                let range = found.address as usize..found.end_address as usize;
+                assert_eq!(&self.buffer[range], &found.original_bytes(self.decode_wide)[..]);
+                self.num_found += 1;
+            }
+        }
 
-        let vec = source.read_unicode().unwrap();
+        // text in every encoding, with the characters which don't fit a byte for the wide ones,
+        // binary noise in between
+        let mut buffer = b"caf\xe9 \xf1u\x01".to_vec();
+        buffer.extend("na\u{ef}ve r\u{e9}sum\u{e9}\x01".as_bytes());
+        buffer.extend(b"\xa3\x85\xa7\xa3\x40\x81\x82\x01");
+        for text in ["caf\u{e9} \u{f1}u", "\u{3b1}\u{3b2}\u{3b3} \u{1f600} text"] {
+            let units: Vec<u16> = text.encode_utf16().collect();
+            buffer.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+            buffer.extend(b"\x01\x01");
+            buffer.extend(units.iter().flat_map(|unit| unit.to_be_bytes()));
+            buffer.extend(b"\x01\x01");
+            buffer.extend(text.chars().flat_map(|c| (c as u32).to_le_bytes()));
+            buffer.extend(b"\x01\x01\x01\x01");
+            buffer.extend(text.chars().flat_map(|c| (c as u32).to_be_bytes()));
+            buffer.extend(b"\x01\x01\x01\x01");
+        }
 
-        assert_eq!(1, vec.len());
-        assert_eq!(0xAA, vec[0]);
+        // the Unicode scan finds the UTF-8 text only
+        let modes = [
+            (false, UnicodeDisplayKind::Default, 8),
+            (true, UnicodeDisplayKind::Default, 8),
+            (false, UnicodeDisplayKind::Escape, 1),
+        ];
+        for (decode_wide, unicode_display, min_found) in modes {
+            let mut options = Options::default();
+            options.encoding = EncodingKind::Bit8;
+            options.extra_encodings = vec![
+                EncodingKind::Ebcdic037, EncodingKind::LittleEndian16, EncodingKind::BigEndian16,
+                EncodingKind::LittleEndian32, EncodingKind::BigEndian32,
+            ];
+            options.decode_wide = decode_wide;
+            options.unicode_display = unicode_display;
+            let mut data = ByteArrayHolder {
+                inner: &buffer,
+                position: 0,
+            };
+            let mut checker = RangeChecker { buffer: &buffer, decode_wide, num_found: 0 };
+            let num_found = scan_strings(OsStr::new("test"), 0, &mut data, &options, &mut checker);
+            assert_eq!(num_found as usize, checker.num_found);
+            assert!(checker.num_found >= min_found, "{}", decode_wide);
+        }
     }
 }
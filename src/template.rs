@@ -0,0 +1,347 @@
+/*
+ Output formatting with a user template, see `--output-format`. The template is text with
+ placeholders in braces, e.g. `{file}:{offset:#x} {string}`. A placeholder may have a spec after
+ a colon, a subset of the Rust format specs: `<` or `>` to align, `#` for the radix prefix, `0`
+ to pad the numbers with zeros, the width, and the radix of the numbers (`x`, `X`, `o`, `d`).
+ `{{` and `}}` are literal braces.
+ */
+
+use std::ffi::OsStr;
+use std::io::Write;
+use super::diagnostics::check_write;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, Options, RadixKind, StringSink};
+use super::utils::os_str_bytes;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Field {
+    File,
+    Offset,
+    End,
+    Length,
+    Section,
+    Encoding,
+    Termination,
+    String,
+    Tags,
+}
+
+const FIELDS: [(&str, Field); 9] = [
+    ("file", Field::File),
+    ("offset", Field::Offset),
+    ("end", Field::End),
+    ("length", Field::Length),
+    ("section", Field::Section),
+    ("encoding", Field::Encoding),
+    ("termination", Field::Termination),
+    ("string", Field::String),
+    ("tags", Field::Tags),
+];
+
+#[derive(Default, PartialEq, Debug)]
+struct Spec {
+    /// `Some(true)` to align to the left, `None` for the default of the field
+    align_left: Option<bool>,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    /// `x`, `X`, `o` or `d`, `None` for the radix of `-t`
+    radix: Option<char>,
+}
+
+#[derive(PartialEq, Debug)]
+enum Piece {
+    Literal(String),
+    Placeholder(Field, Spec),
+}
+
+/**
+A parsed output template.
+ */
+#[derive(Debug)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    /**
+    Parses the template, returns an error message if it is malformed or has an unknown
+    placeholder.
+     */
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(format!(
+                                    "invalid argument to --output-format: unclosed '{{{}'",
+                                    placeholder
+                                ));
+                            }
+                        }
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => {
+                    return Err(
+                        "invalid argument to --output-format: unmatched '}', use '}}'".to_string()
+                    );
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        return Ok(Template { pieces });
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Piece, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, spec),
+        None => (placeholder, "")
+    };
+
+    let field = match FIELDS.iter().find(|(field_name, _)| *field_name == name) {
+        Some((_, field)) => *field,
+        None => {
+            let names: Vec<&str> = FIELDS.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "invalid argument to --output-format: unknown placeholder '{{{}}}', \
+                 expected one of {}",
+                name, names.join(", ")
+            ));
+        }
+    };
+
+    let invalid_spec = || {
+        return format!(
+            "invalid argument to --output-format: invalid spec in '{{{}}}'", placeholder
+        );
+    };
+    let mut parsed = Spec::default();
+    let mut rest = spec;
+    if let Some(align) = rest.chars().next().filter(|c| *c == '<' || *c == '>') {
+        parsed.align_left = Some(align == '<');
+        rest = &rest[1..];
+    }
+    if let Some(after) = rest.strip_prefix('#') {
+        parsed.alternate = true;
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix('0') {
+        parsed.zero = true;
+        rest = after;
+    }
+    let width_len = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if width_len > 0 {
+        parsed.width = rest[..width_len].parse().map_err(|_| invalid_spec())?;
+        rest = &rest[width_len..];
+    }
+    match rest {
+        "" => {}
+        "x" | "X" | "o" | "d" => parsed.radix = rest.chars().next(),
+        _ => return Err(invalid_spec())
+    }
+
+    let is_number = matches!(field, Field::Offset | Field::End | Field::Length);
+    if !is_number && (parsed.alternate || parsed.zero || parsed.radix.is_some()) {
+        return Err(invalid_spec());
+    }
+    return Ok(Piece::Placeholder(field, parsed));
+}
+
+fn format_number(value: u64, spec: &Spec, default_radix: char) -> String {
+    let radix = spec.radix.unwrap_or(default_radix);
+    let digits = match radix {
+        'x' => format!("{:x}", value),
+        'X' => format!("{:X}", value),
+        'o' => format!("{:o}", value),
+        _ => format!("{}", value),
+    };
+    let prefix = match (spec.alternate, radix) {
+        (true, 'x') | (true, 'X') => "0x",
+        (true, 'o') => "0o",
+        _ => "",
+    };
+    if spec.zero && spec.align_left.is_none() {
+        let num_zeros = spec.width.saturating_sub(prefix.len() + digits.len());
+        return format!("{}{}{}", prefix, "0".repeat(num_zeros), digits);
+    }
+    return format!("{}{}", prefix, digits);
+}
+
+/**
+Prints found strings laid out by the template, one per line (or ended with the separator of
+`-s`).
+ */
+pub struct TemplatePrinter<'a> {
+    writer: &'a mut dyn Write,
+    template: &'a Template,
+    options: &'a Options,
+}
+
+impl<'a> TemplatePrinter<'a> {
+    pub fn new(
+        writer: &'a mut dyn Write,
+        template: &'a Template,
+        options: &'a Options,
+    ) -> TemplatePrinter<'a> {
+        return TemplatePrinter { writer, template, options };
+    }
+
+    fn write_padded(&mut self, value: &[u8], spec: &Spec, align_left: bool) {
+        let num_chars = String::from_utf8_lossy(value).chars().count();
+        let padding = " ".repeat(spec.width.saturating_sub(num_chars));
+        let align_left = spec.align_left.unwrap_or(align_left);
+        if !align_left {
+            check_write(self.writer.write_all(padding.as_bytes()));
+        }
+        check_write(self.writer.write_all(value));
+        if align_left {
+            check_write(self.writer.write_all(padding.as_bytes()));
+        }
+    }
+}
+
+impl StringSink for TemplatePrinter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let default_radix = match self.options.address_radix {
+            RadixKind::Oct => 'o',
+            RadixKind::Dec => 'd',
+            RadixKind::Hex => 'x',
+        };
+
+        for piece in &self.template.pieces {
+            let (field, spec) = match piece {
+                Piece::Literal(text) => {
+                    check_write(self.writer.write_all(text.as_bytes()));
+                    continue;
+                }
+                Piece::Placeholder(field, spec) => (*field, spec),
+            };
+
+            let number = match field {
+                Field::Offset => Some(found.address),
+                Field::End => Some(found.end_address),
+                Field::Length => Some(found.end_address - found.address),
+                _ => None
+            };
+            if let Some(number) = number {
+                // the lengths are counts, not positions
+                let radix = if field == Field::Length { 'd' } else { default_radix };
+                let formatted = format_number(number, spec, radix);
+                self.write_padded(formatted.as_bytes(), spec, false);
+                continue;
+            }
+
+            let (filename, tags);
+            let value: &[u8] = match field {
+                Field::File => {
+                    filename = os_str_bytes(found.filename);
+                    &filename
+                }
+                Field::Section => found.section.unwrap_or("").as_bytes(),
+                Field::Encoding => found.encoding.name().as_bytes(),
+                Field::Termination => found.termination.name().as_bytes(),
+                Field::String => found.text,
+                _ => {
+                    tags = found.tags.join(",");
+                    tags.as_bytes()
+                }
+            };
+            self.write_padded(value, spec, true);
+        }
+
+        check_write(match &self.options.output_separator {
+            Some(separator) => self.writer.write_all(separator),
+            None => self.writer.write_all(b"\n")
+        });
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        check_write(self.writer.write_all(&os_str_bytes(filename)));
+        check_write(writeln!(
+            self.writer, ": {} package {} {} {}",
+            package.format, package.name, package.version, package.architecture
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::{EncodingKind, Termination};
+
+    fn render(template: &str, options: &Options) -> String {
+        let template = Template::parse(template).unwrap();
+        let mut output = Vec::new();
+        let mut printer = TemplatePrinter::new(&mut output, &template, options);
+        printer.on_string(&FoundString {
+            filename: OsStr::new("a.out"),
+            address: 0x1a,
+            end_address: 0x20,
+            bytes: b"hello!",
+            text: b"hello!",
+            encoding: EncodingKind::Bit7,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: Some(".rodata"),
+        });
+        return String::from_utf8(output).unwrap();
+    }
+
+    #[test]
+    fn test_render() {
+        let options = Options::default();
+        assert_eq!("a.out:0x1a hello!\n", render("{file}:{offset:#x} {string}", &options));
+        assert_eq!(
+            "[0x0000001a-32] len=6 .rodata/7bit/nul\n",
+            render(
+                "[{offset:#010x}-{end:d}] len={length} {section}/{encoding}/{termination}",
+                &options,
+            )
+        );
+        assert_eq!("   1a|hello!    |{}\n", render("{offset:>5}|{string:10}|{{}}", &options));
+
+        let mut options = Options::default();
+        options.address_radix = RadixKind::Oct;
+        options.output_separator = Some(b"\0".to_vec());
+        assert_eq!("32 6\0", render("{offset} {length}", &options));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::parse("{offset").unwrap_err().contains("unclosed"));
+        assert!(Template::parse("offset}").unwrap_err().contains("unmatched"));
+        assert!(Template::parse("{address}").unwrap_err().contains("unknown placeholder"));
+        assert!(Template::parse("{offset:q}").unwrap_err().contains("invalid spec"));
+        assert!(Template::parse("{string:#x}").unwrap_err().contains("invalid spec"));
+    }
+}
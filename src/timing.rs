@@ -0,0 +1,113 @@
+/*
+ Throughput report, see `--time`: the bytes scanned, the wall-clock time and the speed of every
+ input and of the whole run, to compare the options without external tools. The bytes are the
+ sizes the inputs are reported with, so the members of a package count with their unpacked
+ sizes and stdin with an unknown one.
+ */
+
+use std::ffi::OsStr;
+use std::io::Write;
+use std::time::Duration;
+use super::diagnostics::check_write;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+pub struct InputTiming {
+    pub name: String,
+    /// `None` if the size of some part of the input is unknown
+    pub bytes: Option<u64>,
+    pub elapsed: Duration,
+}
+
+/**
+Sums the sizes of the inputs passed to the inner sink.
+ */
+pub struct ByteCounter<'a> {
+    bytes: Option<u64>,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> ByteCounter<'a> {
+    pub fn new(inner: &'a mut dyn StringSink) -> ByteCounter<'a> {
+        return ByteCounter { bytes: Some(0), inner };
+    }
+
+    /**
+    Returns the bytes counted since the last call and starts counting again.
+     */
+    pub fn take_bytes(&mut self) -> Option<u64> {
+        return self.bytes.replace(0);
+    }
+}
+
+impl StringSink for ByteCounter<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        self.inner.on_string(found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.bytes = self.bytes.zip(size).map(|(bytes, size)| bytes + size);
+        self.inner.on_input(filename, size);
+    }
+}
+
+fn write_timing(writer: &mut dyn Write, name: &str, bytes: Option<u64>, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    check_write(match bytes {
+        Some(bytes) if seconds > 0.0 => writeln!(
+            writer, "{}: {} bytes in {:.3} s, {:.2} MB/s",
+            name, bytes, seconds, bytes as f64 / seconds / 1_000_000.0
+        ),
+        Some(bytes) => writeln!(writer, "{}: {} bytes in {:.3} s", name, bytes, seconds),
+        None => writeln!(writer, "{}: ? bytes in {:.3} s", name, seconds),
+    });
+}
+
+/**
+Prints a line per input and, if there are several, the total. The total is of the inputs with a
+known size, and its time includes the time between the inputs, e.g. to open them.
+ */
+pub fn print_timings(timings: &[InputTiming], total_elapsed: Duration, writer: &mut dyn Write) {
+    for timing in timings {
+        write_timing(writer, &timing.name, timing.bytes, timing.elapsed);
+    }
+    if timings.len() > 1 {
+        let bytes = timings.iter().filter_map(|timing| timing.bytes).sum();
+        write_timing(writer, "total", Some(bytes), total_elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_timings() {
+        let timing = |name: &str, bytes: Option<u64>, millis: u64| {
+            return InputTiming {
+                name: name.to_string(),
+                bytes,
+                elapsed: Duration::from_millis(millis),
+            };
+        };
+
+        let mut output = Vec::new();
+        print_timings(&[timing("a.out", Some(2_000_000), 500)], Duration::ZERO, &mut output);
+        assert_eq!(
+            "a.out: 2000000 bytes in 0.500 s, 4.00 MB/s\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        let mut output = Vec::new();
+        let timings = [timing("a.out", Some(0), 0), timing("<stdin>", None, 10)];
+        print_timings(&timings, Duration::from_millis(20), &mut output);
+        assert_eq!(
+            "a.out: 0 bytes in 0.000 s\n<stdin>: ? bytes in 0.010 s\ntotal: 0 bytes in 0.020 s, 0.00 MB/s\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,428 @@
+/*
+ The terminal browser of the strings, see `--tui`: the strings of all the inputs are listed in a
+ scrollable view, filtered as the filter is typed, and the bytes around the selected string are
+ dumped below the list, the string highlighted.
+
+ The filter is made of terms separated by spaces, which must all match:
+  - `enc:NAME`: the encoding of the string, e.g. `enc:16le`;
+  - `sec:NAME`: the section of the string contains NAME, e.g. `sec:rodata`;
+  - `re:PATTERN`: the string matches the regular expression, e.g. `re:(?i)^http`;
+  - anything else: the string contains the term.
+
+ The keys are the ones of less: the arrows, j and k, Page Up and Page Down, g and G to move, /
+ to edit the filter, Enter or Esc to stop editing it, and q or Esc to quit.
+ */
+
+use std::ffi::OsStr;
+use std::path::Path;
+use regex::Regex;
+use super::packages::PackageInfo;
+use super::strings::{EncodingKind, FoundString, StringSink};
+
+#[cfg(feature = "tui")]
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+#[cfg(feature = "tui")]
+use ratatui::layout::{Constraint, Layout};
+#[cfg(feature = "tui")]
+use ratatui::style::{Color, Modifier, Style};
+#[cfg(feature = "tui")]
+use ratatui::text::{Line, Span};
+#[cfg(feature = "tui")]
+use ratatui::widgets::{Block, Paragraph};
+#[cfg(feature = "tui")]
+use ratatui::{DefaultTerminal, Frame};
+
+/// Number of bytes per line of the dump.
+#[cfg(feature = "tui")]
+const DUMP_LINE_SIZE: usize = 16;
+/// Number of lines of the dump, with its borders.
+#[cfg(feature = "tui")]
+const DUMP_HEIGHT: u16 = 8;
+
+/**
+A found string kept for the browser, with the bytes around it.
+ */
+pub struct BrowsedString {
+    pub filename: String,
+    pub address: u64,
+    pub encoding: EncodingKind,
+    pub section: Option<String>,
+    pub tags: Vec<String>,
+    pub text: String,
+    /// The bytes before the string, the bytes of the string and the bytes after it.
+    pub data: Vec<u8>,
+    /// Where the bytes of the string are in `data`.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl BrowsedString {
+    /**
+    Returns the text with the control characters replaced, so that they don't mess up the view.
+     */
+    #[cfg(feature = "tui")]
+    fn display_text(&self) -> String {
+        return self.text.chars().map(|c| if c.is_control() { '.' } else { c }).collect();
+    }
+}
+
+/**
+Keeps the strings for the browser. The bytes around them are the context attached to them, see
+`Options::context_before`.
+ */
+pub struct StringCollector {
+    pub strings: Vec<BrowsedString>,
+    decode_wide: bool,
+}
+
+impl StringCollector {
+    pub fn new(decode_wide: bool) -> StringCollector {
+        return StringCollector { strings: Vec::new(), decode_wide };
+    }
+}
+
+impl StringSink for StringCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut data = found.context_before.to_vec();
+        let start = data.len();
+        data.extend_from_slice(&found.original_bytes(self.decode_wide));
+        let end = data.len();
+        data.extend_from_slice(found.context_after);
+
+        self.strings.push(BrowsedString {
+            filename: Path::new(found.filename).display().to_string(),
+            address: found.address,
+            encoding: found.encoding,
+            section: found.section.map(str::to_string),
+            tags: found.tags.to_vec(),
+            text: String::from_utf8_lossy(found.text).into_owned(),
+            data,
+            start,
+            end,
+        });
+    }
+
+    fn on_package(&mut self, _filename: &OsStr, _package: &PackageInfo) {}
+}
+
+enum Term {
+    Text(String),
+    Pattern(Regex),
+    Encoding(String),
+    Section(String),
+}
+
+/**
+The filter of the strings, see the module description.
+ */
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Filter, String> {
+        let mut terms = Vec::new();
+        for term in query.split_whitespace() {
+            terms.push(if let Some(name) = term.strip_prefix("enc:") {
+                Term::Encoding(name.to_string())
+            } else if let Some(name) = term.strip_prefix("sec:") {
+                Term::Section(name.to_string())
+            } else if let Some(pattern) = term.strip_prefix("re:") {
+                Term::Pattern(Regex::new(pattern).map_err(|err| err.to_string())?)
+            } else {
+                Term::Text(term.to_string())
+            });
+        }
+        return Ok(Filter { terms });
+    }
+
+    pub fn matches(&self, string: &BrowsedString) -> bool {
+        return self.terms.iter().all(|term| {
+            return match term {
+                Term::Text(text) => string.text.contains(text.as_str()),
+                Term::Pattern(pattern) => pattern.is_match(&string.text),
+                Term::Encoding(name) => string.encoding.name() == name,
+                Term::Section(name) => {
+                    string.section.as_ref().is_some_and(|section| section.contains(name.as_str()))
+                }
+            };
+        });
+    }
+}
+
+/**
+The state of the browser: the shown strings, the selected one and the filter.
+ */
+#[cfg(feature = "tui")]
+struct Browser {
+    strings: Vec<BrowsedString>,
+    /// Indexes of the strings which match the filter.
+    shown: Vec<usize>,
+    /// Index of the selected string in `shown`, and of the first one in the view.
+    selected: usize,
+    offset: usize,
+    /// Number of the strings in the view at the last draw.
+    height: usize,
+    query: String,
+    editing: bool,
+    /// Why the query is not a valid filter, the strings of the last valid one are shown.
+    error: Option<String>,
+    multiple_inputs: bool,
+}
+
+#[cfg(feature = "tui")]
+impl Browser {
+    fn new(strings: Vec<BrowsedString>) -> Browser {
+        let multiple_inputs = strings.windows(2).any(|pair| pair[0].filename != pair[1].filename);
+        return Browser {
+            shown: (0..strings.len()).collect(),
+            strings,
+            selected: 0,
+            offset: 0,
+            height: 1,
+            query: String::new(),
+            editing: false,
+            error: None,
+            multiple_inputs,
+        };
+    }
+
+    fn apply_filter(&mut self) {
+        let filter = match Filter::parse(&self.query) {
+            Ok(filter) => filter,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            }
+        };
+        self.error = None;
+        self.shown = (0..self.strings.len())
+            .filter(|index| filter.matches(&self.strings[*index]))
+            .collect();
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let last = self.shown.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /**
+    Returns false once the browser is to be closed.
+     */
+    fn on_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        let page = self.height.max(1) as isize;
+        if self.editing {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.editing = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.apply_filter();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.apply_filter();
+                }
+                KeyCode::Up => self.move_by(-1),
+                KeyCode::Down => self.move_by(1),
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.move_by(page),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX),
+            _ => {}
+        }
+        return true;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, dump_area, status_area] = Layout::vertical([
+            Constraint::Min(3), Constraint::Length(DUMP_HEIGHT), Constraint::Length(1),
+        ]).areas(frame.area());
+
+        // the selected string stays in the view
+        self.height = list_area.height.saturating_sub(2) as usize;
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + self.height {
+            self.offset = self.selected + 1 - self.height;
+        }
+
+        let lines: Vec<Line> = self.shown.iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.height)
+            .map(|(position, index)| {
+                let line = Line::raw(self.list_line(&self.strings[*index]));
+                return if position == self.selected {
+                    line.style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                };
+            })
+            .collect();
+        let title = format!(" {} of {} strings ", self.shown.len(), self.strings.len());
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), list_area);
+
+        let selected = self.shown.get(self.selected).map(|index| &self.strings[*index]);
+        let (dump_title, dump) = match selected {
+            Some(string) => (dump_title(string), dump_lines(string)),
+            None => (String::new(), Vec::new()),
+        };
+        let dump = Paragraph::new(dump).block(Block::bordered().title(dump_title));
+        frame.render_widget(dump, dump_area);
+
+        let status = match (&self.error, self.editing) {
+            (Some(err), _) => Line::styled(
+                format!("/{}  {}", self.query, err.lines().last().unwrap_or("")),
+                Style::new().fg(Color::Red),
+            ),
+            (None, true) => Line::raw(format!("/{}", self.query)),
+            (None, false) if !self.query.is_empty() => Line::raw(format!(
+                "filter: {}  (/ to edit, q to quit)", self.query
+            )),
+            (None, false) => Line::raw("/ to filter, q to quit"),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+        if self.editing {
+            let column = status_area.x + 1 + self.query.chars().count() as u16;
+            frame.set_cursor_position((column.min(status_area.right()), status_area.y));
+        }
+    }
+
+    fn list_line(&self, string: &BrowsedString) -> String {
+        let mut line = String::new();
+        if self.multiple_inputs {
+            line.push_str(&format!("{}: ", string.filename));
+        }
+        line.push_str(&format!("{:8x}  {:<5} ", string.address, string.encoding.name()));
+        if !string.tags.is_empty() {
+            line.push_str(&format!("{{{}}} ", string.tags.join(",")));
+        }
+        line.push_str(&string.display_text());
+        return line;
+    }
+}
+
+#[cfg(feature = "tui")]
+fn dump_title(string: &BrowsedString) -> String {
+    let section = match &string.section {
+        Some(section) => format!(" [{}]", section),
+        None => String::new(),
+    };
+    let length = string.end - string.start;
+    return format!(" {}{} {:#x}, {} bytes ", string.filename, section, string.address, length);
+}
+
+/**
+Returns the lines of the dump of the bytes around the string, as `hexdump -C` prints them, with
+the bytes of the string highlighted.
+ */
+#[cfg(feature = "tui")]
+fn dump_lines(string: &BrowsedString) -> Vec<Line<'static>> {
+    let highlighted = Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let data_address = string.address - string.start as u64;
+    let mut lines = Vec::new();
+    for (i, chunk) in string.data.chunks(DUMP_LINE_SIZE).enumerate() {
+        let line_start = i * DUMP_LINE_SIZE;
+        let mut spans = vec![Span::raw(format!("{:08x}  ", data_address + line_start as u64))];
+        let mut chars = vec![Span::raw("|")];
+        for (j, byte) in chunk.iter().enumerate() {
+            let in_string = (string.start..string.end).contains(&(line_start + j));
+            let style = if in_string { highlighted } else { Style::new() };
+            spans.push(Span::styled(format!("{:02x}", byte), style));
+            spans.push(Span::raw(" "));
+            let c = if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' };
+            chars.push(Span::styled(c.to_string(), style));
+        }
+        spans.push(Span::raw(" ".repeat(3 * (DUMP_LINE_SIZE - chunk.len()))));
+        chars.push(Span::raw("|"));
+        spans.extend(chars);
+        lines.push(Line::from(spans));
+    }
+    return lines;
+}
+
+/**
+Shows the strings in the terminal until the browser is closed.
+ */
+#[cfg(feature = "tui")]
+pub fn browse(strings: Vec<BrowsedString>) -> Result<(), String> {
+    let mut terminal = ratatui::init();
+    let result = run_browser(&mut terminal, Browser::new(strings));
+    ratatui::restore();
+    return result.map_err(|err| err.to_string());
+}
+
+#[cfg(feature = "tui")]
+fn run_browser(terminal: &mut DefaultTerminal, mut browser: Browser) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !browser.on_key(key.code, key.modifiers) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn browse(_strings: Vec<BrowsedString>) -> Result<(), String> {
+    return Err("strings was built without the tui feature".to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browsed(text: &str, encoding: EncodingKind, section: Option<&str>) -> BrowsedString {
+        return BrowsedString {
+            filename: "test".to_string(),
+            address: 0x40,
+            encoding,
+            section: section.map(str::to_string),
+            tags: Vec::new(),
+            text: text.to_string(),
+            data: text.as_bytes().to_vec(),
+            start: 0,
+            end: text.len(),
+        };
+    }
+
+    #[test]
+    fn test_filter() {
+        let url = browsed("https://example.org", EncodingKind::Bit7, Some(".rodata"));
+        let wide = browsed("Global\\Mutex", EncodingKind::LittleEndian16, Some(".data"));
+        let anywhere = browsed("example text\tand\ttabs", EncodingKind::Bit7, None);
+
+        let matching = |query: &str| -> Vec<&str> {
+            let filter = Filter::parse(query).unwrap();
+            return [&url, &wide, &anywhere].into_iter()
+                .filter(|string| filter.matches(string))
+                .map(|string| string.text.as_str())
+                .collect();
+        };
+        assert_eq!(3, matching("").len());
+        assert_eq!(vec!["https://example.org", "example text\tand\ttabs"], matching("example"));
+        assert_eq!(vec!["https://example.org"], matching("example sec:rodata"));
+        assert_eq!(vec!["Global\\Mutex"], matching("enc:16le"));
+        assert_eq!(vec!["https://example.org", "Global\\Mutex"], matching("sec:data"));
+        assert_eq!(vec!["Global\\Mutex"], matching(r"re:^[A-Z]\w+\\"));
+        assert!(Filter::parse("re:(").is_err());
+
+        #[cfg(feature = "tui")]
+        assert_eq!("example text.and.tabs", anywhere.display_text());
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ Incremental UTF-8 decoding of the scanned data for `--unicode`. The bytes are split into the
+ ASCII ones, the multibyte characters and the bytes which don't start a character, one unit at
+ a time, so the scan knows the exact offset and length of every character. The sequences are
+ accepted as GNU strings accepts them: a lead byte followed by the continuation bytes it asks
+ for, without the checks for the overlong forms.
+ */
+
+/// Longest UTF-8 sequence.
+pub(crate) const MAX_CHAR_LEN: usize = 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Utf8Unit {
+    Ascii(u8),
+    Char { bytes: [u8; MAX_CHAR_LEN], len: usize },
+    /// A byte which doesn't start a character: a stray continuation byte, or a lead byte
+    /// without the continuation bytes it asks for.
+    Invalid(u8),
+}
+
+impl Utf8Unit {
+    /**
+    Returns the number of bytes of the data the unit takes.
+     */
+    pub(crate) fn len(&self) -> usize {
+        return match self {
+            Utf8Unit::Char { len, .. } => *len,
+            Utf8Unit::Ascii(_) | Utf8Unit::Invalid(_) => 1,
+        };
+    }
+}
+
+/**
+Returns the code point of a character, as GNU strings decodes it: from the bits of the lead and
+the continuation bytes, whatever the length of the sequence is.
+ */
+pub(crate) fn code_point(bytes: &[u8]) -> u32 {
+    let lead_bits = match bytes.len() {
+        1 => 0x7f,
+        2 => 0x1f,
+        3 => 0x0f,
+        _ => 0x07,
+    };
+    return bytes[1..].iter()
+        .fold((bytes[0] & lead_bits) as u32, |code_point, byte| {
+            return (code_point << 6) | (byte & 0x3f) as u32;
+        });
+}
+
+/**
+Decodes the units from the bytes of a source. The bytes read after a lead byte which turned out
+to be invalid are given back and decoded again, so a character right after a broken one is
+still found.
+ */
+pub(crate) struct Utf8Decoder {
+    /// the bytes given back, the next one last
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub(crate) fn new() -> Utf8Decoder {
+        return Utf8Decoder { pending: Vec::with_capacity(MAX_CHAR_LEN) };
+    }
+
+    fn read(&mut self, read_byte: &mut dyn FnMut() -> Option<u8>) -> Option<u8> {
+        return match self.pending.pop() {
+            Some(byte) => Some(byte),
+            None => read_byte(),
+        };
+    }
+
+    /**
+    Returns the next unit, or `None` at the end of the data.
+     */
+    pub(crate) fn next(&mut self, read_byte: &mut dyn FnMut() -> Option<u8>) -> Option<Utf8Unit> {
+        let lead = self.read(read_byte)?;
+        let len = match lead {
+            0x00..=0x7f => return Some(Utf8Unit::Ascii(lead)),
+            0x80..=0xbf => return Some(Utf8Unit::Invalid(lead)),
+            _ if lead & 0x20 == 0 => 2,
+            _ if lead & 0x10 == 0 => 3,
+            _ => 4,
+        };
+
+        let mut bytes = [lead, 0, 0, 0];
+        let mut num_read = 1;
+        while num_read < len {
+            let Some(byte) = self.read(read_byte) else {
+                break;
+            };
+            bytes[num_read] = byte;
+            num_read += 1;
+            if byte & 0xc0 != 0x80 {
+                break;
+            }
+        }
+
+        if num_read == len && bytes[1..len].iter().all(|byte| byte & 0xc0 == 0x80) {
+            return Some(Utf8Unit::Char { bytes, len });
+        }
+        self.pending.extend(bytes[1..num_read].iter().rev());
+        return Some(Utf8Unit::Invalid(lead));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Vec<Utf8Unit> {
+        let mut bytes = data.iter().copied();
+        let mut read_byte = || bytes.next();
+        let mut decoder = Utf8Decoder::new();
+        let mut units = Vec::new();
+        while let Some(unit) = decoder.next(&mut read_byte) {
+            units.push(unit);
+        }
+        return units;
+    }
+
+    #[test]
+    fn test_decode() {
+        let char = |text: &str| {
+            let mut bytes = [0u8; MAX_CHAR_LEN];
+            bytes[..text.len()].copy_from_slice(text.as_bytes());
+            return Utf8Unit::Char { bytes, len: text.len() };
+        };
+
+        assert_eq!(
+            vec![Utf8Unit::Ascii(b'a'), char("é"), char("€"), char("𐍈"), Utf8Unit::Ascii(0)],
+            decode("aé€𐍈\0".as_bytes())
+        );
+        assert_eq!(
+            vec![
+                Utf8Unit::Invalid(0x80),
+                // a lead byte cut short by a character
+                Utf8Unit::Invalid(0xe2), char("é"),
+                Utf8Unit::Invalid(0xf0), Utf8Unit::Invalid(0x90), Utf8Unit::Ascii(b'x'),
+                // cut short by the end of the data
+                Utf8Unit::Invalid(0xe2), Utf8Unit::Invalid(0x82),
+            ],
+            decode(b"\x80\xe2\xc3\xa9\xf0\x90x\xe2\x82")
+        );
+        assert_eq!(4, decode("𐍈".as_bytes())[0].len());
+    }
+
+    #[test]
+    fn test_code_point() {
+        for text in ["a", "é", "€", "𐍈"] {
+            assert_eq!(text.chars().next().unwrap() as u32, code_point(text.as_bytes()));
+        }
+    }
+}
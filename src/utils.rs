@@ -1,5 +1,40 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
 use super::strings::EncodingKind;
 
+/**
+Returns the bytes to print for a file name: the raw bytes on Unix, elsewhere the name as UTF-8
+with the invalid sequences replaced.
+ */
+#[cfg(unix)]
+pub(crate) fn os_str_bytes(text: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    return Cow::Borrowed(text.as_bytes());
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_str_bytes(text: &OsStr) -> Cow<'_, [u8]> {
+    return match text.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    };
+}
+
+/**
+The reverse of `os_str_bytes`: the bytes as is on Unix, elsewhere decoded as UTF-8 with the
+invalid sequences replaced.
+ */
+#[cfg(unix)]
+pub(crate) fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    return OsString::from_vec(bytes);
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    return OsString::from(String::from_utf8_lossy(&bytes).into_owned());
+}
+
 pub(crate) fn char_is_printable(c: char, encoding: EncodingKind,
                                 include_all_whitespace: bool) -> bool {
     return c >= '\x00' && c <= '\u{ff}' &&
@@ -9,63 +44,67 @@ pub(crate) fn char_is_printable(c: char, encoding: EncodingKind,
             (include_all_whitespace && (c.is_ascii_whitespace() || c == '\x0b')));
 }
 
-pub(crate) fn to_little_endian_32(symbol: u32) -> u32 {
-    return ((symbol & 0xff) << 24) | ((symbol & 0xff00) << 8) |
-        ((symbol & 0xff0000) >> 8) | ((symbol & 0xff000000) >> 24);
+/**
+Printable check for the decoded UTF-16/UTF-32 characters, see `--decode-wide`.
+ */
+pub(crate) fn wide_char_is_printable(c: char, include_all_whitespace: bool) -> bool {
+    return c == '\t' ||
+        !c.is_control() ||
+        (include_all_whitespace && c.is_whitespace());
 }
 
-pub(crate) fn to_little_endian_16(symbol: u32) -> u32 {
-    return ((symbol & 0xff) << 8) | ((symbol & 0xff00) >> 8);
+pub(crate) fn is_high_surrogate(unit: u32) -> bool {
+    return (0xd800..=0xdbff).contains(&unit);
 }
 
-fn is_printable_ascii(c: char) -> bool {
-    return match c {
-        '\x20'..='\x7e' => true,
-        _ => false
-    };
+pub(crate) fn is_low_surrogate(unit: u32) -> bool {
+    return (0xdc00..=0xdfff).contains(&unit);
+}
+
+pub(crate) fn decode_surrogate_pair(high: u32, low: u32) -> u32 {
+    return 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
 }
 
 /**
-If non-zero, then number of bytes it is using
+Returns the high and the low surrogates of a code point outside of the BMP.
  */
-pub(crate) fn is_valid_utf8(buffer: &[u8]) -> u8 {
-    if buffer[0] < 0xc0 {
-        return 0;
-    }
-
-    if buffer.len() < 2 {
-        return 0;
-    }
-
-    if (buffer[1] & 0xc0) != 0x80 {
-        return 0;
-    }
-
-    if (buffer[0] & 0x20) == 0 {
-        return 2;
-    }
+pub(crate) fn encode_surrogate_pair(code_point: u32) -> (u32, u32) {
+    let offset = code_point - 0x10000;
+    return (0xd800 + (offset >> 10), 0xdc00 + (offset & 0x3ff));
+}
 
-    if buffer.len() < 3 {
-        return 0;
-    }
+pub(crate) fn to_little_endian_32(symbol: u32) -> u32 {
+    return ((symbol & 0xff) << 24) | ((symbol & 0xff00) << 8) |
+        ((symbol & 0xff0000) >> 8) | ((symbol & 0xff000000) >> 24);
+}
 
-    if (buffer[2] & 0xc0) != 0x80 {
-        return 0;
-    }
+pub(crate) fn to_little_endian_16(symbol: u32) -> u32 {
+    return ((symbol & 0xff) << 8) | ((symbol & 0xff00) >> 8);
+}
 
-    if (buffer[0] & 0x10) == 0 {
-        return 3;
-    }
+/**
+Reads the little-endian number at the position, `None` if the data ends before it.
+ */
+pub(crate) fn read_u16_le(data: &[u8], position: usize) -> Option<u16> {
+    let bytes = data.get(position..position.checked_add(2)?)?;
+    return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+}
 
-    if buffer.len() < 4 {
-        return 0;
-    }
+pub(crate) fn read_u32_le(data: &[u8], position: usize) -> Option<u32> {
+    let bytes = data.get(position..position.checked_add(4)?)?;
+    return Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
 
-    if (buffer[3] & 0xc0) != 0x80 {
-        return 0;
-    }
+pub(crate) fn read_u64_le(data: &[u8], position: usize) -> Option<u64> {
+    let bytes = data.get(position..position.checked_add(8)?)?;
+    return Some(u64::from_le_bytes(bytes.try_into().unwrap()));
+}
 
-    return 4;
+fn is_printable_ascii(c: char) -> bool {
+    return match c {
+        '\x20'..='\x7e' => true,
+        _ => false
+    };
 }
 
 #[cfg(test)]
@@ -114,6 +153,25 @@ mod tests {
         assert!(!char_is_printable('\u{100}', EncodingKind::Bit7, false));
     }
 
+    #[test]
+    fn test_wide_char_is_printable() {
+        assert!(wide_char_is_printable('ℓ', false));
+        assert!(wide_char_is_printable('\u{10348}', false));
+        assert!(wide_char_is_printable('\t', false));
+        assert!(!wide_char_is_printable('\n', false));
+        assert!(wide_char_is_printable('\n', true));
+        assert!(!wide_char_is_printable('\u{85}', false));
+    }
+
+    #[test]
+    fn test_decode_surrogate_pair() {
+        let units: Vec<u16> = "𐍈".encode_utf16().collect();
+
+        assert!(is_high_surrogate(units[0] as u32));
+        assert!(is_low_surrogate(units[1] as u32));
+        assert_eq!(0x10348, decode_surrogate_pair(units[0] as u32, units[1] as u32));
+    }
+
     #[test]
     fn test_char_is_graphic_bit8() {
         for char in '\u{80}'..='\u{ff}' {
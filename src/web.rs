@@ -0,0 +1,137 @@
+/*
+ The WebAssembly binding of the `wasm-bindgen` feature, for scanning in the browsers:
+
+     wasm-pack build --target web -- --no-default-features --features wasm-bindgen
+
+ builds the module and its JavaScript glue, which exports `scanBytes(Uint8Array, options)`:
+
+     import init, {scanBytes} from "./pkg/strings.js";
+     await init();
+     const report = scanBytes(new Uint8Array(buffer), {minLength: 6, encoding: "l"});
+     // {count: 2, strings: [{offset, length, encoding, termination, string}, ...]}
+
+ The options are minLength, encoding (as given to -e) and includeAllWhitespace, and an invalid
+ option throws an Error. Nothing here touches the files, so the same scanning runs without a
+ file system.
+ */
+
+use std::ffi::OsStr;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use super::json::StringRecord;
+use super::strings::{self, EncodingKind, FoundString, Options, StringSink};
+use wasm_bindgen::prelude::*;
+
+/**
+The options of `scanBytes`, named as in JavaScript.
+ */
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct ScanOptions {
+    min_length: u16,
+    /// As given to -e.
+    encoding: String,
+    include_all_whitespace: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        return ScanOptions {
+            min_length: Options::default().min_length,
+            encoding: "s".to_string(),
+            include_all_whitespace: false,
+        };
+    }
+}
+
+/**
+Scans the data with the options, an object or `undefined` for the defaults, and returns the
+report: `{count: N, strings: [...]}` with the records of the JSON output.
+ */
+#[wasm_bindgen(js_name = scanBytes)]
+pub fn scan_bytes(data: &[u8], options: JsValue) -> Result<JsValue, JsError> {
+    let options: Option<ScanOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|err| JsError::new(&format!("invalid options: {}", err)))?;
+    let report = scan(data, options.unwrap_or_default()).map_err(|err| JsError::new(&err))?;
+    // plain objects rather than maps
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    return Ok(report.serialize(&serializer)?);
+}
+
+fn scan(data: &[u8], options: ScanOptions) -> Result<Value, String> {
+    let options = to_options(options)?;
+    let mut collector = RecordCollector { records: Vec::new() };
+    let count = strings::print_strings_for_bytes(OsStr::new(""), data, &options, &mut collector);
+    return Ok(json!({"count": count, "strings": collector.records}));
+}
+
+fn to_options(options: ScanOptions) -> Result<Options, String> {
+    if options.min_length == 0 {
+        return Err("invalid minimum string length: 0".to_string());
+    }
+
+    let encoding = match EncodingKind::parse(&options.encoding) {
+        Some(encoding) if !encoding.is_auto() => encoding,
+        _ => return Err(format!("invalid encoding: {}", options.encoding)),
+    };
+    return Ok(Options {
+        min_length: options.min_length,
+        encoding,
+        include_all_whitespace: options.include_all_whitespace,
+        ..Options::default()
+    });
+}
+
+struct RecordCollector {
+    records: Vec<Value>,
+}
+
+impl StringSink for RecordCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut record = serde_json::to_value(StringRecord::new(found)).unwrap();
+        // there is no file name to report
+        record.as_object_mut().unwrap().remove("file");
+        self.records.push(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan_with(data: &[u8], options: &str) -> Result<Value, String> {
+        return scan(data, serde_json::from_str(options).map_err(|err| err.to_string())?);
+    }
+
+    #[test]
+    fn test_scan() {
+        assert_eq!(
+            json!({
+                "count": 1,
+                "strings": [
+                    {"offset": 1, "length": 5, "encoding": "7bit", "termination": "nul", "string": "hello"},
+                ],
+            }),
+            scan(b"\x01hello\0abc\x02", ScanOptions::default()).unwrap()
+        );
+        assert_eq!(
+            json!({
+                "count": 2,
+                "strings": [
+                    {"offset": 1, "length": 5, "encoding": "7bit", "termination": "nul", "string": "hello"},
+                    {"offset": 7, "length": 3, "encoding": "7bit", "termination": "non-printable", "string": "abc"},
+                ],
+            }),
+            scan_with(b"\x01hello\0abc\x02", r#"{"minLength": 3}"#).unwrap()
+        );
+        assert_eq!(
+            Err("invalid encoding: auto16".to_string()),
+            scan_with(b"", r#"{"encoding": "auto16"}"#)
+        );
+        assert_eq!(
+            Err("invalid minimum string length: 0".to_string()),
+            scan_with(b"", r#"{"minLength": 0}"#)
+        );
+        assert!(scan_with(b"", r#"{"radix": "x"}"#).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+/*
+ Single-byte XOR deobfuscation (`--xor`): the data is scanned once, with a scanner per key fed
+ with the bytes XOR-ed with that key, and the strings are reported with the key they were
+ found with. NUL bytes are not decoded: they are likely padding, which would turn into runs
+ of the key.
+ */
+
+use std::ffi::OsStr;
+use super::packages::PackageInfo;
+use super::strings::{FoundString, StringSink};
+
+/// Keys tried by `--xor` without a value. Key 0 is the plain scan.
+pub const DEFAULT_KEYS: &str = "1-255";
+
+/**
+Parses the value of `--xor`: comma-separated keys and inclusive ranges of keys, decimal or
+hexadecimal with the `0x` prefix, e.g. `0x20-0x7f,255`.
+ */
+pub fn parse_keys(value: &str) -> Option<Vec<u8>> {
+    let mut keys = Vec::new();
+
+    for part in value.split(',') {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (parse_key(first)?, parse_key(last)?),
+            None => (parse_key(part)?, parse_key(part)?),
+        };
+        if first > last {
+            return None;
+        }
+        for key in first..=last {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    return Some(keys);
+}
+
+fn parse_key(value: &str) -> Option<u8> {
+    let value = value.trim();
+    return match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+}
+
+/**
+Attaches the key to the strings found in the data XOR-ed with it.
+ */
+pub struct XorKeySink<'a> {
+    key: u8,
+    inner: &'a mut dyn StringSink,
+}
+
+impl<'a> XorKeySink<'a> {
+    pub fn new(key: u8, inner: &'a mut dyn StringSink) -> XorKeySink<'a> {
+        return XorKeySink { key, inner };
+    }
+}
+
+impl StringSink for XorKeySink<'_> {
+    fn on_string(&mut self, found: &FoundString) {
+        let mut found = found.with_tags(found.tags);
+        found.xor_key = Some(self.key);
+        self.inner.on_string(&found);
+    }
+
+    fn on_package(&mut self, filename: &OsStr, package: &PackageInfo) {
+        self.inner.on_package(filename, package);
+    }
+
+    fn on_input(&mut self, filename: &OsStr, size: Option<u64>) {
+        self.inner.on_input(filename, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(Some((1..=255).collect::<Vec<u8>>()), parse_keys(DEFAULT_KEYS));
+        assert_eq!(Some(vec![0x20, 0x21, 0x22, 0xff, 7]), parse_keys("0x20-0x22,255,7,0x21"));
+        assert_eq!(None, parse_keys("0x100"));
+        assert_eq!(None, parse_keys("9-1"));
+        assert_eq!(None, parse_keys("a"));
+        assert_eq!(None, parse_keys(""));
+    }
+}
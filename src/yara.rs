@@ -0,0 +1,244 @@
+/*
+ Generation of YARA rules, see `strings yara-gen`: the strings of the samples are ranked and the
+ best ones make up the skeleton of a rule, to be reviewed and tuned by hand. The strings found in
+ the most samples come first, then the ones which score best: long, of high entropy and rare in
+ the samples, unlike padding, format strings repeated all over the code and short fragments.
+
+ The strings found by the 8-bit scans get the `ascii` modifier and the ones found by the 16-bit
+ little-endian scans the `wide` one, both if a string is found both ways. The strings of the
+ other encodings, XOR-ed or decoded from base64 have no such modifier and are left out.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+use super::diagnostics::check_write;
+use super::entropy;
+use super::strings::{EncodingKind, FoundString, StringSink};
+
+/// Length from which the longer strings don't score better.
+const MAX_SCORED_LENGTH: usize = 64;
+
+/**
+A distinct string of the samples.
+ */
+#[derive(PartialEq, Debug)]
+pub struct Candidate {
+    pub text: Vec<u8>,
+    pub ascii: bool,
+    pub wide: bool,
+    pub occurrences: u64,
+    /// The samples it is found in, by index.
+    pub inputs: Vec<usize>,
+}
+
+impl Candidate {
+    /**
+    Returns the score of the string: its length up to `MAX_SCORED_LENGTH` times its entropy in
+    bits per character, divided by the number of its occurrences per sample.
+     */
+    pub fn score(&self) -> f64 {
+        let length = self.text.len().min(MAX_SCORED_LENGTH) as f64;
+        let occurrences = self.occurrences as f64 / self.inputs.len() as f64;
+        return length * entropy::shannon_entropy(&self.text) / occurrences;
+    }
+}
+
+/**
+Collects the strings of the samples, which are scanned one after another with `input` set to the
+index of the sample.
+ */
+#[derive(Default)]
+pub struct CandidateCollector {
+    pub input: usize,
+    candidates: Vec<Candidate>,
+    positions: HashMap<Vec<u8>, usize>,
+}
+
+impl CandidateCollector {
+    pub fn new() -> CandidateCollector {
+        return CandidateCollector::default();
+    }
+
+    /**
+    Returns the `top` best strings, see the module description.
+     */
+    pub fn rank(&self, top: usize) -> Vec<&Candidate> {
+        let mut ranked: Vec<(&Candidate, f64)> = self.candidates.iter()
+            .map(|candidate| (candidate, candidate.score()))
+            .collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            return b.inputs.len().cmp(&a.inputs.len())
+                .then(b_score.total_cmp(a_score))
+                .then(a.text.cmp(&b.text));
+        });
+        return ranked.into_iter().take(top).map(|(candidate, _)| candidate).collect();
+    }
+}
+
+impl StringSink for CandidateCollector {
+    fn on_string(&mut self, found: &FoundString) {
+        if found.xor_key.is_some() || found.decoded_from.is_some() {
+            return;
+        }
+        let wide = match found.encoding {
+            EncodingKind::Bit7 | EncodingKind::Bit8 => false,
+            // the wide modifier interleaves the bytes with zeros, it doesn't encode UTF-16
+            EncodingKind::LittleEndian16 if found.text.is_ascii() => true,
+            _ => return,
+        };
+
+        let position = match self.positions.get(found.text) {
+            Some(position) => *position,
+            None => {
+                self.positions.insert(found.text.to_vec(), self.candidates.len());
+                self.candidates.push(Candidate {
+                    text: found.text.to_vec(),
+                    ascii: false,
+                    wide: false,
+                    occurrences: 0,
+                    inputs: Vec::new(),
+                });
+                self.candidates.len() - 1
+            }
+        };
+        let candidate = &mut self.candidates[position];
+        if wide {
+            candidate.wide = true;
+        } else {
+            candidate.ascii = true;
+        }
+        candidate.occurrences += 1;
+        if candidate.inputs.last() != Some(&self.input) {
+            candidate.inputs.push(self.input);
+        }
+    }
+}
+
+/**
+Returns a rule identifier made of the name: the characters other than letters, digits and `_`
+are replaced with `_`, and a name starting with a digit is prefixed with `_`.
+ */
+pub fn rule_name(name: &str) -> String {
+    let mut identifier: String = name.chars()
+        .map(|char| if char.is_ascii_alphanumeric() { char } else { '_' })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|char: char| char.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    return identifier;
+}
+
+/**
+Returns the string as a YARA text string, in quotes.
+ */
+fn quote(text: &[u8]) -> String {
+    let mut quoted = String::from("\"");
+    for byte in text {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b' '..=b'~' => quoted.push(*byte as char),
+            _ => quoted.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
+
+/**
+Writes the rule matching half of the strings, rounded up, with the names of the samples in its
+description.
+ */
+pub fn write_rule(name: &str, samples: &[String], strings: &[&Candidate], writer: &mut dyn Write) {
+    let mut rule = format!("rule {}\n{{\n", name);
+    rule.push_str("    meta:\n");
+    let description = format!("Generated from {}", samples.join(", "));
+    rule.push_str(&format!("        description = {}\n", quote(description.as_bytes())));
+    rule.push_str("    strings:\n");
+    for (index, candidate) in strings.iter().enumerate() {
+        let modifiers = match (candidate.ascii, candidate.wide) {
+            (true, true) => " ascii wide",
+            (false, true) => " wide",
+            _ => " ascii",
+        };
+        let text = quote(&candidate.text);
+        rule.push_str(&format!("        $s{} = {}{}\n", index + 1, text, modifiers));
+    }
+    rule.push_str("    condition:\n");
+    rule.push_str(&format!("        {} of them\n", strings.len().div_ceil(2)));
+    rule.push_str("}\n");
+    check_write(writer.write_all(rule.as_bytes()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use crate::strings::Termination;
+
+    fn found(text: &str, encoding: EncodingKind) -> FoundString<'_> {
+        return FoundString {
+            filename: OsStr::new("sample"),
+            address: 0,
+            end_address: text.len() as u64,
+            bytes: text.as_bytes(),
+            text: text.as_bytes(),
+            encoding,
+            tags: &[],
+            xor_key: None,
+            decoded_from: None,
+            termination: Termination::Nul,
+            score: None,
+            context_before: &[],
+            context_after: &[],
+            section: None,
+        };
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut collector = CandidateCollector::new();
+        for text in ["AAAAAAAAAAAAAAAA", "%s: %d", "%s: %d", "%s: %d", "c2.example.net/gate.php"] {
+            collector.on_string(&found(text, EncodingKind::Bit7));
+        }
+        collector.on_string(&found("Global\\MutexName", EncodingKind::LittleEndian16));
+        collector.on_string(&found("c2.example.net/gate.php", EncodingKind::LittleEndian16));
+        collector.on_string(&found("big endian", EncodingKind::BigEndian16));
+        collector.input = 1;
+        collector.on_string(&found("Global\\MutexName", EncodingKind::Bit8));
+
+        let ranked: Vec<&str> = collector.rank(4).iter()
+            .map(|candidate| std::str::from_utf8(&candidate.text).unwrap())
+            .collect();
+        assert_eq!(
+            vec!["Global\\MutexName", "c2.example.net/gate.php", "%s: %d", "AAAAAAAAAAAAAAAA"],
+            ranked
+        );
+
+        let mut output = Vec::new();
+        write_rule("sample_exe", &["sample.exe".to_string()], &collector.rank(2), &mut output);
+        assert_eq!(
+            concat!(
+                "rule sample_exe\n{\n",
+                "    meta:\n",
+                "        description = \"Generated from sample.exe\"\n",
+                "    strings:\n",
+                "        $s1 = \"Global\\\\MutexName\" ascii wide\n",
+                "        $s2 = \"c2.example.net/gate.php\" ascii wide\n",
+                "    condition:\n",
+                "        1 of them\n",
+                "}\n",
+            ),
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rule_name_and_quote() {
+        assert_eq!("sample_exe", rule_name("sample.exe"));
+        assert_eq!("_7z_dll", rule_name("7z.dll"));
+        assert_eq!("\"say \\\"hi\\\"\\n\\x01\\xe9\"", quote(b"say \"hi\"\n\x01\xe9"));
+    }
+}
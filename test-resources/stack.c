@@ -0,0 +1,14 @@
+#include <stdio.h>
+int main()
+{
+    volatile char name[13];
+    name[0] = 'k'; name[1] = 'e'; name[2] = 'r'; name[3] = 'n'; name[4] = 'e'; name[5] = 'l';
+    name[6] = '3'; name[7] = '2'; name[8] = '.'; name[9] = 'd'; name[10] = 'l'; name[11] = 'l';
+    name[12] = 0;
+    volatile unsigned short command[6];
+    command[0] = u'c'; command[1] = u'm'; command[2] = u'd'; command[3] = u'.';
+    command[4] = u'1'; command[5] = 0;
+    puts((const char *) name);
+    printf("%d\n", command[0]);
+    return 0;
+}